@@ -40,6 +40,10 @@ fn main() {
                           "EGL_KHR_create_context",
                           "EGL_EXT_create_context_robustness",
                           "EGL_KHR_create_context_no_error",
+                          "EGL_IMG_context_priority",
+                          "EGL_KHR_swap_buffers_with_damage",
+                          "EGL_EXT_swap_buffers_with_damage",
+                          "EGL_EXT_buffer_age",
                           "EGL_KHR_platform_x11",
                           "EGL_KHR_platform_android",
                           "EGL_KHR_platform_gbm",
@@ -47,6 +51,9 @@ fn main() {
                           "EGL_EXT_platform_x11",
                           "EGL_MESA_platform_gbm",
                           "EGL_EXT_platform_device",
+                          "EGL_KHR_platform_wayland",
+                          "EGL_EXT_platform_wayland",
+                          "EGL_MESA_platform_surfaceless",
                       ])
             .write_bindings(gl_generator::StructGenerator, &mut file).unwrap();
     }
@@ -76,6 +83,10 @@ fn main() {
                           "EGL_KHR_create_context",
                           "EGL_EXT_create_context_robustness",
                           "EGL_KHR_create_context_no_error",
+                          "EGL_IMG_context_priority",
+                          "EGL_KHR_swap_buffers_with_damage",
+                          "EGL_EXT_swap_buffers_with_damage",
+                          "EGL_EXT_buffer_age",
                           "EGL_KHR_platform_x11",
                           "EGL_KHR_platform_android",
                           "EGL_KHR_platform_gbm",
@@ -83,6 +94,9 @@ fn main() {
                           "EGL_EXT_platform_x11",
                           "EGL_MESA_platform_gbm",
                           "EGL_EXT_platform_device",
+                          "EGL_KHR_platform_wayland",
+                          "EGL_EXT_platform_wayland",
+                          "EGL_MESA_platform_surfaceless",
                       ])
             .write_bindings(gl_generator::StructGenerator, &mut file).unwrap();
     }
@@ -93,6 +107,10 @@ fn main() {
                           "EGL_KHR_create_context",
                           "EGL_EXT_create_context_robustness",
                           "EGL_KHR_create_context_no_error",
+                          "EGL_IMG_context_priority",
+                          "EGL_KHR_swap_buffers_with_damage",
+                          "EGL_EXT_swap_buffers_with_damage",
+                          "EGL_EXT_buffer_age",
                           "EGL_KHR_platform_x11",
                           "EGL_KHR_platform_android",
                           "EGL_KHR_platform_gbm",
@@ -100,6 +118,9 @@ fn main() {
                           "EGL_EXT_platform_x11",
                           "EGL_MESA_platform_gbm",
                           "EGL_EXT_platform_device",
+                          "EGL_KHR_platform_wayland",
+                          "EGL_EXT_platform_wayland",
+                          "EGL_MESA_platform_surfaceless",
                       ])
             .write_bindings(gl_generator::StaticStructGenerator, &mut file).unwrap();
     }
@@ -110,6 +131,10 @@ fn main() {
                           "EGL_KHR_create_context",
                           "EGL_EXT_create_context_robustness",
                           "EGL_KHR_create_context_no_error",
+                          "EGL_IMG_context_priority",
+                          "EGL_KHR_swap_buffers_with_damage",
+                          "EGL_EXT_swap_buffers_with_damage",
+                          "EGL_EXT_buffer_age",
                           "EGL_KHR_platform_x11",
                           "EGL_KHR_platform_android",
                           "EGL_KHR_platform_gbm",
@@ -117,6 +142,9 @@ fn main() {
                           "EGL_EXT_platform_x11",
                           "EGL_MESA_platform_gbm",
                           "EGL_EXT_platform_device",
+                          "EGL_KHR_platform_wayland",
+                          "EGL_EXT_platform_wayland",
+                          "EGL_MESA_platform_surfaceless",
                       ])
             .write_bindings(gl_generator::StaticStructGenerator, &mut file).unwrap();
 
@@ -131,6 +159,11 @@ fn main() {
             .write_bindings(gl_generator::GlobalGenerator, &mut file).unwrap();
     }
 
+    // used by `SizedFramebuffer`, the functions are the same in OpenGL 3.0 and OpenGL ES 3.0
+    let mut file = File::create(&dest.join("framebuffer_gl_bindings.rs")).unwrap();
+    Registry::new(Api::Gles2, (3, 0), Profile::Core, Fallbacks::All, [])
+            .write_bindings(gl_generator::StructGenerator, &mut file).unwrap();
+
     // TODO: only build the bindings below if we run tests/examples
 
     let mut file = File::create(&dest.join("test_gl_bindings.rs")).unwrap();
@@ -0,0 +1,96 @@
+#[cfg(target_os = "android")]
+#[macro_use]
+extern crate android_glue;
+
+extern crate glutin;
+
+use std::collections::VecDeque;
+use std::thread;
+use std::time::Duration;
+
+use glutin::{ContextError, Event, GlContext, Rect};
+
+mod gl {
+    pub use self::Gles2 as Gl;
+    include!(concat!(env!("OUT_DIR"), "/test_gl_bindings.rs"));
+}
+
+#[cfg(target_os = "android")]
+android_start!(main);
+
+const BACKGROUND: (f32, f32, f32) = (0.1, 0.1, 0.3);
+const FOREGROUND: (f32, f32, f32) = (1.0, 0.8, 0.0);
+const SQUARE_SIZE: u32 = 64;
+
+/// Fills a rectangle of the framebuffer, whose origin is the bottom-left corner like the damage
+/// rectangles.
+fn fill(gl: &gl::Gl, rect: Rect, color: (f32, f32, f32)) {
+    unsafe {
+        gl.Enable(gl::SCISSOR_TEST);
+        gl.Scissor(rect.x, rect.y, rect.width as gl::types::GLsizei,
+                   rect.height as gl::types::GLsizei);
+        gl.ClearColor(color.0, color.1, color.2, 1.0);
+        gl.Clear(gl::COLOR_BUFFER_BIT);
+        gl.Disable(gl::SCISSOR_TEST);
+    }
+}
+
+fn main() {
+    let window = glutin::WindowBuilder::new().build().unwrap();
+    window.set_title("glutin - Damage rectangles");
+    let current = window.make_current().unwrap();
+
+    let gl = gl::Gl::load_with(|ptr| current.get_proc_address(ptr) as *const _);
+
+    // the positions of the square in the latest frames, the most recent one last
+    let mut history: VecDeque<Rect> = VecDeque::new();
+    let mut x = 0;
+
+    loop {
+        for event in window.poll_events() {
+            if let Event::Closed = event {
+                return;
+            }
+        }
+
+        let (width, height) = match window.get_inner_size_pixels() {
+            Some(size) => size,
+            None => return,
+        };
+        let square = Rect {
+            x: x,
+            y: (height.saturating_sub(SQUARE_SIZE) / 2) as i32,
+            width: SQUARE_SIZE,
+            height: SQUARE_SIZE,
+        };
+
+        // the back buffer contains the frame that was presented `age` frames ago, so only the
+        // squares drawn since then must be erased
+        let age = current.buffer_age().unwrap_or(0) as usize;
+        let damage = if age == 0 || age > history.len() {
+            fill(&gl, Rect { x: 0, y: 0, width: width, height: height }, BACKGROUND);
+            Vec::new()
+        } else {
+            let mut damage: Vec<Rect> = history.iter().rev().take(age).cloned().collect();
+            for rect in &damage {
+                fill(&gl, *rect, BACKGROUND);
+            }
+            damage.push(square);
+            damage
+        };
+        fill(&gl, square, FOREGROUND);
+
+        match current.swap_buffers_with_damage(&damage) {
+            Err(ContextError::Unsupported) => current.swap_buffers().unwrap(),
+            result => result.unwrap(),
+        }
+
+        history.push_back(square);
+        if history.len() > 4 {
+            history.pop_front();
+        }
+        x = (x + 4) % width.saturating_sub(SQUARE_SIZE).max(1) as i32;
+
+        thread::sleep(Duration::from_millis(16));
+    }
+}
@@ -0,0 +1,49 @@
+#[cfg(target_os = "android")]
+#[macro_use]
+extern crate android_glue;
+
+extern crate glutin;
+
+use std::thread;
+use std::time::{Duration, Instant};
+
+#[cfg(target_os = "android")]
+android_start!(main);
+
+// Win32 drops the messages posted to a queue that already holds 10,000 of them, so this stays well
+// below that limit.
+const EVENTS: usize = 5_000;
+
+// Compares the time it takes to fetch a large number of events with `poll_events`, one by one,
+// and with `drain_events`, in batches.
+fn main() {
+    let window = glutin::WindowBuilder::new().with_visibility(false).build().unwrap();
+    let proxy = window.create_window_proxy();
+    let mut events = Vec::with_capacity(EVENTS);
+
+    for round in 0 .. 3 {
+        send_wakeups(&proxy);
+        let start = Instant::now();
+        let count = window.poll_events().count();
+        report(round, "poll_events", count, start.elapsed());
+
+        send_wakeups(&proxy);
+        events.clear();
+        let start = Instant::now();
+        window.drain_events(&mut events);
+        report(round, "drain_events", events.len(), start.elapsed());
+    }
+}
+
+// Queues the synthetic events, then waits for them to reach the window.
+fn send_wakeups(proxy: &glutin::WindowProxy) {
+    for _ in 0 .. EVENTS {
+        proxy.wakeup_event_loop();
+    }
+    thread::sleep(Duration::from_millis(500));
+}
+
+fn report(round: usize, method: &str, count: usize, elapsed: Duration) {
+    let micros = elapsed.as_secs() * 1_000_000 + elapsed.subsec_nanos() as u64 / 1_000;
+    println!("round {}: {:>12} fetched {} events in {} us", round, method, count, micros);
+}
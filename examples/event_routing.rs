@@ -0,0 +1,61 @@
+#[cfg(target_os = "android")]
+#[macro_use]
+extern crate android_glue;
+
+extern crate glutin;
+
+use std::thread;
+use std::time::Duration;
+
+#[cfg(target_os = "android")]
+android_start!(main);
+
+// Checks that the events of two windows are delivered to the right window, even though they may
+// share the same connection to the window system.
+fn main() {
+    let window1 = glutin::WindowBuilder::new().with_title("Window 1").build().unwrap();
+    let window2 = glutin::WindowBuilder::new().with_title("Window 2").build().unwrap();
+
+    // the windows get sizes that only one of them can have
+    window1.set_inner_size(420, 320);
+    window2.set_inner_size(320, 420);
+
+    // only the second window is woken up
+    window2.create_window_proxy().wakeup_event_loop();
+
+    let mut window2_awakened = false;
+    let mut last_size1 = None;
+    let mut last_size2 = None;
+
+    for _ in 0 .. 100 {
+        for event in window1.poll_events() {
+            match event {
+                glutin::Event::Awakened => panic!("Window 1 received the wake-up of window 2"),
+                glutin::Event::Resized(w, h) => last_size1 = Some((w, h)),
+                _ => ()
+            }
+        }
+
+        for event in window2.poll_events() {
+            match event {
+                glutin::Event::Awakened => window2_awakened = true,
+                glutin::Event::Resized(w, h) => last_size2 = Some((w, h)),
+                _ => ()
+            }
+        }
+
+        thread::sleep(Duration::from_millis(10));
+    }
+
+    assert!(window2_awakened, "Window 2 didn't receive its wake-up");
+
+    // the last size that each window received must be its own
+    if last_size1.is_some() {
+        assert_eq!(last_size1, window1.get_inner_size_pixels());
+    }
+    if last_size2.is_some() {
+        assert_eq!(last_size2, window2.get_inner_size_pixels());
+    }
+
+    println!("The events of both windows were delivered to the right window");
+}
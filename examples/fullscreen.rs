@@ -37,20 +37,20 @@ fn main() {
         .build()
         .unwrap();
 
-    let _ = unsafe { window.make_current() };
+    let current = window.make_current().unwrap();
 
     
-    let context = support::load(&window);
+    let context = support::load(&current);
 
     for event in window.wait_events() {
         context.draw_frame((0.0, 1.0, 0.0, 1.0));
-        let _ = window.swap_buffers();
+        let _ = current.swap_buffers();
 
         println!("{:?}", event);
 
         match event {
             glutin::Event::Closed => break,
-            glutin::Event::KeyboardInput(_, _, Some(glutin::VirtualKeyCode::Escape)) => break,
+            glutin::Event::KeyboardInput(_, _, Some(glutin::VirtualKeyCode::Escape), _) => break,
             _ => ()
         }
     }
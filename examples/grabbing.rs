@@ -14,22 +14,32 @@ android_start!(main);
 fn main() {
     let window = glutin::WindowBuilder::new().build().unwrap();
     window.set_title("glutin - Cursor grabbing test");
-    let _ = unsafe { window.make_current() };
+    let current = window.make_current().unwrap();
 
-    let context = support::load(&window);
+    let context = support::load(&current);
     let mut grabbed = false;
 
+    println!("Press any key to grab or release the cursor. While the cursor is grabbed, switch \
+              to another window and back to see it released and grabbed again.");
+
     for event in window.wait_events() {
         match event {
-            Event::KeyboardInput(ElementState::Pressed, _, _) => {
+            Event::KeyboardInput(ElementState::Pressed, _, _, false) => {
                 if grabbed {
                     grabbed = false;
                     window.set_cursor_state(glutin::CursorState::Normal)
                           .ok().expect("could not ungrab mouse cursor");
+                } else if let Err(err) = window.set_cursor_state(glutin::CursorState::Grab) {
+                    println!("could not grab mouse cursor: {}", err);
                 } else {
                     grabbed = true;
-                    window.set_cursor_state(glutin::CursorState::Grab)
-                          .ok().expect("could not grab mouse cursor");
+                }
+            },
+
+            Event::Focused(focused) => {
+                if grabbed {
+                    println!("focus {}, the cursor is {}", if focused { "gained" } else { "lost" },
+                             if focused { "grabbed again" } else { "released" });
                 }
             },
 
@@ -43,6 +53,6 @@ fn main() {
         }
 
         context.draw_frame((0.0, 1.0, 0.0, 1.0));
-        let _ = window.swap_buffers();
+        let _ = current.swap_buffers();
     }
 }
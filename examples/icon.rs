@@ -18,13 +18,13 @@ fn main() {
                          .unwrap();
 
     window.set_title("A fantastic window with an icon!");
-    let _ = unsafe { window.make_current() };
+    let current = window.make_current().unwrap();
 
-    let context = support::load(&window);
+    let context = support::load(&current);
 
     for event in window.wait_events() {
         context.draw_frame((0.0, 1.0, 0.0, 1.0));
-        let _ = window.swap_buffers();
+        let _ = current.swap_buffers();
 
         match event {
             glutin::Event::Closed => break,
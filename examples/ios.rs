@@ -0,0 +1,57 @@
+#[cfg(target_os = "android")]
+#[macro_use]
+extern crate android_glue;
+
+extern crate glutin;
+
+mod support;
+
+#[cfg(target_os = "android")]
+android_start!(main);
+
+// Fills the screen with a color that follows the last touch, and stops drawing while the app is
+// in the background.
+//
+// To run it in the iOS simulator:
+//
+// ```
+// cargo build --example ios --target x86_64-apple-ios
+// mkdir -p ios.app && cp target/x86_64-apple-ios/debug/examples/ios ios.app/
+// /usr/libexec/PlistBuddy -c "Add :CFBundleIdentifier string rs.glutin.ios" \
+//                         -c "Add :CFBundleExecutable string ios" ios.app/Info.plist
+// xcrun simctl install booted ios.app && xcrun simctl launch --console booted rs.glutin.ios
+// ```
+//
+// The example also runs on the other platforms, where the mouse doesn't send touch events.
+fn main() {
+    let window = glutin::WindowBuilder::new()
+        .with_multitouch()
+        .build()
+        .unwrap();
+    let current = window.make_current().unwrap();
+    let context = support::load(&current);
+
+    let (width, height) = window.get_inner_size().unwrap_or((1, 1));
+    let mut color = (0.0, 0.0, 0.0, 1.0);
+    let mut suspended = false;
+
+    for event in window.wait_events() {
+        println!("{:?}", event);
+
+        match event {
+            glutin::Event::Closed => break,
+            glutin::Event::Suspended(state) => suspended = state,
+            glutin::Event::Touch(touch) => {
+                let (x, y) = touch.location;
+                color = ((x / width as f64) as f32, (y / height as f64) as f32, 0.5, 1.0);
+            },
+            _ => ()
+        }
+
+        // drawing in the background kills the app
+        if !suspended {
+            context.draw_frame(color);
+            let _ = current.swap_buffers();
+        }
+    }
+}
@@ -34,13 +34,13 @@ fn main() {
 }
 
 fn run(window: glutin::Window, color: (f32, f32, f32, f32)) {
-    let _ = unsafe { window.make_current() };
+    let current = window.make_current().unwrap();
 
-    let context = support::load(&window);
+    let context = support::load(&current);
 
     for event in window.wait_events() {
         context.draw_frame(color);
-        let _ = window.swap_buffers();
+        let _ = current.swap_buffers();
 
         match event {
             glutin::Event::Closed => break,
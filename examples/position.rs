@@ -0,0 +1,40 @@
+#[cfg(target_os = "android")]
+#[macro_use]
+extern crate android_glue;
+
+extern crate glutin;
+
+use std::thread;
+use std::time::Duration;
+
+#[cfg(target_os = "android")]
+android_start!(main);
+
+fn main() {
+    let window = glutin::WindowBuilder::new()
+        .with_title("Position round-trip")
+        .with_position(100, 100)
+        .build()
+        .unwrap();
+
+    // give the window manager some time to map and decorate the window
+    thread::sleep(Duration::from_millis(500));
+    for _ in window.poll_events() {}
+
+    let initial = window.get_position().unwrap();
+    println!("Initial position: {:?}", initial);
+
+    for i in 0 .. 10 {
+        let position = window.get_position().unwrap();
+        window.set_position(position.0, position.1);
+
+        thread::sleep(Duration::from_millis(100));
+        for _ in window.poll_events() {}
+
+        let position = window.get_position().unwrap();
+        println!("Position after round-trip #{}: {:?}", i + 1, position);
+        assert_eq!(position, initial, "window drifted after {} round-trips", i + 1);
+    }
+
+    println!("No drift after 10 round-trips");
+}
@@ -0,0 +1,59 @@
+#[cfg(target_os = "android")]
+#[macro_use]
+extern crate android_glue;
+
+extern crate glutin;
+
+use std::thread;
+use std::time::Duration;
+
+mod support;
+
+#[cfg(target_os = "android")]
+android_start!(main);
+
+// Opens two windows whose contexts share their objects, and draws in both of them from the same
+// thread until each one of them is closed.
+fn main() {
+    let window1 = glutin::WindowBuilder::new().with_title("Window 1").build().unwrap();
+    let window2 = glutin::WindowBuilder::new()
+        .with_title("Window 2")
+        .with_shared_lists(&window1)
+        .build()
+        .unwrap();
+
+    // the program and the vertex buffer are only created by the first context
+    let context1 = support::load(&window1.make_current().unwrap());
+    let context2 = support::load_shared(&window2.make_current().unwrap(), &context1);
+
+    let mut windows = vec![
+        (Some(window1), context1, (0.0, 1.0, 0.0, 1.0)),
+        (Some(window2), context2, (0.0, 0.0, 1.0, 1.0)),
+    ];
+
+    while windows.iter().any(|&(ref window, _, _)| window.is_some()) {
+        for &mut (ref mut window, ref context, color) in windows.iter_mut() {
+            // the events of each window are only returned by that window
+            let closed = match *window {
+                Some(ref window) => window.poll_events().any(|event| match event {
+                    glutin::Event::Closed => true,
+                    _ => false,
+                }),
+                None => continue,
+            };
+
+            if closed {
+                // the other window keeps drawing with the objects of the closed one
+                *window = None;
+                continue;
+            }
+
+            let window = window.as_ref().unwrap();
+            let current = window.make_current().unwrap();
+            context.draw_frame(color);
+            let _ = current.swap_buffers();
+        }
+
+        thread::sleep(Duration::from_millis(16));
+    }
+}
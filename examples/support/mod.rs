@@ -9,11 +9,13 @@ mod gl {
 }
 
 pub struct Context {
-    gl: gl::Gl
+    gl: gl::Gl,
+    program: gl::types::GLuint,
+    vb: gl::types::GLuint,
 }
 
-pub fn load(window: &glutin::Window) -> Context {
-    let gl = gl::Gl::load_with(|ptr| window.get_proc_address(ptr) as *const _);
+pub fn load(context: &glutin::CurrentContext) -> Context {
+    let gl = load_gl(context);
 
     let version = unsafe {
         let data = CStr::from_ptr(gl.GetString(gl::VERSION) as *const _).to_bytes().to_vec();
@@ -22,7 +24,7 @@ pub fn load(window: &glutin::Window) -> Context {
 
     println!("OpenGL version {}", version);
 
-    unsafe {
+    let (program, vb) = unsafe {
         let vs = gl.CreateShader(gl::VERTEX_SHADER);
         gl.ShaderSource(vs, 1, [VS_SRC.as_ptr() as *const _].as_ptr(), ptr::null());
         gl.CompileShader(vs);
@@ -35,7 +37,6 @@ pub fn load(window: &glutin::Window) -> Context {
         gl.AttachShader(program, vs);
         gl.AttachShader(program, fs);
         gl.LinkProgram(program);
-        gl.UseProgram(program);
 
         let mut vb = mem::uninitialized();
         gl.GenBuffers(1, &mut vb);
@@ -44,6 +45,32 @@ pub fn load(window: &glutin::Window) -> Context {
                            (VERTEX_DATA.len() * mem::size_of::<f32>()) as gl::types::GLsizeiptr,
                            VERTEX_DATA.as_ptr() as *const _, gl::STATIC_DRAW);
 
+        (program, vb)
+    };
+
+    bind(&gl, program, vb);
+    Context { gl: gl, program: program, vb: vb }
+}
+
+/// Loads the context of a window created with `with_shared_lists(other_window)`, which uses the
+/// program and the vertex buffer created by the context of `other_window` instead of its own.
+#[allow(dead_code)]
+pub fn load_shared(context: &glutin::CurrentContext, other: &Context) -> Context {
+    let gl = load_gl(context);
+    bind(&gl, other.program, other.vb);
+    Context { gl: gl, program: other.program, vb: other.vb }
+}
+
+fn load_gl(context: &glutin::CurrentContext) -> gl::Gl {
+    gl::Gl::load_with(|ptr| context.get_proc_address(ptr) as *const _)
+}
+
+/// Sets up the state of the current context that isn't shared, such as the vertex arrays.
+fn bind(gl: &gl::Gl, program: gl::types::GLuint, vb: gl::types::GLuint) {
+    unsafe {
+        gl.UseProgram(program);
+        gl.BindBuffer(gl::ARRAY_BUFFER, vb);
+
         if gl.BindVertexArray.is_loaded() {
             let mut vao = mem::uninitialized();
             gl.GenVertexArrays(1, &mut vao);
@@ -61,8 +88,6 @@ pub fn load(window: &glutin::Window) -> Context {
         gl.EnableVertexAttribArray(pos_attrib as gl::types::GLuint);
         gl.EnableVertexAttribArray(color_attrib as gl::types::GLuint);
     }
-
-    Context { gl: gl }
 }
 
 impl Context {
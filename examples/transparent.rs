@@ -19,15 +19,15 @@ fn main() {
                                                  .build().unwrap();
     window.set_title("A fantastic window!");
     window.set_window_resize_callback(Some(resize_callback as fn(u32, u32)));
-    let _ = unsafe { window.make_current() };
+    let current = window.make_current().unwrap();
 
     println!("Pixel format of the window: {:?}", window.get_pixel_format());
 
-    let context = support::load(&window);
+    let context = support::load(&current);
 
     for event in window.wait_events() {
         context.draw_frame((0.0, 0.0, 0.0, 0.0));
-        let _ = window.swap_buffers();
+        let _ = current.swap_buffers();
 
         println!("{:?}", event);
 
@@ -4,6 +4,8 @@ extern crate android_glue;
 
 extern crate glutin;
 
+use std::env;
+
 mod support;
 
 #[cfg(target_os = "android")]
@@ -17,15 +19,22 @@ fn main() {
     let mut window = glutin::WindowBuilder::new().build().unwrap();
     window.set_title("A fantastic window!");
     window.set_window_resize_callback(Some(resize_callback as fn(u32, u32)));
-    let _ = unsafe { window.make_current() };
-
-    println!("Pixel format of the window: {:?}", window.get_pixel_format());
+    let current = window.make_current().unwrap();
+
+    let pixel_format = window.get_pixel_format();
+    println!("Pixel format of the window: {:?}", pixel_format);
+    if env::args().any(|arg| arg == "--verbose") {
+        match pixel_format.native_id {
+            Some(id) => println!("Native identifier of the pixel format: {:#x}", id),
+            None => println!("The pixel format has no native identifier"),
+        }
+    }
 
-    let context = support::load(&window);
+    let context = support::load(&current);
 
     for event in window.wait_events() {
         context.draw_frame((0.0, 1.0, 0.0, 1.0));
-        let _ = window.swap_buffers();
+        let _ = current.swap_buffers();
 
         println!("{:?}", event);
 
@@ -70,6 +70,17 @@ pub struct ANativeActivityCallbacks {
     pub onLowMemory: extern fn(*mut ANativeActivity),
 }
 
+pub const ANATIVEACTIVITY_SHOW_SOFT_INPUT_IMPLICIT: libc::uint32_t = 0x0001;
+pub const ANATIVEACTIVITY_SHOW_SOFT_INPUT_FORCED: libc::uint32_t = 0x0002;
+pub const ANATIVEACTIVITY_HIDE_SOFT_INPUT_IMPLICIT_ONLY: libc::uint32_t = 0x0001;
+pub const ANATIVEACTIVITY_HIDE_SOFT_INPUT_NOT_ALWAYS: libc::uint32_t = 0x0002;
+
+#[link(name = "android")]
+extern {
+    pub fn ANativeActivity_showSoftInput(activity: *mut ANativeActivity, flags: libc::uint32_t);
+    pub fn ANativeActivity_hideSoftInput(activity: *mut ANativeActivity, flags: libc::uint32_t);
+}
+
 /**
  * looper.h
  */
@@ -4,9 +4,11 @@ extern crate android_glue;
 
 use libc;
 use std::ffi::{CString};
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, Ordering};
 use std::sync::mpsc::{Receiver, channel};
 use std::{thread, time};
-use {CreationError, Event, MouseCursor};
+use {CreationError, Event, MouseCursor, RawEventFilter, ResizeEdge, ScanCode, VirtualKeyCode};
 use CreationError::OsError;
 use events::ElementState::{Pressed, Released};
 use events::{Touch, TouchPhase};
@@ -16,7 +18,13 @@ use std::collections::VecDeque;
 
 use Api;
 use ContextError;
+use ContextPriority;
+use Robustness;
 use CursorState;
+use CursorGrabMode;
+use ContentProtectionError;
+use CursorStateError;
+use Rect;
 use GlAttributes;
 use GlContext;
 use GlRequest;
@@ -31,7 +39,10 @@ use api::egl::Context as EglContext;
 pub struct Window {
     context: EglContext,
     event_rx: Receiver<android_glue::Event>,
-    stopped: Cell<bool>
+    stopped: Cell<bool>,
+    activity: *mut ffi::ANativeActivity,
+    /// Set by `WindowProxy::request_close`, and reset once the `Closed` event is returned
+    close_requested: Arc<AtomicBool>,
 }
 
 #[derive(Clone)]
@@ -47,8 +58,18 @@ pub fn get_available_monitors() -> VecDeque<MonitorId> {
 }
 
 #[inline]
-pub fn get_primary_monitor() -> MonitorId {
-    MonitorId
+pub fn get_primary_monitor() -> Option<MonitorId> {
+    Some(MonitorId)
+}
+
+#[inline]
+pub fn monitor_from_point(_: i32, _: i32) -> Option<MonitorId> {
+    Some(MonitorId)
+}
+
+#[inline]
+pub fn has_hardware_acceleration() -> bool {
+    true
 }
 
 impl MonitorId {
@@ -69,10 +90,28 @@ impl MonitorId {
             (ffi::ANativeWindow_getWidth(window) as u32, ffi::ANativeWindow_getHeight(window) as u32)
         }
     }
+
+    #[inline]
+    pub fn get_icc_profile(&self) -> Option<Vec<u8>> {
+        None
+    }
+}
+
+#[derive(Clone)]
+pub struct PlatformSpecificWindowBuilderAttributes {
+    /// The `ANativeActivity` of the app, or null if the application didn't give it.
+    pub activity: *mut libc::c_void,
+}
+
+impl Default for PlatformSpecificWindowBuilderAttributes {
+    #[inline]
+    fn default() -> PlatformSpecificWindowBuilderAttributes {
+        PlatformSpecificWindowBuilderAttributes {
+            activity: ::std::ptr::null_mut(),
+        }
+    }
 }
 
-#[derive(Clone, Default)]
-pub struct PlatformSpecificWindowBuilderAttributes;
 #[derive(Clone, Default)]
 pub struct PlatformSpecificHeadlessBuilderAttributes;
 
@@ -84,6 +123,10 @@ impl<'a> Iterator for PollEventsIterator<'a> {
     type Item = Event;
 
     fn next(&mut self) -> Option<Event> {
+        if self.window.close_requested.swap(false, Ordering::SeqCst) {
+            return Some(Event::Closed);
+        }
+
         match self.window.event_rx.try_recv() {
             Ok(android_glue::Event::EventMotion(motion)) => {
                 Some(Event::Touch(Touch {
@@ -107,18 +150,34 @@ impl<'a> Iterator for PollEventsIterator<'a> {
             },
             Ok(android_glue::Event::InitWindow) => {
                 // onsurfaceCreated equivalent
-                self.window.on_surface_created();
-                None
+                if self.window.on_surface_created() {
+                    Some(Event::Suspended(false))
+                } else {
+                    None
+                }
             },
             Ok(android_glue::Event::TermWindow) => {
                 // onSurfaceDestroyed equivalent
-                self.window.on_surface_destroyed();
-                None
+                if self.window.on_surface_destroyed() {
+                    Some(Event::Suspended(true))
+                } else {
+                    None
+                }
             },
+            // the orientation changes are reported by the configuration and the content rectangle
             Ok(android_glue::Event::WindowResized) |
+            Ok(android_glue::Event::ContentRectChanged) |
             Ok(android_glue::Event::ConfigChanged) => {
                 self.window.get_inner_size().map(|s| Event::Resized(s.0, s.1))
             },
+            // android_glue doesn't forward the codes of the keys, so the keys of both the
+            // hardware and the soft keyboards are reported without them
+            Ok(android_glue::Event::EventKeyDown) => {
+                Some(Event::KeyboardInput(Pressed, 0, None, false))
+            },
+            Ok(android_glue::Event::EventKeyUp) => {
+                Some(Event::KeyboardInput(Released, 0, None, false))
+            },
             Ok(android_glue::Event::WindowRedrawNeeded) => {
                 /// The activity needs to be redrawn.
                 Some(Event::Refresh)
@@ -153,7 +212,7 @@ impl<'a> Iterator for WaitEventsIterator<'a> {
 
 impl Window {
     pub fn new(win_attribs: &WindowAttributes, pf_reqs: &PixelFormatRequirements,
-               opengl: &GlAttributes<&Window>, _: &PlatformSpecificWindowBuilderAttributes)
+               opengl: &GlAttributes<&Window>, pl_attribs: &PlatformSpecificWindowBuilderAttributes)
                -> Result<Window, CreationError>
     {
         use std::{mem, ptr};
@@ -180,35 +239,50 @@ impl Window {
         Ok(Window {
             context: context,
             event_rx: rx,
-            stopped: Cell::new(false)
+            stopped: Cell::new(false),
+            activity: pl_attribs.activity as *mut ffi::ANativeActivity,
+            close_requested: Arc::new(AtomicBool::new(false)),
         })
     }
 
     // Android has started the activity or sent it to foreground.
     // Restore the EGL surface and animation loop.
-    pub fn on_surface_created(&self) {
-        if self.stopped.get() {
-           self.stopped.set(false);
-           unsafe {
-               let native_window = android_glue::get_native_window();
-               self.context.on_surface_created(native_window as *const _);
-           }
-
-           // We stopped the renderloop when on_surface_destroyed was called.
-           // We need to wakeup the event loop again.
-           android_glue::wake_event_loop();
+    // Returns true if the window has been resumed.
+    pub fn on_surface_created(&self) -> bool {
+        if !self.stopped.get() {
+            return false;
+        }
+
+        let native_window = unsafe { android_glue::get_native_window() };
+        if native_window.is_null() {
+            return false;
+        }
+
+        // the window stays stopped if the surface can't be recreated, so that the context keeps
+        // reporting `ContextLost` instead of rendering nowhere
+        if unsafe { self.context.on_surface_created(native_window as *const _) }.is_err() {
+            return false;
         }
+        self.stopped.set(false);
+
+        // We stopped the renderloop when on_surface_destroyed was called.
+        // We need to wakeup the event loop again.
+        android_glue::wake_event_loop();
+        true
     }
 
     // Android has stopped the activity or sent it to background.
     // Release the EGL surface and stop the animation loop.
-    pub fn on_surface_destroyed(&self) {
-        if !self.stopped.get() {
-            self.stopped.set(true);
-            unsafe {
-                self.context.on_surface_destroyed();
-            }
+    // Returns true if the window has been suspended.
+    pub fn on_surface_destroyed(&self) -> bool {
+        if self.stopped.get() {
+            return false;
         }
+
+        self.stopped.set(true);
+        // the surface is gone even if this fails, and it is recreated on resume anyway
+        let _ = unsafe { self.context.on_surface_destroyed() };
+        true
     }
 
     pub fn is_stopped(&self) -> bool {
@@ -232,11 +306,30 @@ impl Window {
     pub fn hide(&self) {
     }
 
+    #[inline]
+    pub fn drag_window(&self) -> Result<(), ()> {
+        Err(())
+    }
+
+    #[inline]
+    pub fn drag_resize_window(&self, _: ResizeEdge) -> Result<(), ()> {
+        Err(())
+    }
+
+    #[inline]
+    pub fn focus_window(&self) {
+    }
+
     #[inline]
     pub fn get_position(&self) -> Option<(i32, i32)> {
         None
     }
 
+    #[inline]
+    pub fn get_outer_position(&self) -> Option<(i32, i32)> {
+        self.get_position()
+    }
+
     #[inline]
     pub fn set_position(&self, _x: i32, _y: i32) {
     }
@@ -264,9 +357,46 @@ impl Window {
     pub fn set_inner_size(&self, _x: u32, _y: u32) {
     }
 
+    #[inline]
+    pub fn request_inner_size(&self, _x: u32, _y: u32) -> Option<(u32, u32)> {
+        None
+    }
+
+    #[inline]
+    pub fn set_min_dimensions(&self, _: Option<(u32, u32)>) {
+    }
+
+    #[inline]
+    pub fn set_max_dimensions(&self, _: Option<(u32, u32)>) {
+    }
+
+    #[inline]
+    pub fn set_aspect_ratio(&self, _: Option<(u32, u32)>) {
+    }
+
+    #[inline]
+    pub fn set_decorations(&self, _: bool) {
+    }
+
+    #[inline]
+    pub fn set_opacity(&self, _: f32) {
+    }
+
+    #[inline]
+    pub fn set_content_protected(&self, _: bool) -> Result<(), ContentProtectionError> {
+        Err(ContentProtectionError::Unsupported)
+    }
+
+    #[inline]
+    pub fn set_cursor_hittest(&self, _: bool) -> Result<(), ()> {
+        Err(())
+    }
+
     #[inline]
     pub fn create_window_proxy(&self) -> WindowProxy {
-        WindowProxy
+        WindowProxy {
+            close_requested: self.close_requested.clone(),
+        }
     }
 
     #[inline]
@@ -283,6 +413,11 @@ impl Window {
         }
     }
 
+    #[inline]
+    pub fn drain_events(&self, events: &mut Vec<Event>) {
+        events.extend(self.poll_events());
+    }
+
     #[inline]
     pub fn platform_display(&self) -> *mut libc::c_void {
         unimplemented!();
@@ -293,6 +428,40 @@ impl Window {
         unimplemented!()
     }
 
+    /// Returns the `ANativeWindow` of the activity, or null while the app is suspended.
+    #[inline]
+    pub fn get_native_window(&self) -> *mut libc::c_void {
+        unsafe { android_glue::get_native_window() as *mut libc::c_void }
+    }
+
+    /// Shows or hides the soft keyboard of the activity.
+    ///
+    /// The NDK posts the request to the main thread of the activity, where the Java side of the
+    /// input method runs, so this can be called from any thread.
+    pub fn set_soft_keyboard_visible(&self, visible: bool) -> Result<(), ()> {
+        if self.activity.is_null() {
+            return Err(());
+        }
+
+        unsafe {
+            if visible {
+                ffi::ANativeActivity_showSoftInput(self.activity,
+                                                   ffi::ANATIVEACTIVITY_SHOW_SOFT_INPUT_FORCED);
+            } else {
+                ffi::ANativeActivity_hideSoftInput(self.activity, 0);
+            }
+        }
+
+        Ok(())
+    }
+
+    #[inline]
+    pub fn create_shared_context(&self, _: &GlAttributes<&Window>)
+                                 -> Result<SharedContext, CreationError>
+    {
+        Err(CreationError::NotSupported)
+    }
+
     #[inline]
     pub fn get_pixel_format(&self) -> PixelFormat {
         self.context.get_pixel_format()
@@ -307,8 +476,71 @@ impl Window {
     }
 
     #[inline]
-    pub fn set_cursor_state(&self, state: CursorState) -> Result<(), String> {
-        Ok(())
+    pub fn set_cursor_state(&self, state: CursorState) -> Result<(), CursorStateError> {
+        self.set_cursor_grab(state.grab_mode())
+    }
+
+    #[inline]
+    pub fn set_cursor_grab(&self, mode: CursorGrabMode) -> Result<(), CursorStateError> {
+        match mode {
+            CursorGrabMode::None => Ok(()),
+            _ => Err(CursorStateError::Unsupported),
+        }
+    }
+
+    #[inline]
+    pub fn confine_cursor(&self, rect: Option<Rect>) -> Result<(), CursorStateError> {
+        match rect {
+            None => Ok(()),
+            Some(_) => Err(CursorStateError::Unsupported),
+        }
+    }
+
+    #[inline]
+    pub fn set_ime_allowed(&self, _allowed: bool) {
+    }
+
+    #[inline]
+    pub fn set_ime_position(&self, _x: i32, _y: i32) {
+    }
+
+    #[inline]
+    pub fn set_raw_event_filter(&self, _: Option<RawEventFilter>) {
+    }
+
+    #[inline]
+    pub fn scancode_to_keycode(&self, _: ScanCode) -> Option<VirtualKeyCode> {
+        None
+    }
+
+    #[inline]
+    pub fn keycode_to_scancode(&self, _: VirtualKeyCode) -> Option<ScanCode> {
+        None
+    }
+
+    #[inline]
+    pub fn key_name(&self, _: ScanCode) -> Option<String> {
+        None
+    }
+
+    #[inline]
+    pub fn get_context_priority(&self) -> Option<ContextPriority> {
+        self.context.get_context_priority()
+    }
+
+    #[inline]
+    pub fn get_robustness(&self) -> Option<Robustness> {
+        Some(self.context.get_robustness())
+    }
+
+    #[inline]
+    pub fn get_available_monitors(&self) -> VecDeque<MonitorId> {
+        get_available_monitors()
+    }
+
+    #[inline]
+    pub fn get_current_monitor(&self) -> Option<MonitorId> {
+        get_primary_monitor()
     }
 
     #[inline]
@@ -317,7 +549,7 @@ impl Window {
     }
 
     #[inline]
-    pub fn set_cursor_position(&self, x: i32, y: i32) -> Result<(), ()> {
+    pub fn set_cursor_position(&self, x: f64, y: f64) -> Result<(), ()> {
         unimplemented!();
     }
 }
@@ -347,6 +579,11 @@ impl GlContext for Window {
         self.context.get_proc_address(addr)
     }
 
+    #[inline]
+    fn is_extension_supported(&self, name: &str) -> bool {
+        self.context.is_extension_supported(name)
+    }
+
     #[inline]
     fn swap_buffers(&self) -> Result<(), ContextError> {
         if !self.stopped.get() {
@@ -355,6 +592,19 @@ impl GlContext for Window {
         Err(ContextError::ContextLost)
     }
 
+    #[inline]
+    fn swap_buffers_with_damage(&self, rects: &[Rect]) -> Result<(), ContextError> {
+        if !self.stopped.get() {
+            return self.context.swap_buffers_with_damage(rects);
+        }
+        Err(ContextError::ContextLost)
+    }
+
+    #[inline]
+    fn buffer_age(&self) -> Option<u32> {
+        self.context.buffer_age()
+    }
+
     #[inline]
     fn get_api(&self) -> Api {
         self.context.get_api()
@@ -366,14 +616,58 @@ impl GlContext for Window {
     }
 }
 
+/// Windows can't have other contexts on this platform.
+pub enum SharedContext {}
+
+impl GlContext for SharedContext {
+    #[inline]
+    unsafe fn make_current(&self) -> Result<(), ContextError> {
+        match *self {}
+    }
+
+    #[inline]
+    fn is_current(&self) -> bool {
+        match *self {}
+    }
+
+    #[inline]
+    fn get_proc_address(&self, _: &str) -> *const () {
+        match *self {}
+    }
+
+    #[inline]
+    fn swap_buffers(&self) -> Result<(), ContextError> {
+        match *self {}
+    }
+
+    #[inline]
+    fn get_api(&self) -> Api {
+        match *self {}
+    }
+
+    #[inline]
+    fn get_pixel_format(&self) -> PixelFormat {
+        match *self {}
+    }
+}
+
 #[derive(Clone)]
-pub struct WindowProxy;
+pub struct WindowProxy {
+    close_requested: Arc<AtomicBool>,
+}
 
 impl WindowProxy {
     #[inline]
     pub fn wakeup_event_loop(&self) {
         android_glue::wake_event_loop();
     }
+
+    /// Queues a `Closed` event, which the events loop returns once it is woken up.
+    #[inline]
+    pub fn request_close(&self) {
+        self.close_requested.store(true, Ordering::SeqCst);
+        android_glue::wake_event_loop();
+    }
 }
 
 pub struct HeadlessContext(EglContext);
@@ -412,11 +706,26 @@ impl GlContext for HeadlessContext {
         self.0.get_proc_address(addr)
     }
 
+    #[inline]
+    fn is_extension_supported(&self, name: &str) -> bool {
+        self.0.is_extension_supported(name)
+    }
+
     #[inline]
     fn swap_buffers(&self) -> Result<(), ContextError> {
         self.0.swap_buffers()
     }
 
+    #[inline]
+    fn swap_buffers_with_damage(&self, rects: &[Rect]) -> Result<(), ContextError> {
+        self.0.swap_buffers_with_damage(rects)
+    }
+
+    #[inline]
+    fn buffer_age(&self) -> Option<u32> {
+        self.0.buffer_age()
+    }
+
     #[inline]
     fn get_api(&self) -> Api {
         self.0.get_api()
@@ -6,6 +6,8 @@ use api::osmesa::{OsMesaContext, OsMesaCreationError};
 
 use Api;
 use ContextError;
+use ContextPriority;
+use Robustness;
 use CreationError;
 use Event;
 use GlAttributes;
@@ -13,6 +15,9 @@ use GlContext;
 use PixelFormat;
 use PixelFormatRequirements;
 use CursorState;
+use CursorGrabMode;
+use CursorStateError;
+use Rect;
 use MouseCursor;
 use WindowAttributes;
 
@@ -37,6 +42,10 @@ impl WindowProxy {
     pub fn wakeup_event_loop(&self) {
         unimplemented!()
     }
+
+    #[inline]
+    pub fn request_close(&self) {
+    }
 }
 
 pub struct MonitorId;
@@ -46,8 +55,9 @@ pub fn get_available_monitors() -> VecDeque<MonitorId> {
     VecDeque::new()
 }
 #[inline]
-pub fn get_primary_monitor() -> MonitorId {
-    MonitorId
+pub fn get_primary_monitor() -> Option<MonitorId> {
+    // like `get_available_monitors`, the terminal is not a monitor
+    None
 }
 
 impl MonitorId {
@@ -163,6 +173,11 @@ impl Window {
         unimplemented!()
     }
 
+    #[inline]
+    pub fn get_outer_position(&self) -> Option<(i32, i32)> {
+        self.get_position()
+    }
+
     #[inline]
     pub fn set_position(&self, x: i32, y: i32) {
     }
@@ -182,6 +197,22 @@ impl Window {
         unimplemented!()
     }
 
+    #[inline]
+    pub fn set_min_dimensions(&self, _: Option<(u32, u32)>) {
+    }
+
+    #[inline]
+    pub fn set_max_dimensions(&self, _: Option<(u32, u32)>) {
+    }
+
+    #[inline]
+    pub fn set_aspect_ratio(&self, _: Option<(u32, u32)>) {
+    }
+
+    #[inline]
+    pub fn set_decorations(&self, _: bool) {
+    }
+
     #[inline]
     pub fn create_window_proxy(&self) -> WindowProxy {
         unimplemented!()
@@ -225,10 +256,44 @@ impl Window {
     }
 
     #[inline]
-    pub fn set_cursor_state(&self, state: CursorState) -> Result<(), String> {
+    pub fn set_cursor_state(&self, state: CursorState) -> Result<(), CursorStateError> {
+        Ok(())
+    }
+
+    #[inline]
+    pub fn set_cursor_grab(&self, _: CursorGrabMode) -> Result<(), CursorStateError> {
         Ok(())
     }
 
+    #[inline]
+    pub fn confine_cursor(&self, _: Option<Rect>) -> Result<(), CursorStateError> {
+        Ok(())
+    }
+
+    #[inline]
+    pub fn set_ime_allowed(&self, _allowed: bool) {
+    }
+
+    #[inline]
+    pub fn get_context_priority(&self) -> Option<ContextPriority> {
+        None
+    }
+
+    #[inline]
+    pub fn get_robustness(&self) -> Option<Robustness> {
+        None
+    }
+
+    #[inline]
+    pub fn get_available_monitors(&self) -> VecDeque<MonitorId> {
+        get_available_monitors()
+    }
+
+    #[inline]
+    pub fn get_current_monitor(&self) -> Option<MonitorId> {
+        get_primary_monitor()
+    }
+
     #[inline]
     pub fn hidpi_factor(&self) -> f32 {
         1.0
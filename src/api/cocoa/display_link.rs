@@ -0,0 +1,113 @@
+//! The `CVDisplayLink` of a window, see `os::macos::WindowBuilderExt::with_display_link`.
+
+use std::os::raw::c_void;
+use std::ptr;
+
+use cocoa::base::id;
+
+use Event;
+use native_monitor::NativeMonitorId;
+use super::WindowProxy;
+use super::monitor;
+
+type CVDisplayLinkRef = *mut c_void;
+type CVReturn = i32;
+type CVDisplayLinkOutputCallback = extern "C" fn(CVDisplayLinkRef, *const c_void, *const c_void,
+                                                  u64, *mut u64, *mut c_void) -> CVReturn;
+
+const K_CV_RETURN_SUCCESS: CVReturn = 0;
+
+#[link(name = "CoreVideo", kind = "framework")]
+extern "C" {
+    fn CVDisplayLinkCreateWithCGDisplay(display: u32, link: *mut CVDisplayLinkRef) -> CVReturn;
+    fn CVDisplayLinkSetOutputCallback(link: CVDisplayLinkRef,
+                                      callback: CVDisplayLinkOutputCallback,
+                                      user_info: *mut c_void) -> CVReturn;
+    fn CVDisplayLinkSetCurrentCGDisplay(link: CVDisplayLinkRef, display: u32) -> CVReturn;
+    fn CVDisplayLinkStart(link: CVDisplayLinkRef) -> CVReturn;
+    fn CVDisplayLinkStop(link: CVDisplayLinkRef) -> CVReturn;
+    fn CVDisplayLinkRelease(link: CVDisplayLinkRef);
+}
+
+/// A display link that pushes a `DisplayLinkTick` event to a window at each refresh of the
+/// screen of the window.
+pub struct DisplayLink {
+    link: CVDisplayLinkRef,
+    /// Given to the callback, which runs on a thread of CoreVideo.
+    #[allow(dead_code)]
+    proxy: Box<WindowProxy>,
+}
+
+impl DisplayLink {
+    /// Creates and starts a display link for the screen of `window`.
+    pub fn new(window: id, proxy: WindowProxy) -> Option<DisplayLink> {
+        let mut proxy = Box::new(proxy);
+        let display = window_display(window);
+
+        unsafe {
+            let mut link = ptr::null_mut();
+            if CVDisplayLinkCreateWithCGDisplay(display, &mut link) != K_CV_RETURN_SUCCESS {
+                return None;
+            }
+
+            let user_info = &mut *proxy as *mut WindowProxy as *mut c_void;
+            if CVDisplayLinkSetOutputCallback(link, output_callback, user_info) != K_CV_RETURN_SUCCESS ||
+               CVDisplayLinkStart(link) != K_CV_RETURN_SUCCESS
+            {
+                CVDisplayLinkRelease(link);
+                return None;
+            }
+
+            Some(DisplayLink { link: link, proxy: proxy })
+        }
+    }
+
+    /// Follows the refresh of the screen of `window`, after the window moved to another screen.
+    pub fn update_display(&self, window: id) {
+        unsafe { CVDisplayLinkSetCurrentCGDisplay(self.link, window_display(window)) };
+    }
+}
+
+impl Drop for DisplayLink {
+    fn drop(&mut self) {
+        unsafe {
+            // stopping waits for the callback that is running, if any, so the proxy can be freed
+            // afterwards
+            CVDisplayLinkStop(self.link);
+            CVDisplayLinkRelease(self.link);
+        }
+    }
+}
+
+/// Returns the `CGDirectDisplayID` of the screen of `window`.
+fn window_display(window: id) -> u32 {
+    // the monitors are always identified by their display on macOS, and the main one is
+    // returned for the windows that are offscreen
+    match monitor::get_window_monitor(window).map(|monitor| monitor.get_native_identifier()) {
+        Some(NativeMonitorId::Numeric(display)) => display,
+        _ => unreachable!(),
+    }
+}
+
+extern "C" fn output_callback(_: CVDisplayLinkRef, _: *const c_void, _: *const c_void, _: u64,
+                              _: *mut u64, user_info: *mut c_void) -> CVReturn
+{
+    let proxy = unsafe { &*(user_info as *const WindowProxy) };
+
+    // a single tick is kept in the queue, so that the ticks don't pile up when the application
+    // renders slower than the screen refreshes
+    {
+        let mut pending_events = proxy.pending_events.lock().unwrap();
+        let queued = pending_events.iter().any(|event| match *event {
+            Event::DisplayLinkTick => true,
+            _ => false,
+        });
+        if queued {
+            return K_CV_RETURN_SUCCESS;
+        }
+        pending_events.push_back(Event::DisplayLinkTick);
+    }
+
+    proxy.wakeup_event_loop();
+    K_CV_RETURN_SUCCESS
+}
@@ -1,4 +1,67 @@
 use events;
+use libc;
+use ScanCode;
+
+use core_foundation::base::{CFType, CFTypeRef, TCFType};
+use core_foundation::data::{CFData, CFDataRef};
+use core_foundation::string::CFStringRef;
+
+#[link(name = "Carbon", kind = "framework")]
+extern "C" {
+    static kTISPropertyUnicodeKeyLayoutData: CFStringRef;
+    fn TISCopyCurrentKeyboardLayoutInputSource() -> CFTypeRef;
+    fn TISGetInputSourceProperty(source: CFTypeRef, key: CFStringRef) -> CFDataRef;
+    fn LMGetKbdType() -> u8;
+    fn UCKeyTranslate(layout: *const u8, key_code: u16, key_action: u16,
+                      modifier_key_state: u32, keyboard_type: u32, options: u32,
+                      dead_key_state: *mut u32, max_length: libc::c_ulong,
+                      actual_length: *mut libc::c_ulong, unicode_string: *mut u16) -> i32;
+}
+
+const K_UC_KEY_ACTION_DISPLAY: u16 = 3;
+const K_UC_KEY_TRANSLATE_NO_DEAD_KEYS_MASK: u32 = 1;
+
+/// Returns the scan code of the key at the position of a virtual key on an ANSI keyboard.
+pub fn keycode_to_scancode(keycode: events::VirtualKeyCode) -> Option<ScanCode> {
+    (0 .. 128).find(|&code| vkeycode_to_element(code) == Some(keycode)).map(|code| code as ScanCode)
+}
+
+/// Returns the characters that a key produces without modifiers with the current keyboard
+/// layout, or `None` if it doesn't produce any printable ones, like the modifiers and Return.
+pub fn key_name(scancode: ScanCode) -> Option<String> {
+    unsafe {
+        let source = TISCopyCurrentKeyboardLayoutInputSource();
+        if source.is_null() {
+            return None;
+        }
+        let source = CFType::wrap_under_create_rule(source);
+
+        // the input methods, for Japanese for example, have no layout of their own
+        let layout = TISGetInputSourceProperty(source.as_CFTypeRef(),
+                                               kTISPropertyUnicodeKeyLayoutData);
+        if layout.is_null() {
+            return None;
+        }
+        let layout = CFData::wrap_under_get_rule(layout);
+
+        let mut dead_key_state = 0;
+        let mut chars = [0u16; 8];
+        let mut len = 0;
+        let status = UCKeyTranslate(layout.bytes().as_ptr(), scancode as u16,
+                                    K_UC_KEY_ACTION_DISPLAY, 0, LMGetKbdType() as u32,
+                                    K_UC_KEY_TRANSLATE_NO_DEAD_KEYS_MASK, &mut dead_key_state,
+                                    chars.len() as libc::c_ulong, &mut len, chars.as_mut_ptr());
+        if status != 0 {
+            return None;
+        }
+        let name = String::from_utf16_lossy(&chars[.. len as usize]);
+        if name.chars().all(char::is_control) {
+            None
+        } else {
+            Some(name)
+        }
+    }
+}
 
 pub fn vkeycode_to_element(code: u16) -> Option<events::VirtualKeyCode> {
     Some(match code {
@@ -5,19 +5,36 @@ use GlAttributes;
 use GlContext;
 use PixelFormatRequirements;
 
-use core_foundation::base::TCFType;
-use core_foundation::string::CFString;
-use core_foundation::bundle::{CFBundleGetBundleWithIdentifier, CFBundleGetFunctionPointerForName};
+use std::os::raw::c_void;
+
 use cocoa::base::{id, nil};
 use cocoa::appkit::*;
+use core_foundation::base::{CFType, CFTypeRef, TCFType};
+use core_foundation::dictionary::{CFDictionary, CFDictionaryRef};
+use core_foundation::number::CFNumber;
+use core_foundation::string::{CFString, CFStringRef};
 use PixelFormat;
 use api::cocoa::helpers;
 
+#[link(name = "IOSurface", kind = "framework")]
+extern "C" {
+    static kIOSurfaceWidth: CFStringRef;
+    static kIOSurfaceHeight: CFStringRef;
+    static kIOSurfaceBytesPerElement: CFStringRef;
+    static kIOSurfacePixelFormat: CFStringRef;
+
+    fn IOSurfaceCreate(properties: CFDictionaryRef) -> CFTypeRef;
+}
+
 #[derive(Clone, Default)]
 pub struct PlatformSpecificHeadlessBuilderAttributes;
 
 pub struct HeadlessContext {
     context: id,
+    pixel_format: PixelFormat,
+    /// The surface that the context renders to if it has been created by
+    /// `Window::create_io_surface_context`
+    io_surface: Option<CFType>,
 }
 
 impl HeadlessContext {
@@ -27,7 +44,7 @@ impl HeadlessContext {
         opengl: &GlAttributes<&HeadlessContext>,
         _: &PlatformSpecificHeadlessBuilderAttributes,
     ) -> Result<HeadlessContext, CreationError> {
-        let context = unsafe {
+        let (context, pixel_format) = unsafe {
 
             let attributes = try!(helpers::build_nsattributes(pf_reqs, opengl));
 
@@ -39,43 +56,89 @@ impl HeadlessContext {
             if context == nil {
                 return Err(OsError(format!("Could not create the rendering context")));
             }
-            context
+            // the context has no view, and thus no default framebuffer that could be sRGB
+            (context, helpers::get_pixel_format(pixelformat, context))
         };
+        try!(helpers::check_zero_sizes(pf_reqs, &pixel_format));
 
         let headless = HeadlessContext {
             context: context,
+            pixel_format: pixel_format,
+            io_surface: None,
         };
 
         Ok(headless)
     }
+
+    /// Creates a context with the given pixel format that shares its objects with `share`,
+    /// and an `IOSurface` of the given dimensions for it to render to.
+    pub unsafe fn with_io_surface(pixelformat: id, pixel_format: PixelFormat, share: id,
+                                  (width, height): (u32, u32))
+                                  -> Result<HeadlessContext, CreationError>
+    {
+        let properties = CFDictionary::from_CFType_pairs(&[
+            (CFString::wrap_under_get_rule(kIOSurfaceWidth).as_CFType(),
+             CFNumber::from(width as i32).as_CFType()),
+            (CFString::wrap_under_get_rule(kIOSurfaceHeight).as_CFType(),
+             CFNumber::from(height as i32).as_CFType()),
+            (CFString::wrap_under_get_rule(kIOSurfaceBytesPerElement).as_CFType(),
+             CFNumber::from(4).as_CFType()),
+            // `'BGRA'`, the layout that the GPUs sample without conversion
+            (CFString::wrap_under_get_rule(kIOSurfacePixelFormat).as_CFType(),
+             CFNumber::from(0x42475241).as_CFType()),
+        ]);
+
+        let io_surface = IOSurfaceCreate(properties.as_concrete_TypeRef());
+        if io_surface.is_null() {
+            return Err(OsError(format!("Could not create the IOSurface")));
+        }
+        let io_surface = CFType::wrap_under_create_rule(io_surface);
+
+        // fails if the pixel format isn't compatible with the one of the shared context
+        let context = NSOpenGLContext::alloc(nil).initWithFormat_shareContext_(pixelformat, share);
+        if context == nil {
+            return Err(CreationError::NoAvailablePixelFormat);
+        }
+
+        Ok(HeadlessContext {
+            context: context,
+            pixel_format: pixel_format,
+            io_surface: Some(io_surface),
+        })
+    }
+
+    /// Returns the `IOSurfaceRef` that the context renders to. See `os::macos::HeadlessContextExt`.
+    #[inline]
+    pub fn get_io_surface(&self) -> Option<*mut c_void> {
+        self.io_surface.as_ref().map(|io_surface| io_surface.as_CFTypeRef() as *mut c_void)
+    }
+
+    /// Returns the `CGLContextObj` of the context. See `os::macos::ContextExt`.
+    #[inline]
+    pub fn get_cgl_context(&self) -> *mut c_void {
+        unsafe { self.context.CGLContextObj() as *mut c_void }
+    }
 }
 
 impl GlContext for HeadlessContext {
+    #[inline]
     unsafe fn make_current(&self) -> Result<(), ContextError> {
-        self.context.makeCurrentContext();
-        Ok(())
+        helpers::make_current(self.context)
     }
 
     #[inline]
     fn is_current(&self) -> bool {
-        unimplemented!()
+        helpers::is_current(self.context)
     }
 
     #[inline]
-    fn get_proc_address(&self, _addr: &str) -> *const () {
-        let symbol_name: CFString = _addr.parse().unwrap();
-        let framework_name: CFString = "com.apple.opengl".parse().unwrap();
-        let framework = unsafe {
-            CFBundleGetBundleWithIdentifier(framework_name.as_concrete_TypeRef())
-        };
-        let symbol = unsafe {
-            CFBundleGetFunctionPointerForName(framework, symbol_name.as_concrete_TypeRef())
-        };
-        symbol as *const ()
+    fn get_proc_address(&self, addr: &str) -> *const () {
+        helpers::get_proc_address(addr)
     }
 
     #[inline]
     fn swap_buffers(&self) -> Result<(), ContextError> {
+        // `flushBuffer` presents the back buffer, see the comment in `helpers::swap_buffers`
         unsafe { self.context.flushBuffer(); }
         Ok(())
     }
@@ -87,7 +150,7 @@ impl GlContext for HeadlessContext {
 
     #[inline]
     fn get_pixel_format(&self) -> PixelFormat {
-        unimplemented!();
+        self.pixel_format.clone()
     }
 }
 
@@ -1,11 +1,29 @@
 
+use ContextError;
 use CreationError;
 use GlAttributes;
 use GlProfile;
 use GlRequest;
+use PixelFormat;
 use PixelFormatRequirements;
 use ReleaseBehavior;
 use cocoa::appkit::*;
+use cocoa::base::{id, nil};
+use cocoa::foundation::{NSAutoreleasePool, NSPoint, NSRect, NSSize, NSString, NSUInteger};
+use core_foundation::base::TCFType;
+use core_foundation::string::CFString;
+use core_foundation::bundle::{CFBundleGetBundleWithIdentifier, CFBundleGetFunctionPointerForName};
+use cgl::CGLGetCurrentContext;
+use objc::runtime::{BOOL, Class, NO, YES};
+use std::collections::hash_map::DefaultHasher;
+use std::hash::{Hash, Hasher};
+use std::io;
+use std::mem;
+use std::os::raw::c_void;
+use std::str::FromStr;
+use std::sync::atomic::{AtomicUsize, ATOMIC_USIZE_INIT, Ordering};
+
+use super::IdRef;
 
 pub fn build_nsattributes<T>(pf_reqs: &PixelFormatRequirements, opengl: &GlAttributes<&T>)
     -> Result<Vec<u32>, CreationError> {
@@ -49,7 +67,6 @@ pub fn build_nsattributes<T>(pf_reqs: &PixelFormatRequirements, opengl: &GlAttri
     // TODO: handle hardware_accelerated parameter of pf_reqs
 
     let mut attributes = vec![
-        NSOpenGLPFADoubleBuffer as u32,
         NSOpenGLPFAClosestPolicy as u32,
         NSOpenGLPFAColorSize as u32, color_depth as u32,
         NSOpenGLPFAAlphaSize as u32, alpha_depth as u32,
@@ -67,8 +84,8 @@ pub fn build_nsattributes<T>(pf_reqs: &PixelFormatRequirements, opengl: &GlAttri
         unimplemented!();   // TODO:
     }
 
-    if pf_reqs.double_buffer == Some(false) {
-        unimplemented!();   // TODO:
+    if pf_reqs.double_buffer != Some(false) {
+        attributes.push(NSOpenGLPFADoubleBuffer as u32);
     }
 
     if pf_reqs.float_color_buffer {
@@ -86,3 +103,294 @@ pub fn build_nsattributes<T>(pf_reqs: &PixelFormatRequirements, opengl: &GlAttri
 
     Ok(attributes)
 }
+
+/// Returns an error if `pixel_format` has a depth or stencil buffer while a size of 0 was
+/// requested, since the closest policy may choose a format with one if none exists without.
+pub fn check_zero_sizes(pf_reqs: &PixelFormatRequirements, pixel_format: &PixelFormat)
+                        -> Result<(), CreationError>
+{
+    if (pf_reqs.depth_bits == Some(0) && pixel_format.depth_bits != 0) ||
+       (pf_reqs.stencil_bits == Some(0) && pixel_format.stencil_bits != 0)
+    {
+        return Err(CreationError::NoAvailablePixelFormat);
+    }
+    Ok(())
+}
+
+/// Returns the address of an OpenGL function, which are all exported by the OpenGL framework.
+pub fn get_proc_address(addr: &str) -> *const () {
+    let symbol_name: CFString = FromStr::from_str(addr).unwrap();
+    let framework_name: CFString = FromStr::from_str("com.apple.opengl").unwrap();
+    let framework = unsafe {
+        CFBundleGetBundleWithIdentifier(framework_name.as_concrete_TypeRef())
+    };
+    let symbol = unsafe {
+        CFBundleGetFunctionPointerForName(framework, symbol_name.as_concrete_TypeRef())
+    };
+    symbol as *const _
+}
+
+/// Returns the pixel format of a context that was created with `pixelformat`.
+///
+/// The context must already be attached to its view, since a context without a view has no
+/// default framebuffer.
+pub unsafe fn get_pixel_format(pixelformat: id, context: id) -> PixelFormat {
+    let get_attr = |attrib: NSOpenGLPixelFormatAttribute| -> i32 {
+        let mut value = 0;
+
+        NSOpenGLPixelFormat::getValues_forAttribute_forVirtualScreen_(
+            pixelformat,
+            &mut value,
+            attrib,
+            NSOpenGLContext::currentVirtualScreen(context));
+
+        value
+    };
+
+    let double_buffer = get_attr(NSOpenGLPFADoubleBuffer) != 0;
+
+    // pixel formats have no identifier, but two formats with the same attributes are identical
+    let native_id = {
+        let mut hasher = DefaultHasher::new();
+        for &attrib in [NSOpenGLPFAAccelerated, NSOpenGLPFAColorSize, NSOpenGLPFAAlphaSize,
+                        NSOpenGLPFADepthSize, NSOpenGLPFAStencilSize, NSOpenGLPFAStereo,
+                        NSOpenGLPFADoubleBuffer, NSOpenGLPFAMultisample, NSOpenGLPFASamples,
+                        NSOpenGLPFAColorFloat, NSOpenGLPFARendererID, NSOpenGLPFAOpenGLProfile]
+                           .iter()
+        {
+            get_attr(attrib).hash(&mut hasher);
+        }
+        hasher.finish()
+    };
+
+    PixelFormat {
+        hardware_accelerated: get_attr(NSOpenGLPFAAccelerated) != 0,
+        color_bits: (get_attr(NSOpenGLPFAColorSize) - get_attr(NSOpenGLPFAAlphaSize)) as u8,
+        alpha_bits: get_attr(NSOpenGLPFAAlphaSize) as u8,
+        depth_bits: get_attr(NSOpenGLPFADepthSize) as u8,
+        stencil_bits: get_attr(NSOpenGLPFAStencilSize) as u8,
+        stereoscopy: get_attr(NSOpenGLPFAStereo) != 0,
+        double_buffer: double_buffer,
+        multisampling: if get_attr(NSOpenGLPFAMultisample) > 0 {
+            Some(get_attr(NSOpenGLPFASamples) as u16)
+        } else {
+            None
+        },
+        srgb: is_default_framebuffer_srgb(context, double_buffer),
+        aux_buffers: get_attr(NSOpenGLPFAAuxBuffers) as u8,
+        accum_bits: get_attr(NSOpenGLPFAAccumSize) as u8,
+        native_id: Some(native_id),
+    }
+}
+
+/// Returns true if enabling `GL_FRAMEBUFFER_SRGB` converts the colors written to the default
+/// framebuffer of the context to sRGB.
+///
+/// `NSOpenGLPixelFormat` has no attribute for this, so the context is made current temporarily
+/// to ask OpenGL.
+unsafe fn is_default_framebuffer_srgb(context: id, double_buffer: bool) -> bool {
+    const GL_FRAMEBUFFER: u32 = 0x8D40;
+    const GL_FRONT_LEFT: u32 = 0x0400;
+    const GL_BACK_LEFT: u32 = 0x0402;
+    const GL_FRAMEBUFFER_ATTACHMENT_COLOR_ENCODING: u32 = 0x8210;
+    const GL_SRGB: i32 = 0x8C40;
+    const GL_FRAMEBUFFER_SRGB_CAPABLE_EXT: u32 = 0x8DBA;
+    const GL_NO_ERROR: u32 = 0;
+
+    let get_error = get_proc_address("glGetError");
+    let get_integerv = get_proc_address("glGetIntegerv");
+    let get_attachment_parameteriv = get_proc_address("glGetFramebufferAttachmentParameteriv");
+    if get_error.is_null() || get_integerv.is_null() {
+        return false;
+    }
+    let get_error: extern "system" fn() -> u32 = mem::transmute(get_error);
+    let get_integerv: extern "system" fn(u32, *mut i32) = mem::transmute(get_integerv);
+
+    let class = Class::get("NSOpenGLContext").unwrap();
+    let previous: id = msg_send![class, currentContext];
+    let _: () = msg_send![context, makeCurrentContext];
+
+    // the errors of the previous commands must not be mistaken for the errors of the queries
+    for _ in 0 .. 16 {
+        if get_error() == GL_NO_ERROR {
+            break;
+        }
+    }
+
+    // OpenGL 3.0 and later describe the encoding of the default framebuffer
+    let mut encoding = 0;
+    let srgb = if !get_attachment_parameteriv.is_null() {
+        let get_attachment_parameteriv: extern "system" fn(u32, u32, u32, *mut i32) =
+            mem::transmute(get_attachment_parameteriv);
+        get_attachment_parameteriv(GL_FRAMEBUFFER,
+                                   if double_buffer { GL_BACK_LEFT } else { GL_FRONT_LEFT },
+                                   GL_FRAMEBUFFER_ATTACHMENT_COLOR_ENCODING, &mut encoding);
+        if get_error() == GL_NO_ERROR { Some(encoding == GL_SRGB) } else { None }
+    } else {
+        None
+    };
+
+    // legacy contexts only have `EXT_framebuffer_sRGB`
+    let srgb = srgb.unwrap_or_else(|| {
+        let mut capable = 0;
+        get_integerv(GL_FRAMEBUFFER_SRGB_CAPABLE_EXT, &mut capable);
+        get_error() == GL_NO_ERROR && capable != 0
+    });
+
+    if previous == nil {
+        let _: () = msg_send![class, clearCurrentContext];
+    } else {
+        let _: () = msg_send![previous, makeCurrentContext];
+    }
+
+    srgb
+}
+
+/// Returns the frame of the primary screen, the one with the menu bar, in Cocoa's coordinates.
+///
+/// Cocoa's origin is the bottom-left hand corner of this screen and its y axis goes up, while
+/// glutin's origin is its top-left hand corner and its y axis goes down, like on Windows. The
+/// screens that are on the left or above the primary screen have negative coordinates.
+unsafe fn get_primary_screen_frame() -> NSRect {
+    let screens = NSScreen::screens(nil);
+    let count: NSUInteger = msg_send![screens, count];
+    if count == 0 {
+        return NSRect::new(NSPoint::new(0., 0.), NSSize::new(0., 0.));
+    }
+
+    let screen: id = msg_send![screens, objectAtIndex:0 as NSUInteger];
+    NSScreen::frame(screen)
+}
+
+/// Converts a rectangle in Cocoa's coordinates to the position of its top-left hand corner
+/// relative to the top-left hand corner of the primary screen.
+pub unsafe fn cocoa_to_top_left(rect: NSRect) -> (i32, i32) {
+    let primary = get_primary_screen_frame();
+    let x = rect.origin.x - primary.origin.x;
+    let y = (primary.origin.y + primary.size.height) - (rect.origin.y + rect.size.height);
+    (x.round() as i32, y.round() as i32)
+}
+
+/// Converts the position of the top-left hand corner of a rectangle of height `height`,
+/// relative to the top-left hand corner of the primary screen, to the origin of the rectangle in
+/// Cocoa's coordinates.
+///
+/// This is the inverse of `cocoa_to_top_left`.
+pub unsafe fn top_left_to_cocoa(x: i32, y: i32, height: f64) -> NSPoint {
+    let primary = get_primary_screen_frame();
+    NSPoint::new(primary.origin.x + x as f64,
+                 (primary.origin.y + primary.size.height) - (y as f64 + height))
+}
+
+/// The number of windows that have disabled the press and hold popup.
+static PRESS_AND_HOLD_DISABLERS: AtomicUsize = ATOMIC_USIZE_INIT;
+
+/// The value of `ApplePressAndHoldEnabled` in the defaults of the app before the first window
+/// disabled the popup: 0 if it wasn't set, 1 for `NO` and 2 for `YES`.
+static PRESS_AND_HOLD_PREVIOUS: AtomicUsize = ATOMIC_USIZE_INIT;
+
+/// Sets `ApplePressAndHoldEnabled` to `NO` in the defaults of the app, so that holding a key
+/// repeats it instead of showing the accent popup. Must be balanced by `restore_press_and_hold`.
+pub unsafe fn disable_press_and_hold() {
+    if PRESS_AND_HOLD_DISABLERS.fetch_add(1, Ordering::SeqCst) != 0 {
+        return;
+    }
+
+    let defaults: id = msg_send![Class::get("NSUserDefaults").unwrap(), standardUserDefaults];
+    let key = IdRef::new(NSString::alloc(nil).init_str("ApplePressAndHoldEnabled"));
+
+    let value: id = msg_send![defaults, objectForKey:*key];
+    let previous = if value == nil {
+        0
+    } else {
+        let enabled: BOOL = msg_send![value, boolValue];
+        if enabled == NO { 1 } else { 2 }
+    };
+    PRESS_AND_HOLD_PREVIOUS.store(previous, Ordering::SeqCst);
+
+    let _: () = msg_send![defaults, setBool:NO forKey:*key];
+}
+
+/// Restores the value of `ApplePressAndHoldEnabled` once the last window that disabled the
+/// popup is destroyed, since the defaults of the app persist after it exits.
+pub unsafe fn restore_press_and_hold() {
+    if PRESS_AND_HOLD_DISABLERS.fetch_sub(1, Ordering::SeqCst) != 1 {
+        return;
+    }
+
+    let defaults: id = msg_send![Class::get("NSUserDefaults").unwrap(), standardUserDefaults];
+    let key = IdRef::new(NSString::alloc(nil).init_str("ApplePressAndHoldEnabled"));
+
+    match PRESS_AND_HOLD_PREVIOUS.load(Ordering::SeqCst) {
+        0 => { let _: () = msg_send![defaults, removeObjectForKey:*key]; },
+        1 => { let _: () = msg_send![defaults, setBool:NO forKey:*key]; },
+        _ => { let _: () = msg_send![defaults, setBool:YES forKey:*key]; },
+    }
+}
+
+/// Makes `context` current, and checks that CGL agrees.
+///
+/// `makeCurrentContext` doesn't report any error, so the context is made not current again if
+/// it isn't the current CGL context afterwards, so that `is_current` stays accurate.
+pub unsafe fn make_current(context: id) -> Result<(), ContextError> {
+    context.makeCurrentContext();
+
+    if CGLGetCurrentContext() as *mut c_void != context.CGLContextObj() as *mut c_void {
+        NSOpenGLContext::clearCurrentContext(nil);
+        return Err(ContextError::IoError(io::Error::new(io::ErrorKind::Other,
+                                         "the context could not be made current")));
+    }
+
+    Ok(())
+}
+
+/// Makes `context` current after checking that its view is still in a window.
+///
+/// The context keeps working without a drawable once the window is closed, but everything that
+/// is drawn is lost.
+pub unsafe fn make_current_on_view(context: id) -> Result<(), ContextError> {
+    let view: id = msg_send![context, view];
+    let window: id = if view != nil { msg_send![view, window] } else { nil };
+    if window == nil {
+        NSOpenGLContext::clearCurrentContext(nil);
+        return Err(ContextError::IoError(io::Error::new(io::ErrorKind::Other,
+                   "the view of the context is no longer in a window")));
+    }
+
+    let _: () = msg_send![context, update];
+    make_current(context)
+}
+
+/// Presents the back buffer of `context`, or only flushes its commands if it has a single buffer
+/// since there is nothing to present then.
+///
+/// The context must be current.
+pub unsafe fn swap_buffers(context: id, double_buffer: bool) {
+    if !double_buffer {
+        let flush = get_proc_address("glFlush");
+        if !flush.is_null() {
+            let flush: extern "system" fn() = mem::transmute(flush);
+            flush();
+        }
+        return;
+    }
+
+    let pool = NSAutoreleasePool::new(nil);
+    // despite its name, `flushBuffer` presents the back buffer (it's the equivalent of
+    // `CGLFlushDrawable`), it isn't a `glFlush`; see `GlContext::flush` for the latter
+    context.flushBuffer();
+    let _: () = msg_send![pool, release];
+}
+
+/// Returns true if `context` is the current context of the thread.
+pub fn is_current(context: id) -> bool {
+    unsafe {
+        let current = NSOpenGLContext::currentContext(nil);
+        if current != nil {
+            let is_equal: BOOL = msg_send![current, isEqual:context];
+            is_equal != NO
+        } else {
+            false
+        }
+    }
+}
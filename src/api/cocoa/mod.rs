@@ -1,15 +1,22 @@
 #![cfg(target_os = "macos")]
 
-use {CreationError, Event, MouseCursor, CursorState};
+use {CreationError, Event, MouseCursor, CursorState, CursorGrabMode, CursorStateError};
+use ContentProtectionError;
 use CreationError::OsError;
 use libc;
 
 use ContextError;
+use ContextPriority;
 use GlAttributes;
 use GlContext;
 use PixelFormat;
 use PixelFormatRequirements;
+use RawEvent;
+use RawEventFilter;
+use Rect;
+use ResizeEdge;
 use Robustness;
+use ScanCode;
 use WindowAttributes;
 use native_monitor::NativeMonitorId;
 use os::macos::ActivationPolicy;
@@ -21,25 +28,22 @@ use cgl::{CGLEnable, kCGLCECrashOnRemovedFunctions};
 
 use cocoa::base::{id, nil};
 use cocoa::foundation::{NSAutoreleasePool, NSArray, NSDate, NSDefaultRunLoopMode, NSPoint, NSRect};
-use cocoa::foundation::{NSRunLoop, NSSize, NSString, NSUInteger};
+use cocoa::foundation::{NSInteger, NSRunLoop, NSSize, NSString, NSUInteger};
 use cocoa::appkit;
 use cocoa::appkit::*;
 use cocoa::appkit::NSEventSubtype::*;
 
-use core_foundation::base::TCFType;
-use core_foundation::string::CFString;
-use core_foundation::bundle::CFBundleGetBundleWithIdentifier;
-use core_foundation::bundle::{CFBundleGetFunctionPointerForName};
 
 use core_graphics::geometry::{CG_ZERO_POINT, CGRect, CGSize};
-use core_graphics::display::{CGAssociateMouseAndMouseCursorPosition, CGMainDisplayID, CGDisplayPixelsHigh, CGWarpMouseCursorPosition};
+use core_graphics::display::{CGAssociateMouseAndMouseCursorPosition, CGWarpMouseCursorPosition};
 use core_graphics::private::{CGSRegion, CGSSurface};
 
+use std::cmp;
+use std::mem;
 use std::ffi::CStr;
 use std::collections::VecDeque;
-use std::str::FromStr;
 use std::str::from_utf8;
-use std::sync::Mutex;
+use std::sync::{Arc, Mutex};
 use std::ops::Deref;
 use std::path::PathBuf;
 use std::env;
@@ -48,10 +52,16 @@ use events::ElementState;
 use events::{self, MouseButton, TouchPhase};
 
 pub use self::monitor::{MonitorId, get_available_monitors, get_primary_monitor};
+pub use self::monitor::monitor_from_point;
+pub use self::helpers::{cocoa_to_top_left, top_left_to_cocoa};
 pub use self::headless::HeadlessContext;
 pub use self::headless::PlatformSpecificHeadlessBuilderAttributes;
 
+use self::display_link::DisplayLink;
+use api::pressed_inputs::PressedInputs;
+
 mod monitor;
+mod display_link;
 mod event;
 mod headless;
 mod helpers;
@@ -75,10 +85,78 @@ struct DelegateState {
     window: IdRef,
     resize_handler: Option<fn(u32, u32)>,
     visible: bool,
+    /// Whether the window should be miniaturized once it is shown for the first time
+    minimized: bool,
     decorations: bool,
+    /// Whether the user is currently resizing the window
+    in_live_resize: bool,
+    /// The last `backingScaleFactor` of the window, to only report the changes
+    hidpi_factor: f32,
+    /// The last `currentVirtualScreen` of the context, which identifies its renderer
+    virtual_screen: i32,
+    /// The display link that sends the `DisplayLinkTick` events, if enabled
+    display_link: Option<DisplayLink>,
 
     /// Events that have been retreived with XLib but not dispatched with iterators yet
-    pending_events: Mutex<VecDeque<Event>>,
+    pending_events: Arc<Mutex<VecDeque<Event>>>,
+}
+
+impl DelegateState {
+    /// Updates the context after the view changed, and reports a `RendererChanged` event if it
+    /// moved to another GPU.
+    fn update_context(&mut self) {
+        unsafe {
+            let _: () = msg_send![*self.context, update];
+
+            let virtual_screen = NSOpenGLContext::currentVirtualScreen(*self.context) as i32;
+            if virtual_screen != self.virtual_screen {
+                self.virtual_screen = virtual_screen;
+                self.pending_events.lock().unwrap().push_back(Event::RendererChanged);
+            }
+        }
+    }
+
+    /// Returns the size of the view in pixels.
+    fn get_view_size(&self) -> (u32, u32) {
+        unsafe {
+            let rect = NSView::frame(*self.view);
+            let scale_factor = NSWindow::backingScaleFactor(*self.window) as f32;
+            // also given to the resize callback, which doesn't go through `window.rs`
+            (cmp::max((scale_factor * rect.size.width as f32) as u32, 1),
+             cmp::max((scale_factor * rect.size.height as f32) as u32, 1))
+        }
+    }
+}
+
+#[link(name = "AppKit", kind = "framework")]
+extern "C" {
+    static NSTextInputContextKeyboardSelectionDidChangeNotification: id;
+}
+
+/// Called before and after the configuration of a display changes.
+type CGDisplayReconfigurationCallBack = extern "C" fn(u32, u32, *mut libc::c_void);
+
+/// Set in the flags of the first call of a `CGDisplayReconfigurationCallBack`, before the change.
+const K_CG_DISPLAY_BEGIN_CONFIGURATION_FLAG: u32 = 1 << 0;
+
+#[link(name = "CoreGraphics", kind = "framework")]
+extern "C" {
+    fn CGDisplayRegisterReconfigurationCallback(callback: CGDisplayReconfigurationCallBack,
+                                                user_info: *mut libc::c_void) -> i32;
+    fn CGDisplayRemoveReconfigurationCallback(callback: CGDisplayReconfigurationCallBack,
+                                              user_info: *mut libc::c_void) -> i32;
+}
+
+/// Updates the context when the displays are reconfigured, since the system may switch between
+/// the integrated and discrete GPUs without moving the window or changing its backing.
+extern "C" fn display_reconfigured(_: u32, flags: u32, user_info: *mut libc::c_void) {
+    if flags & K_CG_DISPLAY_BEGIN_CONFIGURATION_FLAG != 0 {
+        return;
+    }
+
+    // called by the run loop of the main thread, like the methods of the window delegate
+    let state = unsafe { &mut *(user_info as *mut DelegateState) };
+    state.update_context();
 }
 
 struct WindowDelegate {
@@ -106,17 +184,95 @@ impl WindowDelegate {
                 let state: *mut c_void = *this.get_ivar("glutinState");
                 let state = &mut *(state as *mut DelegateState);
 
-                let _: () = msg_send![*state.context, update];
+                state.update_context();
+
+                let (width, height) = state.get_view_size();
 
+                // this is the only way to redraw during a live resize, since the events loop is
+                // blocked by AppKit until the user releases the mouse
                 if let Some(handler) = state.resize_handler {
-                    let rect = NSView::frame(*state.view);
-                    let scale_factor = NSWindow::backingScaleFactor(*state.window) as f32;
-                    (handler)((scale_factor * rect.size.width as f32) as u32,
-                              (scale_factor * rect.size.height as f32) as u32);
+                    (handler)(width, height);
+                }
+
+                // the intermediate sizes of a live resize would all be delivered at once at the
+                // end, so only the final size is reported (see `window_did_end_live_resize`)
+                if !state.in_live_resize {
+                    state.pending_events.lock().unwrap().push_back(Event::Resized(width, height));
                 }
             }
         }
 
+        extern fn window_did_move(this: &Object, _: Sel, _: id) {
+            unsafe {
+                let state: *mut c_void = *this.get_ivar("glutinState");
+                let state = &mut *(state as *mut DelegateState);
+
+                // AppKit blocks the events loop while the user drags the window, so this is only
+                // called once the window has reached its final position
+                let (x, y) = helpers::cocoa_to_top_left(NSWindow::frame(*state.window));
+                state.pending_events.lock().unwrap().push_back(Event::Moved(x, y));
+            }
+        }
+
+        extern fn keyboard_selection_did_change(this: &Object, _: Sel, _: id) {
+            unsafe {
+                let state: *mut c_void = *this.get_ivar("glutinState");
+                let state = &mut *(state as *mut DelegateState);
+                state.pending_events.lock().unwrap().push_back(Event::KeyboardLayoutChanged);
+            }
+        }
+
+        extern fn window_will_start_live_resize(this: &Object, _: Sel, _: id) {
+            unsafe {
+                let state: *mut c_void = *this.get_ivar("glutinState");
+                let state = &mut *(state as *mut DelegateState);
+                state.in_live_resize = true;
+            }
+        }
+
+        extern fn window_did_end_live_resize(this: &Object, _: Sel, _: id) {
+            unsafe {
+                let state: *mut c_void = *this.get_ivar("glutinState");
+                let state = &mut *(state as *mut DelegateState);
+                state.in_live_resize = false;
+
+                state.update_context();
+
+                let (width, height) = state.get_view_size();
+                let mut pending_events = state.pending_events.lock().unwrap();
+                pending_events.push_back(Event::Resized(width, height));
+                pending_events.push_back(Event::Refresh);
+            }
+        }
+
+        /// Called when the window moves to another screen or when the resolution of its screen
+        /// changes, which may both change the backing scale factor and the GPU that renders the
+        /// context.
+        extern fn window_did_change_backing(this: &Object, _: Sel, _: id) {
+            unsafe {
+                let state: *mut c_void = *this.get_ivar("glutinState");
+                let state = &mut *(state as *mut DelegateState);
+
+                state.update_context();
+
+                if let Some(ref display_link) = state.display_link {
+                    display_link.update_display(*state.window);
+                }
+
+                let factor = NSWindow::backingScaleFactor(*state.window) as f32;
+                if factor == state.hidpi_factor {
+                    return;
+                }
+                state.hidpi_factor = factor;
+
+                // the size of the view in points doesn't change, but its size in pixels does
+                let (width, height) = state.get_view_size();
+                let mut pending_events = state.pending_events.lock().unwrap();
+                pending_events.push_back(Event::HiDpiFactorChanged(factor));
+                pending_events.push_back(Event::Resized(width, height));
+            }
+        }
+
         extern fn window_did_become_key(this: &Object, _: Sel, _: id) {
             unsafe {
                 // TODO: center the cursor if the window had mouse grab when it
@@ -148,6 +304,11 @@ impl WindowDelegate {
                 window.makeKeyWindow();
                 if (*state).visible {
                     window.orderFrontRegardless();
+
+                    // windows are only miniaturized once they are on screen
+                    if (*state).minimized {
+                        let _: () = msg_send![window, miniaturize:nil];
+                    }
                 }
 
                 if !(*state).decorations {
@@ -169,6 +330,17 @@ impl WindowDelegate {
                 window_should_close as extern fn(&Object, Sel, id) -> BOOL);
             decl.add_method(sel!(windowDidResize:),
                 window_did_resize as extern fn(&Object, Sel, id));
+            decl.add_method(sel!(windowDidMove:),
+                window_did_move as extern fn(&Object, Sel, id));
+            decl.add_method(sel!(windowWillStartLiveResize:),
+                window_will_start_live_resize as extern fn(&Object, Sel, id));
+            decl.add_method(sel!(windowDidEndLiveResize:),
+                window_did_end_live_resize as extern fn(&Object, Sel, id));
+
+            decl.add_method(sel!(windowDidChangeScreen:),
+                window_did_change_backing as extern fn(&Object, Sel, id));
+            decl.add_method(sel!(windowDidChangeBackingProperties:),
+                window_did_change_backing as extern fn(&Object, Sel, id));
 
             decl.add_method(sel!(windowDidBecomeKey:),
                 window_did_become_key as extern fn(&Object, Sel, id));
@@ -178,6 +350,9 @@ impl WindowDelegate {
             decl.add_method(sel!(activateWithView:),
                 activate_with_view as extern fn(&Object, Sel, id));
 
+            decl.add_method(sel!(keyboardSelectionDidChange:),
+                keyboard_selection_did_change as extern fn(&Object, Sel, id));
+
             // Store internal state as user data
             decl.add_ivar::<*mut c_void>("glutinState");
 
@@ -199,6 +374,15 @@ impl WindowDelegate {
             (&mut **delegate).set_ivar("glutinState", state_ptr as *mut ::std::os::raw::c_void);
             let _: () = msg_send![*state.window, setDelegate:*delegate];
 
+            let center: id = msg_send![Class::get("NSNotificationCenter").unwrap(), defaultCenter];
+            let _: () = msg_send![center, addObserver:*delegate
+                                          selector:sel!(keyboardSelectionDidChange:)
+                                          name:NSTextInputContextKeyboardSelectionDidChangeNotification
+                                          object:nil];
+
+            CGDisplayRegisterReconfigurationCallback(display_reconfigured,
+                                                     state_ptr as *mut libc::c_void);
+
             WindowDelegate { state: state, this: delegate }
         }
     }
@@ -209,6 +393,13 @@ impl Drop for WindowDelegate {
         unsafe {
             // Nil the window's delegate so it doesn't still reference us
             let _: () = msg_send![*self.state.window, setDelegate:nil];
+
+            let center: id = msg_send![Class::get("NSNotificationCenter").unwrap(), defaultCenter];
+            let _: () = msg_send![center, removeObserver:*self.this];
+
+            let state_ptr: *mut DelegateState = &mut *self.state;
+            CGDisplayRemoveReconfigurationCallback(display_reconfigured,
+                                                   state_ptr as *mut libc::c_void);
         }
     }
 }
@@ -217,6 +408,8 @@ impl Drop for WindowDelegate {
 pub struct PlatformSpecificWindowBuilderAttributes {
     pub activation_policy: ActivationPolicy,
     pub app_name: Option<String>,
+    pub disable_press_and_hold: bool,
+    pub display_link: bool,
 }
 
 pub struct Window {
@@ -225,13 +418,61 @@ pub struct Window {
     context: IdRef,
     pixel_format: PixelFormat,
     delegate: WindowDelegate,
+    /// The rectangle the cursor is confined to, see `confine_cursor`
+    cursor_confinement: Mutex<Option<Rect>>,
+    /// How the cursor is held by the window, see `set_cursor_grab`
+    cursor_grab: Mutex<CursorGrabMode>,
+    /// The position of the cursor when it was locked, where it is moved back when it is unlocked
+    cursor_position_before_lock: Mutex<Option<(f64, f64)>>,
+    /// True if the window has disabled the press and hold popup, which is restored when the
+    /// window is destroyed
+    press_and_hold_disabled: bool,
+    /// The event of the left button while it is pressed, which `drag_window` needs
+    last_mouse_down: Mutex<Option<IdRef>>,
+    /// The edge being dragged since `drag_resize_window`, with the frame of the window and the
+    /// location of the pointer when the resize started
+    resize_drag: Mutex<Option<(ResizeEdge, NSRect, NSPoint)>>,
+    /// The filter of `set_raw_event_filter`, taken out while it runs, and the number of times
+    /// that it has been set
+    raw_event_filter: Mutex<(Option<RawEventFilter>, u64)>,
+    /// The keys and buttons that are held down, which are released when the window loses the
+    /// focus
+    pressed_inputs: Mutex<PressedInputs>,
 }
 
 unsafe impl Send for Window {}
 unsafe impl Sync for Window {}
 
+impl Drop for Window {
+    fn drop(&mut self) {
+        if self.press_and_hold_disabled {
+            unsafe { helpers::restore_press_and_hold() };
+        }
+    }
+}
+
+/// An `NSOpenGLContext` that renders to the view of a window. See
+/// `Window::create_shared_context`.
+pub struct SharedContext {
+    context: IdRef,
+    pixel_format: PixelFormat,
+}
+
+unsafe impl Send for SharedContext {}
+unsafe impl Sync for SharedContext {}
+
+impl SharedContext {
+    /// Returns the `CGLContextObj` of the context. See `os::macos::ContextExt`.
+    #[inline]
+    pub fn get_cgl_context(&self) -> *mut libc::c_void {
+        unsafe { self.context.CGLContextObj() as *mut libc::c_void }
+    }
+}
+
 #[derive(Clone)]
-pub struct WindowProxy;
+pub struct WindowProxy {
+    pending_events: Arc<Mutex<VecDeque<Event>>>,
+}
 
 impl WindowProxy {
     pub fn wakeup_event_loop(&self) {
@@ -241,6 +482,12 @@ impl WindowProxy {
             pool.drain();
         }
     }
+
+    pub fn request_close(&self) {
+        // the same event as the one of `windowShouldClose:`, without closing the window
+        self.pending_events.lock().unwrap().push_back(Event::Closed);
+        self.wakeup_event_loop();
+    }
 }
 
 pub struct PollEventsIterator<'a> {
@@ -252,7 +499,7 @@ impl<'a> Iterator for PollEventsIterator<'a> {
 
     fn next(&mut self) -> Option<Event> {
         if let Some(ev) = self.window.delegate.state.pending_events.lock().unwrap().pop_front() {
-            return Some(ev);
+            return Some(self.window.process_pressed_inputs(ev));
         }
 
         let event: Option<Event>;
@@ -268,7 +515,7 @@ impl<'a> Iterator for PollEventsIterator<'a> {
 
             let _: () = msg_send![pool, release];
         }
-        event
+        event.map(|event| self.window.process_pressed_inputs(event))
     }
 }
 
@@ -281,7 +528,7 @@ impl<'a> Iterator for WaitEventsIterator<'a> {
 
     fn next(&mut self) -> Option<Event> {
         if let Some(ev) = self.window.delegate.state.pending_events.lock().unwrap().pop_front() {
-            return Some(ev);
+            return Some(self.window.process_pressed_inputs(ev));
         }
 
         let event: Option<Event>;
@@ -301,7 +548,7 @@ impl<'a> Iterator for WaitEventsIterator<'a> {
         if event.is_none() {
             return Some(Event::Awakened);
         } else {
-            return event;
+            return event.map(|event| self.window.process_pressed_inputs(event));
         }
     }
 }
@@ -355,14 +602,31 @@ impl Window {
             Err(e) => { return Err(OsError(format!("Couldn't create OpenGL context: {}", e))); },
         };
 
+        let pending_events = Arc::new(Mutex::new(VecDeque::new()));
+
+        let display_link = if pl_attribs.display_link {
+            let proxy = WindowProxy { pending_events: pending_events.clone() };
+            match DisplayLink::new(*window, proxy) {
+                Some(display_link) => Some(display_link),
+                None => { return Err(OsError(format!("Couldn't create CVDisplayLink"))); },
+            }
+        } else {
+            None
+        };
+
         let ds = DelegateState {
             context: context.clone(),
             view: view.clone(),
             window: window.clone(),
             resize_handler: None,
             visible: win_attribs.visible,
+            minimized: win_attribs.minimized,
             decorations: win_attribs.decorations,
-            pending_events: Mutex::new(VecDeque::new()),
+            in_live_resize: false,
+            hidpi_factor: unsafe { NSWindow::backingScaleFactor(*window) as f32 },
+            virtual_screen: unsafe { NSOpenGLContext::currentVirtualScreen(*context) as i32 },
+            display_link: display_link,
+            pending_events: pending_events,
         };
 
         let window = Window {
@@ -371,8 +635,20 @@ impl Window {
             context: context,
             pixel_format: pf,
             delegate: WindowDelegate::new(ds),
+            cursor_confinement: Mutex::new(None),
+            cursor_grab: Mutex::new(CursorGrabMode::None),
+            cursor_position_before_lock: Mutex::new(None),
+            press_and_hold_disabled: pl_attribs.disable_press_and_hold,
+            last_mouse_down: Mutex::new(None),
+            resize_drag: Mutex::new(None),
+            raw_event_filter: Mutex::new((None, 0)),
+            pressed_inputs: Mutex::new(PressedInputs::new()),
         };
 
+        if pl_attribs.disable_press_and_hold {
+            unsafe { helpers::disable_press_and_hold() };
+        }
+
         unsafe {
             let run_loop: id = NSRunLoop::currentRunLoop();
             let modes: id = NSArray::arrayWithObject(nil, NSDefaultRunLoopMode);
@@ -500,11 +776,28 @@ impl Window {
                 NSWindow::setTitle_(*window, *title);
                 window.setAcceptsMouseMovedEvents_(YES);
 
+                // the window isn't ordered in yet, so it is never captured
+                if attrs.content_protected {
+                    set_sharing_type(*window, true);
+                }
+
                 if screen.is_some() {
                     window.setLevel_(appkit::NSMainMenuWindowLevel as i64 + 1);
                 }
                 else {
-                    window.center();
+                    match attrs.initial_position {
+                        Some((x, y)) => {
+                            // the position is the one of the top-left hand corner of the frame,
+                            // like `set_position`
+                            let point = helpers::top_left_to_cocoa(x, y, 0.);
+                            let _: () = msg_send![*window, setFrameTopLeftPoint:point];
+                        },
+                        None => window.center(),
+                    }
+
+                    if attrs.maximized {
+                        let _: () = msg_send![*window, zoom:nil];
+                    }
                 }
                 window
             })
@@ -588,37 +881,10 @@ impl Window {
                 let context = IdRef::new(NSOpenGLContext::alloc(nil).initWithFormat_shareContext_(*pixelformat, nil));
 
                 if let Some(cxt) = context.non_nil() {
-                    let pf = {
-                        let get_attr = |attrib: appkit::NSOpenGLPixelFormatAttribute| -> i32 {
-                            let mut value = 0;
-
-                            NSOpenGLPixelFormat::getValues_forAttribute_forVirtualScreen_(
-                                *pixelformat,
-                                &mut value,
-                                attrib,
-                                NSOpenGLContext::currentVirtualScreen(*cxt));
-
-                            value
-                        };
-
-                        PixelFormat {
-                            hardware_accelerated: get_attr(appkit::NSOpenGLPFAAccelerated) != 0,
-                            color_bits: (get_attr(appkit::NSOpenGLPFAColorSize) - get_attr(appkit::NSOpenGLPFAAlphaSize)) as u8,
-                            alpha_bits: get_attr(appkit::NSOpenGLPFAAlphaSize) as u8,
-                            depth_bits: get_attr(appkit::NSOpenGLPFADepthSize) as u8,
-                            stencil_bits: get_attr(appkit::NSOpenGLPFAStencilSize) as u8,
-                            stereoscopy: get_attr(appkit::NSOpenGLPFAStereo) != 0,
-                            double_buffer: get_attr(appkit::NSOpenGLPFADoubleBuffer) != 0,
-                            multisampling: if get_attr(appkit::NSOpenGLPFAMultisample) > 0 {
-                                Some(get_attr(appkit::NSOpenGLPFASamples) as u16)
-                            } else {
-                                None
-                            },
-                            srgb: true,
-                        }
-                    };
-
                     NSOpenGLContext::setView_(*cxt, view);
+                    let pf = helpers::get_pixel_format(*pixelformat, *cxt);
+                    try!(helpers::check_zero_sizes(pf_reqs, &pf));
+
                     let value = if opengl.vsync { 1 } else { 0 };
                     cxt.setValues_forParameter_(&value, appkit::NSOpenGLContextParameter::NSOpenGLCPSwapInterval);
 
@@ -736,6 +1002,20 @@ impl Window {
         }
     }
 
+    /// Sets the subtitle of the window. See `os::macos::WindowExt`.
+    pub fn set_subtitle(&self, subtitle: &str) {
+        unsafe {
+            // `setSubtitle:` was introduced in macOS 11
+            let responds: BOOL = msg_send![*self.window, respondsToSelector:sel!(setSubtitle:)];
+            if responds == NO {
+                return;
+            }
+
+            let subtitle = IdRef::new(NSString::alloc(nil).init_str(subtitle));
+            let _: () = msg_send![*self.window, setSubtitle:*subtitle];
+        }
+    }
+
     #[inline]
     pub fn show(&self) {
         unsafe { NSWindow::makeKeyAndOrderFront_(*self.window, nil); }
@@ -746,33 +1026,146 @@ impl Window {
         unsafe { NSWindow::orderOut_(*self.window, nil); }
     }
 
-    pub fn get_position(&self) -> Option<(i32, i32)> {
+    /// See the docs in the crate root file.
+    pub fn drag_window(&self) -> Result<(), ()> {
+        let last_mouse_down = self.last_mouse_down.lock().unwrap();
+        let event = match *last_mouse_down {
+            Some(ref event) => **event,
+            // the left button isn't pressed
+            None => return Err(()),
+        };
+
         unsafe {
-            let content_rect = NSWindow::contentRectForFrameRect_(*self.window, NSWindow::frame(*self.window));
+            // `performWindowDragWithEvent:` was introduced in macOS 10.11
+            let responds: BOOL = msg_send![*self.window,
+                                           respondsToSelector:sel!(performWindowDragWithEvent:)];
+            if responds == NO {
+                return Err(());
+            }
 
-            // TODO: consider extrapolating the calculations for the y axis to
-            // a private method
-            Some((content_rect.origin.x as i32, (CGDisplayPixelsHigh(CGMainDisplayID()) as f64 - (content_rect.origin.y + content_rect.size.height)) as i32))
+            let _: () = msg_send![*self.window, performWindowDragWithEvent:event];
         }
+        Ok(())
     }
 
-    pub fn set_position(&self, x: i32, y: i32) {
+    /// See the docs in the crate root file.
+    ///
+    /// AppKit only resizes windows from their frame, so the drag is tracked with the motion of
+    /// the mouse in `update_resize_drag` instead.
+    pub fn drag_resize_window(&self, edge: ResizeEdge) -> Result<(), ()> {
+        if self.last_mouse_down.lock().unwrap().is_none() {
+            // the left button isn't pressed
+            return Err(());
+        }
+
         unsafe {
-            let frame = NSWindow::frame(*self.view);
+            let frame = NSWindow::frame(*self.window);
+            let location: NSPoint = msg_send![Class::get("NSEvent").unwrap(), mouseLocation];
+            *self.resize_drag.lock().unwrap() = Some((edge, frame, location));
+        }
+        Ok(())
+    }
+
+    /// Resizes the window so that the edge dragged since `drag_resize_window` follows the
+    /// pointer.
+    fn update_resize_drag(&self) {
+        let (edge, frame, start) = match *self.resize_drag.lock().unwrap() {
+            Some(drag) => drag,
+            None => return,
+        };
+
+        let (left, right, top, bottom) = match edge {
+            ResizeEdge::Top => (false, false, true, false),
+            ResizeEdge::Bottom => (false, false, false, true),
+            ResizeEdge::Left => (true, false, false, false),
+            ResizeEdge::Right => (false, true, false, false),
+            ResizeEdge::TopLeft => (true, false, true, false),
+            ResizeEdge::TopRight => (false, true, true, false),
+            ResizeEdge::BottomLeft => (true, false, false, true),
+            ResizeEdge::BottomRight => (false, true, false, true),
+        };
+
+        unsafe {
+            // the screen coordinates of Cocoa go up from the bottom-left hand corner, and the
+            // origin of the frame is its bottom-left hand corner
+            let location: NSPoint = msg_send![Class::get("NSEvent").unwrap(), mouseLocation];
+            let (delta_x, delta_y) = (location.x - start.x, location.y - start.y);
+
+            let mut new_frame = frame;
+            if left {
+                new_frame.origin.x += delta_x;
+                new_frame.size.width -= delta_x;
+            } else if right {
+                new_frame.size.width += delta_x;
+            }
+            if bottom {
+                new_frame.origin.y += delta_y;
+                new_frame.size.height -= delta_y;
+            } else if top {
+                new_frame.size.height += delta_y;
+            }
 
-            // NOTE: `setFrameOrigin` might not give desirable results when
-            // setting window, as it treats bottom left as origin.
-            // `setFrameTopLeftPoint` treats top left as origin (duh), but
-            // does not equal the value returned by `get_window_position`
-            // (there is a difference by 22 for me on yosemite)
+            // AppKit only enforces the limits of `set_min_dimensions` and `set_max_dimensions`
+            // during the resizes that it tracks itself
+            let content: NSRect = msg_send![*self.window, contentRectForFrameRect:new_frame];
+            let min_size: NSSize = msg_send![*self.window, contentMinSize];
+            let max_size: NSSize = msg_send![*self.window, contentMaxSize];
+            let content_size = NSSize::new(
+                content.size.width.max(min_size.width).min(max_size.width).max(1.0),
+                content.size.height.max(min_size.height).min(max_size.height).max(1.0));
+            let clamped: NSRect = msg_send![*self.window,
+                frameRectForContentRect:NSRect::new(content.origin, content_size)];
+
+            // the edges that aren't dragged stay where they are
+            if left {
+                new_frame.origin.x = frame.origin.x + frame.size.width - clamped.size.width;
+            }
+            if bottom {
+                new_frame.origin.y = frame.origin.y + frame.size.height - clamped.size.height;
+            }
+            new_frame.size = clamped.size;
+
+            // `windowDidResize:` reports the new size, while the button is still pressed
+            let _: () = msg_send![*self.window, setFrame:new_frame display:YES];
+        }
+    }
+
+    /// See the docs in the crate root file.
+    pub fn focus_window(&self) {
+        unsafe {
+            NSApp().activateIgnoringOtherApps_(YES);
+            NSWindow::makeKeyAndOrderFront_(*self.window, nil);
+
+            // the system may keep another application active, in which case the dock icon
+            // bounces once instead; the request is cancelled once the application is activated
+            let informational_request: NSInteger = 10;
+            let _: NSInteger = msg_send![NSApp(), requestUserAttention:informational_request];
+        }
+    }
+
+    /// See the docs in the crate root file.
+    ///
+    /// This is the position of the frame of the window, including the title bar, converted from
+    /// the bottom-left hand corner origin of Cocoa.
+    #[inline]
+    pub fn get_position(&self) -> Option<(i32, i32)> {
+        unsafe {
+            Some(helpers::cocoa_to_top_left(NSWindow::frame(*self.window)))
+        }
+    }
 
-            // TODO: consider extrapolating the calculations for the y axis to
-            // a private method
-            let dummy = NSRect::new(NSPoint::new(x as f64, CGDisplayPixelsHigh(CGMainDisplayID()) as f64 - (frame.size.height + y as f64)), NSSize::new(0f64, 0f64));
-            let conv = NSWindow::frameRectForContentRect_(*self.window, dummy);
+    /// See the docs in the crate root file.
+    #[inline]
+    pub fn get_outer_position(&self) -> Option<(i32, i32)> {
+        self.get_position()
+    }
 
-            // NSWindow::setFrameTopLeftPoint_(*self.window, conv.origin);
-            NSWindow::setFrameOrigin_(*self.window, conv.origin);
+    /// See the docs in the crate root file.
+    pub fn set_position(&self, x: i32, y: i32) {
+        unsafe {
+            let frame = NSWindow::frame(*self.window);
+            let origin = helpers::top_left_to_cocoa(x, y, frame.size.height);
+            NSWindow::setFrameOrigin_(*self.window, origin);
         }
     }
 
@@ -799,9 +1192,134 @@ impl Window {
         }
     }
 
+    #[inline]
+    pub fn request_inner_size(&self, width: u32, height: u32) -> Option<(u32, u32)> {
+        // `setContentSize:` resizes the window synchronously
+        self.set_inner_size(width, height);
+        self.get_inner_size()
+    }
+
+    /// See the docs in the crate root file.
+    pub fn set_min_dimensions(&self, dimensions: Option<(u32, u32)>) {
+        let (width, height) = dimensions.unwrap_or((0, 0));
+        unsafe {
+            let _: () = msg_send![*self.window, setContentMinSize:NSSize::new(width as f64,
+                                                                              height as f64)];
+        }
+
+        // the limit only applies to the future resizes
+        if let (Some(_), Some((current_width, current_height))) = (dimensions, self.get_inner_size()) {
+            if current_width < width || current_height < height {
+                self.set_inner_size(cmp::max(current_width, width),
+                                    cmp::max(current_height, height));
+            }
+        }
+    }
+
+    /// See the docs in the crate root file.
+    pub fn set_max_dimensions(&self, dimensions: Option<(u32, u32)>) {
+        // `FLT_MAX` is the default maximum size of windows
+        let size = match dimensions {
+            Some((width, height)) => NSSize::new(width as f64, height as f64),
+            None => NSSize::new(::std::f32::MAX as f64, ::std::f32::MAX as f64),
+        };
+        unsafe {
+            let _: () = msg_send![*self.window, setContentMaxSize:size];
+        }
+
+        // the limit only applies to the future resizes
+        if let (Some((width, height)), Some((current_width, current_height))) = (dimensions, self.get_inner_size()) {
+            if current_width > width || current_height > height {
+                self.set_inner_size(cmp::min(current_width, width),
+                                    cmp::min(current_height, height));
+            }
+        }
+    }
+
+    /// See the docs in the crate root file.
+    pub fn set_aspect_ratio(&self, ratio: Option<(u32, u32)>) {
+        unsafe {
+            match ratio {
+                Some((x, y)) if x != 0 && y != 0 => {
+                    let _: () = msg_send![*self.window, setContentAspectRatio:NSSize::new(x as f64,
+                                                                                          y as f64)];
+
+                    // the ratio only applies to the future resizes
+                    if let Some((width, height)) = self.get_inner_size() {
+                        let (x, y) = (x as u64, y as u64);
+                        if width as u64 * y > height as u64 * x {
+                            self.set_inner_size((height as u64 * x / y) as u32, height);
+                        } else if (width as u64 * y) < height as u64 * x {
+                            self.set_inner_size(width, (width as u64 * y / x) as u32);
+                        }
+                    }
+                },
+                _ => {
+                    // setting the resize increments removes the aspect ratio
+                    let _: () = msg_send![*self.window, setContentResizeIncrements:NSSize::new(1.0, 1.0)];
+                },
+            }
+        }
+    }
+
+    /// See the docs in the crate root file.
+    ///
+    /// The view is created for the decorations given at creation, so this does nothing.
+    #[inline]
+    pub fn set_decorations(&self, _: bool) {
+    }
+
+    /// See the docs in the crate root file.
+    #[inline]
+    pub fn set_opacity(&self, opacity: f32) {
+        unsafe {
+            let _: () = msg_send![*self.window, setAlphaValue:opacity as CGFloat];
+        }
+    }
+
+    /// See the docs in the crate root file.
+    #[inline]
+    pub fn set_content_protected(&self, protected: bool) -> Result<(), ContentProtectionError> {
+        unsafe { set_sharing_type(*self.window, protected) };
+        Ok(())
+    }
+
+    /// See the docs in the crate root file.
+    #[inline]
+    pub fn set_cursor_hittest(&self, hittest: bool) -> Result<(), ()> {
+        unsafe {
+            let _: () = msg_send![*self.window, setIgnoresMouseEvents:if hittest { NO } else { YES }];
+        }
+        Ok(())
+    }
+
+    /// Returns the `CGLContextObj` of the context. See `os::macos::ContextExt`.
+    #[inline]
+    pub fn get_cgl_context(&self) -> *mut libc::c_void {
+        unsafe { self.context.CGLContextObj() as *mut libc::c_void }
+    }
+
+    /// Shows or hides the buttons of the title bar. See `os::macos::WindowExt`.
+    pub fn set_button_visibility(&self, close: bool, miniaturize: bool, zoom: bool) {
+        // `NSWindowCloseButton`, `NSWindowMiniaturizeButton` and `NSWindowZoomButton`
+        let buttons = [(0 as NSUInteger, close), (1, miniaturize), (2, zoom)];
+
+        for &(kind, visible) in buttons.iter() {
+            unsafe {
+                // borderless windows don't have any button
+                let button: id = msg_send![*self.window, standardWindowButton:kind];
+                if button != nil {
+                    let _: () = msg_send![button, setHidden:if visible { NO } else { YES }];
+                }
+            }
+        }
+    }
+
     #[inline]
     pub fn create_window_proxy(&self) -> WindowProxy {
-        WindowProxy
+        WindowProxy {
+            pending_events: self.delegate.state.pending_events.clone(),
+        }
     }
 
     #[inline]
@@ -818,11 +1336,66 @@ impl Window {
         }
     }
 
+    pub fn drain_events(&self, events: &mut Vec<Event>) {
+        unsafe {
+            let pool = NSAutoreleasePool::new(nil);
+
+            loop {
+                // the events queued by the delegate are taken with one lock
+                let pending = mem::replace(&mut *self.delegate.state.pending_events.lock().unwrap(),
+                                           VecDeque::new());
+                for event in pending {
+                    events.extend(self.process_inputs(event));
+                }
+
+                let nsevent = appkit::NSApp().nextEventMatchingMask_untilDate_inMode_dequeue_(
+                    NSEventMask::NSAnyEventMask.bits() | NSEventMask::NSEventMaskPressure.bits(),
+                    NSDate::distantPast(nil),
+                    NSDefaultRunLoopMode,
+                    YES);
+                match NSEventToEvent(self, nsevent) {
+                    Some(event) => events.extend(self.process_inputs(event)),
+                    None => break,
+                }
+            }
+
+            let _: () = msg_send![pool, release];
+        }
+    }
+
+    /// Records the keys and buttons that are held down, and releases them before
+    /// `Focused(false)`, since their releases are sent to the window that gets the focus.
+    fn process_pressed_inputs(&self, event: Event) -> Event {
+        let mut events = self.process_inputs(event);
+        if events.len() > 1 {
+            let mut pending_events = self.delegate.state.pending_events.lock().unwrap();
+            for event in events.drain(1 ..).rev() {
+                pending_events.push_front(event);
+            }
+        }
+        events.remove(0)
+    }
+
+    /// Same as `process_pressed_inputs`, but returns all the events to report.
+    fn process_inputs(&self, event: Event) -> Vec<Event> {
+        let events = self.pressed_inputs.lock().unwrap().process(event);
+        if events.len() > 1 {
+            // the modifiers that are released must be reported as pressed again
+            unsafe {
+                SHIFT_PRESSED = false;
+                CTRL_PRESSED = false;
+                WIN_PRESSED = false;
+                ALT_PRESSED = false;
+            }
+        }
+        events
+    }
+
     unsafe fn modifier_event(event: id, keymask: appkit::NSEventModifierFlags, key: events::VirtualKeyCode, key_pressed: bool) -> Option<Event> {
         if !key_pressed && NSEvent::modifierFlags(event).contains(keymask) {
-            return Some(Event::KeyboardInput(ElementState::Pressed, NSEvent::keyCode(event) as u8, Some(key)));
+            return Some(Event::KeyboardInput(ElementState::Pressed, NSEvent::keyCode(event) as u8, Some(key), false));
         } else if key_pressed && !NSEvent::modifierFlags(event).contains(keymask) {
-            return Some(Event::KeyboardInput(ElementState::Released, NSEvent::keyCode(event) as u8, Some(key)));
+            return Some(Event::KeyboardInput(ElementState::Released, NSEvent::keyCode(event) as u8, Some(key), false));
         }
 
         return None;
@@ -838,6 +1411,51 @@ impl Window {
         *self.window as *mut libc::c_void
     }
 
+    /// See the docs in the crate root file.
+    pub fn create_shared_context(&self, opengl: &GlAttributes<&Window>)
+                                 -> Result<SharedContext, CreationError>
+    {
+        unsafe {
+            // only the pixel format of the context of the window can render to its view
+            let pixelformat: id = msg_send![*self.context, pixelFormat];
+            if pixelformat == nil {
+                return Err(CreationError::NoAvailablePixelFormat);
+            }
+
+            // fails if the pixel format isn't compatible with the one of the shared context
+            let share = *opengl.sharing.unwrap_or(self).context;
+            let context = IdRef::new(NSOpenGLContext::alloc(nil)
+                                        .initWithFormat_shareContext_(pixelformat, share));
+            let context = match context.non_nil() {
+                Some(context) => context,
+                None => return Err(CreationError::NoAvailablePixelFormat),
+            };
+
+            NSOpenGLContext::setView_(*context, *self.view);
+
+            Ok(SharedContext {
+                context: context,
+                pixel_format: self.pixel_format.clone(),
+            })
+        }
+    }
+
+    /// See `os::macos::WindowExt::create_io_surface_context`.
+    pub fn create_io_surface_context(&self, dimensions: (u32, u32))
+                                     -> Result<HeadlessContext, CreationError>
+    {
+        unsafe {
+            // the same pixel format as the window, so that the objects can be shared
+            let pixelformat: id = msg_send![*self.context, pixelFormat];
+            if pixelformat == nil {
+                return Err(CreationError::NoAvailablePixelFormat);
+            }
+
+            HeadlessContext::with_io_surface(pixelformat, self.pixel_format.clone(),
+                                             *self.context, dimensions)
+        }
+    }
+
     #[inline]
     pub fn set_window_resize_callback(&mut self, callback: Option<fn(u32, u32)>) {
         self.delegate.state.resize_handler = callback;
@@ -872,34 +1490,156 @@ impl Window {
             MouseCursor::Move | MouseCursor::AllScroll | MouseCursor::ZoomIn |
             MouseCursor::ZoomOut => "arrowCursor",
         };
-        let sel = Sel::register(cursor_name);
-        let cls = Class::get("NSCursor").unwrap();
-        unsafe {
-            use objc::Message;
-            let cursor: id = cls.send_message(sel, ()).unwrap();
-            let _: () = msg_send![cursor, set];
+        set_cursor_by_selector(cursor_name);
+    }
+
+    /// See `os::macos::WindowExt::set_cursor_by_name`.
+    pub fn set_cursor_by_name(&self, name: &str) -> Result<(), ()> {
+        // the other class methods of `NSCursor`, such as `hide`, don't return a cursor
+        let valid = name.ends_with("Cursor") && !name.contains('\0') && unsafe {
+            let cls = Class::get("NSCursor").unwrap();
+            let responds: BOOL = msg_send![cls, respondsToSelector:Sel::register(name)];
+            responds == YES
+        };
+
+        if valid {
+            set_cursor_by_selector(name);
+            Ok(())
+        } else {
+            set_cursor_by_selector("arrowCursor");
+            Err(())
         }
     }
 
-    pub fn set_cursor_state(&self, state: CursorState) -> Result<(), String> {
+    pub fn set_cursor_state(&self, state: CursorState) -> Result<(), CursorStateError> {
         let cls = Class::get("NSCursor").unwrap();
 
         // TODO: Check for errors.
-        match state {
-            CursorState::Normal => {
-                let _: () = unsafe { msg_send![cls, unhide] };
-                let _: i32 = unsafe { CGAssociateMouseAndMouseCursorPosition(true as u32) };
-                Ok(())
-            },
-            CursorState::Hide => {
-                let _: () = unsafe { msg_send![cls, hide] };
-                Ok(())
-            },
-            CursorState::Grab => {
-                let _: i32 = unsafe { CGAssociateMouseAndMouseCursorPosition(false as u32) };
-                Ok(())
+        if state.is_hidden() {
+            let _: () = unsafe { msg_send![cls, hide] };
+        } else {
+            let _: () = unsafe { msg_send![cls, unhide] };
+        }
+
+        self.set_cursor_grab(state.grab_mode())
+    }
+
+    /// See the docs in the crate root file.
+    ///
+    /// Cocoa can't confine the cursor, so a confined cursor is moved back into the window when
+    /// it leaves it instead. A locked cursor is dissociated from the mouse.
+    pub fn set_cursor_grab(&self, mode: CursorGrabMode) -> Result<(), CursorStateError> {
+        let previous_mode = {
+            let mut cursor_grab = self.cursor_grab.lock().unwrap();
+
+            // TODO: Check for errors.
+            let associated = mode != CursorGrabMode::Locked;
+            let _: i32 = unsafe { CGAssociateMouseAndMouseCursorPosition(associated as u32) };
+
+            mem::replace(&mut *cursor_grab, mode)
+        };
+
+        // a locked cursor can still be moved with `set_cursor_position`, so it is moved back to
+        // where it was once it is unlocked, like on the other platforms
+        let mut position_before_lock = self.cursor_position_before_lock.lock().unwrap();
+        if mode == CursorGrabMode::Locked && previous_mode != CursorGrabMode::Locked {
+            *position_before_lock = Some(self.get_cursor_position());
+        } else if mode != CursorGrabMode::Locked {
+            if let Some((x, y)) = position_before_lock.take() {
+                let _ = self.set_cursor_position(x, y);
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Returns the position of the cursor in the view, in pixels.
+    fn get_cursor_position(&self) -> (f64, f64) {
+        unsafe {
+            let location: NSPoint = msg_send![Class::get("NSEvent").unwrap(), mouseLocation];
+            let window_rect = self.window.convertRectFromScreen_(NSRect::new(location, NSSize::new(0.0, 0.0)));
+            let view_point = self.view.convertPoint_fromView_(window_rect.origin, nil);
+            let view_rect = NSView::frame(*self.view);
+            let scale_factor = self.hidpi_factor() as f64;
+            (scale_factor * view_point.x as f64,
+             scale_factor * (view_rect.size.height - view_point.y) as f64)
+        }
+    }
+
+    #[inline]
+    pub fn set_ime_allowed(&self, _allowed: bool) {
+    }
+
+    /// See the docs in the crate root file.
+    ///
+    /// The view doesn't implement `NSTextInputClient`, so the keystrokes never reach the input
+    /// method and there is no `firstRectForCharacterRange:` to report the position from.
+    #[inline]
+    pub fn set_ime_position(&self, _x: i32, _y: i32) {
+    }
+
+    #[inline]
+    pub fn scancode_to_keycode(&self, scancode: ScanCode) -> Option<events::VirtualKeyCode> {
+        event::vkeycode_to_element(scancode as u16)
+    }
+
+    #[inline]
+    pub fn keycode_to_scancode(&self, keycode: events::VirtualKeyCode) -> Option<ScanCode> {
+        event::keycode_to_scancode(keycode)
+    }
+
+    #[inline]
+    pub fn key_name(&self, scancode: ScanCode) -> Option<String> {
+        event::key_name(scancode)
+    }
+
+    #[inline]
+    pub fn set_raw_event_filter(&self, filter: Option<RawEventFilter>) {
+        let mut current = self.raw_event_filter.lock().unwrap();
+        current.0 = filter;
+        current.1 += 1;
+    }
+
+    /// Gives an event to the filter of `set_raw_event_filter`, and returns true if it consumed
+    /// the event.
+    fn filter_raw_event(&self, nsevent: id) -> bool {
+        // the filter is taken out while it runs, so that it can call the methods of the window
+        let (mut filter, generation) = {
+            let mut current = self.raw_event_filter.lock().unwrap();
+            match current.0.take() {
+                Some(filter) => (filter, current.1),
+                None => return false,
             }
+        };
+
+        let consumed = filter(&RawEvent::Cocoa(nsevent as *mut libc::c_void));
+
+        // unless the filter has been replaced or removed meanwhile
+        let mut current = self.raw_event_filter.lock().unwrap();
+        if current.1 == generation {
+            current.0 = Some(filter);
         }
+        consumed
+    }
+
+    #[inline]
+    pub fn get_context_priority(&self) -> Option<ContextPriority> {
+        None
+    }
+
+    #[inline]
+    pub fn get_robustness(&self) -> Option<Robustness> {
+        None
+    }
+
+    #[inline]
+    pub fn get_available_monitors(&self) -> VecDeque<MonitorId> {
+        get_available_monitors()
+    }
+
+    #[inline]
+    pub fn get_current_monitor(&self) -> Option<MonitorId> {
+        monitor::get_window_monitor(*self.window)
     }
 
     #[inline]
@@ -909,64 +1649,101 @@ impl Window {
         }
     }
 
+    /// See the docs in the crate root file.
+    ///
+    /// Cocoa can't confine the cursor, so it is moved back into the rectangle when it leaves it
+    /// instead.
     #[inline]
-    pub fn set_cursor_position(&self, x: i32, y: i32) -> Result<(), ()> {
-        let (window_x, window_y) = self.get_position().unwrap_or((0, 0));
-        let (cursor_x, cursor_y) = (window_x + x, window_y + y);
+    pub fn confine_cursor(&self, rect: Option<Rect>) -> Result<(), CursorStateError> {
+        *self.cursor_confinement.lock().unwrap() = rect;
+        Ok(())
+    }
 
+    /// The position is in pixels relative to the top-left hand corner of the view, like the
+    /// positions of the mouse events, which is the inverse of `get_mouse_position_precise`.
+    #[inline]
+    pub fn set_cursor_position(&self, x: f64, y: f64) -> Result<(), ()> {
         unsafe {
+            let scale_factor = self.hidpi_factor() as f64;
+            let view_rect = NSView::frame(*self.view);
+            let view_point = NSPoint::new((x / scale_factor) as appkit::CGFloat,
+                                          view_rect.size.height -
+                                          (y / scale_factor) as appkit::CGFloat);
+            let window_point: NSPoint = msg_send![*self.view, convertPoint:view_point toView:nil];
+            let screen_rect: NSRect = msg_send![*self.window,
+                convertRectToScreen:NSRect::new(window_point, NSSize::new(0.0, 0.0))];
+
+            // the origin of Quartz is the top-left hand corner of the main screen
+            let screens = NSScreen::screens(nil);
+            let main_screen: id = msg_send![screens, objectAtIndex:0 as NSUInteger];
+            let main_height = NSScreen::frame(main_screen).size.height;
+
             // TODO: Check for errors.
             let _ = CGWarpMouseCursorPosition(appkit::CGPoint {
-                x: cursor_x as appkit::CGFloat,
-                y: cursor_y as appkit::CGFloat,
+                x: screen_rect.origin.x,
+                y: main_height - screen_rect.origin.y,
             });
-            let _ = CGAssociateMouseAndMouseCursorPosition(true as u32);
+            // warping the cursor dissociates it from the mouse for a short time
+            if *self.cursor_grab.lock().unwrap() != CursorGrabMode::Locked {
+                let _ = CGAssociateMouseAndMouseCursorPosition(true as u32);
+            }
         }
 
         Ok(())
     }
 }
 
-impl GlContext for Window {
+impl GlContext for SharedContext {
     #[inline]
     unsafe fn make_current(&self) -> Result<(), ContextError> {
-        let _: () = msg_send![*self.context, update];
-        self.context.makeCurrentContext();
+        helpers::make_current_on_view(*self.context)
+    }
+
+    #[inline]
+    fn is_current(&self) -> bool {
+        helpers::is_current(*self.context)
+    }
+
+    #[inline]
+    fn get_proc_address(&self, addr: &str) -> *const () {
+        helpers::get_proc_address(addr)
+    }
+
+    #[inline]
+    fn swap_buffers(&self) -> Result<(), ContextError> {
+        unsafe { helpers::swap_buffers(*self.context, self.pixel_format.double_buffer) };
         Ok(())
     }
 
+    #[inline]
+    fn get_api(&self) -> ::Api {
+        ::Api::OpenGl
+    }
+
+    #[inline]
+    fn get_pixel_format(&self) -> PixelFormat {
+        self.pixel_format.clone()
+    }
+}
+
+impl GlContext for Window {
+    #[inline]
+    unsafe fn make_current(&self) -> Result<(), ContextError> {
+        helpers::make_current_on_view(*self.context)
+    }
+
     #[inline]
     fn is_current(&self) -> bool {
-        unsafe {
-            let current = NSOpenGLContext::currentContext(nil);
-            if current != nil {
-                let is_equal: BOOL = msg_send![current, isEqual:*self.context];
-                is_equal != NO
-            } else {
-                false
-            }
-        }
+        helpers::is_current(*self.context)
     }
 
     fn get_proc_address(&self, addr: &str) -> *const () {
-        let symbol_name: CFString = FromStr::from_str(addr).unwrap();
-        let framework_name: CFString = FromStr::from_str("com.apple.opengl").unwrap();
-        let framework = unsafe {
-            CFBundleGetBundleWithIdentifier(framework_name.as_concrete_TypeRef())
-        };
-        let symbol = unsafe {
-            CFBundleGetFunctionPointerForName(framework, symbol_name.as_concrete_TypeRef())
-        };
-        symbol as *const _
+        helpers::get_proc_address(addr)
     }
 
     #[inline]
     fn swap_buffers(&self) -> Result<(), ContextError> {
-        unsafe {
-            let pool = NSAutoreleasePool::new(nil);
-            self.context.flushBuffer();
-            let _: () = msg_send![pool, release];
-        }
+        unsafe { helpers::swap_buffers(*self.context, self.pixel_format.double_buffer) };
         Ok(())
     }
 
@@ -981,6 +1758,15 @@ impl GlContext for Window {
     }
 }
 
+/// See the docs in the crate root file.
+pub fn has_hardware_acceleration() -> bool {
+    unsafe {
+        let attributes = [NSOpenGLPFAAccelerated as u32, 0];
+        let pixelformat = IdRef::new(NSOpenGLPixelFormat::alloc(nil).initWithAttributes_(&attributes));
+        pixelformat.non_nil().is_some()
+    }
+}
+
 struct IdRef(id);
 
 impl IdRef {
@@ -988,7 +1774,6 @@ impl IdRef {
         IdRef(i)
     }
 
-    #[allow(dead_code)]
     fn retain(i: id) -> IdRef {
         if i != nil {
             let _: id = unsafe { msg_send![i, retain] };
@@ -1028,6 +1813,11 @@ impl Clone for IdRef {
 #[allow(non_snake_case, non_upper_case_globals)]
 unsafe fn NSEventToEvent(window: &Window, nsevent: id) -> Option<Event> {
     unsafe fn get_mouse_position(window: &Window, nsevent: id) -> (i32, i32) {
+        let (x, y) = get_mouse_position_precise(window, nsevent);
+        (x as i32, y as i32)
+    }
+
+    unsafe fn get_mouse_position_precise(window: &Window, nsevent: id) -> (f64, f64) {
         let window_point = nsevent.locationInWindow();
         let cWindow: id = msg_send![nsevent, window];
         let view_point = if cWindow == nil {
@@ -1037,13 +1827,16 @@ unsafe fn NSEventToEvent(window: &Window, nsevent: id) -> Option<Event> {
             window.view.convertPoint_fromView_(window_point, nil)
         };
         let view_rect = NSView::frame(*window.view);
-        let scale_factor = window.hidpi_factor();
-        ((scale_factor * view_point.x as f32) as i32,
-         (scale_factor * (view_rect.size.height - view_point.y) as f32) as i32)
+        let scale_factor = window.hidpi_factor() as f64;
+        (scale_factor * view_point.x as f64,
+         scale_factor * (view_rect.size.height - view_point.y) as f64)
     }
 
     if nsevent == nil { return None; }
 
+    // a consumed event isn't dispatched to AppKit either
+    if window.filter_raw_event(nsevent) { return None; }
+
     let event_type = nsevent.eventType();
     match event_type {
         NSKeyDown | NSApplicationDefined => {}
@@ -1052,10 +1845,13 @@ unsafe fn NSEventToEvent(window: &Window, nsevent: id) -> Option<Event> {
 
     match event_type {
         NSLeftMouseDown         => {
+            *window.last_mouse_down.lock().unwrap() = Some(IdRef::retain(nsevent));
             Some(Event::MouseInput(ElementState::Pressed, MouseButton::Left,
                                    Some(get_mouse_position(window, nsevent))))
         },
         NSLeftMouseUp           => {
+            *window.last_mouse_down.lock().unwrap() = None;
+            *window.resize_drag.lock().unwrap() = None;
             Some(Event::MouseInput(ElementState::Released, MouseButton::Left,
                                    Some(get_mouse_position(window, nsevent))))
         },
@@ -1071,8 +1867,47 @@ unsafe fn NSEventToEvent(window: &Window, nsevent: id) -> Option<Event> {
         NSLeftMouseDragged      |
         NSOtherMouseDragged     |
         NSRightMouseDragged     => {
-            let (x, y) = get_mouse_position(window, nsevent);
-            Some(Event::MouseMoved(x, y))
+            if event_type == NSLeftMouseDragged {
+                window.update_resize_drag();
+            }
+
+            let cursor_grab = *window.cursor_grab.lock().unwrap();
+
+            // the deltas are in points, but the positions of the events are in pixels
+            if cursor_grab == CursorGrabMode::Locked {
+                let scale_factor = window.hidpi_factor() as f64;
+                let delta_x: appkit::CGFloat = msg_send![nsevent, deltaX];
+                let delta_y: appkit::CGFloat = msg_send![nsevent, deltaY];
+                return Some(Event::MouseMotion(delta_x as f64 * scale_factor,
+                                               delta_y as f64 * scale_factor));
+            }
+
+            let (x, y) = get_mouse_position_precise(window, nsevent);
+
+            // a confined cursor is kept in the window
+            let confinement = match *window.cursor_confinement.lock().unwrap() {
+                None if cursor_grab == CursorGrabMode::Confined => {
+                    let (width, height) = window.delegate.state.get_view_size();
+                    Some(Rect { x: 0, y: 0, width: width, height: height })
+                },
+                confinement => confinement,
+            };
+
+            // the cursor is moved back into the confinement rectangle if it left it
+            let (x, y) = match confinement {
+                Some(rect) => {
+                    let (confined_x, confined_y) = rect.clamp_precise(x, y);
+                    if (confined_x, confined_y) != (x, y) {
+                        let _ = window.set_cursor_position(confined_x, confined_y);
+                    }
+                    (confined_x, confined_y)
+                },
+                None => (x, y),
+            };
+
+            window.delegate.state.pending_events.lock().unwrap()
+                  .push_back(Event::MouseMovedPrecise(x, y));
+            Some(Event::MouseMoved(x as i32, y as i32))
         },
         appkit::NSKeyDown => {
             let mut events = VecDeque::new();
@@ -1083,7 +1918,8 @@ unsafe fn NSEventToEvent(window: &Window, nsevent: id) -> Option<Event> {
             }
 
             let vkey =  event::vkeycode_to_element(NSEvent::keyCode(nsevent));
-            events.push_back(Event::KeyboardInput(ElementState::Pressed, NSEvent::keyCode(nsevent) as u8, vkey));
+            let repeat = NSEvent::isARepeat(nsevent) == YES;
+            events.push_back(Event::KeyboardInput(ElementState::Pressed, NSEvent::keyCode(nsevent) as u8, vkey, repeat));
             let event = events.pop_front();
             window.delegate.state.pending_events.lock().unwrap().extend(events.into_iter());
             event
@@ -1091,7 +1927,7 @@ unsafe fn NSEventToEvent(window: &Window, nsevent: id) -> Option<Event> {
         appkit::NSKeyUp => {
             let vkey =  event::vkeycode_to_element(NSEvent::keyCode(nsevent));
 
-            Some(Event::KeyboardInput(ElementState::Released, NSEvent::keyCode(nsevent) as u8, vkey))
+            Some(Event::KeyboardInput(ElementState::Released, NSEvent::keyCode(nsevent) as u8, vkey, false))
         },
         appkit::NSFlagsChanged => {
             let mut events = VecDeque::new();
@@ -1276,3 +2112,21 @@ thread_local! {
     }
 }
 
+
+/// Sets the `sharingType` of the window to `NSWindowSharingNone`, which excludes it from the
+/// screen captures, or back to the default `NSWindowSharingReadOnly`.
+unsafe fn set_sharing_type(window: id, protected: bool) {
+    let sharing_type: NSUInteger = if protected { 0 } else { 1 };
+    let _: () = msg_send![window, setSharingType:sharing_type];
+}
+
+/// Sets the cursor returned by the class method of `NSCursor` with the given name.
+fn set_cursor_by_selector(cursor_name: &str) {
+    let sel = Sel::register(cursor_name);
+    let cls = Class::get("NSCursor").unwrap();
+    unsafe {
+        use objc::Message;
+        let cursor: id = cls.send_message(sel, ()).unwrap();
+        let _: () = msg_send![cursor, set];
+    }
+}
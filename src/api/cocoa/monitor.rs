@@ -1,7 +1,24 @@
+use cocoa::appkit;
+use cocoa::base::{id, nil};
+use cocoa::foundation::{NSString, NSUInteger};
+use core_foundation::base::TCFType;
+use core_foundation::data::{CFData, CFDataRef};
 use core_graphics::display;
 use std::collections::VecDeque;
+use libc::c_void;
 use native_monitor::NativeMonitorId;
 
+use super::IdRef;
+
+type CGColorSpaceRef = *const c_void;
+
+#[link(name = "CoreGraphics", kind = "framework")]
+extern "C" {
+    fn CGDisplayCopyColorSpace(display: u32) -> CGColorSpaceRef;
+    fn CGColorSpaceCopyICCData(space: CGColorSpaceRef) -> CFDataRef;
+    fn CGColorSpaceRelease(space: CGColorSpaceRef);
+}
+
 #[derive(Clone)]
 pub struct MonitorId(u32);
 
@@ -22,11 +39,65 @@ pub fn get_available_monitors() -> VecDeque<MonitorId> {
 }
 
 #[inline]
-pub fn get_primary_monitor() -> MonitorId {
+pub fn get_primary_monitor() -> Option<MonitorId> {
     let id = unsafe {
         MonitorId(display::CGMainDisplayID())
     };
-    id
+    Some(id)
+}
+
+/// Returns the monitor of the screen that contains the largest part of `window`, or the main
+/// monitor if the window is offscreen.
+pub fn get_window_monitor(window: id) -> Option<MonitorId> {
+    unsafe {
+        let screen: id = msg_send![window, screen];
+        if screen == nil {
+            return get_primary_monitor();
+        }
+
+        let device_description = appkit::NSScreen::deviceDescription(screen);
+        let key = IdRef::new(NSString::alloc(nil).init_str("NSScreenNumber"));
+        let value: id = msg_send![device_description, objectForKey:*key];
+        if value == nil {
+            return get_primary_monitor();
+        }
+
+        let screen_number: NSUInteger = msg_send![value, unsignedIntegerValue];
+        Some(MonitorId(screen_number as u32))
+    }
+}
+
+/// Returns the monitor of the screen that contains the point, in the coordinates of
+/// `Window::get_position`.
+pub fn monitor_from_point(x: i32, y: i32) -> Option<MonitorId> {
+    unsafe {
+        let point = super::helpers::top_left_to_cocoa(x, y, 0.);
+        let screens = appkit::NSScreen::screens(nil);
+        let count: NSUInteger = msg_send![screens, count];
+
+        for i in 0 .. count {
+            let screen: id = msg_send![screens, objectAtIndex:i];
+            let frame = appkit::NSScreen::frame(screen);
+            // the top and right edges are excluded, the y axis of Cocoa goes up
+            if point.x < frame.origin.x || point.x >= frame.origin.x + frame.size.width ||
+               point.y <= frame.origin.y || point.y > frame.origin.y + frame.size.height
+            {
+                continue;
+            }
+
+            let device_description = appkit::NSScreen::deviceDescription(screen);
+            let key = IdRef::new(NSString::alloc(nil).init_str("NSScreenNumber"));
+            let value: id = msg_send![device_description, objectForKey:*key];
+            if value == nil {
+                return None;
+            }
+
+            let screen_number: NSUInteger = msg_send![value, unsignedIntegerValue];
+            return Some(MonitorId(screen_number as u32));
+        }
+
+        None
+    }
 }
 
 impl MonitorId {
@@ -53,4 +124,23 @@ impl MonitorId {
         };
         dimension
     }
+
+    pub fn get_icc_profile(&self) -> Option<Vec<u8>> {
+        let MonitorId(display_id) = *self;
+        unsafe {
+            let color_space = CGDisplayCopyColorSpace(display_id);
+            if color_space.is_null() {
+                return None;
+            }
+
+            let data = CGColorSpaceCopyICCData(color_space);
+            CGColorSpaceRelease(color_space);
+            if data.is_null() {
+                return None;
+            }
+
+            let data = CFData::wrap_under_create_rule(data);
+            Some(data.bytes().to_vec())
+        }
+    }
 }
@@ -18,6 +18,9 @@ pub mod egl {
     pub type NativeWindowType = super::EGLNativeWindowType;
 
     include!(concat!(env!("OUT_DIR"), "/egl_bindings.rs"));
+
+    // EGL_ANGLE_platform_angle, which isn't in the registry
+    pub const PLATFORM_ANGLE_ANGLE: types::EGLenum = 0x3202;
 }
 
 pub type khronos_utime_nanoseconds_t = khronos_uint64_t;
@@ -3,18 +3,22 @@
 #![allow(unused_variables)]
 
 use ContextError;
+use ContextPriority;
 use CreationError;
 use GlAttributes;
 use GlContext;
 use GlRequest;
 use PixelFormat;
 use PixelFormatRequirements;
+use Rect;
 use ReleaseBehavior;
 use Robustness;
 use Api;
+use extensions;
 
 use std::cell::Cell;
 use std::ffi::{CStr, CString};
+use std::io;
 use std::os::raw::{c_void, c_int};
 use std::{mem, ptr};
 
@@ -25,12 +29,19 @@ pub enum NativeDisplay {
     /// `None` means `EGL_DEFAULT_DISPLAY`.
     X11(Option<ffi::EGLNativeDisplayType>),
     /// `None` means `EGL_DEFAULT_DISPLAY`.
+    Wayland(Option<ffi::EGLNativeDisplayType>),
+    /// `None` means `EGL_DEFAULT_DISPLAY`.
     Gbm(Option<ffi::EGLNativeDisplayType>),
     /// `EGL_DEFAULT_DISPLAY` is mandatory for Android.
     Android,
+    /// The display of ANGLE, whose backend is chosen by ANGLE. `None` means
+    /// `EGL_DEFAULT_DISPLAY`.
+    Angle(Option<ffi::EGLNativeDisplayType>),
+    /// A display without any window system, which can only render to pbuffers.
+    Surfaceless,
     // TODO: should be `EGLDeviceEXT`
     Device(ffi::EGLNativeDisplayType),
-    /// Don't specify any display type. Useful on windows. `None` means `EGL_DEFAULT_DISPLAY`.
+    /// Don't specify any display type. `None` means `EGL_DEFAULT_DISPLAY`.
     Other(Option<ffi::EGLNativeDisplayType>),
 }
 
@@ -41,19 +52,38 @@ pub struct Context {
     surface: Cell<ffi::egl::types::EGLSurface>,
     api: Api,
     pixel_format: PixelFormat,
-    config_id: ffi::egl::types::EGLConfig
+    config_id: ffi::egl::types::EGLConfig,
+    /// The EGL extensions supported by the display of the context.
+    extensions: Vec<String>,
+    /// The robustness that the context was created with, which may be lower than the requested
+    /// one with the `Try*` variants.
+    robustness: Robustness,
+    /// The priority that the implementation granted to the context, if it supports priorities.
+    priority: Option<ContextPriority>,
+    /// True if the context was created without any surface with `EGL_KHR_surfaceless_context`,
+    /// as opposed to a surface that was destroyed by Android.
+    surfaceless: bool,
 }
 
+/// Returns the display, and a description of the function that was used to get it for the
+/// error messages.
 #[cfg(target_os = "android")]
 #[inline]
 fn get_native_display(egl: &ffi::egl::Egl,
-                      native_display: NativeDisplay) -> *const c_void {
-    unsafe { egl.GetDisplay(ffi::egl::DEFAULT_DISPLAY as *mut _) }
+                      native_display: NativeDisplay) -> (*const c_void, String) {
+    let display = unsafe { egl.GetDisplay(ffi::egl::DEFAULT_DISPLAY as *mut _) };
+    (display, format!("eglGetDisplay(EGL_DEFAULT_DISPLAY)"))
 }
 
+/// Returns the display, and a description of the function that was used to get it for the
+/// error messages.
+///
+/// `eglGetPlatformDisplay(EXT)` is used whenever the platform is supported, since the legacy
+/// `eglGetDisplay` has to guess the platform of the native display, and implementations that
+/// support several platforms sometimes guess wrong.
 #[cfg(not(target_os = "android"))]
 fn get_native_display(egl: &ffi::egl::Egl,
-                      native_display: NativeDisplay) -> *const c_void {
+                      native_display: NativeDisplay) -> (*const c_void, String) {
     // the first step is to query the list of extensions without any display, if supported
     let dp_extensions = unsafe {
         let p = egl.QueryString(ffi::egl::NO_DISPLAY, ffi::egl::EXTENSIONS as i32);
@@ -71,70 +101,107 @@ fn get_native_display(egl: &ffi::egl::Egl,
 
     let has_dp_extension = |e: &str| dp_extensions.iter().find(|s| s == &e).is_some();
 
-    match native_display {
-        // Note: Some EGL implementations are missing the `eglGetPlatformDisplay(EXT)` symbol
-        //       despite reporting `EGL_EXT_platform_base`. I'm pretty sure this is a bug.
-        //       Therefore we detect whether the symbol is loaded in addition to checking for
-        //       extensions.
-        NativeDisplay::X11(display) if has_dp_extension("EGL_KHR_platform_x11") &&
-                                       egl.GetPlatformDisplay.is_loaded() =>
-        {
-            let d = display.unwrap_or(ffi::egl::DEFAULT_DISPLAY as *const _);
-            // TODO: `PLATFORM_X11_SCREEN_KHR`
-            unsafe { egl.GetPlatformDisplay(ffi::egl::PLATFORM_X11_KHR, d as *mut _,
-                                            ptr::null()) }
-        },
+    // the version of the client library can also be queried without any display since EGL 1.5,
+    // which is the version that made `eglGetPlatformDisplay` part of the core
+    let client_version = unsafe {
+        let p = egl.QueryString(ffi::egl::NO_DISPLAY, ffi::egl::VERSION as i32);
+        if p.is_null() {
+            None
+        } else {
+            let version = String::from_utf8_lossy(CStr::from_ptr(p).to_bytes()).into_owned();
+            parse_egl_version(&version)
+        }
+    };
 
-        NativeDisplay::X11(display) if has_dp_extension("EGL_EXT_platform_x11") &&
-                                       egl.GetPlatformDisplayEXT.is_loaded() =>
-        {
-            let d = display.unwrap_or(ffi::egl::DEFAULT_DISPLAY as *const _);
-            // TODO: `PLATFORM_X11_SCREEN_EXT`
-            unsafe { egl.GetPlatformDisplayEXT(ffi::egl::PLATFORM_X11_EXT, d as *mut _,
-                                               ptr::null()) }
+    // Note: Some EGL implementations are missing the `eglGetPlatformDisplay(EXT)` symbol
+    //       despite reporting `EGL_EXT_platform_base`. I'm pretty sure this is a bug.
+    //       Therefore we detect whether the symbol is loaded in addition to checking for
+    //       extensions.
+    //
+    // The KHR platform extensions require EGL 1.5, even if the version isn't reported.
+    let has_core_platform = egl.GetPlatformDisplay.is_loaded() &&
+                            (client_version.map_or(false, |v| v >= (1, 5)) ||
+                             dp_extensions.iter().any(|e| e.starts_with("EGL_KHR_platform_")));
+    let has_ext_platform = has_dp_extension("EGL_EXT_platform_base") &&
+                           egl.GetPlatformDisplayEXT.is_loaded();
+
+    let default_display = ffi::egl::DEFAULT_DISPLAY as ffi::EGLNativeDisplayType;
+
+    // the platform, the extensions that provide it and its name
+    let (platform, platform_extensions, platform_name, display): (_, &[&str], _, _) =
+        match native_display
+    {
+        NativeDisplay::X11(display) => {
+            (ffi::egl::PLATFORM_X11_KHR, &["EGL_KHR_platform_x11", "EGL_EXT_platform_x11"],
+             "EGL_PLATFORM_X11_KHR", display.unwrap_or(default_display))
         },
-
-        NativeDisplay::Gbm(display) if has_dp_extension("EGL_KHR_platform_gbm") &&
-                                       egl.GetPlatformDisplay.is_loaded() =>
-        {
-            let d = display.unwrap_or(ffi::egl::DEFAULT_DISPLAY as *const _);
-            unsafe { egl.GetPlatformDisplay(ffi::egl::PLATFORM_GBM_KHR, d as *mut _,
-                                            ptr::null()) }
+        NativeDisplay::Wayland(display) => {
+            (ffi::egl::PLATFORM_WAYLAND_KHR,
+             &["EGL_KHR_platform_wayland", "EGL_EXT_platform_wayland"],
+             "EGL_PLATFORM_WAYLAND_KHR", display.unwrap_or(default_display))
         },
-
-        NativeDisplay::Gbm(display) if has_dp_extension("EGL_MESA_platform_gbm") &&
-                                       egl.GetPlatformDisplayEXT.is_loaded() =>
-        {
-            let d = display.unwrap_or(ffi::egl::DEFAULT_DISPLAY as *const _);
-            unsafe { egl.GetPlatformDisplayEXT(ffi::egl::PLATFORM_GBM_KHR, d as *mut _,
-                                               ptr::null()) }
+        NativeDisplay::Gbm(display) => {
+            (ffi::egl::PLATFORM_GBM_KHR, &["EGL_KHR_platform_gbm", "EGL_MESA_platform_gbm"],
+             "EGL_PLATFORM_GBM_KHR", display.unwrap_or(default_display))
         },
-
         // TODO: This will never be reached right now, as the android egl bindings
         // use the static generator, so can't rely on GetPlatformDisplay(EXT).
-        NativeDisplay::Android if has_dp_extension("EGL_KHR_platform_android") &&
-                                  egl.GetPlatformDisplay.is_loaded() =>
-        {
-            unsafe { egl.GetPlatformDisplay(ffi::egl::PLATFORM_ANDROID_KHR,
-                                            ffi::egl::DEFAULT_DISPLAY as *mut _, ptr::null()) }
+        NativeDisplay::Android => {
+            (ffi::egl::PLATFORM_ANDROID_KHR, &["EGL_KHR_platform_android"],
+             "EGL_PLATFORM_ANDROID_KHR", default_display)
         },
-
-        NativeDisplay::Device(display) if has_dp_extension("EGL_EXT_platform_device") &&
-                                          egl.GetPlatformDisplay.is_loaded() =>
-        {
-            unsafe { egl.GetPlatformDisplay(ffi::egl::PLATFORM_DEVICE_EXT, display as *mut _,
-                                            ptr::null()) }
+        NativeDisplay::Angle(display) => {
+            (ffi::egl::PLATFORM_ANGLE_ANGLE, &["EGL_ANGLE_platform_angle"],
+             "EGL_PLATFORM_ANGLE_ANGLE", display.unwrap_or(default_display))
+        },
+        NativeDisplay::Surfaceless => {
+            (ffi::egl::PLATFORM_SURFACELESS_MESA, &["EGL_MESA_platform_surfaceless"],
+             "EGL_PLATFORM_SURFACELESS_MESA", default_display)
+        },
+        NativeDisplay::Device(display) => {
+            (ffi::egl::PLATFORM_DEVICE_EXT, &["EGL_EXT_platform_device"],
+             "EGL_PLATFORM_DEVICE_EXT", display)
         },
+        NativeDisplay::Other(display) => {
+            let display = display.unwrap_or(default_display);
+            let d = unsafe { egl.GetDisplay(display as *mut _) };
+            return (d, format!("eglGetDisplay"));
+        },
+    };
 
-        NativeDisplay::X11(Some(display)) | NativeDisplay::Gbm(Some(display)) |
-        NativeDisplay::Device(display) | NativeDisplay::Other(Some(display)) => {
-            unsafe { egl.GetDisplay(display as *mut _) }
+    if platform_extensions.iter().any(|e| has_dp_extension(e)) {
+        if has_core_platform {
+            let d = unsafe { egl.GetPlatformDisplay(platform, display as *mut _, ptr::null()) };
+            return (d, format!("eglGetPlatformDisplay({})", platform_name));
         }
 
-        NativeDisplay::X11(None) | NativeDisplay::Gbm(None) |
-        NativeDisplay::Android | NativeDisplay::Other(None) => {
-            unsafe { egl.GetDisplay(ffi::egl::DEFAULT_DISPLAY as *mut _) }
-        },
+        if has_ext_platform {
+            let d = unsafe { egl.GetPlatformDisplayEXT(platform, display as *mut _,
+                                                       ptr::null()) };
+            return (d, format!("eglGetPlatformDisplayEXT({})", platform_name));
+        }
+    }
+
+    // the legacy display would use a window system
+    if platform == ffi::egl::PLATFORM_SURFACELESS_MESA {
+        return (ptr::null(), format!("{}, which isn't supported", platform_name));
+    }
+
+    let d = unsafe { egl.GetDisplay(display as *mut _) };
+    (d, format!("eglGetDisplay, as {} isn't supported", platform_name))
+}
+
+/// Parses the `major.minor` at the start of the string returned by `EGL_VERSION`.
+#[cfg(not(target_os = "android"))]
+fn parse_egl_version(version: &str) -> Option<(i32, i32)> {
+    let number = match version.split(' ').next() {
+        Some(n) => n,
+        None => return None,
+    };
+    let mut parts = number.split('.');
+    match (parts.next().and_then(|n| n.parse().ok()), parts.next().and_then(|n| n.parse().ok())) {
+        (Some(major), Some(minor)) => Some((major, minor)),
+        _ => None,
     }
 }
 
@@ -148,15 +215,62 @@ impl Context {
                    opengl: &'a GlAttributes<&'a Context>, native_display: NativeDisplay)
                    -> Result<ContextPrototype<'a>, CreationError>
     {
+        Context::new_with_config_filter(egl, pf_reqs, opengl, native_display, |_, _, _| true)
+    }
+
+    /// Same as `new`, but the config is chosen for `.finish_pbuffer(dimensions)` instead of
+    /// `.finish(window)`.
+    pub fn new_pbuffer<'a>(egl: ffi::egl::Egl, pf_reqs: &PixelFormatRequirements,
+                           opengl: &'a GlAttributes<&'a Context>, native_display: NativeDisplay)
+                           -> Result<ContextPrototype<'a>, CreationError>
+    {
+        Context::new_impl(egl, pf_reqs, opengl, native_display, ffi::egl::PBUFFER_BIT,
+                          |_, _, _| true)
+    }
+
+    /// Same as `new`, but the config is chosen for `.finish_surfaceless()` instead of
+    /// `.finish(window)`.
+    pub fn new_surfaceless<'a>(egl: ffi::egl::Egl, pf_reqs: &PixelFormatRequirements,
+                               opengl: &'a GlAttributes<&'a Context>,
+                               native_display: NativeDisplay)
+                               -> Result<ContextPrototype<'a>, CreationError>
+    {
+        // no surface will be created, so the config doesn't need to support any
+        Context::new_impl(egl, pf_reqs, opengl, native_display, 0, |_, _, _| true)
+    }
+
+    /// Same as `new`, but only the configs for which `config_filter` returns true are
+    /// considered.
+    pub fn new_with_config_filter<'a, F>(egl: ffi::egl::Egl, pf_reqs: &PixelFormatRequirements,
+                                         opengl: &'a GlAttributes<&'a Context>,
+                                         native_display: NativeDisplay, config_filter: F)
+                                         -> Result<ContextPrototype<'a>, CreationError>
+        where F: Fn(&ffi::egl::Egl, ffi::egl::types::EGLDisplay,
+                    ffi::egl::types::EGLConfig) -> bool
+    {
+        Context::new_impl(egl, pf_reqs, opengl, native_display, ffi::egl::WINDOW_BIT,
+                          config_filter)
+    }
+
+    /// `surface_type` is the `EGL_SURFACE_TYPE` that the config must support.
+    fn new_impl<'a, F>(egl: ffi::egl::Egl, pf_reqs: &PixelFormatRequirements,
+                       opengl: &'a GlAttributes<&'a Context>, native_display: NativeDisplay,
+                       surface_type: ffi::egl::types::EGLenum, config_filter: F)
+                       -> Result<ContextPrototype<'a>, CreationError>
+        where F: Fn(&ffi::egl::Egl, ffi::egl::types::EGLDisplay,
+                    ffi::egl::types::EGLConfig) -> bool
+    {
+        // TODO: share the objects with the context
         if opengl.sharing.is_some() {
-            unimplemented!()
+            return Err(CreationError::NotSupported);
         }
 
         // calling `eglGetDisplay` or equivalent
-        let display = get_native_display(&egl, native_display);
+        let (display, display_path) = get_native_display(&egl, native_display);
 
         if display.is_null() {
-            return Err(CreationError::OsError("Could not create EGL display object".to_string()));
+            return Err(CreationError::OsError(format!("Could not create EGL display object \
+                                                       with {}", display_path)));
         }
 
         let egl_version = unsafe {
@@ -164,7 +278,8 @@ impl Context {
             let mut minor: ffi::egl::types::EGLint = mem::uninitialized();
 
             if egl.Initialize(display, &mut major, &mut minor) == 0 {
-                return Err(CreationError::OsError(format!("eglInitialize failed")))
+                return Err(CreationError::OsError(format!("eglInitialize failed on the display \
+                                                           obtained with {}", display_path)))
             }
 
             (major, minor)
@@ -232,7 +347,8 @@ impl Context {
         };
 
         let (config_id, pixel_format) = unsafe {
-            try!(choose_fbconfig(&egl, display, &egl_version, api, version, pf_reqs))
+            try!(choose_fbconfig(&egl, display, &egl_version, &extensions, api, version,
+                                 surface_type, pf_reqs, &config_filter))
         };
 
         Ok(ContextPrototype {
@@ -248,35 +364,83 @@ impl Context {
         })
     }
 
+    /// Returns the robustness that the context was actually created with.
+    ///
+    /// The `Try*` variants are never returned: they become `NotRobust` if the implementation
+    /// couldn't create a robust context.
+    #[inline]
+    pub fn get_robustness(&self) -> Robustness {
+        self.robustness
+    }
+
+    /// Returns the raw `EGLDisplay`.
+    #[inline]
+    pub fn get_egl_display(&self) -> ffi::egl::types::EGLDisplay {
+        self.display
+    }
+
+    /// Returns the raw `EGLContext`.
+    #[inline]
+    pub fn get_egl_context(&self) -> ffi::egl::types::EGLContext {
+        self.context
+    }
+
+    /// Returns the raw `EGLSurface`, which is `EGL_NO_SURFACE` for a surfaceless context or while
+    /// Android has destroyed the surface.
+    #[inline]
+    pub fn get_egl_surface(&self) -> ffi::egl::types::EGLSurface {
+        self.surface.get()
+    }
+
+    /// Returns the priority that the implementation granted to the context, which may differ
+    /// from the requested one.
+    ///
+    /// Returns `None` if the implementation doesn't support `EGL_IMG_context_priority`.
+    #[inline]
+    pub fn get_context_priority(&self) -> Option<ContextPriority> {
+        self.priority
+    }
+
     // Handle Android Life Cycle.
     // Android has started the activity or sent it to foreground.
     // Create a new surface and attach it to the recreated ANativeWindow.
     // Restore the EGLContext.
     #[cfg(target_os = "android")]
-    pub unsafe fn on_surface_created(&self, native_window: ffi::EGLNativeWindowType) {
-        self.surface.set(self.egl.CreateWindowSurface(self.display, self.config_id, native_window, ptr::null()));
-        if self.surface.get().is_null() {
-            panic!("on_surface_created: eglCreateWindowSurface failed")
-        }
-        let ret = self.egl.MakeCurrent(self.display, self.surface.get(), self.surface.get(), self.context);
-        if ret == 0 {
-            panic!("on_surface_created: eglMakeCurrent failed");
+    pub unsafe fn on_surface_created(&self, native_window: ffi::EGLNativeWindowType)
+                                     -> Result<(), ContextError>
+    {
+        let attributes = surface_attributes(&self.pixel_format);
+        let surface = self.egl.CreateWindowSurface(self.display, self.config_id, native_window,
+                                                   attributes.as_ptr());
+        if surface.is_null() {
+            return Err(ContextError::IoError(io::Error::new(io::ErrorKind::Other,
+                format!("eglCreateWindowSurface failed (eglGetError returned 0x{:x})",
+                        self.egl.GetError()))));
         }
+        self.surface.set(surface);
+        self.make_current()
     }
 
     // Handle Android Life Cycle.
     // Android has stopped the activity or sent it to background.
     // Release the surface attached to the destroyed ANativeWindow.
     // The EGLContext is not destroyed so it can be restored later.
+    // The surface is destroyed even if the context can't be released, since the native window
+    // is gone anyway.
     #[cfg(target_os = "android")]
-    pub unsafe fn on_surface_destroyed(&self) {
+    pub unsafe fn on_surface_destroyed(&self) -> Result<(), ContextError> {
         let ret = self.egl.MakeCurrent(self.display, ffi::egl::NO_SURFACE, ffi::egl::NO_SURFACE, ffi::egl::NO_CONTEXT);
-        if ret == 0 {
-            panic!("on_surface_destroyed: eglMakeCurrent failed");
-        }
+        let result = if ret == 0 {
+            Err(ContextError::IoError(io::Error::new(io::ErrorKind::Other,
+                format!("eglMakeCurrent failed (eglGetError returned 0x{:x})",
+                        self.egl.GetError()))))
+        } else {
+            Ok(())
+        };
 
         self.egl.DestroySurface(self.display, self.surface.get());
         self.surface.set(ffi::egl::NO_SURFACE);
+        result
     }
 }
 
@@ -287,7 +451,9 @@ impl GlContext for Context {
         if ret == 0 {
             match self.egl.GetError() as u32 {
                 ffi::egl::CONTEXT_LOST => return Err(ContextError::ContextLost),
-                err => panic!("eglMakeCurrent failed (eglGetError returned 0x{:x})", err)
+                // for example `EGL_BAD_NATIVE_WINDOW` if the window has been destroyed
+                err => return Err(ContextError::IoError(io::Error::new(io::ErrorKind::Other,
+                    format!("eglMakeCurrent failed (eglGetError returned 0x{:x})", err)))),
             }
 
         } else {
@@ -308,8 +474,18 @@ impl GlContext for Context {
         }
     }
 
+    fn is_extension_supported(&self, name: &str) -> bool {
+        self.extensions.iter().any(|e| e == name) ||
+            extensions::is_gl_extension_supported(self, name)
+    }
+
     #[inline]
     fn swap_buffers(&self) -> Result<(), ContextError> {
+        // there is nothing to present, but the commands must still be executed
+        if self.surfaceless {
+            self.flush();
+            return Ok(());
+        }
         if self.surface.get() == ffi::egl::NO_SURFACE {
             return Err(ContextError::ContextLost);
         }
@@ -317,6 +493,73 @@ impl GlContext for Context {
             self.egl.SwapBuffers(self.display, self.surface.get())
         };
 
+        self.check_swap_result(ret)
+    }
+
+    fn swap_buffers_with_damage(&self, rects: &[Rect]) -> Result<(), ContextError> {
+        if rects.is_empty() || self.surfaceless {
+            return self.swap_buffers();
+        }
+        if self.surface.get() == ffi::egl::NO_SURFACE {
+            return Err(ContextError::ContextLost);
+        }
+
+        // the extensions take the rectangles as a list of `x, y, width, height`
+        let mut damage = Vec::with_capacity(rects.len() * 4);
+        for rect in rects {
+            damage.extend_from_slice(&[rect.x, rect.y, rect.width as i32, rect.height as i32]);
+        }
+        let count = rects.len() as ffi::egl::types::EGLint;
+
+        let ret = unsafe {
+            if self.egl.SwapBuffersWithDamageKHR.is_loaded() &&
+               self.extensions.iter().any(|e| e == "EGL_KHR_swap_buffers_with_damage")
+            {
+                self.egl.SwapBuffersWithDamageKHR(self.display, self.surface.get(),
+                                                  damage.as_mut_ptr(), count)
+            } else if self.egl.SwapBuffersWithDamageEXT.is_loaded() &&
+                      self.extensions.iter().any(|e| e == "EGL_EXT_swap_buffers_with_damage")
+            {
+                self.egl.SwapBuffersWithDamageEXT(self.display, self.surface.get(),
+                                                  damage.as_mut_ptr(), count)
+            } else {
+                return Err(ContextError::Unsupported);
+            }
+        };
+
+        self.check_swap_result(ret)
+    }
+
+    fn buffer_age(&self) -> Option<u32> {
+        if self.surface.get() == ffi::egl::NO_SURFACE ||
+           !self.extensions.iter().any(|e| e == "EGL_EXT_buffer_age")
+        {
+            return None;
+        }
+
+        let mut age = 0;
+        let ret = unsafe {
+            self.egl.QuerySurface(self.display, self.surface.get(),
+                                  ffi::egl::BUFFER_AGE_EXT as ffi::egl::types::EGLint, &mut age)
+        };
+
+        if ret == 0 { None } else { Some(age as u32) }
+    }
+
+    #[inline]
+    fn get_api(&self) -> Api {
+        self.api
+    }
+
+    #[inline]
+    fn get_pixel_format(&self) -> PixelFormat {
+        self.pixel_format.clone()
+    }
+}
+
+impl Context {
+    /// Turns the value returned by one of the `eglSwapBuffers` functions into a result.
+    fn check_swap_result(&self, ret: ffi::egl::types::EGLBoolean) -> Result<(), ContextError> {
         if ret == 0 {
             match unsafe { self.egl.GetError() } as u32 {
                 ffi::egl::CONTEXT_LOST => return Err(ContextError::ContextLost),
@@ -326,6 +569,7 @@ impl GlContext for Context {
                 // We treat it as CONTEXT_LOST error to avoid a panic in servo.
                 // The surface will be restored when the app comes to foreground.
                 #[cfg(target_os = "android")] ffi::egl::BAD_SURFACE => return Err(ContextError::ContextLost),
+                #[cfg(target_os = "android")] ffi::egl::BAD_NATIVE_WINDOW => return Err(ContextError::ContextLost),
                 err => panic!("eglSwapBuffers failed (eglGetError returned 0x{:x})", err)
             }
 
@@ -333,16 +577,6 @@ impl GlContext for Context {
             Ok(())
         }
     }
-
-    #[inline]
-    fn get_api(&self) -> Api {
-        self.api
-    }
-
-    #[inline]
-    fn get_pixel_format(&self) -> PixelFormat {
-        self.pixel_format.clone()
-    }
 }
 
 unsafe impl Send for Context {}
@@ -389,9 +623,10 @@ impl<'a> ContextPrototype<'a> {
     pub fn finish(self, native_window: ffi::EGLNativeWindowType)
                   -> Result<Context, CreationError>
     {
+        let attributes = surface_attributes(&self.pixel_format);
         let surface = unsafe {
             let surface = self.egl.CreateWindowSurface(self.display, self.config_id, native_window,
-                                                       ptr::null());
+                                                       attributes.as_ptr());
             if surface.is_null() {
                 return Err(CreationError::OsError(format!("eglCreateWindowSurface failed")))
             }
@@ -402,11 +637,11 @@ impl<'a> ContextPrototype<'a> {
     }
 
     pub fn finish_pbuffer(self, dimensions: (u32, u32)) -> Result<Context, CreationError> {
-        let attrs = &[
+        let mut attrs = vec![
             ffi::egl::WIDTH as c_int, dimensions.0 as c_int,
             ffi::egl::HEIGHT as c_int, dimensions.1 as c_int,
-            ffi::egl::NONE as c_int,
         ];
+        attrs.extend(surface_attributes(&self.pixel_format));
 
         let surface = unsafe {
             let surface = self.egl.CreatePbufferSurface(self.display, self.config_id,
@@ -420,25 +655,35 @@ impl<'a> ContextPrototype<'a> {
         self.finish_impl(surface)
     }
 
+    /// Creates a context that isn't bound to any surface, which requires
+    /// `EGL_KHR_surfaceless_context`. The context has no default framebuffer, so it can only
+    /// render to framebuffer objects.
+    pub fn finish_surfaceless(self) -> Result<Context, CreationError> {
+        if !self.extensions.iter().any(|e| e == "EGL_KHR_surfaceless_context") {
+            return Err(CreationError::NotSupported);
+        }
+
+        self.finish_impl(ffi::egl::NO_SURFACE)
+    }
+
     fn finish_impl(self, surface: ffi::egl::types::EGLSurface)
                    -> Result<Context, CreationError>
     {
-        let context = unsafe {
+        let (context, robustness) = unsafe {
             if let Some(version) = self.version {
                 try!(create_context(&self.egl, self.display, &self.egl_version,
-                                    &self.extensions, self.api, version, self.config_id,
-                                    self.opengl.debug, self.opengl.robustness))
+                                    &self.extensions, self.api, version,
+                                    self.config_id, self.opengl))
 
             } else if self.api == Api::OpenGlEs {
                 if let Ok(ctxt) = create_context(&self.egl, self.display, &self.egl_version,
-                                                 &self.extensions, self.api, (2, 0), self.config_id,
-                                                 self.opengl.debug, self.opengl.robustness)
+                                                 &self.extensions, self.api, (2, 0),
+                                                 self.config_id, self.opengl)
                 {
                     ctxt
                 } else if let Ok(ctxt) = create_context(&self.egl, self.display, &self.egl_version,
                                                         &self.extensions, self.api, (1, 0),
-                                                        self.config_id, self.opengl.debug,
-                                                        self.opengl.robustness)
+                                                        self.config_id, self.opengl)
                 {
                     ctxt
                 } else {
@@ -447,20 +692,18 @@ impl<'a> ContextPrototype<'a> {
 
             } else {
                 if let Ok(ctxt) = create_context(&self.egl, self.display, &self.egl_version,
-                                                 &self.extensions, self.api, (3, 2), self.config_id,
-                                                 self.opengl.debug, self.opengl.robustness)
+                                                 &self.extensions, self.api, (3, 2),
+                                                 self.config_id, self.opengl)
                 {
                     ctxt
                 } else if let Ok(ctxt) = create_context(&self.egl, self.display, &self.egl_version,
                                                         &self.extensions, self.api, (3, 1),
-                                                        self.config_id, self.opengl.debug,
-                                                        self.opengl.robustness)
+                                                        self.config_id, self.opengl)
                 {
                     ctxt
                 } else if let Ok(ctxt) = create_context(&self.egl, self.display, &self.egl_version,
                                                         &self.extensions, self.api, (1, 0),
-                                                        self.config_id, self.opengl.debug,
-                                                        self.opengl.robustness)
+                                                        self.config_id, self.opengl)
                 {
                     ctxt
                 } else {
@@ -469,6 +712,10 @@ impl<'a> ContextPrototype<'a> {
             }
         };
 
+        let priority = unsafe {
+            query_context_priority(&self.egl, self.display, context, &self.extensions)
+        };
+
         Ok(Context {
             egl: self.egl,
             display: self.display,
@@ -476,15 +723,22 @@ impl<'a> ContextPrototype<'a> {
             surface: Cell::new(surface),
             api: self.api,
             pixel_format: self.pixel_format,
-            config_id: self.config_id
+            config_id: self.config_id,
+            extensions: self.extensions,
+            robustness: robustness,
+            priority: priority,
+            surfaceless: surface == ffi::egl::NO_SURFACE,
         })
     }
 }
 
-unsafe fn choose_fbconfig(egl: &ffi::egl::Egl, display: ffi::egl::types::EGLDisplay,
-                          egl_version: &(ffi::egl::types::EGLint, ffi::egl::types::EGLint),
-                          api: Api, version: Option<(u8, u8)>, reqs: &PixelFormatRequirements)
-                          -> Result<(ffi::egl::types::EGLConfig, PixelFormat), CreationError>
+unsafe fn choose_fbconfig<F>(egl: &ffi::egl::Egl, display: ffi::egl::types::EGLDisplay,
+                             egl_version: &(ffi::egl::types::EGLint, ffi::egl::types::EGLint),
+                             extensions: &[String], api: Api, version: Option<(u8, u8)>,
+                             surface_type: ffi::egl::types::EGLenum,
+                             reqs: &PixelFormatRequirements, config_filter: &F)
+                             -> Result<(ffi::egl::types::EGLConfig, PixelFormat), CreationError>
+    where F: Fn(&ffi::egl::Egl, ffi::egl::types::EGLDisplay, ffi::egl::types::EGLConfig) -> bool
 {
     let descriptor = {
         let mut out: Vec<c_int> = Vec::with_capacity(37);
@@ -495,9 +749,9 @@ unsafe fn choose_fbconfig(egl: &ffi::egl::Egl, display: ffi::egl::types::EGLDisp
         }
 
         out.push(ffi::egl::SURFACE_TYPE as c_int);
-        // TODO: Some versions of Mesa report a BAD_ATTRIBUTE error
-        // if we ask for PBUFFER_BIT as well as WINDOW_BIT
-        out.push((ffi::egl::WINDOW_BIT) as c_int);
+        // Some versions of Mesa report a BAD_ATTRIBUTE error if we ask for PBUFFER_BIT as well
+        // as WINDOW_BIT, so only the surface that will be created is asked for
+        out.push(surface_type as c_int);
 
         match (api, version) {
             (Api::OpenGlEs, Some((3, _))) => {
@@ -579,8 +833,6 @@ unsafe fn choose_fbconfig(egl: &ffi::egl::Egl, display: ffi::egl::types::EGLDisp
             return Err(CreationError::NoAvailablePixelFormat);
         }
 
-        // FIXME: srgb is not taken into account
-
         match reqs.release_behavior {
             ReleaseBehavior::Flush => (),
             ReleaseBehavior::None => {
@@ -593,23 +845,91 @@ unsafe fn choose_fbconfig(egl: &ffi::egl::Egl, display: ffi::egl::types::EGLDisp
         out
     };
 
-    // calling `eglChooseConfig`
-    let mut config_id = mem::uninitialized();
-    let mut num_configs = mem::uninitialized();
-    if egl.ChooseConfig(display, descriptor.as_ptr(), &mut config_id, 1, &mut num_configs) == 0 {
+    // calling `eglChooseConfig`, once to get the number of configs and once to get the configs;
+    // the sizes in the descriptor are only minimums, so the configs are then compared with the
+    // requirements by `select_config`
+    let mut num_configs = 0;
+    if egl.ChooseConfig(display, descriptor.as_ptr(), ptr::null_mut(), 0, &mut num_configs) == 0 {
         return Err(CreationError::OsError(format!("eglChooseConfig failed")));
     }
     if num_configs == 0 {
         return Err(CreationError::NoAvailablePixelFormat);
     }
 
-    // analyzing each config
+    let mut configs = Vec::with_capacity(num_configs as usize);
+    if egl.ChooseConfig(display, descriptor.as_ptr(), configs.as_mut_ptr(), num_configs,
+                        &mut num_configs) == 0
+    {
+        return Err(CreationError::OsError(format!("eglChooseConfig failed")));
+    }
+    configs.set_len(num_configs as usize);
+
+    // sRGB is a property of the surfaces, which can be requested for the configs with 8 bits per
+    // channel if `EGL_GL_COLORSPACE` is supported
+    let colorspace_supported = egl_version >= &(1, 5) ||
+                               extensions.iter().any(|e| e == "EGL_KHR_gl_colorspace");
+
+    let mut attribs = Vec::with_capacity(configs.len());
+    for &config in configs.iter() {
+        attribs.push(try!(get_config_attribs(egl, display, config, colorspace_supported)));
+    }
+
+    let index = match select_config(reqs, &attribs, |i| config_filter(egl, display, configs[i])) {
+        Some(i) => i,
+        None => return Err(CreationError::NoAvailablePixelFormat),
+    };
+
+    let mut config_id = 0;
+    if egl.GetConfigAttrib(display, configs[index], ffi::egl::CONFIG_ID as ffi::egl::types::EGLint,
+                           &mut config_id) == 0
+    {
+        return Err(CreationError::OsError(format!("eglGetConfigAttrib failed")));
+    }
+
+    let config = &attribs[index];
+    let desc = PixelFormat {
+        hardware_accelerated: config.hardware_accelerated,
+        color_bits: config.red_bits + config.green_bits + config.blue_bits,
+        alpha_bits: config.alpha_bits,
+        depth_bits: config.depth_bits,
+        stencil_bits: config.stencil_bits,
+        stereoscopy: false,
+        double_buffer: true,
+        multisampling: if config.samples > 1 { Some(config.samples) } else { None },
+        // the surfaces are only created with the sRGB colorspace when it is requested
+        srgb: reqs.srgb,
+        aux_buffers: 0,
+        accum_bits: 0,
+        native_id: Some(config_id as u64),
+    };
+
+    Ok((configs[index], desc))
+}
+
+/// The attributes of a config that are compared with the requirements.
+#[derive(Debug, Clone, Copy)]
+struct ConfigAttribs {
+    hardware_accelerated: bool,
+    red_bits: u8,
+    green_bits: u8,
+    blue_bits: u8,
+    alpha_bits: u8,
+    depth_bits: u8,
+    stencil_bits: u8,
+    samples: u16,
+    srgb_capable: bool,
+}
+
+unsafe fn get_config_attribs(egl: &ffi::egl::Egl, display: ffi::egl::types::EGLDisplay,
+                             config: ffi::egl::types::EGLConfig, colorspace_supported: bool)
+                             -> Result<ConfigAttribs, CreationError>
+{
     macro_rules! attrib {
-        ($egl:expr, $display:expr, $config:expr, $attr:expr) => (
+        ($attr:expr) => (
             {
                 let mut value = mem::uninitialized();
-                let res = $egl.GetConfigAttrib($display, $config,
-                                               $attr as ffi::egl::types::EGLint, &mut value);
+                let res = egl.GetConfigAttrib(display, config,
+                                              $attr as ffi::egl::types::EGLint, &mut value);
                 if res == 0 {
                     return Err(CreationError::OsError(format!("eglGetConfigAttrib failed")));
                 }
@@ -618,35 +938,226 @@ unsafe fn choose_fbconfig(egl: &ffi::egl::Egl, display: ffi::egl::types::EGLDisp
         )
     };
 
-    let desc = PixelFormat {
-        hardware_accelerated: attrib!(egl, display, config_id, ffi::egl::CONFIG_CAVEAT)
-                                      != ffi::egl::SLOW_CONFIG as i32,
-        color_bits: attrib!(egl, display, config_id, ffi::egl::RED_SIZE) as u8 +
-                    attrib!(egl, display, config_id, ffi::egl::BLUE_SIZE) as u8 +
-                    attrib!(egl, display, config_id, ffi::egl::GREEN_SIZE) as u8,
-        alpha_bits: attrib!(egl, display, config_id, ffi::egl::ALPHA_SIZE) as u8,
-        depth_bits: attrib!(egl, display, config_id, ffi::egl::DEPTH_SIZE) as u8,
-        stencil_bits: attrib!(egl, display, config_id, ffi::egl::STENCIL_SIZE) as u8,
-        stereoscopy: false,
-        double_buffer: true,
-        multisampling: match attrib!(egl, display, config_id, ffi::egl::SAMPLES) {
-            0 | 1 => None,
-            a => Some(a as u16),
-        },
-        srgb: false,        // TODO: use EGL_KHR_gl_colorspace to know that
+    let red_bits = attrib!(ffi::egl::RED_SIZE) as u8;
+    let green_bits = attrib!(ffi::egl::GREEN_SIZE) as u8;
+    let blue_bits = attrib!(ffi::egl::BLUE_SIZE) as u8;
+
+    Ok(ConfigAttribs {
+        hardware_accelerated: attrib!(ffi::egl::CONFIG_CAVEAT) != ffi::egl::SLOW_CONFIG as i32,
+        red_bits: red_bits,
+        green_bits: green_bits,
+        blue_bits: blue_bits,
+        alpha_bits: attrib!(ffi::egl::ALPHA_SIZE) as u8,
+        depth_bits: attrib!(ffi::egl::DEPTH_SIZE) as u8,
+        stencil_bits: attrib!(ffi::egl::STENCIL_SIZE) as u8,
+        samples: attrib!(ffi::egl::SAMPLES) as u16,
+        srgb_capable: colorspace_supported && red_bits == 8 && green_bits == 8 && blue_bits == 8,
+    })
+}
+
+/// How far from the requirements a config can be, from the closest to the loosest. Each step
+/// also allows what the previous ones allow.
+///
+/// A config never has less than what is requested, whatever the step.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+enum Relaxation {
+    /// All the sizes are the requested ones, and there is no multisampling unless requested.
+    Exact,
+    /// The depth and stencil buffers can be bigger than requested.
+    LargerDepthStencil,
+    /// The alpha can be bigger than requested.
+    LargerAlpha,
+    /// The color can be bigger than requested.
+    LargerColor,
+    /// There can be more samples than requested, or some samples if multisampling doesn't matter.
+    MoreSamples,
+}
+
+const RELAXATIONS: [Relaxation; 5] = [
+    Relaxation::Exact,
+    Relaxation::LargerDepthStencil,
+    Relaxation::LargerAlpha,
+    Relaxation::LargerColor,
+    Relaxation::MoreSamples,
+];
+
+/// Returns the index of the config that best matches `reqs`, trying the configs in their order
+/// for each relaxation of the requirements in turn. Only the configs for which `filter` returns
+/// true are considered.
+fn select_config<F>(reqs: &PixelFormatRequirements, configs: &[ConfigAttribs], filter: F)
+                    -> Option<usize>
+    where F: Fn(usize) -> bool
+{
+    for &relaxation in RELAXATIONS.iter() {
+        let found = configs.iter().enumerate()
+                           .position(|(i, c)| config_matches(reqs, c, relaxation) && filter(i));
+        if found.is_some() {
+            return found;
+        }
+    }
+
+    None
+}
+
+fn config_matches(reqs: &PixelFormatRequirements, config: &ConfigAttribs,
+                  relaxation: Relaxation) -> bool
+{
+    // `requested` is a minimum, which must be reached exactly unless `larger` is allowed
+    let size_matches = |value: u8, requested: Option<u8>, larger: Relaxation| {
+        match requested {
+            None => true,
+            Some(requested) if relaxation >= larger => value >= requested,
+            Some(requested) => value == requested,
+        }
     };
 
-    Ok((config_id, desc))
+    if let Some(hardware_accelerated) = reqs.hardware_accelerated {
+        if config.hardware_accelerated != hardware_accelerated {
+            return false;
+        }
+    }
+
+    if reqs.srgb && !config.srgb_capable {
+        return false;
+    }
+
+    // a depth or stencil size of 0 means that there must be no such buffer, which isn't relaxed
+    if (reqs.depth_bits == Some(0) && config.depth_bits != 0) ||
+       (reqs.stencil_bits == Some(0) && config.stencil_bits != 0)
+    {
+        return false;
+    }
+
+    let color_bits = config.red_bits + config.green_bits + config.blue_bits;
+    if !size_matches(color_bits, reqs.color_bits, Relaxation::LargerColor) ||
+       !size_matches(config.alpha_bits, reqs.alpha_bits, Relaxation::LargerAlpha) ||
+       !size_matches(config.depth_bits, reqs.depth_bits, Relaxation::LargerDepthStencil) ||
+       !size_matches(config.stencil_bits, reqs.stencil_bits, Relaxation::LargerDepthStencil)
+    {
+        return false;
+    }
+
+    // a single sample is the same as no multisampling
+    let samples = if config.samples > 1 { config.samples } else { 0 };
+    match reqs.multisampling {
+        Some(0) => samples == 0,
+        Some(requested) if relaxation >= Relaxation::MoreSamples => samples >= requested,
+        Some(requested) => samples == requested,
+        None => samples == 0 || relaxation >= Relaxation::MoreSamples,
+    }
 }
 
+/// The attributes of the surfaces created for `pixel_format`, terminated by `EGL_NONE`.
+fn surface_attributes(pixel_format: &PixelFormat) -> Vec<c_int> {
+    let mut out = Vec::with_capacity(3);
+    if pixel_format.srgb {
+        out.push(ffi::egl::GL_COLORSPACE as c_int);
+        out.push(ffi::egl::GL_COLORSPACE_SRGB as c_int);
+    }
+    out.push(ffi::egl::NONE as c_int);
+    out
+}
+
+/// The way the robustness of a context is requested from the implementation.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+enum RobustnessAttributes {
+    /// `EGL_EXT_create_context_robustness`, which only concerns OpenGL ES contexts.
+    Ext,
+    /// The attributes of EGL 1.5.
+    Core,
+    /// `EGL_KHR_create_context`, which only concerns OpenGL contexts.
+    Khr,
+}
+
+fn robustness_attributes(egl_version: &(ffi::egl::types::EGLint, ffi::egl::types::EGLint),
+                         extensions: &[String], api: Api) -> Option<RobustnessAttributes>
+{
+    if api == Api::OpenGlEs &&
+       extensions.iter().find(|s| s == &"EGL_EXT_create_context_robustness").is_some()
+    {
+        Some(RobustnessAttributes::Ext)
+    } else if egl_version >= &(1, 5) {
+        Some(RobustnessAttributes::Core)
+    } else if api == Api::OpenGl &&
+              extensions.iter().find(|s| s == &"EGL_KHR_create_context").is_some()
+    {
+        Some(RobustnessAttributes::Khr)
+    } else {
+        None
+    }
+}
+
+/// Creates the context, and returns it with the robustness that it actually has.
+///
+/// The `Try*` variants of `Robustness` fall back to a context that isn't robust if the
+/// implementation doesn't support robustness or refuses to create a robust context.
 unsafe fn create_context(egl: &ffi::egl::Egl, display: ffi::egl::types::EGLDisplay,
                          egl_version: &(ffi::egl::types::EGLint, ffi::egl::types::EGLint),
                          extensions: &[String], api: Api, version: (u8, u8),
-                         config_id: ffi::egl::types::EGLConfig, gl_debug: bool,
-                         gl_robustness: Robustness)
-                         -> Result<ffi::egl::types::EGLContext, CreationError>
+                         config_id: ffi::egl::types::EGLConfig,
+                         opengl: &GlAttributes<&Context>)
+                         -> Result<(ffi::egl::types::EGLContext, Robustness), CreationError>
+{
+    let gl_debug = opengl.debug;
+    let gl_robustness = opengl.robustness;
+
+    // the priority is only a hint, so it's dropped if the implementation doesn't support it
+    let priority = if extensions.iter().find(|s| s == &"EGL_IMG_context_priority").is_some() {
+        opengl.context_priority
+    } else {
+        None
+    };
+
+    let (strategy, obtained) = match gl_robustness {
+        Robustness::RobustNoResetNotification | Robustness::TryRobustNoResetNotification => {
+            (Some(ffi::egl::NO_RESET_NOTIFICATION), Robustness::RobustNoResetNotification)
+        },
+        Robustness::RobustLoseContextOnReset | Robustness::TryRobustLoseContextOnReset => {
+            (Some(ffi::egl::LOSE_CONTEXT_ON_RESET), Robustness::RobustLoseContextOnReset)
+        },
+        Robustness::NotRobust | Robustness::NoError => (None, Robustness::NotRobust),
+    };
+
+    let fallback = match gl_robustness {
+        Robustness::TryRobustNoResetNotification | Robustness::TryRobustLoseContextOnReset => true,
+        _ => false,
+    };
+
+    if let Some(strategy) = strategy {
+        match robustness_attributes(egl_version, extensions, api) {
+            Some(robustness) => {
+                let attributes = context_attributes(egl_version, extensions, api, version,
+                                                    gl_debug, Some((robustness, strategy)), false,
+                                                    priority);
+                match create_context_with_attributes(egl, display, config_id, &attributes) {
+                    Ok(context) => return Ok((context, obtained)),
+                    Err(_) if fallback => (),
+                    Err(err) => return Err(err),
+                }
+            },
+            None if fallback => (),
+            None => return Err(CreationError::RobustnessNotSupported),
+        }
+    }
+
+    let no_error = gl_robustness == Robustness::NoError &&
+                   extensions.iter().find(|s| s == &"EGL_KHR_create_context_no_error").is_some();
+    let attributes = context_attributes(egl_version, extensions, api, version, gl_debug, None,
+                                        no_error, priority);
+    let context = try!(create_context_with_attributes(egl, display, config_id, &attributes));
+    Ok((context, if no_error { Robustness::NoError } else { Robustness::NotRobust }))
+}
+
+/// Builds the list of attributes of `eglCreateContext`. `robustness` contains the way to
+/// request robustness and the reset notification strategy, and is only passed if
+/// `robustness_attributes` returned something. `priority` is only passed if the implementation
+/// supports `EGL_IMG_context_priority`.
+fn context_attributes(egl_version: &(ffi::egl::types::EGLint, ffi::egl::types::EGLint),
+                      extensions: &[String], api: Api, version: (u8, u8), gl_debug: bool,
+                      robustness: Option<(RobustnessAttributes, ffi::egl::types::EGLenum)>,
+                      no_error: bool, priority: Option<ContextPriority>) -> Vec<c_int>
 {
-    let mut context_attributes = Vec::with_capacity(10);
+    let mut context_attributes = Vec::with_capacity(16);
     let mut flags = 0;
 
     if egl_version >= &(1, 5) || extensions.iter().find(|s| s == &"EGL_KHR_create_context")
@@ -657,61 +1168,9 @@ unsafe fn create_context(egl: &ffi::egl::Egl, display: ffi::egl::types::EGLDispl
         context_attributes.push(ffi::egl::CONTEXT_MINOR_VERSION as i32);
         context_attributes.push(version.1 as i32);
 
-        // handling robustness
-        let supports_robustness = egl_version >= &(1, 5) ||
-                                  extensions.iter()
-                                            .find(|s| s == &"EGL_EXT_create_context_robustness")
-                                            .is_some();
-
-        match gl_robustness {
-            Robustness::NotRobust => (),
-
-            Robustness::NoError => {
-                if extensions.iter().find(|s| s == &"EGL_KHR_create_context_no_error").is_some() {
-                    context_attributes.push(ffi::egl::CONTEXT_OPENGL_NO_ERROR_KHR as c_int);
-                    context_attributes.push(1);
-                }
-            },
-
-            Robustness::RobustNoResetNotification => {
-                if supports_robustness {
-                    context_attributes.push(ffi::egl::CONTEXT_OPENGL_RESET_NOTIFICATION_STRATEGY
-                                            as c_int);
-                    context_attributes.push(ffi::egl::NO_RESET_NOTIFICATION as c_int);
-                    flags = flags | ffi::egl::CONTEXT_OPENGL_ROBUST_ACCESS as c_int;
-                } else {
-                    return Err(CreationError::RobustnessNotSupported);
-                }
-            },
-
-            Robustness::TryRobustNoResetNotification => {
-                if supports_robustness {
-                    context_attributes.push(ffi::egl::CONTEXT_OPENGL_RESET_NOTIFICATION_STRATEGY
-                                            as c_int);
-                    context_attributes.push(ffi::egl::NO_RESET_NOTIFICATION as c_int);
-                    flags = flags | ffi::egl::CONTEXT_OPENGL_ROBUST_ACCESS as c_int;
-                }
-            },
-
-            Robustness::RobustLoseContextOnReset => {
-                if supports_robustness {
-                    context_attributes.push(ffi::egl::CONTEXT_OPENGL_RESET_NOTIFICATION_STRATEGY
-                                            as c_int);
-                    context_attributes.push(ffi::egl::LOSE_CONTEXT_ON_RESET as c_int);
-                    flags = flags | ffi::egl::CONTEXT_OPENGL_ROBUST_ACCESS as c_int;
-                } else {
-                    return Err(CreationError::RobustnessNotSupported);
-                }
-            },
-
-            Robustness::TryRobustLoseContextOnReset => {
-                if supports_robustness {
-                    context_attributes.push(ffi::egl::CONTEXT_OPENGL_RESET_NOTIFICATION_STRATEGY
-                                            as c_int);
-                    context_attributes.push(ffi::egl::LOSE_CONTEXT_ON_RESET as c_int);
-                    flags = flags | ffi::egl::CONTEXT_OPENGL_ROBUST_ACCESS as c_int;
-                }
-            },
+        if no_error {
+            context_attributes.push(ffi::egl::CONTEXT_OPENGL_NO_ERROR_KHR as c_int);
+            context_attributes.push(1);
         }
 
         if gl_debug {
@@ -727,33 +1186,182 @@ unsafe fn create_context(egl: &ffi::egl::Egl, display: ffi::egl::types::EGLDispl
             //flags = flags | ffi::egl::CONTEXT_OPENGL_DEBUG_BIT_KHR as i32;
         }
 
-        context_attributes.push(ffi::egl::CONTEXT_FLAGS_KHR as i32);
-        context_attributes.push(flags);
-
     } else if egl_version >= &(1, 3) && api == Api::OpenGlEs {
-        // robustness is not supported
-        match gl_robustness {
-            Robustness::RobustNoResetNotification | Robustness::RobustLoseContextOnReset => {
-                return Err(CreationError::RobustnessNotSupported);
-            },
-            _ => ()
-        }
-
         context_attributes.push(ffi::egl::CONTEXT_CLIENT_VERSION as i32);
         context_attributes.push(version.0 as i32);
     }
 
+    // the extensions and EGL 1.5 use the same values for the strategies
+    match robustness {
+        Some((RobustnessAttributes::Ext, strategy)) => {
+            context_attributes.push(ffi::egl::CONTEXT_OPENGL_ROBUST_ACCESS_EXT as c_int);
+            context_attributes.push(ffi::egl::TRUE as c_int);
+            context_attributes.push(ffi::egl::CONTEXT_OPENGL_RESET_NOTIFICATION_STRATEGY_EXT
+                                    as c_int);
+            context_attributes.push(strategy as c_int);
+        },
+        Some((RobustnessAttributes::Core, strategy)) => {
+            context_attributes.push(ffi::egl::CONTEXT_OPENGL_ROBUST_ACCESS as c_int);
+            context_attributes.push(ffi::egl::TRUE as c_int);
+            context_attributes.push(ffi::egl::CONTEXT_OPENGL_RESET_NOTIFICATION_STRATEGY
+                                    as c_int);
+            context_attributes.push(strategy as c_int);
+        },
+        Some((RobustnessAttributes::Khr, strategy)) => {
+            flags = flags | ffi::egl::CONTEXT_OPENGL_ROBUST_ACCESS_BIT_KHR as c_int;
+            context_attributes.push(ffi::egl::CONTEXT_OPENGL_RESET_NOTIFICATION_STRATEGY_KHR
+                                    as c_int);
+            context_attributes.push(strategy as c_int);
+        },
+        None => (),
+    }
+
+    if flags != 0 {
+        context_attributes.push(ffi::egl::CONTEXT_FLAGS_KHR as i32);
+        context_attributes.push(flags);
+    }
+
+    if let Some(priority) = priority {
+        context_attributes.push(ffi::egl::CONTEXT_PRIORITY_LEVEL_IMG as c_int);
+        context_attributes.push(match priority {
+            ContextPriority::Low => ffi::egl::CONTEXT_PRIORITY_LOW_IMG,
+            ContextPriority::Medium => ffi::egl::CONTEXT_PRIORITY_MEDIUM_IMG,
+            ContextPriority::High => ffi::egl::CONTEXT_PRIORITY_HIGH_IMG,
+        } as c_int);
+    }
+
     context_attributes.push(ffi::egl::NONE as i32);
+    context_attributes
+}
 
-    let context = egl.CreateContext(display, config_id, ptr::null(),
-                                    context_attributes.as_ptr());
+/// Returns the priority that the implementation granted to the context, or `None` if it
+/// doesn't support `EGL_IMG_context_priority`.
+unsafe fn query_context_priority(egl: &ffi::egl::Egl, display: ffi::egl::types::EGLDisplay,
+                                 context: ffi::egl::types::EGLContext, extensions: &[String])
+                                 -> Option<ContextPriority>
+{
+    if extensions.iter().find(|s| s == &"EGL_IMG_context_priority").is_none() {
+        return None;
+    }
+
+    let mut value = 0;
+    if egl.QueryContext(display, context, ffi::egl::CONTEXT_PRIORITY_LEVEL_IMG as c_int,
+                        &mut value) == 0
+    {
+        return None;
+    }
+
+    match value as ffi::egl::types::EGLenum {
+        ffi::egl::CONTEXT_PRIORITY_LOW_IMG => Some(ContextPriority::Low),
+        ffi::egl::CONTEXT_PRIORITY_MEDIUM_IMG => Some(ContextPriority::Medium),
+        ffi::egl::CONTEXT_PRIORITY_HIGH_IMG => Some(ContextPriority::High),
+        _ => None,
+    }
+}
+
+unsafe fn create_context_with_attributes(egl: &ffi::egl::Egl,
+                                         display: ffi::egl::types::EGLDisplay,
+                                         config_id: ffi::egl::types::EGLConfig,
+                                         attributes: &[c_int])
+                                         -> Result<ffi::egl::types::EGLContext, CreationError>
+{
+    let context = egl.CreateContext(display, config_id, ptr::null(), attributes.as_ptr());
 
     if context.is_null() {
         match egl.GetError() as u32 {
-            ffi::egl::BAD_ATTRIBUTE => return Err(CreationError::OpenGlVersionNotSupported),
+            ffi::egl::BAD_ATTRIBUTE | ffi::egl::BAD_MATCH => {
+                return Err(CreationError::OpenGlVersionNotSupported)
+            },
             e => panic!("eglCreateContext failed: 0x{:x}", e),
         }
     }
 
     Ok(context)
 }
+
+#[cfg(test)]
+mod tests {
+    use super::{ConfigAttribs, RobustnessAttributes, robustness_attributes, select_config};
+    use Api;
+    use PixelFormatRequirements;
+
+    fn config(color: (u8, u8, u8), alpha: u8, depth: u8, stencil: u8, samples: u16)
+              -> ConfigAttribs
+    {
+        ConfigAttribs {
+            hardware_accelerated: true,
+            red_bits: color.0,
+            green_bits: color.1,
+            blue_bits: color.2,
+            alpha_bits: alpha,
+            depth_bits: depth,
+            stencil_bits: stencil,
+            samples: samples,
+            srgb_capable: color == (8, 8, 8),
+        }
+    }
+
+    #[test]
+    fn smaller_color_is_rejected() {
+        let configs = [config((5, 6, 5), 8, 24, 8, 0)];
+        assert_eq!(select_config(&Default::default(), &configs, |_| true), None);
+    }
+
+    #[test]
+    fn exact_sizes_are_preferred() {
+        let configs = [config((10, 10, 10), 8, 24, 8, 0), config((8, 8, 8), 8, 24, 8, 0)];
+        assert_eq!(select_config(&Default::default(), &configs, |_| true), Some(1));
+    }
+
+    #[test]
+    fn larger_depth_is_preferred_to_larger_color() {
+        let configs = [config((10, 10, 10), 8, 24, 8, 0), config((8, 8, 8), 8, 32, 8, 0)];
+        assert_eq!(select_config(&Default::default(), &configs, |_| true), Some(1));
+    }
+
+    #[test]
+    fn unrequested_multisampling_is_a_last_resort() {
+        let configs = [config((8, 8, 8), 8, 24, 8, 4), config((10, 10, 10), 8, 32, 8, 0)];
+        assert_eq!(select_config(&Default::default(), &configs, |_| true), Some(1));
+
+        let configs = [config((8, 8, 8), 8, 24, 8, 4)];
+        assert_eq!(select_config(&Default::default(), &configs, |_| true), Some(0));
+
+        let reqs = PixelFormatRequirements { multisampling: Some(0), .. Default::default() };
+        assert_eq!(select_config(&reqs, &configs, |_| true), None);
+    }
+
+    #[test]
+    fn zero_depth_and_stencil_are_never_relaxed() {
+        let reqs = PixelFormatRequirements { depth_bits: Some(0), stencil_bits: Some(0),
+                                             .. Default::default() };
+        let configs = [config((8, 8, 8), 8, 24, 8, 0), config((8, 8, 8), 8, 0, 0, 0)];
+        assert_eq!(select_config(&reqs, &configs, |_| true), Some(1));
+        assert_eq!(select_config(&reqs, &configs[.. 1], |_| true), None);
+    }
+
+    #[test]
+    fn srgb_is_never_relaxed() {
+        let reqs = PixelFormatRequirements { srgb: true, .. Default::default() };
+        let configs = [config((10, 10, 10), 8, 24, 8, 0), config((8, 8, 8), 8, 24, 8, 0)];
+        assert_eq!(select_config(&reqs, &configs, |_| true), Some(1));
+        assert_eq!(select_config(&reqs, &configs[.. 1], |_| true), None);
+    }
+
+    #[test]
+    fn filtered_configs_are_skipped() {
+        let configs = [config((8, 8, 8), 8, 24, 8, 0), config((8, 8, 8), 8, 24, 8, 0)];
+        assert_eq!(select_config(&Default::default(), &configs, |i| i != 0), Some(1));
+    }
+
+    #[test]
+    fn robustness_ext_only_applies_to_gles() {
+        let extensions = vec!["EGL_EXT_create_context_robustness".to_owned()];
+        assert_eq!(robustness_attributes(&(1, 4), &extensions, Api::OpenGlEs),
+                   Some(RobustnessAttributes::Ext));
+        assert_eq!(robustness_attributes(&(1, 4), &extensions, Api::OpenGl), None);
+        assert_eq!(robustness_attributes(&(1, 5), &extensions, Api::OpenGl),
+                   Some(RobustnessAttributes::Core));
+        assert_eq!(robustness_attributes(&(1, 4), &[], Api::OpenGlEs), None);
+    }
+}
@@ -6,7 +6,17 @@ use Api;
 use Event;
 use CreationError;
 use ContextError;
+use ContextPriority;
+use Robustness;
 use CursorState;
+use CursorGrabMode;
+use ContentProtectionError;
+use CursorStateError;
+use Rect;
+use RawEventFilter;
+use ResizeEdge;
+use ScanCode;
+use VirtualKeyCode;
 use GlAttributes;
 use GlContext;
 use MouseCursor;
@@ -48,6 +58,41 @@ impl<'a> Iterator for WaitEventsIterator<'a> {
     }
 }
 
+/// Windows can't have other contexts on this platform.
+pub enum SharedContext {}
+
+impl GlContext for SharedContext {
+    #[inline]
+    unsafe fn make_current(&self) -> Result<(), ContextError> {
+        match *self {}
+    }
+
+    #[inline]
+    fn is_current(&self) -> bool {
+        match *self {}
+    }
+
+    #[inline]
+    fn get_proc_address(&self, _: &str) -> *const () {
+        match *self {}
+    }
+
+    #[inline]
+    fn swap_buffers(&self) -> Result<(), ContextError> {
+        match *self {}
+    }
+
+    #[inline]
+    fn get_api(&self) -> Api {
+        match *self {}
+    }
+
+    #[inline]
+    fn get_pixel_format(&self) -> PixelFormat {
+        match *self {}
+    }
+}
+
 #[derive(Clone)]
 pub struct WindowProxy;
 
@@ -56,6 +101,10 @@ impl WindowProxy {
     pub fn wakeup_event_loop(&self) {
         unimplemented!()
     }
+
+    #[inline]
+    pub fn request_close(&self) {
+    }
 }
 
 #[derive(Clone)]
@@ -69,8 +118,18 @@ pub fn get_available_monitors() -> VecDeque<MonitorId> {
 }
 
 #[inline]
-pub fn get_primary_monitor() -> MonitorId {
-    MonitorId
+pub fn get_primary_monitor() -> Option<MonitorId> {
+    Some(MonitorId)
+}
+
+#[inline]
+pub fn monitor_from_point(_: i32, _: i32) -> Option<MonitorId> {
+    Some(MonitorId)
+}
+
+#[inline]
+pub fn has_hardware_acceleration() -> bool {
+    true
 }
 
 impl MonitorId {
@@ -88,6 +147,11 @@ impl MonitorId {
     pub fn get_dimensions(&self) -> (u32, u32) {
         unimplemented!()
     }
+
+    #[inline]
+    pub fn get_icc_profile(&self) -> Option<Vec<u8>> {
+        None
+    }
 }
 
 impl Window {
@@ -139,6 +203,11 @@ impl Window {
         Some((0, 0))
     }
 
+    #[inline]
+    pub fn get_outer_position(&self) -> Option<(i32, i32)> {
+        self.get_position()
+    }
+
     #[inline]
     pub fn set_position(&self, _: i32, _: i32) {
     }
@@ -173,6 +242,42 @@ impl Window {
         }
     }
 
+    #[inline]
+    pub fn request_inner_size(&self, width: u32, height: u32) -> Option<(u32, u32)> {
+        self.set_inner_size(width, height);
+        self.get_inner_size()
+    }
+
+    #[inline]
+    pub fn set_min_dimensions(&self, _: Option<(u32, u32)>) {
+    }
+
+    #[inline]
+    pub fn set_max_dimensions(&self, _: Option<(u32, u32)>) {
+    }
+
+    #[inline]
+    pub fn set_aspect_ratio(&self, _: Option<(u32, u32)>) {
+    }
+
+    #[inline]
+    pub fn set_decorations(&self, _: bool) {
+    }
+
+    #[inline]
+    pub fn set_opacity(&self, _: f32) {
+    }
+
+    #[inline]
+    pub fn set_content_protected(&self, _: bool) -> Result<(), ContentProtectionError> {
+        Err(ContentProtectionError::Unsupported)
+    }
+
+    #[inline]
+    pub fn set_cursor_hittest(&self, _: bool) -> Result<(), ()> {
+        Err(())
+    }
+
     #[inline]
     pub fn poll_events(&self) -> PollEventsIterator {
         PollEventsIterator {
@@ -187,6 +292,11 @@ impl Window {
         }
     }
 
+    #[inline]
+    pub fn drain_events(&self, events: &mut Vec<Event>) {
+        events.extend(self.poll_events());
+    }
+
     #[inline]
     pub fn create_window_proxy(&self) -> WindowProxy {
         WindowProxy
@@ -196,6 +306,12 @@ impl Window {
     pub fn show(&self) {}
     #[inline]
     pub fn hide(&self) {}
+    #[inline]
+    pub fn drag_window(&self) -> Result<(), ()> { Err(()) }
+    #[inline]
+    pub fn drag_resize_window(&self, _: ResizeEdge) -> Result<(), ()> { Err(()) }
+    #[inline]
+    pub fn focus_window(&self) {}
 
     #[inline]
     pub fn platform_display(&self) -> *mut libc::c_void {
@@ -207,6 +323,13 @@ impl Window {
         unimplemented!()
     }
 
+    #[inline]
+    pub fn create_shared_context(&self, _: &GlAttributes<&Window>)
+                                 -> Result<SharedContext, CreationError>
+    {
+        Err(CreationError::NotSupported)
+    }
+
     #[inline]
     pub fn set_window_resize_callback(&mut self, _: Option<fn(u32, u32)>) {
     }
@@ -216,8 +339,71 @@ impl Window {
     }
 
     #[inline]
-    pub fn set_cursor_state(&self, state: CursorState) -> Result<(), String> {
-        Ok(())
+    pub fn set_cursor_state(&self, state: CursorState) -> Result<(), CursorStateError> {
+        self.set_cursor_grab(state.grab_mode())
+    }
+
+    #[inline]
+    pub fn set_cursor_grab(&self, mode: CursorGrabMode) -> Result<(), CursorStateError> {
+        match mode {
+            CursorGrabMode::None => Ok(()),
+            _ => Err(CursorStateError::Unsupported),
+        }
+    }
+
+    #[inline]
+    pub fn confine_cursor(&self, rect: Option<Rect>) -> Result<(), CursorStateError> {
+        match rect {
+            None => Ok(()),
+            Some(_) => Err(CursorStateError::Unsupported),
+        }
+    }
+
+    #[inline]
+    pub fn set_ime_allowed(&self, _allowed: bool) {
+    }
+
+    #[inline]
+    pub fn set_ime_position(&self, _x: i32, _y: i32) {
+    }
+
+    #[inline]
+    pub fn set_raw_event_filter(&self, _: Option<RawEventFilter>) {
+    }
+
+    #[inline]
+    pub fn scancode_to_keycode(&self, _: ScanCode) -> Option<VirtualKeyCode> {
+        None
+    }
+
+    #[inline]
+    pub fn keycode_to_scancode(&self, _: VirtualKeyCode) -> Option<ScanCode> {
+        None
+    }
+
+    #[inline]
+    pub fn key_name(&self, _: ScanCode) -> Option<String> {
+        None
+    }
+
+    #[inline]
+    pub fn get_context_priority(&self) -> Option<ContextPriority> {
+        None
+    }
+
+    #[inline]
+    pub fn get_robustness(&self) -> Option<Robustness> {
+        None
+    }
+
+    #[inline]
+    pub fn get_available_monitors(&self) -> VecDeque<MonitorId> {
+        get_available_monitors()
+    }
+
+    #[inline]
+    pub fn get_current_monitor(&self) -> Option<MonitorId> {
+        get_primary_monitor()
     }
 
     #[inline]
@@ -226,7 +412,7 @@ impl Window {
     }
 
     #[inline]
-    pub fn set_cursor_position(&self, x: i32, y: i32) -> Result<(), ()> {
+    pub fn set_cursor_position(&self, x: f64, y: f64) -> Result<(), ()> {
         Ok(())
     }
 }
@@ -7,6 +7,7 @@ use GlContext;
 use GlProfile;
 use GlRequest;
 use Api;
+use extensions;
 use PixelFormat;
 use PixelFormatRequirements;
 use ReleaseBehavior;
@@ -15,18 +16,25 @@ use Robustness;
 use libc;
 use libc::c_int;
 use std::ffi::{CStr, CString};
-use std::{mem, ptr, slice};
+use std::{io, mem, ptr, slice};
 
 use api::x11::ffi;
 
 use platform::Window as PlatformWindow;
 
+// attribute of `GLX_EXT_buffer_age`, which isn't in the registry that the bindings are
+// generated from
+const GLX_BACK_BUFFER_AGE_EXT: c_int = 0x20F4;
+
 pub struct Context {
     glx: ffi::glx::Glx,
     display: *mut ffi::Display,
     window: ffi::Window,
     context: ffi::GLXContext,
+    fb_config: ffi::glx::types::GLXFBConfig,
     pixel_format: PixelFormat,
+    /// The GLX extensions supported by the screen of the context.
+    extensions: String,
 }
 
 // TODO: remove me
@@ -39,7 +47,8 @@ fn with_c_str<F, T>(s: &str, f: F) -> T where F: FnOnce(*const libc::c_char) ->
 impl Context {
     pub fn new<'a>(glx: ffi::glx::Glx, xlib: &ffi::Xlib, pf_reqs: &PixelFormatRequirements,
                    opengl: &'a GlAttributes<&'a Context>, display: *mut ffi::Display,
-                   screen_id: libc::c_int) -> Result<ContextPrototype<'a>, CreationError>
+                   screen_id: libc::c_int, transparent: bool)
+                   -> Result<ContextPrototype<'a>, CreationError>
     {
         // This is completely ridiculous, but VirtualBox's OpenGL driver needs some call handled by
         // *it* (i.e. not Mesa) to occur before anything else can happen. That is because
@@ -62,7 +71,7 @@ impl Context {
 
         // finding the pixel format we want
         let (fb_config, pixel_format) = unsafe {
-            try!(choose_fbconfig(&glx, &extensions, xlib, display, screen_id, pf_reqs)
+            try!(choose_fbconfig(&glx, &extensions, xlib, display, screen_id, pf_reqs, transparent)
                                           .map_err(|_| CreationError::NoAvailablePixelFormat))
         };
 
@@ -87,14 +96,60 @@ impl Context {
             pixel_format: pixel_format,
         })
     }
+
+    /// Creates another context that renders to the window of this one, with the same
+    /// framebuffer configuration since GLX requires the configurations to be compatible.
+    pub fn new_shared<'a>(&self, xlib: &ffi::Xlib, opengl: &'a GlAttributes<&'a Context>)
+                          -> Result<Context, CreationError>
+    {
+        let visual_infos: ffi::glx::types::XVisualInfo = unsafe {
+            let vi = self.glx.GetVisualFromFBConfig(self.display as *mut _, self.fb_config);
+            if vi.is_null() {
+                return Err(CreationError::OsError(format!("glxGetVisualFromFBConfig failed")));
+            }
+            let vi_copy = ptr::read(vi as *const _);
+            (xlib.XFree)(vi as *mut _);
+            vi_copy
+        };
+
+        let prototype = ContextPrototype {
+            glx: self.glx.clone(),
+            extensions: self.extensions.clone(),
+            opengl: opengl,
+            display: self.display,
+            fb_config: self.fb_config,
+            visual_infos: unsafe { mem::transmute(visual_infos) },
+            pixel_format: self.pixel_format.clone(),
+        };
+
+        prototype.finish(self.window)
+    }
+
+    /// Returns the connection that the context has been created on.
+    #[inline]
+    pub fn get_display(&self) -> *mut ffi::Display {
+        self.display
+    }
+
+    /// Returns the raw `GLXContext`.
+    #[inline]
+    pub fn get_glx_context(&self) -> ffi::GLXContext {
+        self.context
+    }
+
+    /// Returns the window that the context renders to.
+    #[inline]
+    pub fn get_drawable(&self) -> ffi::Window {
+        self.window
+    }
 }
 
 impl GlContext for Context {
     unsafe fn make_current(&self) -> Result<(), ContextError> {
-        // TODO: glutin needs some internal changes for proper error recovery
         let res = self.glx.MakeCurrent(self.display as *mut _, self.window, self.context);
         if res == 0 {
-            panic!("glx::MakeCurrent failed");
+            return Err(ContextError::IoError(io::Error::new(io::ErrorKind::Other,
+                                                            "glXMakeCurrent failed")));
         }
         Ok(())
     }
@@ -112,13 +167,38 @@ impl GlContext for Context {
         }
     }
 
+    fn is_extension_supported(&self, name: &str) -> bool {
+        self.extensions.split(' ').any(|e| e == name) ||
+            extensions::is_gl_extension_supported(self, name)
+    }
+
     #[inline]
     fn swap_buffers(&self) -> Result<(), ContextError> {
+        // `glXSwapBuffers` does nothing with a single buffer, but the commands must still be
+        // executed
+        if !self.pixel_format.double_buffer {
+            self.flush();
+            return Ok(());
+        }
+
         // TODO: glutin needs some internal changes for proper error recovery
         unsafe { self.glx.SwapBuffers(self.display as *mut _, self.window); }
         Ok(())
     }
 
+    fn buffer_age(&self) -> Option<u32> {
+        if !self.extensions.split(' ').any(|e| e == "GLX_EXT_buffer_age") {
+            return None;
+        }
+
+        let mut age = 0;
+        unsafe {
+            self.glx.QueryDrawable(self.display as *mut _, self.window, GLX_BACK_BUFFER_AGE_EXT,
+                                   &mut age);
+        }
+        Some(age)
+    }
+
     #[inline]
     fn get_api(&self) -> ::Api {
         ::Api::OpenGl
@@ -161,6 +241,16 @@ impl<'a> ContextPrototype<'a> {
         &self.visual_infos
     }
 
+    /// Returns the `GLX_FBCONFIG_ID` of the framebuffer configuration that has been chosen.
+    pub fn get_fbconfig_id(&self) -> c_int {
+        let mut value = 0;
+        unsafe {
+            self.glx.GetFBConfigAttrib(self.display as *mut _, self.fb_config,
+                                       ffi::glx::FBCONFIG_ID as c_int, &mut value);
+        }
+        value
+    }
+
     pub fn finish(self, window: ffi::Window) -> Result<Context, CreationError> {
         let share = match self.opengl.sharing {
             Some(ctxt) => ctxt.context,
@@ -202,14 +292,14 @@ impl<'a> ContextPrototype<'a> {
                 try!(create_context(&self.glx, &extra_functions, &self.extensions, (major, minor),
                                     self.opengl.profile, self.opengl.debug,
                                     self.opengl.robustness, share, self.display, self.fb_config,
-                                    &self.visual_infos))
+                                    &self.visual_infos).map_err(version_error))
             },
             GlRequest::Specific(_, _) => panic!("Only OpenGL is supported"),
             GlRequest::GlThenGles { opengl_version: (major, minor), .. } => {
                 try!(create_context(&self.glx, &extra_functions, &self.extensions, (major, minor),
                                     self.opengl.profile, self.opengl.debug,
                                     self.opengl.robustness, share, self.display, self.fb_config,
-                                    &self.visual_infos))
+                                    &self.visual_infos).map_err(version_error))
             },
         };
 
@@ -263,7 +353,9 @@ impl<'a> ContextPrototype<'a> {
             display: self.display,
             window: window,
             context: context,
+            fb_config: self.fb_config,
             pixel_format: self.pixel_format,
+            extensions: self.extensions,
         })
     }
 }
@@ -345,7 +437,6 @@ fn create_context(glx: &ffi::glx::Glx, extra_functions: &ffi::glx_extra::Glx, ex
         };
 
         if context.is_null() {
-            // TODO: check for errors and return `OpenGlVersionNotSupported`
             return Err(CreationError::OsError(format!("GL context creation failed")));
         }
 
@@ -353,10 +444,53 @@ fn create_context(glx: &ffi::glx::Glx, extra_functions: &ffi::glx_extra::Glx, ex
     }
 }
 
+/// Maps the failure of the creation of a context of an explicit version to
+/// `OpenGlVersionNotSupported`.
+///
+/// `glXCreateContextAttribsARB` only reports a `BadMatch` error when the version isn't
+/// supported, which can't be told apart from the other reasons for a failure, but an
+/// unsupported version is by far the most common one.
+fn version_error(err: CreationError) -> CreationError {
+    match err {
+        CreationError::OsError(_) => CreationError::OpenGlVersionNotSupported,
+        err => err,
+    }
+}
+
+/// Returns true if one of the configs of the screen that can render to windows isn't marked as
+/// slow, which is what `PixelFormat::hardware_accelerated` reports.
+pub unsafe fn has_hardware_acceleration(glx: &ffi::glx::Glx, xlib: &ffi::Xlib,
+                                        display: *mut ffi::Display, screen_id: libc::c_int)
+                                        -> bool
+{
+    let descriptor = [
+        ffi::glx::X_RENDERABLE as c_int, 1,
+        ffi::glx::DRAWABLE_TYPE as c_int, ffi::glx::WINDOW_BIT as c_int,
+        ffi::glx::RENDER_TYPE as c_int, ffi::glx::RGBA_BIT as c_int,
+        0,
+    ];
+
+    let mut num_configs = 0;
+    let configs = glx.ChooseFBConfig(display as *mut _, screen_id, descriptor.as_ptr(),
+                                     &mut num_configs);
+    if configs.is_null() {
+        return false;
+    }
+
+    let accelerated = slice::from_raw_parts(configs, num_configs as usize).iter().any(|&config| {
+        let mut caveat = 0;
+        glx.GetFBConfigAttrib(display as *mut _, config, ffi::glx::CONFIG_CAVEAT as c_int,
+                              &mut caveat) == 0 &&
+            caveat != ffi::glx::SLOW_CONFIG as c_int
+    });
+    (xlib.XFree)(configs as *mut _);
+    accelerated
+}
+
 /// Enumerates all available FBConfigs
 unsafe fn choose_fbconfig(glx: &ffi::glx::Glx, extensions: &str, xlib: &ffi::Xlib,
                           display: *mut ffi::Display, screen_id: libc::c_int,
-                          reqs: &PixelFormatRequirements)
+                          reqs: &PixelFormatRequirements, transparent: bool)
                           -> Result<(ffi::glx::types::GLXFBConfig, PixelFormat), ()>
 {
     let descriptor = {
@@ -459,10 +593,43 @@ unsafe fn choose_fbconfig(glx: &ffi::glx::Glx, extensions: &str, xlib: &ffi::Xli
         let result = glx.ChooseFBConfig(display as *mut _, screen_id, descriptor.as_ptr(),
                                         &mut num_configs);
         if result.is_null() { return Err(()); }
-        if num_configs == 0 { return Err(()); }
-        let val = *result;
+        if num_configs == 0 { (xlib.XFree)(result as *mut _); return Err(()); }
+
+        // the configs are sorted by preference, so we take the first one that fits. Transparent
+        // windows need a config whose visual has an alpha channel, and a depth or stencil size
+        // of 0 requires a config without that buffer while the sizes of `glXChooseFBConfig` are
+        // minimums, so neither can be expressed with its attributes
+        let val = slice::from_raw_parts(result, num_configs as usize).iter().cloned()
+                                                                     .find(|&config| {
+            let has_buffer = |attrib: u32| {
+                let mut value = 0;
+                glx.GetFBConfigAttrib(display as *mut _, config, attrib as c_int, &mut value);
+                value != 0
+            };
+            if (reqs.depth_bits == Some(0) && has_buffer(ffi::glx::DEPTH_SIZE)) ||
+               (reqs.stencil_bits == Some(0) && has_buffer(ffi::glx::STENCIL_SIZE))
+            {
+                return false;
+            }
+
+            if !transparent {
+                return true;
+            }
+
+            let vi = glx.GetVisualFromFBConfig(display as *mut _, config);
+            if vi.is_null() {
+                return false;
+            }
+            let argb = ::api::x11::is_argb_visual(&*(vi as *const ffi::XVisualInfo));
+            (xlib.XFree)(vi as *mut _);
+            argb
+        });
         (xlib.XFree)(result as *mut _);
-        val
+
+        match val {
+            Some(val) => val,
+            None => return Err(()),
+        }
     };
 
     let get_attrib = |attrib: c_int| -> i32 {
@@ -490,6 +657,12 @@ unsafe fn choose_fbconfig(glx: &ffi::glx::Glx, extensions: &str, xlib: &ffi::Xli
         },
         srgb: get_attrib(ffi::glx_extra::FRAMEBUFFER_SRGB_CAPABLE_ARB as c_int) != 0 ||
               get_attrib(ffi::glx_extra::FRAMEBUFFER_SRGB_CAPABLE_EXT as c_int) != 0,
+        aux_buffers: get_attrib(ffi::glx::AUX_BUFFERS as c_int) as u8,
+        accum_bits: (get_attrib(ffi::glx::ACCUM_RED_SIZE as c_int) +
+                     get_attrib(ffi::glx::ACCUM_GREEN_SIZE as c_int) +
+                     get_attrib(ffi::glx::ACCUM_BLUE_SIZE as c_int) +
+                     get_attrib(ffi::glx::ACCUM_ALPHA_SIZE as c_int)) as u8,
+        native_id: Some(get_attrib(ffi::glx::FBCONFIG_ID as c_int) as u64),
     };
 
     Ok((fb_config, pf_desc))
@@ -55,6 +55,13 @@
 //! Keep in mind that after Closed event is received every attempt to draw with opengl will result in segfault.
 //!
 //! Also note that app will not receive Closed event if suspended, it will be SIGKILL'ed
+//!
+//! # OpenGL ES
+//!
+//! The context is an `EAGLContext` of OpenGL ES 3 if the device supports it and the version
+//! isn't specified, otherwise of the requested version, 2 or 3. Sharing a context shares its
+//! `EAGLSharegroup`. The window is always fullscreen, so the methods about its position, its
+//! size, its decorations and the cursor have no effect.
 
 
 
@@ -74,8 +81,8 @@ use libc::c_void;
 use objc::runtime::{Class, BOOL, YES, NO };
 
 use native_monitor::NativeMonitorId;
-use { Api, PixelFormat, CreationError, GlContext, CursorState, MouseCursor, Event };
-use { PixelFormatRequirements, GlAttributes, WindowAttributes, ContextError };
+use { Api, PixelFormat, ContentProtectionError, CreationError, GlContext, CursorState, CursorGrabMode, CursorStateError, MouseCursor, Event, RawEventFilter, Rect, ResizeEdge, ScanCode, VirtualKeyCode };
+use { PixelFormatRequirements, GlAttributes, GlRequest, WindowAttributes, ContextError, ContextPriority, Robustness };
 
 mod delegate;
 use self::delegate::{ create_delegate_class, create_view_class };
@@ -99,7 +106,9 @@ use self::ffi::{
     id,
     nil,
     NSString,
-    CGFloat
+    NSUInteger,
+    CGFloat,
+    CGRect
  };
 
 #[allow(non_upper_case_globals)]
@@ -113,6 +122,41 @@ pub struct Window {
     delegate_state: *mut DelegateState
 }
 
+/// Windows can't have other contexts on this platform.
+pub enum SharedContext {}
+
+impl GlContext for SharedContext {
+    #[inline]
+    unsafe fn make_current(&self) -> Result<(), ContextError> {
+        match *self {}
+    }
+
+    #[inline]
+    fn is_current(&self) -> bool {
+        match *self {}
+    }
+
+    #[inline]
+    fn get_proc_address(&self, _: &str) -> *const () {
+        match *self {}
+    }
+
+    #[inline]
+    fn swap_buffers(&self) -> Result<(), ContextError> {
+        match *self {}
+    }
+
+    #[inline]
+    fn get_api(&self) -> Api {
+        match *self {}
+    }
+
+    #[inline]
+    fn get_pixel_format(&self) -> PixelFormat {
+        match *self {}
+    }
+}
+
 #[derive(Clone)]
 pub struct WindowProxy;
 
@@ -157,8 +201,18 @@ pub fn get_available_monitors() -> VecDeque<MonitorId> {
 }
 
 #[inline]
-pub fn get_primary_monitor() -> MonitorId {
-    MonitorId
+pub fn get_primary_monitor() -> Option<MonitorId> {
+    Some(MonitorId)
+}
+
+#[inline]
+pub fn monitor_from_point(_: i32, _: i32) -> Option<MonitorId> {
+    Some(MonitorId)
+}
+
+#[inline]
+pub fn has_hardware_acceleration() -> bool {
+    true
 }
 
 impl MonitorId {
@@ -172,9 +226,20 @@ impl MonitorId {
         NativeMonitorId::Unavailable
     }
 
+    /// Returns the size of the main screen in pixels.
     #[inline]
     pub fn get_dimensions(&self) -> (u32, u32) {
-        unimplemented!()
+        unsafe {
+            let main_screen: id = msg_send![Class::get("UIScreen").unwrap(), mainScreen];
+            let bounds: CGRect = msg_send![main_screen, bounds];
+            let scale: CGFloat = msg_send![main_screen, nativeScale];
+            ((bounds.size.width * scale) as u32, (bounds.size.height * scale) as u32)
+        }
+    }
+
+    #[inline]
+    pub fn get_icc_profile(&self) -> Option<Vec<u8>> {
+        None
     }
 }
 
@@ -183,8 +248,9 @@ pub struct PlatformSpecificWindowBuilderAttributes;
 
 impl Window {
 
-    pub fn new(win_attribs: &WindowAttributes, _: &PixelFormatRequirements, _: &GlAttributes<&Window>,
-               _: &PlatformSpecificWindowBuilderAttributes) -> Result<Window, CreationError>
+    pub fn new(win_attribs: &WindowAttributes, _: &PixelFormatRequirements,
+               opengl: &GlAttributes<&Window>, _: &PlatformSpecificWindowBuilderAttributes)
+               -> Result<Window, CreationError>
     {
         unsafe {
             if setjmp(mem::transmute(&mut jmpbuf)) != 0 {
@@ -193,7 +259,7 @@ impl Window {
                 let state: *mut raw::c_void = *(&*delegate).get_ivar("glutinState");
                 let state = state as *mut DelegateState;
 
-                let context = Window::create_context();
+                let context = try!(Window::create_context(opengl));
 
                 let mut window = Window {
                     eagl_context: context,
@@ -262,12 +328,42 @@ impl Window {
         }
     }
 
-    fn create_context() -> id {
+    /// Creates an `EAGLContext` for the requested version of OpenGL ES, sharing the objects of
+    /// the context of `opengl.sharing` if any.
+    fn create_context(opengl: &GlAttributes<&Window>) -> Result<id, CreationError> {
+        // the values of `EAGLRenderingAPI`
+        const ES2: NSUInteger = 2;
+        const ES3: NSUInteger = 3;
+
+        let apis: &[NSUInteger] = match opengl.version {
+            GlRequest::Latest => &[ES3, ES2],
+            GlRequest::Specific(Api::OpenGlEs, (3, _)) |
+            GlRequest::GlThenGles { opengles_version: (3, _), .. } => &[ES3],
+            GlRequest::Specific(Api::OpenGlEs, (2, _)) |
+            GlRequest::GlThenGles { opengles_version: (2, _), .. } => &[ES2],
+            GlRequest::Specific(Api::OpenGlEs, _) |
+            GlRequest::GlThenGles { .. } => return Err(CreationError::OpenGlVersionNotSupported),
+            GlRequest::Specific(_, _) => return Err(CreationError::NotSupported),
+        };
+
         unsafe {
-            let eagl_context: id = msg_send![Class::get("EAGLContext").unwrap(), alloc];
-            let eagl_context: id = msg_send![eagl_context, initWithAPI:2]; // es2
-            eagl_context
+            let sharegroup: id = match opengl.sharing {
+                Some(window) => msg_send![window.eagl_context, sharegroup],
+                None => nil,
+            };
+
+            for &api in apis {
+                let eagl_context: id = msg_send![Class::get("EAGLContext").unwrap(), alloc];
+                // returns nil if the device doesn't support this version
+                let eagl_context: id = msg_send![eagl_context, initWithAPI:api
+                                                                sharegroup:sharegroup];
+                if eagl_context != nil {
+                    return Ok(eagl_context);
+                }
+            }
         }
+
+        Err(CreationError::OpenGlVersionNotSupported)
     }
 
     #[inline]
@@ -289,11 +385,30 @@ impl Window {
     pub fn hide(&self) {
     }
 
+    #[inline]
+    pub fn drag_window(&self) -> Result<(), ()> {
+        Err(())
+    }
+
+    #[inline]
+    pub fn drag_resize_window(&self, _: ResizeEdge) -> Result<(), ()> {
+        Err(())
+    }
+
+    #[inline]
+    pub fn focus_window(&self) {
+    }
+
     #[inline]
     pub fn get_position(&self) -> Option<(i32, i32)> {
         None
     }
 
+    #[inline]
+    pub fn get_outer_position(&self) -> Option<(i32, i32)> {
+        self.get_position()
+    }
+
     #[inline]
     pub fn set_position(&self, _x: i32, _y: i32) {
     }
@@ -312,6 +427,41 @@ impl Window {
     pub fn set_inner_size(&self, _x: u32, _y: u32) {
     }
 
+    #[inline]
+    pub fn request_inner_size(&self, _x: u32, _y: u32) -> Option<(u32, u32)> {
+        None
+    }
+
+    #[inline]
+    pub fn set_min_dimensions(&self, _: Option<(u32, u32)>) {
+    }
+
+    #[inline]
+    pub fn set_max_dimensions(&self, _: Option<(u32, u32)>) {
+    }
+
+    #[inline]
+    pub fn set_aspect_ratio(&self, _: Option<(u32, u32)>) {
+    }
+
+    #[inline]
+    pub fn set_decorations(&self, _: bool) {
+    }
+
+    #[inline]
+    pub fn set_opacity(&self, _: f32) {
+    }
+
+    #[inline]
+    pub fn set_content_protected(&self, _: bool) -> Result<(), ContentProtectionError> {
+        Err(ContentProtectionError::Unsupported)
+    }
+
+    #[inline]
+    pub fn set_cursor_hittest(&self, _: bool) -> Result<(), ()> {
+        Err(())
+    }
+
     #[inline]
     pub fn poll_events(&self) -> PollEventsIterator {
         PollEventsIterator {
@@ -326,19 +476,48 @@ impl Window {
         }
     }
 
+    #[inline]
+    pub fn drain_events(&self, events: &mut Vec<Event>) {
+        events.extend(self.poll_events());
+    }
+
+    /// There is no display connection on iOS.
     #[inline]
     pub fn platform_display(&self) -> *mut c_void {
-        unimplemented!();
+        ptr::null_mut()
     }
 
+    /// Returns the `UIWindow`.
     #[inline]
     pub fn platform_window(&self) -> *mut c_void {
-        unimplemented!()
+        unsafe { (&*self.delegate_state).window as *mut c_void }
+    }
+
+    #[inline]
+    pub fn create_shared_context(&self, _: &GlAttributes<&Window>)
+                                 -> Result<SharedContext, CreationError>
+    {
+        Err(CreationError::NotSupported)
     }
 
+    /// The renderbuffer is always allocated with `kEAGLColorFormatRGB565`, without a depth or a
+    /// stencil buffer.
     #[inline]
     pub fn get_pixel_format(&self) -> PixelFormat {
-        unimplemented!();
+        PixelFormat {
+            hardware_accelerated: true,
+            color_bits: 16,
+            alpha_bits: 0,
+            depth_bits: 0,
+            stencil_bits: 0,
+            stereoscopy: false,
+            double_buffer: true,
+            multisampling: None,
+            srgb: false,
+            aux_buffers: 0,
+            accum_bits: 0,
+            native_id: None,
+        }
     }
 
     #[inline]
@@ -350,8 +529,71 @@ impl Window {
     }
 
     #[inline]
-    pub fn set_cursor_state(&self, _: CursorState) -> Result<(), String> {
-        Ok(())
+    pub fn set_cursor_state(&self, state: CursorState) -> Result<(), CursorStateError> {
+        self.set_cursor_grab(state.grab_mode())
+    }
+
+    #[inline]
+    pub fn set_cursor_grab(&self, mode: CursorGrabMode) -> Result<(), CursorStateError> {
+        match mode {
+            CursorGrabMode::None => Ok(()),
+            _ => Err(CursorStateError::Unsupported),
+        }
+    }
+
+    #[inline]
+    pub fn confine_cursor(&self, rect: Option<Rect>) -> Result<(), CursorStateError> {
+        match rect {
+            None => Ok(()),
+            Some(_) => Err(CursorStateError::Unsupported),
+        }
+    }
+
+    #[inline]
+    pub fn set_ime_allowed(&self, _allowed: bool) {
+    }
+
+    #[inline]
+    pub fn set_ime_position(&self, _x: i32, _y: i32) {
+    }
+
+    #[inline]
+    pub fn set_raw_event_filter(&self, _: Option<RawEventFilter>) {
+    }
+
+    #[inline]
+    pub fn scancode_to_keycode(&self, _: ScanCode) -> Option<VirtualKeyCode> {
+        None
+    }
+
+    #[inline]
+    pub fn keycode_to_scancode(&self, _: VirtualKeyCode) -> Option<ScanCode> {
+        None
+    }
+
+    #[inline]
+    pub fn key_name(&self, _: ScanCode) -> Option<String> {
+        None
+    }
+
+    #[inline]
+    pub fn get_context_priority(&self) -> Option<ContextPriority> {
+        None
+    }
+
+    #[inline]
+    pub fn get_robustness(&self) -> Option<Robustness> {
+        None
+    }
+
+    #[inline]
+    pub fn get_available_monitors(&self) -> VecDeque<MonitorId> {
+        get_available_monitors()
+    }
+
+    #[inline]
+    pub fn get_current_monitor(&self) -> Option<MonitorId> {
+        get_primary_monitor()
     }
 
     #[inline]
@@ -360,8 +602,8 @@ impl Window {
     }
 
     #[inline]
-    pub fn set_cursor_position(&self, _x: i32, _y: i32) -> Result<(), ()> {
-        unimplemented!();
+    pub fn set_cursor_position(&self, _x: f64, _y: f64) -> Result<(), ()> {
+        Err(())
     }
 
     #[inline]
@@ -384,7 +626,10 @@ impl GlContext for Window {
 
     #[inline]
     fn is_current(&self) -> bool {
-        false
+        unsafe {
+            let current: id = msg_send![Class::get("EAGLContext").unwrap(), currentContext];
+            current == self.eagl_context
+        }
     }
 
     fn get_proc_address(&self, addr: &str) -> *const () {
@@ -410,19 +655,24 @@ impl GlContext for Window {
 
     #[inline]
     fn get_api(&self) -> Api {
-        unimplemented!()
+        Api::OpenGlEs
     }
 
     #[inline]
     fn get_pixel_format(&self) -> PixelFormat {
-        unimplemented!()
+        self.get_pixel_format()
     }
 }
 
 impl WindowProxy {
+    /// `wait_events` keeps running the run loop until an event arrives, so there is nothing to
+    /// wake up.
     #[inline]
     pub fn wakeup_event_loop(&self) {
-        unimplemented!()
+    }
+
+    #[inline]
+    pub fn request_close(&self) {
     }
 }
 
@@ -6,6 +6,7 @@ pub mod egl;
 pub mod emscripten;
 pub mod glx;
 pub mod osmesa;
+pub mod pressed_inputs;
 pub mod wgl;
 pub mod win32;
 pub mod x11;
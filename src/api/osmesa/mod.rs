@@ -13,7 +13,7 @@ use PixelFormat;
 use PixelFormatRequirements;
 use Robustness;
 use libc;
-use std::{mem, ptr};
+use std::{mem, ptr, slice};
 use std::ffi::CString;
 
 pub struct OsMesaContext {
@@ -21,6 +21,7 @@ pub struct OsMesaContext {
     buffer: Vec<u32>,
     width: u32,
     height: u32,
+    pixel_format: PixelFormat,
 }
 
 pub enum OsMesaCreationError {
@@ -36,14 +37,17 @@ impl From<CreationError> for OsMesaCreationError {
 }
 
 impl OsMesaContext {
-    pub fn new(dimensions: (u32, u32), _pf_reqs: &PixelFormatRequirements,
+    pub fn new(dimensions: (u32, u32), pf_reqs: &PixelFormatRequirements,
                opengl: &GlAttributes<&OsMesaContext>) -> Result<OsMesaContext, OsMesaCreationError>
     {
         if let Err(_) = osmesa_sys::OsMesa::try_loading() {
             return Err(OsMesaCreationError::NotSupported);
         }
 
-        if opengl.sharing.is_some() { unimplemented!() }        // TODO: proper error
+        // TODO: share with `OSMesaCreateContextAttribs`
+        if opengl.sharing.is_some() {
+            return Err(CreationError::NotSupported.into());
+        }
 
         match opengl.robustness {
             Robustness::RobustNoResetNotification | Robustness::RobustLoseContextOnReset => {
@@ -52,10 +56,38 @@ impl OsMesaContext {
             _ => ()
         }
 
-        // TODO: use `pf_reqs` for the format
+        // the color buffer is always RGBA8, and OSMesa doesn't multisample
+        if pf_reqs.color_bits.unwrap_or(0) > 24 || pf_reqs.alpha_bits.unwrap_or(0) > 8 ||
+           pf_reqs.float_color_buffer || pf_reqs.multisampling.is_some() || pf_reqs.stereoscopy ||
+           pf_reqs.srgb
+        {
+            return Err(CreationError::NoAvailablePixelFormat.into());
+        }
+
+        let pixel_format = PixelFormat {
+            hardware_accelerated: false,
+            color_bits: 24,
+            alpha_bits: 8,
+            depth_bits: pf_reqs.depth_bits.unwrap_or(0),
+            stencil_bits: pf_reqs.stencil_bits.unwrap_or(0),
+            stereoscopy: false,
+            double_buffer: false,
+            multisampling: None,
+            srgb: false,
+            aux_buffers: 0,
+            accum_bits: 0,
+            native_id: None,
+        };
 
         let mut attribs = Vec::new();
 
+        attribs.push(osmesa_sys::OSMESA_FORMAT);
+        attribs.push(osmesa_sys::OSMESA_RGBA as libc::c_int);
+        attribs.push(osmesa_sys::OSMESA_DEPTH_BITS);
+        attribs.push(pixel_format.depth_bits as libc::c_int);
+        attribs.push(osmesa_sys::OSMESA_STENCIL_BITS);
+        attribs.push(pixel_format.stencil_bits as libc::c_int);
+
         if let Some(profile) = opengl.profile {
             attribs.push(osmesa_sys::OSMESA_PROFILE);
 
@@ -95,6 +127,7 @@ impl OsMesaContext {
         Ok(OsMesaContext {
             width: dimensions.0,
             height: dimensions.1,
+            pixel_format: pixel_format,
             buffer: ::std::iter::repeat(unsafe { mem::uninitialized() })
                 .take((dimensions.0 * dimensions.1) as usize).collect(),
             context: unsafe {
@@ -112,11 +145,23 @@ impl OsMesaContext {
         &self.buffer
     }
 
+    /// Returns the buffer as RGBA bytes, the bottom row first.
+    #[inline]
+    pub fn get_framebuffer_bytes(&self) -> &[u8] {
+        unsafe { slice::from_raw_parts(self.buffer.as_ptr() as *const u8, self.buffer.len() * 4) }
+    }
+
     #[inline]
     pub fn get_dimensions(&self) -> (u32, u32) {
         (self.width, self.height)
     }
 
+    /// Returns the raw `OSMesaContext`.
+    #[inline]
+    pub fn get_osmesa_context(&self) -> osmesa_sys::OSMesaContext {
+        self.context
+    }
+
     #[allow(dead_code)]
     // TODO: can we remove this without causing havoc?
     #[inline]
@@ -164,7 +209,7 @@ impl GlContext for OsMesaContext {
 
     #[inline]
     fn get_pixel_format(&self) -> PixelFormat {
-        unimplemented!();
+        self.pixel_format.clone()
     }
 }
 
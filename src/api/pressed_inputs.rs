@@ -0,0 +1,133 @@
+#![cfg(any(target_os = "windows", target_os = "macos", target_os = "linux", target_os = "dragonfly",
+          target_os = "freebsd", target_os = "openbsd"))]
+
+//! The keys and mouse buttons of a window that are held down.
+//!
+//! The releases of the keys and buttons that are held down when the window loses the focus are
+//! sent to the window that gets it, so they are reported before `Focused(false)` instead.
+
+use Event;
+use ScanCode;
+use events::{ElementState, MouseButton, VirtualKeyCode};
+
+#[derive(Clone, Debug, Default)]
+pub struct PressedInputs {
+    /// The keys in the order they were pressed, with the virtual key of their press.
+    keys: Vec<(ScanCode, Option<VirtualKeyCode>)>,
+    buttons: Vec<MouseButton>,
+}
+
+impl PressedInputs {
+    #[inline]
+    pub fn new() -> PressedInputs {
+        PressedInputs::default()
+    }
+
+    /// Returns true if the key has been reported as pressed and not released yet.
+    #[inline]
+    pub fn is_key_pressed(&self, scancode: ScanCode) -> bool {
+        self.keys.iter().any(|&(pressed, _)| pressed == scancode)
+    }
+
+    /// Records an event that is reported to the window, and returns the events to report
+    /// instead: the event itself, preceded by the releases of the keys and buttons that are
+    /// held down if it is `Focused(false)`.
+    pub fn process(&mut self, event: Event) -> Vec<Event> {
+        match event {
+            Event::KeyboardInput(ElementState::Pressed, scancode, vkey, _) => {
+                if !self.is_key_pressed(scancode) {
+                    self.keys.push((scancode, vkey));
+                }
+            },
+            Event::KeyboardInput(ElementState::Released, scancode, _, _) => {
+                self.keys.retain(|&(pressed, _)| pressed != scancode);
+            },
+            Event::MouseInput(ElementState::Pressed, button, _) => {
+                if !self.buttons.contains(&button) {
+                    self.buttons.push(button);
+                }
+            },
+            Event::MouseInput(ElementState::Released, button, _) => {
+                self.buttons.retain(|&pressed| pressed != button);
+            },
+            Event::Focused(false) => {
+                let mut events = self.release_all();
+                events.push(event);
+                return events;
+            },
+            _ => (),
+        }
+
+        vec![event]
+    }
+
+    /// Forgets the keys and buttons that are held down, and returns the events of their
+    /// releases, in the order they were pressed.
+    fn release_all(&mut self) -> Vec<Event> {
+        let keys = self.keys.drain(..).map(|(scancode, vkey)| {
+            Event::KeyboardInput(ElementState::Released, scancode, vkey, false)
+        });
+        let buttons = self.buttons.drain(..).map(|button| {
+            Event::MouseInput(ElementState::Released, button, None)
+        });
+        keys.chain(buttons).collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::PressedInputs;
+    use Event;
+    use events::ElementState::{Pressed, Released};
+    use events::MouseButton;
+    use events::VirtualKeyCode;
+
+    fn process_all(pressed_inputs: &mut PressedInputs, events: Vec<Event>) -> Vec<Event> {
+        events.into_iter().flat_map(|event| pressed_inputs.process(event)).collect()
+    }
+
+    #[test]
+    fn releases_the_held_inputs_before_losing_the_focus() {
+        let mut pressed_inputs = PressedInputs::new();
+        let events = process_all(&mut pressed_inputs, vec![
+            Event::KeyboardInput(Pressed, 56, Some(VirtualKeyCode::LAlt), false),
+            Event::KeyboardInput(Pressed, 30, Some(VirtualKeyCode::A), false),
+            Event::KeyboardInput(Pressed, 30, Some(VirtualKeyCode::A), true),
+            Event::KeyboardInput(Released, 30, Some(VirtualKeyCode::A), false),
+            Event::KeyboardInput(Pressed, 15, Some(VirtualKeyCode::Tab), false),
+            Event::MouseInput(Pressed, MouseButton::Left, None),
+            Event::Focused(false),
+        ]);
+
+        let released: Vec<_> = events[6..].iter().map(|event| match *event {
+            Event::KeyboardInput(Released, scancode, vkey, false) => Ok((scancode, vkey)),
+            Event::MouseInput(Released, button, None) => Err(Some(button)),
+            Event::Focused(false) => Err(None),
+            ref event => panic!("unexpected event {:?}", event),
+        }).collect();
+        assert_eq!(released, vec![
+            Ok((56, Some(VirtualKeyCode::LAlt))),
+            Ok((15, Some(VirtualKeyCode::Tab))),
+            Err(Some(MouseButton::Left)),
+            Err(None),
+        ]);
+    }
+
+    #[test]
+    fn forgets_the_inputs_released_on_focus_loss() {
+        let mut pressed_inputs = PressedInputs::new();
+        process_all(&mut pressed_inputs, vec![
+            Event::KeyboardInput(Pressed, 56, Some(VirtualKeyCode::LAlt), false),
+            Event::Focused(false),
+            Event::Focused(true),
+        ]);
+        assert!(!pressed_inputs.is_key_pressed(56));
+
+        let events = pressed_inputs.process(Event::Focused(false));
+        assert_eq!(events.len(), 1);
+        match events[0] {
+            Event::Focused(false) => (),
+            ref event => panic!("unexpected event {:?}", event),
+        }
+    }
+}
@@ -11,13 +11,14 @@ use PixelFormatRequirements;
 use ReleaseBehavior;
 use Robustness;
 use Api;
+use extensions;
 
 use self::make_current_guard::CurrentContextGuard;
 
 use std::ffi::{CStr, CString, OsStr};
 use std::os::raw::{c_void, c_int};
 use std::os::windows::ffi::OsStrExt;
-use std::{mem, ptr};
+use std::{cmp, mem, ptr};
 use std::io;
 
 use winapi;
@@ -28,6 +29,27 @@ use gdi32;
 mod make_current_guard;
 mod gl;
 
+/// Returns true if one of the pixel formats of `hdc` that can be used with OpenGL in a window is
+/// provided by the driver rather than by the generic implementation of Windows, which is what
+/// `PixelFormat::hardware_accelerated` reports.
+pub unsafe fn has_hardware_acceleration(hdc: winapi::HDC) -> bool {
+    let size = mem::size_of::<winapi::PIXELFORMATDESCRIPTOR>() as u32;
+    let mut output: winapi::PIXELFORMATDESCRIPTOR = mem::zeroed();
+
+    // the return value is the number of pixel formats
+    let num_formats = gdi32::DescribePixelFormat(hdc, 1, size, &mut output);
+
+    (1 .. num_formats + 1).any(|id| {
+        if gdi32::DescribePixelFormat(hdc, id, size, &mut output) == 0 {
+            return false;
+        }
+
+        (output.dwFlags & winapi::PFD_DRAW_TO_WINDOW) != 0 &&
+        (output.dwFlags & winapi::PFD_SUPPORT_OPENGL) != 0 &&
+        (output.dwFlags & winapi::PFD_GENERIC_FORMAT) == 0
+    })
+}
+
 /// A WGL context.
 ///
 /// Note: should be destroyed before its window.
@@ -44,6 +66,9 @@ pub struct Context {
 
     /// The pixel format that has been used to create this context.
     pixel_format: PixelFormat,
+
+    /// The WGL extensions supported by the device context.
+    extensions: String,
 }
 
 /// A simple wrapper that destroys the window when it is destroyed.
@@ -143,6 +168,39 @@ impl Context {
             hdc: hdc,
             gl_library: gl_library,
             pixel_format: pixel_format,
+            extensions: extensions,
+        })
+    }
+
+    /// Creates another context for the window of this one.
+    ///
+    /// WGL requires the contexts that render to a window to use the same pixel format, so
+    /// `NoAvailablePixelFormat` is returned if the pixel format of the window is no longer the
+    /// one of this context.
+    ///
+    /// # Unsafety
+    ///
+    /// The `window` must be the window of this context, and continue to exist as long as the
+    /// resulting `Context` exists.
+    pub unsafe fn new_shared(&self, opengl: &GlAttributes<winapi::HGLRC>, window: winapi::HWND)
+                             -> Result<Context, CreationError>
+    {
+        if Some(gdi32::GetPixelFormat(self.hdc) as u64) != self.pixel_format.native_id {
+            return Err(CreationError::NoAvailablePixelFormat);
+        }
+
+        let extra_functions = try!(load_extra_functions(window));
+        let context = try!(create_context(Some((&extra_functions, &Default::default(), opengl,
+                                                &self.extensions)),
+                                          window, self.hdc));
+        let gl_library = try!(load_opengl32_dll());
+
+        Ok(Context {
+            context: context,
+            hdc: self.hdc,
+            gl_library: gl_library,
+            pixel_format: self.pixel_format.clone(),
+            extensions: self.extensions.clone(),
         })
     }
 
@@ -151,15 +209,25 @@ impl Context {
     pub fn get_hglrc(&self) -> winapi::HGLRC {
         self.context.0
     }
+
+    /// Returns the device context that the context renders to.
+    #[inline]
+    pub fn get_hdc(&self) -> winapi::HDC {
+        self.hdc
+    }
 }
 
 impl GlContext for Context {
     #[inline]
     unsafe fn make_current(&self) -> Result<(), ContextError> {
+        // on failure, the context that was current is no longer current, so `is_current` stays
+        // accurate
         if gl::wgl::MakeCurrent(self.hdc as *const _, self.context.0 as *const _) != 0 {
             Ok(())
         } else {
-            Err(ContextError::IoError(io::Error::last_os_error()))
+            let error = io::Error::last_os_error();
+            Err(ContextError::IoError(io::Error::new(error.kind(),
+                                      format!("wglMakeCurrent failed: {}", error))))
         }
     }
 
@@ -173,14 +241,30 @@ impl GlContext for Context {
         let addr = addr.as_ptr();
 
         unsafe {
-            let p = gl::wgl::GetProcAddress(addr) as *const _;
-            if !p.is_null() { return p; }
-            kernel32::GetProcAddress(self.gl_library, addr) as *const _
+            // `wglGetProcAddress` doesn't return the functions of OpenGL 1.1, which are exported
+            // by opengl32.dll, and some drivers return 1, 2, 3 or -1 instead of null on failure
+            let p = gl::wgl::GetProcAddress(addr) as *const ();
+            match p as isize {
+                0 | 1 | 2 | 3 | -1 => kernel32::GetProcAddress(self.gl_library, addr) as *const _,
+                _ => p,
+            }
         }
     }
 
+    fn is_extension_supported(&self, name: &str) -> bool {
+        self.extensions.split(' ').any(|e| e == name) ||
+            extensions::is_gl_extension_supported(self, name)
+    }
+
     #[inline]
     fn swap_buffers(&self) -> Result<(), ContextError> {
+        // `SwapBuffers` does nothing with a single buffer, but the commands must still be
+        // executed
+        if !self.pixel_format.double_buffer {
+            self.flush();
+            return Ok(());
+        }
+
         // TODO: decide how to handle the error
         /*if unsafe { gdi32::SwapBuffers(self.hdc) } != 0 {
             Ok(())
@@ -206,6 +290,9 @@ impl GlContext for Context {
 unsafe impl Send for Context {}
 unsafe impl Sync for Context {}
 
+/// Error set by `wglCreateContextAttribsARB` when the profile isn't supported.
+const ERROR_INVALID_PROFILE_ARB: i32 = 0x2096;
+
 /// Creates an OpenGL context.
 ///
 /// If `extra` is `Some`, this function will attempt to use the latest WGL functions to create the
@@ -319,8 +406,20 @@ unsafe fn create_context(extra: Option<(&gl::wgl_extra::Wgl, &PixelFormatRequire
                                                                attributes.as_ptr());
 
             if ctxt.is_null() {
+                let error = io::Error::last_os_error();
+
+                // drivers report `ERROR_INVALID_VERSION_ARB` inconsistently, some of them with
+                // the bits of an `HRESULT` and others with a generic error, so only the profile
+                // error is told apart
+                let invalid_profile = error.raw_os_error()
+                                           .map(|code| code & 0xffff == ERROR_INVALID_PROFILE_ARB)
+                                           .unwrap_or(false);
+                if opengl.version != GlRequest::Latest && !invalid_profile {
+                    return Err(CreationError::OpenGlVersionNotSupported);
+                }
+
                 return Err(CreationError::OsError(format!("wglCreateContextAttribsARB failed: {}",
-                                                      format!("{}", io::Error::last_os_error()))));
+                                                          error)));
             } else {
                 return Ok(ContextWrapper(ctxt as winapi::HGLRC));
             }
@@ -457,6 +556,9 @@ unsafe fn choose_native_pixel_format(hdc: winapi::HDC, reqs: &PixelFormatRequire
         double_buffer: (output.dwFlags & winapi::PFD_DOUBLEBUFFER) != 0,
         multisampling: None,
         srgb: false,
+        aux_buffers: output.cAuxBuffers,
+        accum_bits: output.cAccumBits,
+        native_id: Some(pf_id as u64),
     };
 
     if pf_desc.alpha_bits < reqs.alpha_bits.unwrap_or(0) {
@@ -468,6 +570,12 @@ unsafe fn choose_native_pixel_format(hdc: winapi::HDC, reqs: &PixelFormatRequire
     if pf_desc.stencil_bits < reqs.stencil_bits.unwrap_or(0) {
         return Err(());
     }
+    // a size of 0 means that there must be no depth or stencil buffer
+    if (reqs.depth_bits == Some(0) && pf_desc.depth_bits != 0) ||
+       (reqs.stencil_bits == Some(0) && pf_desc.stencil_bits != 0)
+    {
+        return Err(());
+    }
     if pf_desc.color_bits < reqs.color_bits.unwrap_or(0) {
         return Err(());
     }
@@ -586,26 +694,47 @@ unsafe fn choose_arb_pixel_format(extra: &gl::wgl_extra::Wgl, extensions: &str,
         out
     };
 
-    let mut format_id = mem::uninitialized();
-    let mut num_formats = mem::uninitialized();
-    if extra.ChoosePixelFormatARB(hdc as *const _, descriptor.as_ptr(), ptr::null(), 1,
-                                  &mut format_id, &mut num_formats) == 0
+    // the formats are sorted by preference, but the sizes of the depth and stencil buffers are
+    // minimums, so the formats without these buffers must be searched when a size of 0 is
+    // requested
+    // the first call only counts the matching formats, and the second one retrieves all of them
+    let mut num_formats = 0;
+    if extra.ChoosePixelFormatARB(hdc as *const _, descriptor.as_ptr(), ptr::null(),
+                                  0, ptr::null_mut(), &mut num_formats) == 0
     {
         return Err(());
     }
 
-    if num_formats == 0 {
+    let mut format_ids: Vec<c_int> = vec![0; num_formats as usize];
+    if extra.ChoosePixelFormatARB(hdc as *const _, descriptor.as_ptr(), ptr::null(),
+                                  format_ids.len() as u32, format_ids.as_mut_ptr(),
+                                  &mut num_formats) == 0
+    {
         return Err(());
     }
 
-    let get_info = |attrib: u32| {
+    let get_format_info = |format_id: c_int, attrib: u32| {
         let mut value = mem::uninitialized();
-        extra.GetPixelFormatAttribivARB(hdc as *const _, format_id as c_int,
+        extra.GetPixelFormatAttribivARB(hdc as *const _, format_id,
                                         0, 1, [attrib as c_int].as_ptr(),
                                         &mut value);
         value as u32
     };
 
+    let num_formats = cmp::min(num_formats as usize, format_ids.len());
+    let format_id = format_ids[.. num_formats].iter().cloned().find(|&format_id| {
+        (reqs.depth_bits != Some(0) ||
+         get_format_info(format_id, gl::wgl_extra::DEPTH_BITS_ARB) == 0) &&
+        (reqs.stencil_bits != Some(0) ||
+         get_format_info(format_id, gl::wgl_extra::STENCIL_BITS_ARB) == 0)
+    });
+    let format_id = match format_id {
+        Some(format_id) => format_id,
+        None => return Err(()),
+    };
+
+    let get_info = |attrib: u32| get_format_info(format_id, attrib);
+
     let pf_desc = PixelFormat {
         hardware_accelerated: get_info(gl::wgl_extra::ACCELERATION_ARB) !=
                                                                 gl::wgl_extra::NO_ACCELERATION_ARB,
@@ -634,6 +763,9 @@ unsafe fn choose_arb_pixel_format(extra: &gl::wgl_extra::Wgl, extensions: &str,
         } else {
             false
         },
+        aux_buffers: get_info(gl::wgl_extra::AUX_BUFFERS_ARB) as u8,
+        accum_bits: get_info(gl::wgl_extra::ACCUM_BITS_ARB) as u8,
+        native_id: Some(format_id as u64),
     };
 
     Ok((format_id, pf_desc))
@@ -1,13 +1,17 @@
+use std::cmp;
 use std::mem;
 use std::ptr;
 use std::cell::RefCell;
+use std::collections::HashMap;
 use std::sync::mpsc::Sender;
 use std::sync::{Arc, Mutex};
 use std::ffi::OsString;
 use std::os::windows::ffi::OsStringExt;
 
+use CursorGrabMode;
+use RawEvent;
+use RawEventFilter;
 use WindowAttributes;
-use CursorState;
 use Event;
 use super::event;
 use super::WindowState;
@@ -17,14 +21,54 @@ use shell32;
 use winapi;
 
 /// There's no parameters passed to the callback function, so it needs to get
-/// its context (the HWND, the Sender for events, etc.) stashed in
-/// a thread-local variable.
-thread_local!(pub static CONTEXT_STASH: RefCell<Option<ThreadLocalData>> = RefCell::new(None));
+/// its context (the Sender for events, etc.) stashed in a thread-local variable.
+///
+/// All the windows that have a message thread share the same thread, so the context of each
+/// window is found with its HWND.
+thread_local!(pub static CONTEXT_STASH: RefCell<HashMap<winapi::HWND, ThreadLocalData>> =
+              RefCell::new(HashMap::new()));
 
 pub struct ThreadLocalData {
-    pub win: winapi::HWND,
     pub sender: Sender<Event>,
-    pub window_state: Arc<Mutex<WindowState>>
+    pub window_state: Arc<Mutex<WindowState>>,
+    pub raw_event_filter: Arc<Mutex<(Option<RawEventFilter>, u64)>>,
+}
+
+// `ImmAssociateContextEx` flag that restores the default input context of the window
+const IACE_DEFAULT: winapi::DWORD = 0x0010;
+
+// `COMPOSITIONFORM` and `CANDIDATEFORM` styles that place the windows of the input method at a
+// point of the client area
+const CFS_POINT: winapi::DWORD = 0x0002;
+const CFS_CANDIDATEPOS: winapi::DWORD = 0x0040;
+
+// sent when the input method starts composing characters, which is missing from `winapi`
+const WM_IME_STARTCOMPOSITION: winapi::UINT = 0x010D;
+
+#[repr(C)]
+struct CompositionForm {
+    style: winapi::DWORD,
+    current_pos: winapi::POINT,
+    area: winapi::RECT,
+}
+
+#[repr(C)]
+struct CandidateForm {
+    index: winapi::DWORD,
+    style: winapi::DWORD,
+    current_pos: winapi::POINT,
+    area: winapi::RECT,
+}
+
+#[link(name = "imm32")]
+extern "system" {
+    fn ImmAssociateContextEx(hwnd: winapi::HWND, himc: winapi::HANDLE, flags: winapi::DWORD)
+                             -> winapi::BOOL;
+    fn ImmGetContext(hwnd: winapi::HWND) -> winapi::HANDLE;
+    fn ImmReleaseContext(hwnd: winapi::HWND, himc: winapi::HANDLE) -> winapi::BOOL;
+    fn ImmSetCompositionWindow(himc: winapi::HANDLE, form: *const CompositionForm)
+                               -> winapi::BOOL;
+    fn ImmSetCandidateWindow(himc: winapi::HANDLE, form: *const CandidateForm) -> winapi::BOOL;
 }
 
 struct MinMaxInfo {
@@ -35,23 +79,41 @@ struct MinMaxInfo {
     max_track: winapi::POINT
 }
 
-/// Checks that the window is the good one, and if so send the event to it.
+// sent to the per-monitor DPI-aware applications, which is missing from `winapi`
+const WM_DPICHANGED: winapi::UINT = 0x02E0;
+
+// the edges of the window that `WM_SIZING` reports as being dragged
+const WMSZ_TOP: winapi::WPARAM = 3;
+const WMSZ_TOPLEFT: winapi::WPARAM = 4;
+const WMSZ_TOPRIGHT: winapi::WPARAM = 5;
+const WMSZ_BOTTOM: winapi::WPARAM = 6;
+
+/// Sends the event to the window, if it is one of ours.
 fn send_event(input_window: winapi::HWND, event: Event) {
     CONTEXT_STASH.with(|context_stash| {
         let context_stash = context_stash.borrow();
-        let stored = match *context_stash {
+        let stored = match context_stash.get(&input_window) {
             None => return,
-            Some(ref v) => v
+            Some(v) => v
         };
 
-        let &ThreadLocalData { ref win, ref sender, .. } = stored;
-
-        if win != &input_window {
-            return;
-        }
+        stored.sender.send(event).ok();  // ignoring if closed
+    });
+}
 
-        sender.send(event).ok();  // ignoring if closed
+/// Sends a keyboard or mouse button event to the window, and records the keys and buttons
+/// that are held down. Their releases are sent with `Focused(false)`, since the releases of the
+/// keys and buttons that are held down when the window loses the focus go to the window that
+/// gets it.
+fn send_input_event(input_window: winapi::HWND, event: Event) {
+    let events = CONTEXT_STASH.with(|context_stash| {
+        context_stash.borrow().get(&input_window)
+                     .map(|cstash| cstash.window_state.lock().unwrap().pressed_inputs.process(event))
     });
+
+    for event in events.into_iter().flat_map(|events| events.into_iter()) {
+        send_event(input_window, event);
+    }
 }
 
 /// This is the callback that is called by `DispatchMessage` in the events loop.
@@ -62,25 +124,21 @@ pub unsafe extern "system" fn callback(window: winapi::HWND, msg: winapi::UINT,
                                        wparam: winapi::WPARAM, lparam: winapi::LPARAM)
                                        -> winapi::LRESULT
 {
+    if filter_raw_event(window, msg, wparam, lparam) {
+        return 0;
+    }
+
     match msg {
         winapi::WM_DESTROY => {
             use events::Event::Closed;
 
-            CONTEXT_STASH.with(|context_stash| {
-                let context_stash = context_stash.borrow();
-                let stored = match *context_stash {
-                    None => return,
-                    Some(ref v) => v
-                };
-
-                let &ThreadLocalData { ref win, .. } = stored;
+            send_event(window, Closed);
 
-                if win == &window {
-                    user32::PostQuitMessage(0);
-                }
+            // dropping the sender ends the `wait_events` of the window, while the thread keeps
+            // dispatching the messages of the other windows
+            CONTEXT_STASH.with(|context_stash| {
+                context_stash.borrow_mut().remove(&window);
             });
-
-            send_event(window, Closed);
             0
         },
 
@@ -96,6 +154,42 @@ pub unsafe extern "system" fn callback(window: winapi::HWND, msg: winapi::UINT,
             0
         },
 
+        WM_DPICHANGED => {
+            use events::Event::{HiDpiFactorChanged, HiDpiSuggestedSize};
+
+            // both words contain the DPI, which is the same horizontally and vertically
+            let dpi = winapi::LOWORD(wparam as winapi::DWORD) as u32;
+            let factor = dpi as f32 / super::dpi::BASE_DPI as f32;
+
+            let dpi_resize = CONTEXT_STASH.with(|context_stash| {
+                match context_stash.borrow().get(&window) {
+                    Some(cstash) => {
+                        let mut window_state = cstash.window_state.lock().unwrap();
+                        window_state.hidpi_factor = factor;
+                        window_state.dpi_resize
+                    },
+                    None => true,
+                }
+            });
+
+            send_event(window, HiDpiFactorChanged(factor));
+
+            // the rectangle suggested by Windows keeps the same physical size on the new monitor,
+            // and includes the frame
+            let rect = &*(lparam as *const winapi::RECT);
+            if let Some((w, h)) = suggested_inner_size(window, rect) {
+                send_event(window, HiDpiSuggestedSize(w, h));
+            }
+
+            // `WM_SIZE` reports the new size with a `Resized` event
+            if dpi_resize {
+                user32::SetWindowPos(window, ptr::null_mut(), rect.left, rect.top,
+                                     rect.right - rect.left, rect.bottom - rect.top,
+                                     winapi::SWP_NOZORDER | winapi::SWP_NOACTIVATE);
+            }
+            0
+        },
+
         winapi::WM_MOVE => {
             use events::Event::Moved;
             let x = winapi::LOWORD(lparam as winapi::DWORD) as i32;
@@ -121,12 +215,43 @@ pub unsafe extern "system" fn callback(window: winapi::HWND, msg: winapi::UINT,
         }
 
         winapi::WM_MOUSEMOVE => {
-            use events::Event::MouseMoved;
+            use events::Event::{MouseMoved, MouseMovedPrecise, MouseMotion};
 
             let x = winapi::GET_X_LPARAM(lparam) as i32;
             let y = winapi::GET_Y_LPARAM(lparam) as i32;
 
+            let locked = CONTEXT_STASH.with(|context_stash| {
+                context_stash.borrow().get(&window)
+                             .map(|cstash| {
+                                 let window_state = cstash.window_state.lock().unwrap();
+                                 window_state.cursor_grab == CursorGrabMode::Locked
+                             })
+                             .unwrap_or(false)
+            });
+
+            // a locked cursor is moved back to the center of the window after every motion,
+            // unless another window is active
+            if locked && user32::GetForegroundWindow() == window {
+                let mut rect = mem::uninitialized();
+                user32::GetClientRect(window, &mut rect);
+                let mut center = winapi::POINT {
+                    x: (rect.right - rect.left) / 2,
+                    y: (rect.bottom - rect.top) / 2,
+                };
+
+                // moving the cursor back to the center produces a motion without any distance
+                if (x, y) != (center.x, center.y) {
+                    send_event(window, MouseMotion((x - center.x) as f64, (y - center.y) as f64));
+                    user32::ClientToScreen(window, &mut center);
+                    user32::SetCursorPos(center.x, center.y);
+                }
+
+                return 0;
+            }
+
             send_event(window, MouseMoved(x, y));
+            // win32 only reports whole pixels
+            send_event(window, MouseMovedPrecise(x as f64, y as f64));
 
             0
         },
@@ -152,7 +277,9 @@ pub unsafe extern "system" fn callback(window: winapi::HWND, msg: winapi::UINT,
                 user32::DefWindowProcW(window, msg, wparam, lparam)
             } else {
                 let (scancode, vkey) = event::vkeycode_to_element(wparam, lparam);
-                send_event(window, KeyboardInput(Pressed, scancode, vkey));
+                // bit 30 is the previous state of the key, which is set for the auto-repeat
+                let repeat = (lparam & 0x40000000) != 0;
+                send_input_event(window, KeyboardInput(Pressed, scancode, vkey, repeat));
                 0
             }
         },
@@ -161,7 +288,7 @@ pub unsafe extern "system" fn callback(window: winapi::HWND, msg: winapi::UINT,
             use events::Event::KeyboardInput;
             use events::ElementState::Released;
             let (scancode, vkey) = event::vkeycode_to_element(wparam, lparam);
-            send_event(window, KeyboardInput(Released, scancode, vkey));
+            send_input_event(window, KeyboardInput(Released, scancode, vkey, false));
             0
         },
 
@@ -169,7 +296,7 @@ pub unsafe extern "system" fn callback(window: winapi::HWND, msg: winapi::UINT,
             use events::Event::MouseInput;
             use events::MouseButton::Left;
             use events::ElementState::Pressed;
-            send_event(window, MouseInput(Pressed, Left, None));
+            send_input_event(window, MouseInput(Pressed, Left, None));
             0
         },
 
@@ -177,7 +304,7 @@ pub unsafe extern "system" fn callback(window: winapi::HWND, msg: winapi::UINT,
             use events::Event::MouseInput;
             use events::MouseButton::Left;
             use events::ElementState::Released;
-            send_event(window, MouseInput(Released, Left, None));
+            send_input_event(window, MouseInput(Released, Left, None));
             0
         },
 
@@ -185,7 +312,7 @@ pub unsafe extern "system" fn callback(window: winapi::HWND, msg: winapi::UINT,
             use events::Event::MouseInput;
             use events::MouseButton::Right;
             use events::ElementState::Pressed;
-            send_event(window, MouseInput(Pressed, Right, None));
+            send_input_event(window, MouseInput(Pressed, Right, None));
             0
         },
 
@@ -193,7 +320,7 @@ pub unsafe extern "system" fn callback(window: winapi::HWND, msg: winapi::UINT,
             use events::Event::MouseInput;
             use events::MouseButton::Right;
             use events::ElementState::Released;
-            send_event(window, MouseInput(Released, Right, None));
+            send_input_event(window, MouseInput(Released, Right, None));
             0
         },
 
@@ -201,7 +328,7 @@ pub unsafe extern "system" fn callback(window: winapi::HWND, msg: winapi::UINT,
             use events::Event::MouseInput;
             use events::MouseButton::Middle;
             use events::ElementState::Pressed;
-            send_event(window, MouseInput(Pressed, Middle, None));
+            send_input_event(window, MouseInput(Pressed, Middle, None));
             0
         },
 
@@ -209,7 +336,7 @@ pub unsafe extern "system" fn callback(window: winapi::HWND, msg: winapi::UINT,
             use events::Event::MouseInput;
             use events::MouseButton::Middle;
             use events::ElementState::Released;
-            send_event(window, MouseInput(Released, Middle, None));
+            send_input_event(window, MouseInput(Released, Middle, None));
             0
         },
 
@@ -218,7 +345,7 @@ pub unsafe extern "system" fn callback(window: winapi::HWND, msg: winapi::UINT,
             use events::MouseButton::Other;
             use events::ElementState::Pressed;
             let xbutton = winapi::HIWORD(wparam as winapi::DWORD) as winapi::c_int; // waiting on PR for winapi to add GET_XBUTTON_WPARAM
-            send_event(window, MouseInput(Pressed, Other(xbutton as u8), None));
+            send_input_event(window, MouseInput(Pressed, Other(xbutton as u8), None));
             0
         },
 
@@ -227,7 +354,7 @@ pub unsafe extern "system" fn callback(window: winapi::HWND, msg: winapi::UINT,
             use events::MouseButton::Other;
             use events::ElementState::Released;
             let xbutton = winapi::HIWORD(wparam as winapi::DWORD) as winapi::c_int; 
-            send_event(window, MouseInput(Released, Other(xbutton as u8), None));
+            send_input_event(window, MouseInput(Released, Other(xbutton as u8), None));
             0
         },
 
@@ -251,35 +378,65 @@ pub unsafe extern "system" fn callback(window: winapi::HWND, msg: winapi::UINT,
             }
         },
 
+        winapi::WM_INPUTLANGCHANGE => {
+            use events::Event::KeyboardLayoutChanged;
+            send_event(window, KeyboardLayoutChanged);
+            user32::DefWindowProcW(window, msg, wparam, lparam)
+        },
+
         winapi::WM_SETFOCUS => {
             use events::Event::Focused;
+
+            // the system released the cursor when the window lost the focus, and the grabs that
+            // were requested meanwhile are applied now
+            CONTEXT_STASH.with(|context_stash| {
+                if let Some(cstash) = context_stash.borrow().get(&window) {
+                    let mut window_state = cstash.window_state.lock().unwrap();
+                    if let Some(mode) = window_state.deferred_cursor_grab.take() {
+                        if mode == CursorGrabMode::Locked &&
+                           window_state.cursor_grab != CursorGrabMode::Locked
+                        {
+                            window_state.cursor_position_before_lock =
+                                super::get_cursor_position(window);
+                        }
+                        window_state.cursor_grab = mode;
+                    }
+                    if window_state.cursor_grab != CursorGrabMode::None {
+                        let _ = super::clip_cursor_to_client_area(window);
+                    }
+                }
+            });
+
             send_event(window, Focused(true));
             0
         },
 
         winapi::WM_KILLFOCUS => {
             use events::Event::Focused;
-            send_event(window, Focused(false));
+            send_input_event(window, Focused(false));
             0
         },
 
+        winapi::WM_SETCURSOR if winapi::LOWORD(lparam as winapi::DWORD) as winapi::LRESULT !=
+                                 winapi::HTCLIENT as winapi::LRESULT => {
+            // the borders of resizable borderless windows show the cursors of the system
+            user32::DefWindowProcW(window, msg, wparam, lparam)
+        },
+
         winapi::WM_SETCURSOR => {
             CONTEXT_STASH.with(|context_stash| {
                 let cstash = context_stash.borrow();
-                let cstash = cstash.as_ref();
+                let cstash = cstash.get(&window);
                 // there's a very bizarre borrow checker bug
                 // possibly related to rust-lang/rust/#23338
                 let _cursor_state = if let Some(cstash) = cstash {
                     if let Ok(window_state) = cstash.window_state.lock() {
-                        match window_state.cursor_state {
-                            CursorState::Normal => {
-                                user32::SetCursor(user32::LoadCursorW(
-                                        ptr::null_mut(),
-                                        window_state.cursor));
-                            },
-                            CursorState::Grab | CursorState::Hide => {
-                                user32::SetCursor(ptr::null_mut());
-                            }
+                        if window_state.cursor_state.is_hidden() {
+                            user32::SetCursor(ptr::null_mut());
+                        } else {
+                            user32::SetCursor(user32::LoadCursorW(
+                                    ptr::null_mut(),
+                                    window_state.cursor));
                         }
                     }
                 } else {
@@ -310,26 +467,60 @@ pub unsafe extern "system" fn callback(window: winapi::HWND, msg: winapi::UINT,
             0
         },
 
+        winapi::WM_NCHITTEST => {
+            let hit = CONTEXT_STASH.with(|context_stash| {
+                match context_stash.borrow().get(&window) {
+                    Some(cstash) => {
+                        let window_state = cstash.window_state.lock().unwrap();
+                        hit_test(window, &window_state, lparam)
+                    },
+                    _ => None,
+                }
+            });
+
+            match hit {
+                Some(hit) => hit,
+                None => user32::DefWindowProcW(window, msg, wparam, lparam),
+            }
+        },
+
+        winapi::WM_SIZING => {
+            let ratio = CONTEXT_STASH.with(|context_stash| {
+                context_stash.borrow().get(&window)
+                             .and_then(|cstash| cstash.window_state.lock().unwrap().aspect_ratio)
+            });
+
+            match ratio {
+                Some(ratio) => {
+                    apply_aspect_ratio(window, &mut *(lparam as *mut winapi::RECT), wparam, ratio);
+                    1
+                },
+                None => user32::DefWindowProcW(window, msg, wparam, lparam),
+            }
+        },
+
         winapi::WM_GETMINMAXINFO => {
             let mut mmi = lparam as *mut MinMaxInfo;
             //(*mmi).max_position = winapi::POINT { x: -8, y: -8 }; // The upper left corner of the window if it were maximized on the primary monitor.
             //(*mmi).max_size = winapi::POINT { x: .., y: .. }; // The dimensions of the primary monitor.
 
             CONTEXT_STASH.with(|context_stash| {
-                match context_stash.borrow().as_ref() {
+                match context_stash.borrow().get(&window) {
                     Some(cstash) => {
                         let window_state = cstash.window_state.lock().unwrap();
 
+                        // the dimensions are the ones of the client area, like on the other
+                        // platforms, while the tracking sizes include the frame
                         match window_state.attributes.min_dimensions {
                             Some((width, height)) => {
-                                (*mmi).min_track = winapi::POINT { x: width as i32, y: height as i32 };
+                                (*mmi).min_track = outer_size(window, width, height);
                             },
                             None => { }
                         }
 
                         match window_state.attributes.max_dimensions {
                             Some((width, height)) => {
-                                (*mmi).max_track = winapi::POINT { x: width as i32, y: height as i32 };
+                                (*mmi).max_track = outer_size(window, width, height);
                             },
                             None => { }
                         }
@@ -346,8 +537,250 @@ pub unsafe extern "system" fn callback(window: winapi::HWND, msg: winapi::UINT,
             0
         },
 
+        x if x == *super::REQUEST_CLOSE_MSG_ID => {
+            use events::Event::Closed;
+            send_event(window, Closed);
+            0
+        },
+
+        x if x == *super::SET_IME_ALLOWED_MSG_ID => {
+            // without an input context, the keystrokes are given to the window as they are; the
+            // input context can only be changed by the thread that created the window
+            let flags = if wparam != 0 { IACE_DEFAULT } else { 0 };
+            ImmAssociateContextEx(window, ptr::null_mut(), flags);
+            0
+        },
+
+        x if x == *super::SET_IME_POSITION_MSG_ID || x == WM_IME_STARTCOMPOSITION => {
+            // the position is also applied when a composition starts, since the input method
+            // may have moved its windows meanwhile
+            let position = CONTEXT_STASH.with(|context_stash| {
+                context_stash.borrow().get(&window)
+                             .and_then(|cstash| cstash.window_state.lock().unwrap().ime_position)
+            });
+            if let Some(position) = position {
+                set_ime_position(window, position);
+            }
+
+            if msg == WM_IME_STARTCOMPOSITION {
+                user32::DefWindowProcW(window, msg, wparam, lparam)
+            } else {
+                0
+            }
+        },
+
+        x if x == *super::DRAG_WINDOW_MSG_ID => {
+            // the window has the capture of the mouse since the button was pressed, which must be
+            // released for the system to track it; then the press is handled as if it was on the
+            // title bar or on the border, which runs the move or resize loop until the button is
+            // released and reports the positions and the sizes with `WM_MOVE` and `WM_SIZE`
+            user32::ReleaseCapture();
+
+            let mut position: winapi::POINT = mem::uninitialized();
+            user32::GetCursorPos(&mut position);
+            let lparam = ((position.y as u16 as u32) << 16 | position.x as u16 as u32) as winapi::LPARAM;
+            user32::DefWindowProcW(window, winapi::WM_NCLBUTTONDOWN, wparam, lparam);
+
+            // the move loop returns once the button is released, and the final position is
+            // reported even if the window didn't move, with the same origin as `WM_MOVE`
+            if wparam == winapi::HTCAPTION as winapi::WPARAM {
+                let mut origin = winapi::POINT { x: 0, y: 0 };
+                if user32::ClientToScreen(window, &mut origin) != 0 {
+                    use events::Event::Moved;
+                    send_event(window, Moved(origin.x, origin.y));
+                }
+            }
+            0
+        },
+
+        x if x == *super::DESTROY_MSG_ID => {
+            // a window can only be destroyed by the thread that created it
+            user32::DestroyWindow(window);
+            0
+        },
+
         _ => {
             user32::DefWindowProcW(window, msg, wparam, lparam)
         }
     }
 }
+
+/// Gives a message to the filter of `set_raw_event_filter`, and returns true if it consumed the
+/// message.
+unsafe fn filter_raw_event(window: winapi::HWND, msg: winapi::UINT, wparam: winapi::WPARAM,
+                           lparam: winapi::LPARAM) -> bool
+{
+    let raw_event_filter = CONTEXT_STASH.with(|context_stash| {
+        context_stash.borrow().get(&window).map(|cstash| cstash.raw_event_filter.clone())
+    });
+    let raw_event_filter = match raw_event_filter {
+        Some(raw_event_filter) => raw_event_filter,
+        None => return false,
+    };
+
+    // the filter is taken out while it runs, so that it can call the methods of the window, and
+    // the messages that these methods send meanwhile aren't filtered
+    let (mut filter, generation) = {
+        let mut current = raw_event_filter.lock().unwrap();
+        match current.0.take() {
+            Some(filter) => (filter, current.1),
+            None => return false,
+        }
+    };
+
+    let consumed = filter(&RawEvent::Win32 {
+        hwnd: window as *mut _,
+        message: msg,
+        wparam: wparam as usize,
+        lparam: lparam as isize,
+    });
+
+    // unless the filter has been replaced or removed meanwhile
+    let mut current = raw_event_filter.lock().unwrap();
+    if current.1 == generation {
+        current.0 = Some(filter);
+    }
+    consumed
+}
+
+/// Places the composition and candidate windows of the input method at a point of the client
+/// area. This only works on the thread that created the window.
+unsafe fn set_ime_position(window: winapi::HWND, (x, y): (i32, i32)) {
+    // the window has no input context while the input method isn't allowed
+    let himc = ImmGetContext(window);
+    if himc.is_null() {
+        return;
+    }
+
+    let point = winapi::POINT { x: x, y: y };
+    let empty = winapi::RECT { left: 0, top: 0, right: 0, bottom: 0 };
+    ImmSetCompositionWindow(himc, &CompositionForm {
+        style: CFS_POINT,
+        current_pos: point,
+        area: empty,
+    });
+    ImmSetCandidateWindow(himc, &CandidateForm {
+        index: 0,
+        style: CFS_CANDIDATEPOS,
+        current_pos: point,
+        area: empty,
+    });
+
+    ImmReleaseContext(window, himc);
+}
+
+/// Returns the size of the window whose client area has the given dimensions.
+unsafe fn outer_size(window: winapi::HWND, width: u32, height: u32) -> winapi::POINT {
+    let mut rect = winapi::RECT { left: 0, top: 0, right: width as winapi::LONG,
+                                  bottom: height as winapi::LONG };
+    let style = user32::GetWindowLongA(window, winapi::GWL_STYLE) as winapi::DWORD;
+    let ex_style = user32::GetWindowLongA(window, winapi::GWL_EXSTYLE) as winapi::DWORD;
+    user32::AdjustWindowRectEx(&mut rect, style, 0, ex_style);
+    winapi::POINT { x: rect.right - rect.left, y: rect.bottom - rect.top }
+}
+
+/// Returns the part of a borderless window that is under the point given to `WM_NCHITTEST`, so
+/// that the resize border and the caption region of the window state behave like a frame.
+///
+/// Returns `None` if the window has decorations or none of these regions, in which case the
+/// default handling applies.
+unsafe fn hit_test(window: winapi::HWND, window_state: &WindowState, lparam: winapi::LPARAM)
+                   -> Option<winapi::LRESULT>
+{
+    if window_state.attributes.decorations || window_state.attributes.monitor.is_some() {
+        return None;
+    }
+
+    if window_state.resize_border == 0 && window_state.caption_region.is_none() {
+        return None;
+    }
+
+    // the position is in screen coordinates
+    let mut point = winapi::POINT {
+        x: winapi::GET_X_LPARAM(lparam),
+        y: winapi::GET_Y_LPARAM(lparam),
+    };
+    if user32::ScreenToClient(window, &mut point) == 0 {
+        return None;
+    }
+
+    let mut rect: winapi::RECT = mem::uninitialized();
+    if user32::GetClientRect(window, &mut rect) == 0 {
+        return None;
+    }
+
+    let border = window_state.resize_border as i32;
+    let left = point.x < border;
+    let right = point.x >= rect.right - border;
+    let top = point.y < border;
+    let bottom = point.y >= rect.bottom - border;
+
+    let hit = match (left, right, top, bottom) {
+        (true, _, true, _) => winapi::HTTOPLEFT,
+        (_, true, true, _) => winapi::HTTOPRIGHT,
+        (true, _, _, true) => winapi::HTBOTTOMLEFT,
+        (_, true, _, true) => winapi::HTBOTTOMRIGHT,
+        (true, _, _, _) => winapi::HTLEFT,
+        (_, true, _, _) => winapi::HTRIGHT,
+        (_, _, true, _) => winapi::HTTOP,
+        (_, _, _, true) => winapi::HTBOTTOM,
+        _ => match window_state.caption_region {
+            Some(ref region) if region.contains(point.x, point.y) => winapi::HTCAPTION,
+            _ => winapi::HTCLIENT,
+        },
+    };
+
+    Some(hit as winapi::LRESULT)
+}
+
+/// Returns the size of the client area of the window once it is moved to the rectangle suggested
+/// by `WM_DPICHANGED`.
+unsafe fn suggested_inner_size(window: winapi::HWND, rect: &winapi::RECT) -> Option<(u32, u32)> {
+    let mut window_rect: winapi::RECT = mem::uninitialized();
+    let mut client_rect: winapi::RECT = mem::uninitialized();
+    if user32::GetWindowRect(window, &mut window_rect) == 0 ||
+       user32::GetClientRect(window, &mut client_rect) == 0
+    {
+        return None;
+    }
+    let frame_width = (window_rect.right - window_rect.left) - client_rect.right;
+    let frame_height = (window_rect.bottom - window_rect.top) - client_rect.bottom;
+
+    let width = cmp::max(rect.right - rect.left - frame_width, 0);
+    let height = cmp::max(rect.bottom - rect.top - frame_height, 0);
+    Some((width as u32, height as u32))
+}
+
+/// Adjusts the window rectangle proposed by `WM_SIZING` so that the client area has the ratio
+/// `x:y`. The dimension that follows the other one is changed by moving an edge that isn't being
+/// dragged.
+unsafe fn apply_aspect_ratio(window: winapi::HWND, rect: &mut winapi::RECT, edge: winapi::WPARAM,
+                             (x, y): (u32, u32))
+{
+    // the frame isn't part of the ratio
+    let mut window_rect: winapi::RECT = mem::uninitialized();
+    let mut client_rect: winapi::RECT = mem::uninitialized();
+    if user32::GetWindowRect(window, &mut window_rect) == 0 ||
+       user32::GetClientRect(window, &mut client_rect) == 0
+    {
+        return;
+    }
+    let frame_width = (window_rect.right - window_rect.left) - client_rect.right;
+    let frame_height = (window_rect.bottom - window_rect.top) - client_rect.bottom;
+
+    let width = cmp::max(rect.right - rect.left - frame_width, 1) as i64;
+    let height = cmp::max(rect.bottom - rect.top - frame_height, 1) as i64;
+    let (x, y) = (x as i64, y as i64);
+
+    match edge {
+        WMSZ_TOP | WMSZ_BOTTOM => {
+            rect.right = rect.left + (height * x / y) as i32 + frame_width;
+        },
+        WMSZ_TOPLEFT | WMSZ_TOPRIGHT => {
+            rect.top = rect.bottom - (width * y / x) as i32 - frame_height;
+        },
+        _ => {
+            rect.bottom = rect.top + (width * y / x) as i32 + frame_height;
+        },
+    }
+}
@@ -0,0 +1,90 @@
+//! HiDPI support of Win32 windows.
+//!
+//! Windows only tells the applications that declare themselves DPI-aware about the density of
+//! the monitors, and scales the windows of the others as bitmaps. The process is therefore made
+//! per-monitor DPI-aware before the first window is created, so that each window receives
+//! `WM_DPICHANGED` when it moves to a monitor with another density.
+//!
+//! The functions that are missing on older versions of Windows are loaded at runtime.
+
+use std::mem;
+use std::os::raw::c_int;
+use std::sync::{Once, ONCE_INIT};
+
+use winapi;
+use gdi32;
+use kernel32;
+use user32;
+
+/// The DPI that corresponds to a HiDPI factor of `1.0`.
+pub const BASE_DPI: u32 = 96;
+
+// `PROCESS_DPI_AWARENESS` of `SetProcessDpiAwareness`
+const PROCESS_PER_MONITOR_DPI_AWARE: c_int = 2;
+
+// `GetDeviceCaps` index of the horizontal DPI
+const LOGPIXELSX: c_int = 88;
+
+type SetProcessDpiAwareness = unsafe extern "system" fn(c_int) -> winapi::HRESULT;
+type SetProcessDPIAware = unsafe extern "system" fn() -> winapi::BOOL;
+type GetDpiForWindow = unsafe extern "system" fn(winapi::HWND) -> winapi::UINT;
+
+static BECOME_DPI_AWARE: Once = ONCE_INIT;
+
+/// Makes the process per-monitor DPI-aware (Windows 8.1 and later), or system DPI-aware
+/// (Windows Vista and later). Does nothing the second time.
+///
+/// An application manifest that already declares the awareness takes precedence.
+pub fn become_dpi_aware() {
+    BECOME_DPI_AWARE.call_once(|| unsafe {
+        if let Some(f) = get_function::<SetProcessDpiAwareness>(b"shcore.dll\0",
+                                                                b"SetProcessDpiAwareness\0")
+        {
+            f(PROCESS_PER_MONITOR_DPI_AWARE);
+        } else if let Some(f) = get_function::<SetProcessDPIAware>(b"user32.dll\0",
+                                                                   b"SetProcessDPIAware\0")
+        {
+            f();
+        }
+    });
+}
+
+/// Returns the HiDPI factor of the monitor of the window.
+pub fn get_hidpi_factor(window: winapi::HWND) -> f32 {
+    unsafe {
+        // Windows 10 version 1607 and later
+        if let Some(f) = get_function::<GetDpiForWindow>(b"user32.dll\0", b"GetDpiForWindow\0") {
+            let dpi = f(window);
+            if dpi != 0 {
+                return dpi as f32 / BASE_DPI as f32;
+            }
+        }
+
+        // otherwise all the monitors have the DPI of the system
+        let hdc = user32::GetDC(window);
+        if hdc.is_null() {
+            return 1.0;
+        }
+        let dpi = gdi32::GetDeviceCaps(hdc, LOGPIXELSX);
+        user32::ReleaseDC(window, hdc);
+
+        if dpi > 0 { dpi as f32 / BASE_DPI as f32 } else { 1.0 }
+    }
+}
+
+/// Returns the function `name` of the system library `library`, which must be a function
+/// pointer type `F`.
+unsafe fn get_function<F: Copy>(library: &[u8], name: &[u8]) -> Option<F> {
+    let module = kernel32::LoadLibraryA(library.as_ptr() as *const _);
+    if module.is_null() {
+        return None;
+    }
+
+    let address = kernel32::GetProcAddress(module, name.as_ptr() as *const _);
+    if address.is_null() {
+        return None;
+    }
+
+    debug_assert_eq!(mem::size_of::<F>(), mem::size_of_val(&address));
+    Some(mem::transmute_copy(&address))
+}
@@ -1,10 +1,58 @@
+use std::ptr;
+
 use events::VirtualKeyCode;
 use winapi;
 use user32;
 use ScanCode;
 
+const MAPVK_VK_TO_VSC: u32 = 0;
 const MAPVK_VSC_TO_VK_EX: u32 = 3;
 
+/// Returns the keyboard layout of the thread that receives the messages of a window, which
+/// changes when the user switches layouts.
+unsafe fn keyboard_layout(hwnd: winapi::HWND) -> winapi::HKL {
+    user32::GetKeyboardLayout(user32::GetWindowThreadProcessId(hwnd, ptr::null_mut()))
+}
+
+/// Returns the virtual key that a scan code produces with the current keyboard layout.
+pub fn scancode_to_keycode(hwnd: winapi::HWND, scancode: ScanCode) -> Option<VirtualKeyCode> {
+    let vk = unsafe {
+        user32::MapVirtualKeyExW(scancode as winapi::UINT, MAPVK_VSC_TO_VK_EX,
+                                 keyboard_layout(hwnd))
+    };
+    vkey_to_keycode(vk as i32)
+}
+
+/// Returns the scan code of the key that produces a virtual key with the current keyboard
+/// layout.
+pub fn keycode_to_scancode(hwnd: winapi::HWND, keycode: VirtualKeyCode) -> Option<ScanCode> {
+    let vk = match (0 .. 256).find(|&vk| vkey_to_keycode(vk) == Some(keycode)) {
+        Some(vk) => vk,
+        None => return None,
+    };
+
+    let scancode = unsafe {
+        user32::MapVirtualKeyExW(vk as winapi::UINT, MAPVK_VK_TO_VSC, keyboard_layout(hwnd))
+    };
+    match scancode {
+        0 => None,
+        scancode => Some(scancode as ScanCode),
+    }
+}
+
+/// Returns the name of a key, as the keyboard layout of the calling thread labels it.
+pub fn key_name(scancode: ScanCode) -> Option<String> {
+    let mut buffer = [0u16; 64];
+    let len = unsafe {
+        user32::GetKeyNameTextW((scancode as winapi::LONG) << 16, buffer.as_mut_ptr(),
+                                buffer.len() as winapi::c_int)
+    };
+    match len {
+        0 => None,
+        len => Some(String::from_utf16_lossy(&buffer[.. len as usize])),
+    }
+}
+
 pub fn vkeycode_to_element(wparam: winapi::WPARAM, lparam: winapi::LPARAM) -> (ScanCode, Option<VirtualKeyCode>) {
     let scancode = ((lparam >> 16) & 0xff) as u8;
     let extended = (lparam & 0x01000000) != 0;
@@ -14,7 +62,13 @@ pub fn vkeycode_to_element(wparam: winapi::WPARAM, lparam: winapi::LPARAM) -> (S
         winapi::VK_MENU => if extended { winapi::VK_RMENU } else { winapi::VK_LMENU },
         other => other
     };
-    (scancode, match vk {
+    (scancode, vkey_to_keycode(vk))
+}
+
+/// Maps a virtual-key code, where the left and right modifiers are told apart, to a
+/// `VirtualKeyCode`.
+fn vkey_to_keycode(vk: i32) -> Option<VirtualKeyCode> {
+    match vk {
         //winapi::VK_LBUTTON => Some(VirtualKeyCode::Lbutton),
         //winapi::VK_RBUTTON => Some(VirtualKeyCode::Rbutton),
         //winapi::VK_CANCEL => Some(VirtualKeyCode::Cancel),
@@ -186,5 +240,5 @@ pub fn vkeycode_to_element(wparam: winapi::WPARAM, lparam: winapi::LPARAM) -> (S
         winapi::VK_PA1 => Some(VirtualKeyCode::Pa1),
         winapi::VK_OEM_CLEAR => Some(VirtualKeyCode::Oem_clear),*/
         _ => None
-    })
+    }
 }
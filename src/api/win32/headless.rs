@@ -0,0 +1,174 @@
+//! Headless WGL contexts.
+//!
+//! WGL can only create contexts for a device context that has a pixel format, so the context is
+//! created on a hidden 1×1 window which belongs to the thread that builds it. Contrary to the
+//! windows of `Window`, this window has no message thread and never receives input.
+//!
+//! A window can only be destroyed by the thread that created it, so the context isn't `Send`.
+
+use std::ffi::OsStr;
+use std::io;
+use std::mem;
+use std::os::windows::ffi::OsStrExt;
+use std::ptr;
+use std::sync::Mutex;
+
+use Api;
+use ContextError;
+use CreationError;
+use GlAttributes;
+use GlContext;
+use PixelFormat;
+use PixelFormatRequirements;
+
+use winapi;
+use kernel32;
+use user32;
+
+use api::wgl::Context as WglContext;
+
+lazy_static! {
+    /// Held while the window class is registered and a window is created with it, or while a
+    /// window is destroyed and the class unregistered, so that a class is never unregistered
+    /// between the registration and the creation of another window.
+    static ref WINDOW_CLASS_LOCK: Mutex<()> = Mutex::new(());
+}
+
+/// A WGL context on a hidden window.
+pub struct HeadlessContext {
+    // the fields are dropped in this order, the context must be destroyed before its window
+    context: WglContext,
+    window: HiddenWindow,
+}
+
+/// Destroys the window when dropped, then unregisters the class if no other headless context
+/// uses it.
+struct HiddenWindow {
+    hwnd: winapi::HWND,
+    class_name: Vec<u16>,
+}
+
+impl HeadlessContext {
+    /// Creates the hidden window and a context on it.
+    ///
+    /// The default framebuffer of the window has a size of 1×1, so the dimensions requested by
+    /// the user are ignored and rendering must be done in a framebuffer object.
+    pub fn new(pf_reqs: &PixelFormatRequirements, opengl: &GlAttributes<winapi::HGLRC>)
+               -> Result<HeadlessContext, CreationError>
+    {
+        let window = unsafe {
+            let _lock = WINDOW_CLASS_LOCK.lock().unwrap();
+
+            let class_name = register_window_class();
+            let window = user32::CreateWindowExW(winapi::WS_EX_TOOLWINDOW, class_name.as_ptr(),
+                ptr::null(), winapi::WS_POPUP | winapi::WS_CLIPSIBLINGS | winapi::WS_CLIPCHILDREN,
+                0, 0, 1, 1, ptr::null_mut(), ptr::null_mut(), kernel32::GetModuleHandleW(ptr::null()),
+                ptr::null_mut());
+
+            if window.is_null() {
+                let error = io::Error::last_os_error();
+                user32::UnregisterClassW(class_name.as_ptr(), kernel32::GetModuleHandleW(ptr::null()));
+                return Err(CreationError::OsError(format!("CreateWindowEx function failed: {}",
+                                                          error)));
+            }
+
+            HiddenWindow { hwnd: window, class_name: class_name }
+        };
+
+        let context = try!(unsafe { WglContext::new(pf_reqs, opengl, window.hwnd) });
+
+        Ok(HeadlessContext {
+            context: context,
+            window: window,
+        })
+    }
+
+    /// Returns the raw HGLRC.
+    #[inline]
+    pub fn get_hglrc(&self) -> winapi::HGLRC {
+        self.context.get_hglrc()
+    }
+
+    #[inline]
+    pub fn get_raw_context(&self) -> ::os::windows::RawContext {
+        ::os::windows::RawContext::from_wgl(&self.context)
+    }
+}
+
+impl Drop for HiddenWindow {
+    fn drop(&mut self) {
+        let _lock = WINDOW_CLASS_LOCK.lock().unwrap();
+
+        unsafe {
+            user32::DestroyWindow(self.hwnd);
+
+            // fails with `ERROR_CLASS_HAS_WINDOWS` as long as other headless contexts exist, in
+            // which case the last one to be dropped unregisters the class
+            user32::UnregisterClassW(self.class_name.as_ptr(),
+                                     kernel32::GetModuleHandleW(ptr::null()));
+        }
+    }
+}
+
+impl GlContext for HeadlessContext {
+    #[inline]
+    unsafe fn make_current(&self) -> Result<(), ContextError> {
+        self.context.make_current()
+    }
+
+    #[inline]
+    fn is_current(&self) -> bool {
+        self.context.is_current()
+    }
+
+    #[inline]
+    fn get_proc_address(&self, addr: &str) -> *const () {
+        self.context.get_proc_address(addr)
+    }
+
+    #[inline]
+    fn is_extension_supported(&self, name: &str) -> bool {
+        self.context.is_extension_supported(name)
+    }
+
+    #[inline]
+    fn swap_buffers(&self) -> Result<(), ContextError> {
+        self.context.swap_buffers()
+    }
+
+    #[inline]
+    fn get_api(&self) -> Api {
+        self.context.get_api()
+    }
+
+    #[inline]
+    fn get_pixel_format(&self) -> PixelFormat {
+        self.context.get_pixel_format()
+    }
+}
+
+unsafe fn register_window_class() -> Vec<u16> {
+    let class_name = OsStr::new("Headless Window Class").encode_wide().chain(Some(0).into_iter())
+                                                        .collect::<Vec<_>>();
+
+    let class = winapi::WNDCLASSEXW {
+        cbSize: mem::size_of::<winapi::WNDCLASSEXW>() as winapi::UINT,
+        style: winapi::CS_OWNDC,
+        lpfnWndProc: Some(user32::DefWindowProcW),
+        cbClsExtra: 0,
+        cbWndExtra: 0,
+        hInstance: kernel32::GetModuleHandleW(ptr::null()),
+        hIcon: ptr::null_mut(),
+        hCursor: ptr::null_mut(),
+        hbrBackground: ptr::null_mut(),
+        lpszMenuName: ptr::null(),
+        lpszClassName: class_name.as_ptr(),
+        hIconSm: ptr::null_mut(),
+    };
+
+    // registering the class a second time fails, which is fine since it is shared by all the
+    // headless contexts
+    user32::RegisterClassExW(&class);
+
+    class_name
+}
@@ -1,3 +1,4 @@
+use std::collections::VecDeque;
 use std::sync::{Arc, Mutex};
 use std::io;
 use std::ptr;
@@ -5,16 +6,20 @@ use std::mem;
 use std::thread;
 
 use super::callback;
+use super::dpi;
 use super::WindowState;
 use super::Window;
 use super::MonitorId;
 use super::WindowWrapper;
 use super::Context;
+use super::PlatformSpecificWindowBuilderAttributes;
+use os::windows::Backdrop;
 
 use Api;
 use CreationError;
 use CreationError::OsError;
 use CursorState;
+use CursorGrabMode;
 use GlAttributes;
 use GlRequest;
 use PixelFormatRequirements;
@@ -22,17 +27,19 @@ use WindowAttributes;
 
 use std::ffi::{OsStr};
 use std::os::windows::ffi::OsStrExt;
-use std::sync::mpsc::channel;
+use std::sync::mpsc::{channel, Sender};
 
 use winapi;
 use kernel32;
 use dwmapi;
+use gdi32;
 use user32;
 
 use api::wgl::Context as WglContext;
 use api::egl;
 use api::egl::Context as EglContext;
 use api::egl::ffi::egl::Egl;
+use api::pressed_inputs::PressedInputs;
 
 #[derive(Clone)]
 pub enum RawContext {
@@ -43,61 +50,162 @@ pub enum RawContext {
 unsafe impl Send for RawContext {}
 unsafe impl Sync for RawContext {}
 
+lazy_static! {
+    /// The thread that creates the windows that have a message thread, and dispatches their
+    /// messages. It is started when the first of these windows is built.
+    static ref EVENTS_THREAD: EventsThread = EventsThread::spawn();
+
+    /// Posted to the requests window of `EVENTS_THREAD` when windows must be created.
+    static ref CREATE_WINDOW_MSG_ID: u32 = unsafe { user32::RegisterWindowMessageA("Glutin::CreateWindow\0".as_ptr() as *const i8) };
+}
+
+/// The parent of message-only windows, which are never shown and only receive messages.
+const HWND_MESSAGE: winapi::HWND = -3isize as winapi::HWND;
+
+/// A window that `EVENTS_THREAD` must create, and where to send it.
+struct CreationRequest {
+    title: Vec<u16>,
+    window: WindowAttributes,
+    pf_reqs: PixelFormatRequirements,
+    opengl: GlAttributes<RawContext>,
+    egl: Option<Egl>,
+    pl_attribs: PlatformSpecificWindowBuilderAttributes,
+    result: Sender<Result<Window, CreationError>>,
+}
+
+struct EventsThread {
+    /// A message-only window of the thread that receives `CREATE_WINDOW_MSG_ID`.
+    ///
+    /// Messages posted to the thread itself would be lost while the user moves or resizes one
+    /// of the windows, since Windows then runs its own events loop which only dispatches the
+    /// messages of windows.
+    requests_window: winapi::HWND,
+
+    /// The windows to create, in the order they were requested.
+    requests: Mutex<VecDeque<CreationRequest>>,
+}
+
+unsafe impl Send for EventsThread {}
+unsafe impl Sync for EventsThread {}
+
+impl EventsThread {
+    fn spawn() -> EventsThread {
+        let (tx, rx) = channel();
+
+        thread::spawn(move || {
+            unsafe {
+                let class_name = register_requests_window_class();
+                let requests_window = user32::CreateWindowExW(0, class_name.as_ptr(), ptr::null(),
+                    0, 0, 0, 0, 0, HWND_MESSAGE, ptr::null_mut(),
+                    kernel32::GetModuleHandleW(ptr::null()), ptr::null_mut());
+
+                // raw pointers can't be sent to another thread
+                tx.send(requests_window as usize).unwrap();
+
+                loop {
+                    let mut msg = mem::uninitialized();
+
+                    if user32::GetMessageW(&mut msg, ptr::null_mut(), 0, 0) == 0 {
+                        break;
+                    }
+
+                    user32::TranslateMessage(&msg);
+                    user32::DispatchMessageW(&msg);   // calls `callback` (see the callback module)
+                }
+            }
+        });
+
+        let requests_window = rx.recv().unwrap() as winapi::HWND;
+        assert!(!requests_window.is_null(), "Failed to create the window of the events thread");
+
+        EventsThread {
+            requests_window: requests_window,
+            requests: Mutex::new(VecDeque::new()),
+        }
+    }
+}
+
 pub fn new_window(window: &WindowAttributes, pf_reqs: &PixelFormatRequirements,
-                  opengl: &GlAttributes<RawContext>, egl: Option<&Egl>)
+                  opengl: &GlAttributes<RawContext>, egl: Option<&Egl>,
+                  pl_attribs: &PlatformSpecificWindowBuilderAttributes)
                   -> Result<Window, CreationError>
 {
+    // must be done before the first window is created
+    dpi::become_dpi_aware();
+
     let egl = egl.map(|e| e.clone());
     let window = window.clone();
     let pf_reqs = pf_reqs.clone();
     let opengl = opengl.clone();
+    let pl_attribs = pl_attribs.clone();
 
     // initializing variables to be sent to the task
 
     let title = OsStr::new(&window.title).encode_wide().chain(Some(0).into_iter())
                                           .collect::<Vec<_>>();
 
+    // without a message thread, the window belongs to the calling thread and the user is
+    // responsible for pumping its messages
+    if !pl_attribs.message_thread {
+        return unsafe { init(title, &window, &pf_reqs, &opengl, egl, pl_attribs) };
+    }
+
+    // `GetMessage` must be called in the same thread as CreateWindow, so the window is created
+    // by the thread that dispatches the messages of all the windows
     let (tx, rx) = channel();
 
-    // `GetMessage` must be called in the same thread as CreateWindow, so we create a new thread
-    // dedicated to this window.
-    thread::spawn(move || {
-        unsafe {
-            // creating and sending the `Window`
-            match init(title, &window, &pf_reqs, &opengl, egl) {
-                Ok(w) => tx.send(Ok(w)).ok(),
-                Err(e) => {
-                    tx.send(Err(e)).ok();
-                    return;
-                }
-            };
+    EVENTS_THREAD.requests.lock().unwrap().push_back(CreationRequest {
+        title: title,
+        window: window,
+        pf_reqs: pf_reqs,
+        opengl: opengl,
+        egl: egl,
+        pl_attribs: pl_attribs,
+        result: tx,
+    });
 
-            // now that the `Window` struct is initialized, the main `Window::new()` function will
-            //  return and this events loop will run in parallel
-            loop {
-                let mut msg = mem::uninitialized();
+    unsafe {
+        user32::PostMessageW(EVENTS_THREAD.requests_window, *CREATE_WINDOW_MSG_ID, 0, 0);
+    }
 
-                if user32::GetMessageW(&mut msg, ptr::null_mut(), 0, 0) == 0 {
-                    break;
-                }
+    rx.recv().unwrap()
+}
 
-                user32::TranslateMessage(&msg);
-                user32::DispatchMessageW(&msg);   // calls `callback` (see the callback module)
-            }
-        }
-    });
+/// The callback of the requests window of `EVENTS_THREAD`, which creates the windows.
+unsafe extern "system" fn requests_callback(window: winapi::HWND, msg: winapi::UINT,
+                                            wparam: winapi::WPARAM, lparam: winapi::LPARAM)
+                                            -> winapi::LRESULT
+{
+    if msg != *CREATE_WINDOW_MSG_ID {
+        return user32::DefWindowProcW(window, msg, wparam, lparam);
+    }
 
-    rx.recv().unwrap()
+    // the lock isn't held while a window is created, so that other threads can add requests
+    loop {
+        let request = match EVENTS_THREAD.requests.lock().unwrap().pop_front() {
+            Some(request) => request,
+            None => break,
+        };
+
+        let CreationRequest { title, window, pf_reqs, opengl, egl, pl_attribs, result } = request;
+        result.send(init(title, &window, &pf_reqs, &opengl, egl, pl_attribs)).ok();
+    }
+
+    0
 }
 
 unsafe fn init(title: Vec<u16>, window: &WindowAttributes, pf_reqs: &PixelFormatRequirements,
-               opengl: &GlAttributes<RawContext>, egl: Option<Egl>)
+               opengl: &GlAttributes<RawContext>, egl: Option<Egl>,
+               pl_attribs: PlatformSpecificWindowBuilderAttributes)
                -> Result<Window, CreationError>
 {
+    let message_thread = pl_attribs.message_thread;
+
+    // `Window::new` only lets WGL contexts through
     let opengl = opengl.clone().map_sharing(|sharelists| {
         match sharelists {
             RawContext::Wgl(c) => c,
-            _ => unimplemented!()
+            RawContext::Egl(_) => unreachable!(),
         }
     });
 
@@ -139,11 +247,25 @@ unsafe fn init(title: Vec<u16>, window: &WindowAttributes, pf_reqs: &PixelFormat
 
         let (x, y) = if window.monitor.is_some() {
             (Some(rect.left), Some(rect.top))
+        } else if let Some((x, y)) = window.initial_position {
+            (Some(x as winapi::LONG), Some(y as winapi::LONG))
         } else {
             (None, None)
         };
 
-        let style = if !window.visible {
+        let maximized = window.maximized && window.monitor.is_none();
+
+        // `WS_MAXIMIZE` makes the window restore to its maximized state if it is minimized
+        let style = if maximized {
+            style | winapi::WS_MAXIMIZE
+        } else {
+            style
+        };
+
+        // windows that start maximized, minimized or protected are shown below instead
+        let style = if !window.visible || maximized || window.minimized ||
+                       window.content_protected
+        {
             style
         } else {
             style | winapi::WS_VISIBLE
@@ -169,15 +291,29 @@ unsafe fn init(title: Vec<u16>, window: &WindowAttributes, pf_reqs: &PixelFormat
                                        format!("{}", io::Error::last_os_error()))));
         }
 
+        // the window isn't visible yet, so it is never captured
+        if window.content_protected {
+            set_display_affinity(handle, true);
+        }
+
+        if window.visible && window.minimized {
+            user32::ShowWindow(handle, winapi::SW_SHOWMINIMIZED);
+        } else if window.visible && maximized {
+            user32::ShowWindow(handle, winapi::SW_SHOWMAXIMIZED);
+        } else if window.visible && window.content_protected {
+            user32::ShowWindow(handle, winapi::SW_SHOW);
+        }
+
         WindowWrapper(handle, hdc)
     };
 
     // creating the OpenGL context
     let context = match opengl.version {
-        GlRequest::Specific(Api::OpenGlEs, (_major, _minor)) => {
+        // an EGL context couldn't share the objects of the WGL context, so WGL is used instead
+        GlRequest::Specific(Api::OpenGlEs, (_major, _minor)) if opengl.sharing.is_none() => {
             if let Some(egl) = egl {
-                if let Ok(c) = EglContext::new(egl, &pf_reqs, &opengl.clone().map_sharing(|_| unimplemented!()),
-                                               egl::NativeDisplay::Other(Some(ptr::null())))
+                if let Ok(c) = EglContext::new(egl, &pf_reqs, &opengl.clone().map_sharing(|_| unreachable!()),
+                                               egl::NativeDisplay::Angle(None))
                                                              .and_then(|p| p.finish(real_window.0))
                 {
                     Context::Egl(c)
@@ -193,21 +329,60 @@ unsafe fn init(title: Vec<u16>, window: &WindowAttributes, pf_reqs: &PixelFormat
                                     .map(Context::Wgl))
             }
         },
+        // desktop OpenGL is tried first, then OpenGL ES through ANGLE
+        GlRequest::GlThenGles { opengles_version, .. } => {
+            match WglContext::new(&pf_reqs, &opengl, real_window.0) {
+                Ok(c) => Context::Wgl(c),
+                Err(wgl_err) => {
+                    let egl = match egl {
+                        Some(egl) if opengl.sharing.is_none() => egl,
+                        _ => return Err(wgl_err),
+                    };
+
+                    let mut opengles = opengl.clone().map_sharing(|_| unreachable!());
+                    opengles.version = GlRequest::Specific(Api::OpenGlEs, opengles_version);
+                    match EglContext::new(egl, &pf_reqs, &opengles, egl::NativeDisplay::Angle(None))
+                                     .and_then(|p| p.finish(real_window.0))
+                    {
+                        Ok(c) => Context::Egl(c),
+                        Err(egl_err) => {
+                            return Err(CreationError::CreationErrorPair(Box::new(wgl_err),
+                                                                        Box::new(egl_err)));
+                        },
+                    }
+                },
+            }
+        },
         _ => {
             try!(WglContext::new(&pf_reqs, &opengl, real_window.0).map(Context::Wgl))
         }
     };
 
     // making the window transparent
+    //
+    // the blur behind and the frame that `set_drop_shadow` extends into the client area both
+    // make DWM compose the client area with the alpha of its pixels, so they can be combined,
+    // except that the transparent pixels of the 1px margin show the frame instead of the blurred
+    // desktop
+    let mut drop_shadow = pl_attribs.drop_shadow;
     if window.transparent {
-        let bb = winapi::DWM_BLURBEHIND {
-            dwFlags: 0x1, // FIXME: DWM_BB_ENABLE;
-            fEnable: 1,
-            hRgnBlur: ptr::null_mut(),
-            fTransitionOnMaximized: 0,
+        let system_backdrop = match pl_attribs.backdrop {
+            Backdrop::Acrylic => set_system_backdrop(real_window.0, DWMSBT_TRANSIENTWINDOW),
+            Backdrop::Mica => set_system_backdrop(real_window.0, DWMSBT_MAINWINDOW),
+            Backdrop::None | Backdrop::BlurBehind => false,
         };
 
-        dwmapi::DwmEnableBlurBehindWindow(real_window.0, &bb);
+        if system_backdrop {
+            // the frame already covers the whole client area
+            drop_shadow = false;
+        } else {
+            set_blur_behind(real_window.0, pl_attribs.backdrop != Backdrop::None);
+        }
+    }
+
+    // restoring the shadow of a borderless window
+    if drop_shadow && window.monitor.is_none() && !window.decorations {
+        set_drop_shadow(real_window.0, true);
     }
 
     // calling SetForegroundWindow if fullscreen
@@ -219,20 +394,33 @@ unsafe fn init(title: Vec<u16>, window: &WindowAttributes, pf_reqs: &PixelFormat
     let window_state = Arc::new(Mutex::new(WindowState {
         cursor: winapi::IDC_ARROW, // use arrow by default
         cursor_state: CursorState::Normal,
-        attributes: window.clone()
+        cursor_grab: CursorGrabMode::None,
+        deferred_cursor_grab: None,
+        cursor_position_before_lock: None,
+        ime_position: None,
+        attributes: window.clone(),
+        resize_border: pl_attribs.resize_border,
+        caption_region: pl_attribs.caption_region,
+        drop_shadow: drop_shadow,
+        aspect_ratio: None,
+        hidpi_factor: dpi::get_hidpi_factor(real_window.0),
+        dpi_resize: pl_attribs.dpi_resize,
+        pressed_inputs: PressedInputs::new(),
     }));
 
+    let raw_event_filter = Arc::new(Mutex::new((None, 0)));
+
     // filling the CONTEXT_STASH task-local storage so that we can start receiving events
     let events_receiver = {
         let (tx, rx) = channel();
         let mut tx = Some(tx);
         callback::CONTEXT_STASH.with(|context_stash| {
             let data = callback::ThreadLocalData {
-                win: real_window.0,
                 sender: tx.take().unwrap(),
-                window_state: window_state.clone()
+                window_state: window_state.clone(),
+                raw_event_filter: raw_event_filter.clone(),
             };
-            (*context_stash.borrow_mut()) = Some(data);
+            context_stash.borrow_mut().insert(real_window.0, data);
         });
         rx
     };
@@ -243,6 +431,10 @@ unsafe fn init(title: Vec<u16>, window: &WindowAttributes, pf_reqs: &PixelFormat
         context: context,
         events_receiver: events_receiver,
         window_state: window_state,
+        raw_event_filter: raw_event_filter,
+        message_thread: message_thread,
+        owner_thread: thread::current().id(),
+        quit_code: Mutex::new(None),
     })
 }
 
@@ -274,6 +466,31 @@ unsafe fn register_window_class() -> Vec<u16> {
     class_name
 }
 
+unsafe fn register_requests_window_class() -> Vec<u16> {
+    let class_name = OsStr::new("Requests Window Class").encode_wide().chain(Some(0).into_iter())
+                                                        .collect::<Vec<_>>();
+
+    let class = winapi::WNDCLASSEXW {
+        cbSize: mem::size_of::<winapi::WNDCLASSEXW>() as winapi::UINT,
+        style: 0,
+        lpfnWndProc: Some(requests_callback),
+        cbClsExtra: 0,
+        cbWndExtra: 0,
+        hInstance: kernel32::GetModuleHandleW(ptr::null()),
+        hIcon: ptr::null_mut(),
+        hCursor: ptr::null_mut(),
+        hbrBackground: ptr::null_mut(),
+        lpszMenuName: ptr::null(),
+        lpszClassName: class_name.as_ptr(),
+        hIconSm: ptr::null_mut(),
+    };
+
+    // the class is only registered once, by the events thread
+    user32::RegisterClassExW(&class);
+
+    class_name
+}
+
 unsafe fn switch_to_fullscreen(rect: &mut winapi::RECT, monitor: &MonitorId)
                                -> Result<(), CreationError>
 {
@@ -304,3 +521,113 @@ unsafe fn switch_to_fullscreen(rect: &mut winapi::RECT, monitor: &MonitorId)
 
     Ok(())
 }
+
+// `MARGINS`, `DwmExtendFrameIntoClientArea` and `DwmSetWindowAttribute` are missing from
+// `winapi` and `dwmapi-sys`
+#[repr(C)]
+struct Margins {
+    cx_left_width: winapi::c_int,
+    cx_right_width: winapi::c_int,
+    cy_top_height: winapi::c_int,
+    cy_bottom_height: winapi::c_int,
+}
+
+#[link(name = "dwmapi")]
+extern "system" {
+    fn DwmExtendFrameIntoClientArea(hwnd: winapi::HWND, margins: *const Margins)
+                                    -> winapi::HRESULT;
+    fn DwmSetWindowAttribute(hwnd: winapi::HWND, attribute: winapi::DWORD,
+                             value: winapi::LPCVOID, size: winapi::DWORD) -> winapi::HRESULT;
+}
+
+/// Shows or hides the drop shadow of a window without decorations.
+///
+/// `WS_POPUP` windows have no frame, and DWM only draws a shadow around frames. Extending the
+/// frame into the client area by 1px gives it one again, which is covered by the client area.
+/// The `CS_DROPSHADOW` class style would work as well, but the class is shared by all the
+/// windows and its shadow is meant for short-lived windows such as menus.
+pub unsafe fn set_drop_shadow(hwnd: winapi::HWND, enabled: bool) {
+    let width = if enabled { 1 } else { 0 };
+    let margins = Margins {
+        cx_left_width: width,
+        cx_right_width: width,
+        cy_top_height: width,
+        cy_bottom_height: width,
+    };
+
+    // fails if the composition is disabled, in which case there is no shadow anyway
+    DwmExtendFrameIntoClientArea(hwnd, &margins);
+}
+
+const DWM_BB_ENABLE: winapi::DWORD = 0x1;
+const DWM_BB_BLURREGION: winapi::DWORD = 0x2;
+
+/// Makes DWM compose the client area of a window with the alpha of its pixels.
+///
+/// If `blur` is false, the blur is restricted to an empty region, so the desktop is visible
+/// as it is behind the transparent pixels.
+unsafe fn set_blur_behind(hwnd: winapi::HWND, blur: bool) {
+    let region = if blur { ptr::null_mut() } else { gdi32::CreateRectRgn(0, 0, -1, -1) };
+
+    let bb = winapi::DWM_BLURBEHIND {
+        dwFlags: if blur { DWM_BB_ENABLE } else { DWM_BB_ENABLE | DWM_BB_BLURREGION },
+        fEnable: 1,
+        hRgnBlur: region,
+        fTransitionOnMaximized: 0,
+    };
+    dwmapi::DwmEnableBlurBehindWindow(hwnd, &bb);
+
+    if !region.is_null() {
+        gdi32::DeleteObject(region as winapi::HGDIOBJ);
+    }
+}
+
+// `DWMWA_SYSTEMBACKDROP_TYPE` attribute and values
+const DWMWA_SYSTEMBACKDROP_TYPE: winapi::DWORD = 38;
+const DWMSBT_MAINWINDOW: winapi::DWORD = 2;
+const DWMSBT_TRANSIENTWINDOW: winapi::DWORD = 3;
+
+/// Sets the material that DWM draws behind the window. Returns false if it failed, which
+/// happens before Windows 11 version 22H2.
+unsafe fn set_system_backdrop(hwnd: winapi::HWND, backdrop_type: winapi::DWORD) -> bool {
+    let result = DwmSetWindowAttribute(hwnd, DWMWA_SYSTEMBACKDROP_TYPE,
+                                       &backdrop_type as *const winapi::DWORD as winapi::LPCVOID,
+                                       mem::size_of::<winapi::DWORD>() as winapi::DWORD);
+    if result < 0 {
+        return false;
+    }
+
+    // the material is only visible where the frame is, so the frame is extended into the
+    // whole client area
+    let margins = Margins {
+        cx_left_width: -1,
+        cx_right_width: -1,
+        cy_top_height: -1,
+        cy_bottom_height: -1,
+    };
+    DwmExtendFrameIntoClientArea(hwnd, &margins);
+    true
+}
+
+// `SetWindowDisplayAffinity` values
+const WDA_NONE: winapi::DWORD = 0x00;
+const WDA_MONITOR: winapi::DWORD = 0x01;
+const WDA_EXCLUDEFROMCAPTURE: winapi::DWORD = 0x11;
+
+#[link(name = "user32")]
+extern "system" {
+    fn SetWindowDisplayAffinity(hwnd: winapi::HWND, affinity: winapi::DWORD) -> winapi::BOOL;
+}
+
+/// Excludes the content of a window from the screen captures, or includes it again. Returns
+/// false if it failed, which happens when desktop composition is disabled.
+pub unsafe fn set_display_affinity(hwnd: winapi::HWND, protected: bool) -> bool {
+    if !protected {
+        return SetWindowDisplayAffinity(hwnd, WDA_NONE) != 0;
+    }
+
+    // `WDA_EXCLUDEFROMCAPTURE` only exists since Windows 10 version 2004, while `WDA_MONITOR`
+    // makes the window appear black in the captures instead of leaving it out
+    SetWindowDisplayAffinity(hwnd, WDA_EXCLUDEFROMCAPTURE) != 0 ||
+        SetWindowDisplayAffinity(hwnd, WDA_MONITOR) != 0
+}
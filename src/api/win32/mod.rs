@@ -1,27 +1,44 @@
 #![cfg(target_os = "windows")]
 
+use std::cmp;
+use std::collections::VecDeque;
+use std::io;
 use std::mem;
 use std::ptr;
+use std::thread::{self, ThreadId};
 use std::ffi::OsStr;
 use std::os::windows::ffi::OsStrExt;
 use std::sync::{
     Arc,
     Mutex
 };
-use std::sync::mpsc::Receiver;
+use std::sync::mpsc::{Receiver, TryRecvError};
 use libc;
 use ContextError;
+use ContextPriority;
+use Robustness;
 use {CreationError, Event, MouseCursor};
 use CursorState;
+use CursorGrabMode;
+use ContentProtectionError;
+use CursorStateError;
 use GlAttributes;
 use GlContext;
 
 use Api;
 use PixelFormat;
 use PixelFormatRequirements;
+use Rect;
+use RawEventFilter;
+use ResizeEdge;
+use ScanCode;
 use WindowAttributes;
+use os::windows::Backdrop;
+use events::VirtualKeyCode;
 
+pub use self::headless::HeadlessContext;
 pub use self::monitor::{MonitorId, get_available_monitors, get_primary_monitor};
+pub use self::monitor::{monitor_from_point, has_hardware_acceleration};
 
 use winapi;
 use user32;
@@ -30,27 +47,139 @@ use kernel32;
 use api::wgl::Context as WglContext;
 use api::egl::Context as EglContext;
 use api::egl::ffi::egl::Egl;
+use api::pressed_inputs::PressedInputs;
 
 use self::init::RawContext;
 
+#[link(name = "user32")]
+extern "system" {
+    fn GetLayeredWindowAttributes(hwnd: winapi::HWND, key: *mut winapi::COLORREF,
+                                  alpha: *mut winapi::BYTE, flags: *mut winapi::DWORD)
+                                  -> winapi::BOOL;
+}
+
 mod callback;
+mod dpi;
 mod event;
+mod headless;
 mod init;
 mod monitor;
 
 lazy_static! {
     static ref WAKEUP_MSG_ID: u32 = unsafe { user32::RegisterWindowMessageA("Glutin::EventID".as_ptr() as *const i8) };
+
+    /// Posted to a window by `WindowProxy::request_close`. Contrary to `WM_CLOSE`, the window
+    /// isn't destroyed, it only reports `Closed`.
+    static ref REQUEST_CLOSE_MSG_ID: u32 = unsafe { user32::RegisterWindowMessageA("Glutin::RequestClose\0".as_ptr() as *const i8) };
+
+    /// Posted to a window to destroy it from the thread that created it.
+    static ref DESTROY_MSG_ID: u32 = unsafe { user32::RegisterWindowMessageA("Glutin::DestroyWindow\0".as_ptr() as *const i8) };
+
+    /// Posted to a window to allow (`wparam` is 1) or disallow (`wparam` is 0) its input method
+    /// from the thread that created it.
+    static ref SET_IME_ALLOWED_MSG_ID: u32 = unsafe { user32::RegisterWindowMessageA("Glutin::SetImeAllowed\0".as_ptr() as *const i8) };
+
+    /// Posted to a window to place the windows of its input method at `WindowState::ime_position`
+    /// from the thread that created it.
+    static ref SET_IME_POSITION_MSG_ID: u32 = unsafe { user32::RegisterWindowMessageA("Glutin::SetImePosition\0".as_ptr() as *const i8) };
+
+    /// Posted to a window by `drag_window` and `drag_resize_window` to start moving or resizing
+    /// it from the thread that created it. `wparam` is the hit-test code of the frame that the
+    /// button is considered to be pressed on.
+    static ref DRAG_WINDOW_MSG_ID: u32 = unsafe { user32::RegisterWindowMessageA("Glutin::DragWindow\0".as_ptr() as *const i8) };
 }
 
 /// Cursor
 pub type Cursor = *const winapi::wchar_t;
 
+/// The names and the resource identifiers of the system cursors, for `set_cursor_by_name`.
+const SYSTEM_CURSORS: &'static [(&'static str, u16)] = &[
+    ("IDC_ARROW", 32512),
+    ("IDC_IBEAM", 32513),
+    ("IDC_WAIT", 32514),
+    ("IDC_CROSS", 32515),
+    ("IDC_UPARROW", 32516),
+    ("IDC_SIZENWSE", 32642),
+    ("IDC_SIZENESW", 32643),
+    ("IDC_SIZEWE", 32644),
+    ("IDC_SIZENS", 32645),
+    ("IDC_SIZEALL", 32646),
+    ("IDC_NO", 32648),
+    ("IDC_HAND", 32649),
+    ("IDC_APPSTARTING", 32650),
+    ("IDC_HELP", 32651),
+    ("IDC_PIN", 32671),
+    ("IDC_PERSON", 32672),
+];
+
 /// Contains information about states and the window for the callback.
 #[derive(Clone)]
 pub struct WindowState {
     pub cursor: Cursor,
     pub cursor_state: CursorState,
-    pub attributes: WindowAttributes
+    /// How the cursor is held by the window, which `WM_MOUSEMOVE` needs to lock the cursor.
+    pub cursor_grab: CursorGrabMode,
+    /// The grab mode that was requested while the window didn't have the focus, which
+    /// `WM_SETFOCUS` applies.
+    pub deferred_cursor_grab: Option<CursorGrabMode>,
+    /// The position of the cursor in the client area when it was locked, where it is moved back
+    /// when it is unlocked.
+    pub cursor_position_before_lock: Option<(i32, i32)>,
+    /// The position of the windows of the input method in the client area, which is applied
+    /// again when a composition starts.
+    pub ime_position: Option<(i32, i32)>,
+    pub attributes: WindowAttributes,
+    /// Width of the borders of a borderless window that can be dragged to resize it.
+    pub resize_border: u32,
+    /// The region of a borderless window that can be dragged to move it.
+    pub caption_region: Option<Rect>,
+    /// The ratio between the width and the height of the client area that `WM_SIZING` keeps.
+    pub aspect_ratio: Option<(u32, u32)>,
+    /// The HiDPI factor of the monitor of the window, updated by `WM_DPICHANGED`.
+    pub hidpi_factor: f32,
+    /// Whether `WM_DPICHANGED` resizes the window to keep its physical size.
+    pub dpi_resize: bool,
+    /// Whether the window has a drop shadow when it doesn't have decorations.
+    pub drop_shadow: bool,
+    /// The keys and buttons that are held down, which are released when the window loses the
+    /// focus.
+    pub pressed_inputs: PressedInputs,
+}
+
+#[derive(Clone)]
+pub struct PlatformSpecificWindowBuilderAttributes {
+    /// If false, the window is created on the calling thread and its events are only dispatched
+    /// when the messages of that thread are pumped.
+    pub message_thread: bool,
+
+    /// Initial value of `WindowState::resize_border`.
+    pub resize_border: u32,
+
+    /// Initial value of `WindowState::caption_region`.
+    pub caption_region: Option<Rect>,
+
+    /// Initial value of `WindowState::drop_shadow`.
+    pub drop_shadow: bool,
+
+    /// What DWM draws behind the window if it's transparent.
+    pub backdrop: Backdrop,
+
+    /// Initial value of `WindowState::dpi_resize`.
+    pub dpi_resize: bool,
+}
+
+impl Default for PlatformSpecificWindowBuilderAttributes {
+    #[inline]
+    fn default() -> PlatformSpecificWindowBuilderAttributes {
+        PlatformSpecificWindowBuilderAttributes {
+            message_thread: true,
+            resize_border: 0,
+            caption_region: None,
+            drop_shadow: false,
+            backdrop: Backdrop::BlurBehind,
+            dpi_resize: true,
+        }
+    }
 }
 
 /// The Win32 implementation of the main `Window` object.
@@ -66,8 +195,25 @@ pub struct Window {
 
     /// The current window state.
     window_state: Arc<Mutex<WindowState>>,
+
+    /// The filter of `set_raw_event_filter`, shared with the window callback, and the number of
+    /// times that it has been set.
+    raw_event_filter: Arc<Mutex<(Option<RawEventFilter>, u64)>>,
+
+    /// True if the window belongs to the thread that runs the events loop of all these windows,
+    /// false if the messages are pumped by the thread that created the window.
+    message_thread: bool,
+
+    /// The thread that created the window, whose messages are the ones of the window.
+    owner_thread: ThreadId,
+
+    /// The exit code of the `WM_QUIT` message received by `pump_messages`, if any.
+    quit_code: Mutex<Option<libc::c_int>>,
 }
 
+// Without a message thread, the functions that dispatch the messages of the window panic when
+// called on another thread than the one that created it, and dropping the window on another
+// thread posts its destruction to its thread.
 unsafe impl Send for Window {}
 unsafe impl Sync for Window {}
 
@@ -76,6 +222,57 @@ enum Context {
     Wgl(WglContext),
 }
 
+/// A WGL context that renders to the window of another one. See
+/// `Window::create_shared_context`.
+pub struct SharedContext(WglContext);
+
+unsafe impl Send for SharedContext {}
+unsafe impl Sync for SharedContext {}
+
+impl SharedContext {
+    #[inline]
+    pub fn get_raw_context(&self) -> ::os::windows::RawContext {
+        ::os::windows::RawContext::from_wgl(&self.0)
+    }
+}
+
+impl GlContext for SharedContext {
+    #[inline]
+    unsafe fn make_current(&self) -> Result<(), ContextError> {
+        self.0.make_current()
+    }
+
+    #[inline]
+    fn is_current(&self) -> bool {
+        self.0.is_current()
+    }
+
+    #[inline]
+    fn get_proc_address(&self, addr: &str) -> *const () {
+        self.0.get_proc_address(addr)
+    }
+
+    #[inline]
+    fn is_extension_supported(&self, name: &str) -> bool {
+        self.0.is_extension_supported(name)
+    }
+
+    #[inline]
+    fn swap_buffers(&self) -> Result<(), ContextError> {
+        self.0.swap_buffers()
+    }
+
+    #[inline]
+    fn get_api(&self) -> Api {
+        self.0.get_api()
+    }
+
+    #[inline]
+    fn get_pixel_format(&self) -> PixelFormat {
+        self.0.get_pixel_format()
+    }
+}
+
 /// A simple wrapper that destroys the window when it is destroyed.
 // FIXME: remove `pub` (https://github.com/rust-lang/rust/issues/23585)
 #[doc(hidden)]
@@ -105,22 +302,37 @@ impl WindowProxy {
             user32::PostMessageA(self.hwnd, *WAKEUP_MSG_ID, 0, 0);
         }
     }
+
+    pub fn request_close(&self) {
+        unsafe {
+            user32::PostMessageA(self.hwnd, *REQUEST_CLOSE_MSG_ID, 0, 0);
+        }
+    }
 }
 
 impl Window {
     /// See the docs in the crate root file.
     pub fn new(window: &WindowAttributes, pf_reqs: &PixelFormatRequirements,
-               opengl: &GlAttributes<&Window>, egl: Option<&Egl>)
+               opengl: &GlAttributes<&Window>, egl: Option<&Egl>,
+               pl_attribs: &PlatformSpecificWindowBuilderAttributes)
                -> Result<Window, CreationError>
     {
+        // WGL and EGL contexts can't share their objects with each other, and the EGL backend
+        // doesn't implement sharing between EGL contexts yet, see `egl::Context::new`
+        if let Some(sharing) = opengl.sharing {
+            if let Context::Egl(_) = sharing.context {
+                return Err(CreationError::NotSupported);
+            }
+        }
+
         let opengl = opengl.clone().map_sharing(|sharing| {
             match sharing.context {
                 Context::Wgl(ref c) => RawContext::Wgl(c.get_hglrc()),
-                Context::Egl(_) => unimplemented!(),        // FIXME:
+                Context::Egl(_) => unreachable!(),
             }
         });
 
-        init::new_window(window, pf_reqs, &opengl, egl)
+        init::new_window(window, pf_reqs, &opengl, egl, pl_attribs)
     }
 
     /// See the docs in the crate root file.
@@ -149,6 +361,63 @@ impl Window {
         }
     }
 
+    /// See the docs in the crate root file.
+    #[inline]
+    pub fn drag_window(&self) -> Result<(), ()> {
+        self.drag_frame(winapi::HTCAPTION)
+    }
+
+    /// See the docs in the crate root file.
+    pub fn drag_resize_window(&self, edge: ResizeEdge) -> Result<(), ()> {
+        self.drag_frame(match edge {
+            ResizeEdge::Top => winapi::HTTOP,
+            ResizeEdge::Bottom => winapi::HTBOTTOM,
+            ResizeEdge::Left => winapi::HTLEFT,
+            ResizeEdge::Right => winapi::HTRIGHT,
+            ResizeEdge::TopLeft => winapi::HTTOPLEFT,
+            ResizeEdge::TopRight => winapi::HTTOPRIGHT,
+            ResizeEdge::BottomLeft => winapi::HTBOTTOMLEFT,
+            ResizeEdge::BottomRight => winapi::HTBOTTOMRIGHT,
+        })
+    }
+
+    /// Handles the pressed left button as if it was on the given part of the frame.
+    fn drag_frame(&self, hit_test: libc::c_int) -> Result<(), ()> {
+        unsafe {
+            // the move or the resize ends when the button is released, so it must be pressed for
+            // it to start
+            if user32::GetAsyncKeyState(winapi::VK_LBUTTON) >= 0 {
+                return Err(());
+            }
+
+            user32::PostMessageW(self.window.0, *DRAG_WINDOW_MSG_ID, hit_test as winapi::WPARAM, 0);
+        }
+        Ok(())
+    }
+
+    /// See the docs in the crate root file.
+    pub fn focus_window(&self) {
+        unsafe {
+            if user32::IsIconic(self.window.0) != 0 {
+                user32::ShowWindow(self.window.0, winapi::SW_RESTORE);
+            }
+
+            // the system only lets the process that received the last input event or that owns
+            // the foreground window change the foreground window, in which case the taskbar
+            // button flashes until the user activates the window
+            if user32::SetForegroundWindow(self.window.0) == 0 {
+                let mut info = winapi::FLASHWINFO {
+                    cbSize: mem::size_of::<winapi::FLASHWINFO>() as winapi::UINT,
+                    hwnd: self.window.0,
+                    dwFlags: winapi::FLASHW_TRAY | winapi::FLASHW_TIMERNOFG,
+                    uCount: 0,
+                    dwTimeout: 0,
+                };
+                user32::FlashWindowEx(&mut info);
+            }
+        }
+    }
+
     /// See the docs in the crate root file.
     pub fn get_position(&self) -> Option<(i32, i32)> {
         use std::mem;
@@ -164,6 +433,12 @@ impl Window {
         Some((rect.left as i32, rect.top as i32))
     }
 
+    /// See the docs in the crate root file.
+    #[inline]
+    pub fn get_outer_position(&self) -> Option<(i32, i32)> {
+        self.get_position()
+    }
+
     /// See the docs in the crate root file.
     pub fn set_position(&self, x: i32, y: i32) {
         use libc;
@@ -225,6 +500,14 @@ impl Window {
         }
     }
 
+    /// See the docs in the crate root file.
+    #[inline]
+    pub fn request_inner_size(&self, x: u32, y: u32) -> Option<(u32, u32)> {
+        // `SetWindowPos` resizes the window synchronously
+        self.set_inner_size(x, y);
+        self.get_inner_size()
+    }
+
     #[inline]
     pub fn create_window_proxy(&self) -> WindowProxy {
         WindowProxy { hwnd: self.window.0 }
@@ -246,6 +529,43 @@ impl Window {
         }
     }
 
+    /// See the docs in the crate root file.
+    #[inline]
+    pub fn drain_events(&self, events: &mut Vec<Event>) {
+        events.extend(self.events_receiver.try_iter());
+    }
+
+    /// Dispatches all the messages that are waiting in the queue of the calling thread, which
+    /// turns the messages of the window into events.
+    ///
+    /// This is only needed if the window was created without a message thread, in which case
+    /// it must be called on the thread that created the window. Returns `Err` on other threads,
+    /// whose queues don't contain the messages of the window.
+    pub fn pump_messages(&self) -> Result<(), ()> {
+        if thread::current().id() != self.owner_thread {
+            return Err(());
+        }
+
+        unsafe {
+            let mut msg = mem::uninitialized();
+
+            while user32::PeekMessageW(&mut msg, ptr::null_mut(), 0, 0, winapi::PM_REMOVE) != 0 {
+                if msg.message == winapi::WM_QUIT {
+                    // the quit message belongs to the events loop of the user; putting it back
+                    // right away would wake up `WaitMessage` forever, so it is posted again
+                    // when the window is dropped
+                    *self.quit_code.lock().unwrap() = Some(msg.wParam as libc::c_int);
+                    break;
+                }
+
+                user32::TranslateMessage(&msg);
+                user32::DispatchMessageW(&msg);   // calls `callback` (see the callback module)
+            }
+        }
+
+        Ok(())
+    }
+
     #[inline]
     pub fn platform_display(&self) -> *mut libc::c_void {
         // What should this return on win32?
@@ -259,6 +579,26 @@ impl Window {
         self.window.0 as *mut libc::c_void
     }
 
+    /// See the docs in the crate root file.
+    pub fn create_shared_context(&self, opengl: &GlAttributes<&Window>)
+                                 -> Result<SharedContext, CreationError>
+    {
+        // an EGL surface can only be current on one thread at a time
+        let context = match self.context {
+            Context::Wgl(ref c) => c,
+            Context::Egl(_) => return Err(CreationError::NotSupported),
+        };
+
+        let share = match opengl.sharing.unwrap_or(self).context {
+            Context::Wgl(ref c) => c.get_hglrc(),
+            Context::Egl(_) => return Err(CreationError::NotSupported),
+        };
+        let mut opengl = opengl.clone().map_sharing(|_| share);
+        opengl.sharing = Some(share);
+
+        unsafe { context.new_shared(&opengl, self.window.0) }.map(SharedContext)
+    }
+
     #[inline]
     pub fn set_window_resize_callback(&mut self, _: Option<fn(u32, u32)>) {
     }
@@ -286,73 +626,451 @@ impl Window {
         cur.cursor = cursor_id;
     }
 
+    /// See `os::windows::WindowExt::set_cursor_by_name`.
+    pub fn set_cursor_by_name(&self, name: &str) -> Result<(), ()> {
+        let cursor_id = match SYSTEM_CURSORS.iter().find(|&&(n, _)| n == name) {
+            Some(&(_, id)) => id as usize as Cursor,
+            None => ptr::null(),
+        };
 
-    pub fn set_cursor_state(&self, state: CursorState) -> Result<(), String> {
-        let mut current_state = self.window_state.lock().unwrap();
+        // some cursors only exist on recent versions of Windows
+        let valid = !cursor_id.is_null() &&
+                    unsafe { !user32::LoadCursorW(ptr::null_mut(), cursor_id).is_null() };
 
-        let foreground_thread_id = unsafe { user32::GetWindowThreadProcessId(self.window.0, ptr::null_mut()) };
-        let current_thread_id = unsafe { kernel32::GetCurrentThreadId() };
+        let mut cur = self.window_state.lock().unwrap();
+        if valid {
+            cur.cursor = cursor_id;
+            Ok(())
+        } else {
+            cur.cursor = winapi::IDC_ARROW;
+            Err(())
+        }
+    }
 
-        unsafe { user32::AttachThreadInput(foreground_thread_id, current_thread_id, 1) };
 
-        let res = match (state, current_state.cursor_state) {
-            (CursorState::Normal, CursorState::Normal) => Ok(()),
-            (CursorState::Hide, CursorState::Hide) => Ok(()),
-            (CursorState::Grab, CursorState::Grab) => Ok(()),
+    /// See the docs in the crate root file.
+    pub fn confine_cursor(&self, rect: Option<Rect>) -> Result<(), CursorStateError> {
+        let current_state = self.window_state.lock().unwrap();
 
-            (CursorState::Hide, CursorState::Normal) => {
-                current_state.cursor_state = CursorState::Hide;
-                Ok(())
+        let mut clip = match rect {
+            // the system releases the cursor when another window gets the focus
+            Some(_) if unsafe { user32::GetForegroundWindow() } != self.window.0 => {
+                return Err(CursorStateError::WindowNotFocused);
             },
 
-            (CursorState::Normal, CursorState::Hide) => {
-                current_state.cursor_state = CursorState::Normal;
-                Ok(())
+            Some(rect) => winapi::RECT {
+                left: rect.x as winapi::LONG,
+                top: rect.y as winapi::LONG,
+                // the right and bottom edges are excluded
+                right: (rect.x + cmp::max(rect.width, 1) as i32) as winapi::LONG,
+                bottom: (rect.y + cmp::max(rect.height, 1) as i32) as winapi::LONG,
             },
 
-            (CursorState::Grab, CursorState::Normal) | (CursorState::Grab, CursorState::Hide) => {
-                unsafe {
-                    let mut rect = mem::uninitialized();
-                    if user32::GetClientRect(self.window.0, &mut rect) == 0 {
-                        return Err(format!("GetWindowRect failed"));
-                    }
-                    user32::ClientToScreen(self.window.0, mem::transmute(&mut rect.left));
-                    user32::ClientToScreen(self.window.0, mem::transmute(&mut rect.right));
-                    if user32::ClipCursor(&rect) == 0 {
-                        return Err(format!("ClipCursor failed"));
-                    }
-                    current_state.cursor_state = CursorState::Grab;
-                    Ok(())
+            // a grabbed cursor goes back to being confined to the window
+            None if current_state.cursor_grab != CursorGrabMode::None => unsafe {
+                let mut rect = mem::uninitialized();
+                if user32::GetClientRect(self.window.0, &mut rect) == 0 {
+                    return Err(last_cursor_error("GetClientRect"));
                 }
+                rect
             },
 
-            (CursorState::Normal, CursorState::Grab) => {
-                unsafe {
-                    if user32::ClipCursor(ptr::null()) == 0 {
-                        return Err(format!("ClipCursor failed"));
-                    }
-                    current_state.cursor_state = CursorState::Normal;
-                    Ok(())
+            None => unsafe {
+                if user32::ClipCursor(ptr::null()) == 0 {
+                    return Err(last_cursor_error("ClipCursor"));
                 }
+                return Ok(());
             },
+        };
+
+        unsafe {
+            user32::ClientToScreen(self.window.0, mem::transmute(&mut clip.left));
+            user32::ClientToScreen(self.window.0, mem::transmute(&mut clip.right));
+            if user32::ClipCursor(&clip) == 0 {
+                return Err(last_cursor_error("ClipCursor"));
+            }
+        }
+
+        Ok(())
+    }
+
+    /// See the docs in the crate root file.
+    pub fn set_min_dimensions(&self, dimensions: Option<(u32, u32)>) {
+        self.window_state.lock().unwrap().attributes.min_dimensions = dimensions;
+
+        // `WM_GETMINMAXINFO` only limits the future resizes
+        if let (Some((min_width, min_height)), Some((width, height))) = (dimensions, self.get_inner_size()) {
+            if width < min_width || height < min_height {
+                self.set_inner_size(cmp::max(width, min_width), cmp::max(height, min_height));
+            }
+        }
+    }
+
+    /// See the docs in the crate root file.
+    pub fn set_max_dimensions(&self, dimensions: Option<(u32, u32)>) {
+        self.window_state.lock().unwrap().attributes.max_dimensions = dimensions;
+
+        // `WM_GETMINMAXINFO` only limits the future resizes
+        if let (Some((max_width, max_height)), Some((width, height))) = (dimensions, self.get_inner_size()) {
+            if width > max_width || height > max_height {
+                self.set_inner_size(cmp::min(width, max_width), cmp::min(height, max_height));
+            }
+        }
+    }
 
-            _ => unimplemented!(),
+    /// See the docs in the crate root file.
+    pub fn set_aspect_ratio(&self, ratio: Option<(u32, u32)>) {
+        let ratio = match ratio {
+            Some((x, y)) if x != 0 && y != 0 => Some((x as u64, y as u64)),
+            _ => None,
+        };
+        self.window_state.lock().unwrap().aspect_ratio = ratio.map(|(x, y)| (x as u32, y as u32));
+
+        // `WM_SIZING` only constrains the future resizes; the dimension that is too large is
+        // shrunk, with one pixel of rounding tolerated
+        if let (Some((x, y)), Some((width, height))) = (ratio, self.get_inner_size()) {
+            let height_for_width = cmp::max(width as u64 * y / x, 1) as u32;
+            let width_for_height = cmp::max(height as u64 * x / y, 1) as u32;
+            if height > height_for_width + 1 {
+                self.set_inner_size(width, height_for_width);
+            } else if width > width_for_height + 1 {
+                self.set_inner_size(width_for_height, height);
+            }
+        }
+    }
+
+    /// See the docs in the crate root file.
+    pub fn set_decorations(&self, decorations: bool) {
+        let (inner_size, drop_shadow) = {
+            let mut window_state = self.window_state.lock().unwrap();
+            window_state.attributes.decorations = decorations;
+
+            // fullscreen windows never have decorations
+            if window_state.attributes.monitor.is_some() {
+                return;
+            }
+
+            (self.get_inner_size(), window_state.drop_shadow)
+        };
+
+        // same styles as in `init`
+        let (ex_style, style) = if decorations {
+            (winapi::WS_EX_APPWINDOW | winapi::WS_EX_WINDOWEDGE,
+                winapi::WS_OVERLAPPEDWINDOW | winapi::WS_CLIPSIBLINGS | winapi::WS_CLIPCHILDREN)
+        } else {
+            (winapi::WS_EX_APPWINDOW, winapi::WS_POPUP | winapi::WS_CLIPSIBLINGS | winapi::WS_CLIPCHILDREN)
         };
 
+        unsafe {
+            let current_style = user32::GetWindowLongA(self.window.0, winapi::GWL_STYLE) as winapi::DWORD;
+            let kept = current_style & (winapi::WS_VISIBLE | winapi::WS_MAXIMIZE | winapi::WS_MINIMIZE);
+
+            // the window is layered while `set_opacity` makes it translucent, or while
+            // `set_cursor_hittest` lets the clicks through it
+            let current_ex_style = user32::GetWindowLongA(self.window.0, winapi::GWL_EXSTYLE) as winapi::DWORD;
+            let kept_ex = current_ex_style & (winapi::WS_EX_LAYERED | winapi::WS_EX_TRANSPARENT);
+
+            user32::SetWindowLongA(self.window.0, winapi::GWL_STYLE, (style | kept) as winapi::LONG);
+            user32::SetWindowLongA(self.window.0, winapi::GWL_EXSTYLE, (ex_style | kept_ex) as winapi::LONG);
+
+            // the frame is only recomputed when the window is told that it changed
+            user32::SetWindowPos(self.window.0, ptr::null_mut(), 0, 0, 0, 0,
+                winapi::SWP_NOMOVE | winapi::SWP_NOSIZE | winapi::SWP_NOZORDER |
+                winapi::SWP_NOACTIVATE | winapi::SWP_FRAMECHANGED);
+
+            // decorated windows have a shadow of their own
+            if drop_shadow {
+                init::set_drop_shadow(self.window.0, !decorations);
+            }
+        }
+
+        // the client area keeps its size, and the frame grows or shrinks around it
+        if let Some((width, height)) = inner_size {
+            self.set_inner_size(width, height);
+        }
+    }
+
+    /// See the docs in the crate root file.
+    ///
+    /// The window only becomes layered while it is translucent, since layered windows are
+    /// composed by DWM even when they are opaque.
+    pub fn set_opacity(&self, opacity: f32) {
+        unsafe {
+            let ex_style = user32::GetWindowLongA(self.window.0, winapi::GWL_EXSTYLE) as winapi::DWORD;
+
+            // a window that lets the clicks through must stay layered
+            if opacity >= 1.0 && ex_style & winapi::WS_EX_TRANSPARENT == 0 {
+                user32::SetWindowLongA(self.window.0, winapi::GWL_EXSTYLE,
+                                       (ex_style & !winapi::WS_EX_LAYERED) as winapi::LONG);
+                return;
+            }
+
+            if ex_style & winapi::WS_EX_LAYERED == 0 {
+                user32::SetWindowLongA(self.window.0, winapi::GWL_EXSTYLE,
+                                       (ex_style | winapi::WS_EX_LAYERED) as winapi::LONG);
+            }
+
+            user32::SetLayeredWindowAttributes(self.window.0, 0, (opacity * 255.0) as winapi::BYTE,
+                                               winapi::LWA_ALPHA);
+        }
+    }
+
+    /// See the docs in the crate root file.
+    ///
+    /// Only the clicks on layered windows go through them, so the window stays layered while it
+    /// ignores the cursor, even if it is opaque.
+    pub fn set_cursor_hittest(&self, hittest: bool) -> Result<(), ()> {
+        unsafe {
+            let ex_style = user32::GetWindowLongA(self.window.0, winapi::GWL_EXSTYLE) as winapi::DWORD;
+            let layered = ex_style & winapi::WS_EX_LAYERED != 0;
+
+            if !hittest {
+                user32::SetWindowLongA(self.window.0, winapi::GWL_EXSTYLE,
+                                       (ex_style | winapi::WS_EX_LAYERED |
+                                        winapi::WS_EX_TRANSPARENT) as winapi::LONG);
+                // a layered window is invisible until its attributes are set
+                if !layered {
+                    user32::SetLayeredWindowAttributes(self.window.0, 0, 255, winapi::LWA_ALPHA);
+                }
+                return Ok(());
+            }
+
+            // the window stays layered if `set_opacity` made it translucent
+            let mut alpha = 255;
+            if layered {
+                GetLayeredWindowAttributes(self.window.0, ptr::null_mut(), &mut alpha,
+                                           ptr::null_mut());
+            }
+            let removed = if alpha == 255 {
+                winapi::WS_EX_LAYERED | winapi::WS_EX_TRANSPARENT
+            } else {
+                winapi::WS_EX_TRANSPARENT
+            };
+            user32::SetWindowLongA(self.window.0, winapi::GWL_EXSTYLE,
+                                   (ex_style & !removed) as winapi::LONG);
+        }
+        Ok(())
+    }
+
+    /// See the docs in the crate root file.
+    #[inline]
+    pub fn set_content_protected(&self, protected: bool) -> Result<(), ContentProtectionError> {
+        if unsafe { init::set_display_affinity(self.window.0, protected) } {
+            Ok(())
+        } else {
+            Err(ContentProtectionError::OsError {
+                code: io::Error::last_os_error().raw_os_error(),
+                message: "SetWindowDisplayAffinity failed".to_owned(),
+            })
+        }
+    }
+
+    /// Sets the width of the borders that can be dragged to resize the window when it doesn't
+    /// have decorations. 0 disables the resizing.
+    #[inline]
+    pub fn set_resize_border(&self, width: u32) {
+        self.window_state.lock().unwrap().resize_border = width;
+    }
+
+    /// Sets the region, in client coordinates, that can be dragged to move the window when it
+    /// doesn't have decorations.
+    #[inline]
+    pub fn set_caption_region(&self, region: Option<Rect>) {
+        self.window_state.lock().unwrap().caption_region = region;
+    }
+
+    pub fn set_cursor_state(&self, state: CursorState) -> Result<(), CursorStateError> {
+        let mut current_state = self.window_state.lock().unwrap();
+
+        let foreground_thread_id = unsafe { user32::GetWindowThreadProcessId(self.window.0, ptr::null_mut()) };
+        let current_thread_id = unsafe { kernel32::GetCurrentThreadId() };
+
+        unsafe { user32::AttachThreadInput(foreground_thread_id, current_thread_id, 1) };
+
+        let res = self.update_cursor_state(&mut current_state, state);
+
+        unsafe { user32::AttachThreadInput(foreground_thread_id, current_thread_id, 0) };
+
+        res
+    }
+
+    /// See the docs in the crate root file.
+    ///
+    /// A locked cursor is confined to the window, and `WM_MOUSEMOVE` moves it back to the
+    /// center of the window.
+    pub fn set_cursor_grab(&self, mode: CursorGrabMode) -> Result<(), CursorStateError> {
+        let mut current_state = self.window_state.lock().unwrap();
+
+        let foreground_thread_id = unsafe { user32::GetWindowThreadProcessId(self.window.0, ptr::null_mut()) };
+        let current_thread_id = unsafe { kernel32::GetCurrentThreadId() };
+
+        unsafe { user32::AttachThreadInput(foreground_thread_id, current_thread_id, 1) };
+
+        let res = self.update_cursor_grab(&mut current_state, mode);
+
         unsafe { user32::AttachThreadInput(foreground_thread_id, current_thread_id, 0) };
 
         res
     }
 
+    /// Confines or releases the cursor and shows or hides it, independently of each other.
+    ///
+    /// The cursor is hidden by `WM_SETCURSOR` rather than with `ShowCursor`, so there is no
+    /// display counter that could stay unbalanced. `SetCursor` is called here so that the change
+    /// is visible without waiting for the next `WM_SETCURSOR`.
+    fn update_cursor_state(&self, current_state: &mut WindowState, state: CursorState)
+                           -> Result<(), CursorStateError>
+    {
+        // the cursor is still hidden if the grab is deferred until the window gets the focus
+        let grab = self.update_cursor_grab(current_state, state.grab_mode());
+        match grab {
+            Ok(()) | Err(CursorStateError::WindowNotFocused) => (),
+            Err(err) => return Err(err),
+        }
+
+        if current_state.cursor_state.is_hidden() != state.is_hidden() {
+            unsafe {
+                if state.is_hidden() {
+                    user32::SetCursor(ptr::null_mut());
+                } else {
+                    user32::SetCursor(user32::LoadCursorW(ptr::null_mut(), current_state.cursor));
+                }
+            }
+        }
+
+        current_state.cursor_state = state;
+        grab
+    }
+
+    /// Confines the cursor to the client area, or releases it if the mode is `None`.
+    fn update_cursor_grab(&self, current_state: &mut WindowState, mode: CursorGrabMode)
+                          -> Result<(), CursorStateError>
+    {
+        let grabbed = mode != CursorGrabMode::None;
+        current_state.deferred_cursor_grab = None;
+
+        if (current_state.cursor_grab != CursorGrabMode::None) != grabbed {
+            unsafe {
+                // the system releases the cursor when another window gets the focus, so the
+                // grab is applied by `WM_SETFOCUS` instead
+                if grabbed && user32::GetForegroundWindow() != self.window.0 {
+                    current_state.deferred_cursor_grab = Some(mode);
+                    return Err(CursorStateError::WindowNotFocused);
+                }
+
+                if grabbed {
+                    try!(clip_cursor_to_client_area(self.window.0));
+                } else if user32::ClipCursor(ptr::null()) == 0 {
+                    return Err(last_cursor_error("ClipCursor"));
+                }
+            }
+        }
+
+        // a locked cursor is moved to the center of the window, so it is moved back to where it
+        // was once it is unlocked
+        if mode == CursorGrabMode::Locked && current_state.cursor_grab != CursorGrabMode::Locked {
+            current_state.cursor_position_before_lock = unsafe { get_cursor_position(self.window.0) };
+        } else if mode != CursorGrabMode::Locked {
+            if let Some((x, y)) = current_state.cursor_position_before_lock.take() {
+                let _ = self.set_cursor_position(x as f64, y as f64);
+            }
+        }
+
+        current_state.cursor_grab = mode;
+        Ok(())
+    }
+
+    /// See the docs in the crate root file.
+    #[inline]
+    pub fn set_ime_allowed(&self, allowed: bool) {
+        unsafe {
+            user32::PostMessageW(self.window.0, *SET_IME_ALLOWED_MSG_ID,
+                                 allowed as winapi::WPARAM, 0);
+        }
+    }
+
+    /// See the docs in the crate root file.
+    #[inline]
+    pub fn set_raw_event_filter(&self, filter: Option<RawEventFilter>) {
+        let mut current = self.raw_event_filter.lock().unwrap();
+        current.0 = filter;
+        current.1 += 1;
+    }
+
+    /// See the docs in the crate root file.
+    #[inline]
+    pub fn scancode_to_keycode(&self, scancode: ScanCode) -> Option<VirtualKeyCode> {
+        event::scancode_to_keycode(self.window.0, scancode)
+    }
+
+    /// See the docs in the crate root file.
+    #[inline]
+    pub fn keycode_to_scancode(&self, keycode: VirtualKeyCode) -> Option<ScanCode> {
+        event::keycode_to_scancode(self.window.0, keycode)
+    }
+
+    /// See the docs in the crate root file.
+    #[inline]
+    pub fn key_name(&self, scancode: ScanCode) -> Option<String> {
+        event::key_name(scancode)
+    }
+
+    /// See the docs in the crate root file.
+    pub fn set_ime_position(&self, x: i32, y: i32) {
+        self.window_state.lock().unwrap().ime_position = Some((x, y));
+        unsafe {
+            user32::PostMessageW(self.window.0, *SET_IME_POSITION_MSG_ID, 0, 0);
+        }
+    }
+
+    /// See the docs in the crate root file.
+    #[inline]
+    pub fn get_context_priority(&self) -> Option<ContextPriority> {
+        match self.context {
+            Context::Egl(ref c) => c.get_context_priority(),
+            Context::Wgl(_) => None,
+        }
+    }
+
+    /// See the docs in the crate root file.
+    #[inline]
+    pub fn get_robustness(&self) -> Option<Robustness> {
+        match self.context {
+            Context::Egl(ref c) => Some(c.get_robustness()),
+            Context::Wgl(_) => None,
+        }
+    }
+
+    #[inline]
+    pub fn get_raw_context(&self) -> ::os::windows::RawContext {
+        match self.context {
+            Context::Egl(ref c) => ::os::windows::RawContext::from_egl(c),
+            Context::Wgl(ref c) => ::os::windows::RawContext::from_wgl(c),
+        }
+    }
+
+    /// See the docs in the crate root file.
+    #[inline]
+    pub fn get_available_monitors(&self) -> VecDeque<MonitorId> {
+        get_available_monitors()
+    }
+
+    /// See the docs in the crate root file.
+    #[inline]
+    pub fn get_current_monitor(&self) -> Option<MonitorId> {
+        monitor::get_window_monitor(self.window.0)
+    }
+
     #[inline]
     pub fn hidpi_factor(&self) -> f32 {
-        1.0
+        self.window_state.lock().unwrap().hidpi_factor
     }
 
-    pub fn set_cursor_position(&self, x: i32, y: i32) -> Result<(), ()> {
+    /// The position is in pixels, since the process is DPI-aware.
+    pub fn set_cursor_position(&self, x: f64, y: f64) -> Result<(), ()> {
         let mut point = winapi::POINT {
-            x: x,
-            y: y,
+            x: x.round() as winapi::LONG,
+            y: y.round() as winapi::LONG,
         };
 
         unsafe {
@@ -394,6 +1112,14 @@ impl GlContext for Window {
         }
     }
 
+    #[inline]
+    fn is_extension_supported(&self, name: &str) -> bool {
+        match self.context {
+            Context::Wgl(ref c) => c.is_extension_supported(name),
+            Context::Egl(ref c) => c.is_extension_supported(name),
+        }
+    }
+
     #[inline]
     fn swap_buffers(&self) -> Result<(), ContextError> {
         match self.context {
@@ -402,6 +1128,22 @@ impl GlContext for Window {
         }
     }
 
+    #[inline]
+    fn swap_buffers_with_damage(&self, rects: &[Rect]) -> Result<(), ContextError> {
+        match self.context {
+            Context::Wgl(ref c) => c.swap_buffers_with_damage(rects),
+            Context::Egl(ref c) => c.swap_buffers_with_damage(rects),
+        }
+    }
+
+    #[inline]
+    fn buffer_age(&self) -> Option<u32> {
+        match self.context {
+            Context::Wgl(ref c) => c.buffer_age(),
+            Context::Egl(ref c) => c.buffer_age(),
+        }
+    }
+
     #[inline]
     fn get_api(&self) -> Api {
         match self.context {
@@ -439,19 +1181,85 @@ pub struct WaitEventsIterator<'a> {
 impl<'a> Iterator for WaitEventsIterator<'a> {
     type Item = Event;
 
-    #[inline]
     fn next(&mut self) -> Option<Event> {
-        self.window.events_receiver.recv().ok()
+        // on another thread than the one that built the window, the events arrive when that
+        // thread dispatches its messages
+        if self.window.message_thread || thread::current().id() != self.window.owner_thread {
+            return self.window.events_receiver.recv().ok();
+        }
+
+        // nobody else is going to dispatch the messages, so we wait for them ourselves
+        loop {
+            match self.window.events_receiver.try_recv() {
+                Ok(event) => return Some(event),
+                Err(TryRecvError::Disconnected) => return None,
+                Err(TryRecvError::Empty) => (),
+            }
+
+            // the thread is quitting, no other message is going to come
+            if self.window.quit_code.lock().unwrap().is_some() {
+                return None;
+            }
+
+            unsafe { user32::WaitMessage() };
+            let _ = self.window.pump_messages();
+        }
     }
 }
 
 impl Drop for Window {
     #[inline]
     fn drop(&mut self) {
+        let owner_thread = thread::current().id() == self.owner_thread;
+
+        // the events loop of the user gets the quit message that `pump_messages` kept
+        if let Some(code) = *self.quit_code.lock().unwrap() {
+            if owner_thread {
+                unsafe { user32::PostQuitMessage(code) };
+            }
+        }
+
+        // without a message thread, the window is destroyed right away by `WindowWrapper`,
+        // unless it is dropped on another thread, where `DestroyWindow` would fail
+        if !self.message_thread && owner_thread {
+            return;
+        }
+
         unsafe {
             // we don't call MakeCurrent(0, 0) because we are not sure that the context
             // is still the current one
-            user32::PostMessageW(self.window.0, winapi::WM_DESTROY, 0, 0);
+            user32::PostMessageW(self.window.0, *DESTROY_MSG_ID, 0, 0);
         }
     }
 }
+
+/// Confines the cursor to the client area of a window.
+unsafe fn clip_cursor_to_client_area(hwnd: winapi::HWND) -> Result<(), CursorStateError> {
+    let mut rect = mem::uninitialized();
+    if user32::GetClientRect(hwnd, &mut rect) == 0 {
+        return Err(last_cursor_error("GetClientRect"));
+    }
+    user32::ClientToScreen(hwnd, mem::transmute(&mut rect.left));
+    user32::ClientToScreen(hwnd, mem::transmute(&mut rect.right));
+    if user32::ClipCursor(&rect) == 0 {
+        return Err(last_cursor_error("ClipCursor"));
+    }
+    Ok(())
+}
+
+/// Returns the position of the cursor in the client area of a window.
+unsafe fn get_cursor_position(hwnd: winapi::HWND) -> Option<(i32, i32)> {
+    let mut point = mem::uninitialized();
+    if user32::GetCursorPos(&mut point) == 0 || user32::ScreenToClient(hwnd, &mut point) == 0 {
+        return None;
+    }
+    Some((point.x as i32, point.y as i32))
+}
+
+/// Returns the error of the function of the system that just failed, such as `ClipCursor`.
+fn last_cursor_error(function: &str) -> CursorStateError {
+    CursorStateError::OsError {
+        code: io::Error::last_os_error().raw_os_error(),
+        message: format!("{} failed", function),
+    }
+}
@@ -1,8 +1,14 @@
 use winapi;
+use gdi32;
 use user32;
 
 use std::collections::VecDeque;
+use std::ffi::OsString;
+use std::fs::File;
+use std::io::Read;
 use std::mem;
+use std::os::windows::ffi::OsStringExt;
+use std::ptr;
 
 use native_monitor::NativeMonitorId;
 
@@ -136,17 +142,60 @@ pub fn get_available_monitors() -> VecDeque<MonitorId> {
 }
 
 /// Win32 implementation of the main `get_primary_monitor` function.
-pub fn get_primary_monitor() -> MonitorId {
+pub fn get_primary_monitor() -> Option<MonitorId> {
     // we simply get all available monitors and return the one with the `PRIMARY_DEVICE` flag
     // TODO: it is possible to query the win32 API for the primary monitor, this should be done
     //  instead
-    for monitor in get_available_monitors().into_iter() {
-        if monitor.primary {
-            return monitor;
-        }
+    get_available_monitors().into_iter().find(|monitor| monitor.primary)
+}
+
+/// Returns the monitor that has the largest intersection with `window`, or the nearest monitor
+/// if the window doesn't intersect any.
+pub fn get_window_monitor(window: winapi::HWND) -> Option<MonitorId> {
+    let monitor = unsafe { user32::MonitorFromWindow(window, winapi::MONITOR_DEFAULTTONEAREST) };
+    get_hmonitor_monitor(monitor).or_else(get_primary_monitor)
+}
+
+/// Win32 implementation of the main `monitor_from_point` function.
+pub fn monitor_from_point(x: i32, y: i32) -> Option<MonitorId> {
+    let point = winapi::POINT { x: x as winapi::LONG, y: y as winapi::LONG };
+    let monitor = unsafe { user32::MonitorFromPoint(point, winapi::MONITOR_DEFAULTTONULL) };
+    get_hmonitor_monitor(monitor)
+}
+
+/// Returns the `MonitorId` of an `HMONITOR`.
+fn get_hmonitor_monitor(monitor: winapi::HMONITOR) -> Option<MonitorId> {
+    if monitor.is_null() {
+        return None;
     }
 
-    panic!("Failed to find the primary monitor")
+    let device = unsafe {
+        let mut info: winapi::MONITORINFOEXW = mem::zeroed();
+        info.cbSize = mem::size_of::<winapi::MONITORINFOEXW>() as winapi::DWORD;
+        if user32::GetMonitorInfoW(monitor, &mut info as *mut _ as winapi::LPMONITORINFO) == 0 {
+            return None;
+        }
+        info.szDevice
+    };
+
+    // `szDevice` is the name of the adapter, so the first monitor of the adapter is returned
+    get_available_monitors().into_iter()
+                            .find(|monitor| monitor.adapter_name[..] == device[..])
+}
+
+/// Win32 implementation of the main `has_hardware_acceleration` function.
+pub fn has_hardware_acceleration() -> bool {
+    unsafe {
+        // the pixel formats are the same for all the windows, so those of the screen are used
+        let hdc = user32::GetDC(ptr::null_mut());
+        if hdc.is_null() {
+            return false;
+        }
+
+        let result = ::api::wgl::has_hardware_acceleration(hdc);
+        user32::ReleaseDC(ptr::null_mut(), hdc);
+        result
+    }
 }
 
 impl MonitorId {
@@ -169,6 +218,40 @@ impl MonitorId {
         self.dimensions
     }
 
+    /// See the docs of the crate root file.
+    ///
+    /// Windows only gives the path of the profile, which is then read from the disk.
+    pub fn get_icc_profile(&self) -> Option<Vec<u8>> {
+        let path = unsafe {
+            let hdc = gdi32::CreateDCW(ptr::null(), self.adapter_name.as_ptr(), ptr::null(),
+                                       ptr::null());
+            if hdc.is_null() {
+                return None;
+            }
+
+            // the first call gives the size of the buffer
+            let mut size: winapi::DWORD = 0;
+            gdi32::GetICMProfileW(hdc, &mut size, ptr::null_mut());
+            let mut path = vec![0 as winapi::WCHAR; size as usize];
+            let success = size != 0 &&
+                          gdi32::GetICMProfileW(hdc, &mut size, path.as_mut_ptr()) != 0;
+            gdi32::DeleteDC(hdc);
+
+            if !success {
+                return None;
+            }
+
+            let len = path.iter().position(|&c| c == 0).unwrap_or(path.len());
+            OsString::from_wide(&path[..len])
+        };
+
+        let mut profile = Vec::new();
+        match File::open(path).and_then(|mut file| file.read_to_end(&mut profile)) {
+            Ok(_) => Some(profile),
+            Err(_) => None,
+        }
+    }
+
     /// This is a Win32-only function for `MonitorId` that returns the system name of the adapter
     /// device.
     #[inline]
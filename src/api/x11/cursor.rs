@@ -0,0 +1,149 @@
+//! Correspondence between the `MouseCursor` values and the X11 cursors.
+
+use std::ffi::CString;
+use std::mem;
+
+use libc::{c_char, c_uint};
+
+use MouseCursor;
+
+use super::ffi;
+use super::XConnection;
+
+// glyphs of the core cursor font, see `X11/cursorfont.h`
+const XC_BOTTOM_LEFT_CORNER: c_uint = 12;
+const XC_BOTTOM_RIGHT_CORNER: c_uint = 14;
+const XC_BOTTOM_SIDE: c_uint = 16;
+const XC_CIRCLE: c_uint = 24;
+const XC_CROSSHAIR: c_uint = 34;
+const XC_FLEUR: c_uint = 52;
+const XC_HAND1: c_uint = 58;
+const XC_HAND2: c_uint = 60;
+const XC_LEFT_PTR: c_uint = 68;
+const XC_LEFT_SIDE: c_uint = 70;
+const XC_PLUS: c_uint = 90;
+const XC_QUESTION_ARROW: c_uint = 92;
+const XC_RIGHT_SIDE: c_uint = 96;
+const XC_SB_H_DOUBLE_ARROW: c_uint = 108;
+const XC_SB_V_DOUBLE_ARROW: c_uint = 116;
+const XC_SIZING: c_uint = 120;
+const XC_TOP_LEFT_CORNER: c_uint = 134;
+const XC_TOP_RIGHT_CORNER: c_uint = 136;
+const XC_TOP_SIDE: c_uint = 138;
+const XC_WATCH: c_uint = 150;
+const XC_XTERM: c_uint = 152;
+
+/// Creates the X11 cursor corresponding to `cursor`.
+///
+/// The cursor is looked up by name in the current cursor theme, falling back to the core cursor
+/// font if the theme doesn't have it. `NoneCursor` is an invisible 1x1 cursor. Returns 0 if the
+/// cursor couldn't be created.
+pub fn load_cursor(xconn: &XConnection, cursor: MouseCursor) -> ffi::Cursor {
+    if cursor == MouseCursor::NoneCursor {
+        return create_empty_cursor(xconn);
+    }
+
+    let (names, glyph) = get_cursor_names(cursor);
+
+    for name in names {
+        let name = CString::new(*name).unwrap();
+        let xcursor = unsafe {
+            (xconn.xcursor.XcursorLibraryLoadCursor)(xconn.display, name.as_ptr())
+        };
+        if xcursor != 0 {
+            return xcursor;
+        }
+    }
+
+    unsafe { (xconn.xlib.XCreateFontCursor)(xconn.display, glyph) }
+}
+
+/// Creates the X11 cursor with the given name in the current cursor theme. Returns 0 if the
+/// theme doesn't have it.
+pub fn load_named_cursor(xconn: &XConnection, name: &str) -> ffi::Cursor {
+    let name = match CString::new(name) {
+        Ok(name) => name,
+        Err(_) => return 0,
+    };
+
+    unsafe { (xconn.xcursor.XcursorLibraryLoadCursor)(xconn.display, name.as_ptr()) }
+}
+
+fn create_empty_cursor(xconn: &XConnection) -> ffi::Cursor {
+    let data: c_char = 0;
+    unsafe {
+        let root = (xconn.xlib.XDefaultRootWindow)(xconn.display);
+        let pixmap = (xconn.xlib.XCreateBitmapFromData)(xconn.display, root, &data, 1, 1);
+        if pixmap == 0 {
+            // Failed to allocate
+            return 0;
+        }
+
+        // We don't care about this color, since it only fills bytes
+        // in the pixmap which are not 0 in the mask.
+        let dummy_color: ffi::XColor = mem::uninitialized();
+        let cursor = (xconn.xlib.XCreatePixmapCursor)(xconn.display,
+                                                      pixmap,
+                                                      pixmap,
+                                                      &dummy_color as *const _ as *mut _,
+                                                      &dummy_color as *const _ as *mut _, 0, 0);
+        (xconn.xlib.XFreePixmap)(xconn.display, pixmap);
+        cursor
+    }
+}
+
+/// Returns the names of the cursor in the cursor themes, by order of preference, and the glyph
+/// of the core cursor font to use if the theme has none of them.
+///
+/// The first name is the one of the freedesktop cursor specification (which are the CSS names),
+/// and the others are the legacy names that older themes use instead.
+///
+/// Must not be called with `MouseCursor::NoneCursor`, which has no name.
+fn get_cursor_names(cursor: MouseCursor) -> (&'static [&'static str], c_uint) {
+    match cursor {
+        MouseCursor::Default => (&["default", "left_ptr"], XC_LEFT_PTR),
+        MouseCursor::Arrow => (&["default", "arrow", "left_ptr"], XC_LEFT_PTR),
+        MouseCursor::Crosshair => (&["crosshair", "cross"], XC_CROSSHAIR),
+        MouseCursor::Hand => (&["pointer", "hand2", "hand1"], XC_HAND2),
+        MouseCursor::Move => (&["move", "fleur"], XC_FLEUR),
+        MouseCursor::Text => (&["text", "xterm"], XC_XTERM),
+        MouseCursor::VerticalText => (&["vertical-text"], XC_XTERM),
+        MouseCursor::Wait => (&["wait", "watch"], XC_WATCH),
+        MouseCursor::Progress => (&["progress", "left_ptr_watch"], XC_WATCH),
+        MouseCursor::Help => (&["help", "question_arrow"], XC_QUESTION_ARROW),
+        MouseCursor::ContextMenu => (&["context-menu"], XC_LEFT_PTR),
+        MouseCursor::Cell => (&["cell", "plus"], XC_PLUS),
+        MouseCursor::Alias => (&["alias", "link"], XC_LEFT_PTR),
+        MouseCursor::Copy => (&["copy"], XC_LEFT_PTR),
+        MouseCursor::NotAllowed => (&["not-allowed", "crossed_circle"], XC_CIRCLE),
+        MouseCursor::NoDrop => (&["no-drop", "circle"], XC_CIRCLE),
+        MouseCursor::Grab => (&["grab", "openhand", "hand1"], XC_HAND1),
+        MouseCursor::Grabbing => (&["grabbing", "closedhand"], XC_FLEUR),
+        MouseCursor::AllScroll => (&["all-scroll", "fleur"], XC_FLEUR),
+        MouseCursor::ZoomIn => (&["zoom-in"], XC_PLUS),
+        MouseCursor::ZoomOut => (&["zoom-out"], XC_LEFT_PTR),
+
+        MouseCursor::EResize => (&["e-resize", "right_side"], XC_RIGHT_SIDE),
+        MouseCursor::NResize => (&["n-resize", "top_side"], XC_TOP_SIDE),
+        MouseCursor::NeResize => (&["ne-resize", "top_right_corner"], XC_TOP_RIGHT_CORNER),
+        MouseCursor::NwResize => (&["nw-resize", "top_left_corner"], XC_TOP_LEFT_CORNER),
+        MouseCursor::SResize => (&["s-resize", "bottom_side"], XC_BOTTOM_SIDE),
+        MouseCursor::SeResize => (&["se-resize", "bottom_right_corner"], XC_BOTTOM_RIGHT_CORNER),
+        MouseCursor::SwResize => (&["sw-resize", "bottom_left_corner"], XC_BOTTOM_LEFT_CORNER),
+        MouseCursor::WResize => (&["w-resize", "left_side"], XC_LEFT_SIDE),
+        MouseCursor::EwResize => (&["ew-resize", "h_double_arrow", "sb_h_double_arrow"],
+                                  XC_SB_H_DOUBLE_ARROW),
+        MouseCursor::NsResize => (&["ns-resize", "v_double_arrow", "sb_v_double_arrow"],
+                                  XC_SB_V_DOUBLE_ARROW),
+        // "bd" and "fd" stand for backward and forward diagonal, while Qt's "size_fdiag" is the
+        // one that goes from the top-left corner to the bottom-right one
+        MouseCursor::NwseResize => (&["nwse-resize", "bd_double_arrow", "size_fdiag"], XC_SIZING),
+        MouseCursor::NeswResize => (&["nesw-resize", "fd_double_arrow", "size_bdiag"], XC_SIZING),
+        MouseCursor::ColResize => (&["col-resize", "split_h", "sb_h_double_arrow"],
+                                   XC_SB_H_DOUBLE_ARROW),
+        MouseCursor::RowResize => (&["row-resize", "split_v", "sb_v_double_arrow"],
+                                   XC_SB_V_DOUBLE_ARROW),
+
+        MouseCursor::NoneCursor => unreachable!(),
+    }
+}
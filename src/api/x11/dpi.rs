@@ -0,0 +1,214 @@
+//! Computation of the HiDPI factor of X11 windows.
+//!
+//! X11 has no notion of a scaling factor, so the factor is guessed from the following sources,
+//! in this order:
+//!
+//! - `xft`: the `Xft.dpi` resource of the `RESOURCE_MANAGER` property of the root window,
+//!   divided by 96. This is set by most desktop environments (including GNOME and KDE) and by
+//!   users with `xrdb`.
+//! - `xsettings`: the `Gdk/WindowScalingFactor` setting of the XSETTINGS manager, which is set
+//!   by GNOME when integer scaling is enabled.
+//! - `randr`: the physical size of the screen that the server reported when the connection was
+//!   opened (`XDisplayWidthMM`), compared to a reference of 96 DPI. Despite its name, this
+//!   doesn't query the RandR extension, so the size is the one of the whole X screen and not of
+//!   the monitor of the window, and later changes of the configuration are ignored.
+//!
+//! The first source that gives a value is used, and the factor is `1.0` if none does.
+//!
+//! For troubleshooting, the `GLUTIN_X11_HIDPI_SOURCES` environment variable can be set to a
+//! comma-separated list of the sources above to use instead of the default order, and
+//! `GLUTIN_X11_HIDPI_FACTOR` can be set to a number to force the factor.
+
+use std::env;
+
+use super::ffi;
+use super::XConnection;
+
+const DEFAULT_SOURCES: &'static [&'static str] = &["xft", "xsettings", "randr"];
+
+/// Returns the HiDPI factor of the given screen.
+pub fn get_hidpi_factor(display: &XConnection, screen_id: i32) -> f32 {
+    if let Ok(factor) = env::var("GLUTIN_X11_HIDPI_FACTOR") {
+        if let Ok(factor) = factor.trim().parse::<f32>() {
+            if factor > 0.0 {
+                return factor;
+            }
+        }
+    }
+
+    let sources = match env::var("GLUTIN_X11_HIDPI_SOURCES") {
+        Ok(sources) => sources.split(',').map(|s| s.trim().to_lowercase()).collect(),
+        Err(_) => DEFAULT_SOURCES.iter().map(|s| s.to_string()).collect::<Vec<_>>(),
+    };
+
+    for source in sources.iter() {
+        let factor = match &source[..] {
+            "xft" => get_xft_factor(display, screen_id),
+            "xsettings" => get_xsettings_factor(display, screen_id),
+            "randr" => get_physical_size_factor(display, screen_id),
+            _ => None,
+        };
+
+        if let Some(factor) = factor {
+            return factor;
+        }
+    }
+
+    1.0
+}
+
+/// Returns the window whose properties contain the XSETTINGS of the screen, if any.
+pub fn get_xsettings_window(display: &XConnection, screen_id: i32) -> Option<ffi::Window> {
+    let selection = display.get_atom(&format!("_XSETTINGS_S{}", screen_id));
+    if selection == 0 {
+        return None;
+    }
+
+    let owner = unsafe { (display.xlib.XGetSelectionOwner)(display.display, selection) };
+    display.ignore_error();
+
+    if owner == 0 {
+        None
+    } else {
+        Some(owner)
+    }
+}
+
+/// Returns true if a change of `atom` on `window` can modify the HiDPI factor.
+pub fn is_hidpi_property(display: &XConnection, screen_id: i32, window: ffi::Window,
+                         atom: ffi::Atom) -> bool
+{
+    let root = unsafe { (display.xlib.XRootWindow)(display.display, screen_id) };
+
+    if window == root {
+        atom == display.get_atom("RESOURCE_MANAGER")
+    } else {
+        Some(window) == get_xsettings_window(display, screen_id) &&
+            atom == display.get_atom("_XSETTINGS_SETTINGS")
+    }
+}
+
+fn get_xft_factor(display: &XConnection, screen_id: i32) -> Option<f32> {
+    let root = unsafe { (display.xlib.XRootWindow)(display.display, screen_id) };
+    let resources = display.get_atom("RESOURCE_MANAGER");
+    if resources == 0 {
+        return None;
+    }
+
+    let data = match display.read_property(root, resources, ffi::XA_STRING) {
+        Some(data) => data,
+        None => return None,
+    };
+
+    String::from_utf8_lossy(&data).lines().filter_map(|line| {
+        let mut parts = line.splitn(2, ':');
+        match (parts.next(), parts.next()) {
+            (Some(name), Some(value)) if name.trim() == "Xft.dpi" => value.trim().parse().ok(),
+            _ => None,
+        }
+    }).next().and_then(|dpi: f32| if dpi > 0.0 { Some(dpi / 96.0) } else { None })
+}
+
+fn get_xsettings_factor(display: &XConnection, screen_id: i32) -> Option<f32> {
+    let window = match get_xsettings_window(display, screen_id) {
+        Some(w) => w,
+        None => return None,
+    };
+
+    let settings = display.get_atom("_XSETTINGS_SETTINGS");
+    if settings == 0 {
+        return None;
+    }
+
+    let data = match display.read_property(window, settings, settings) {
+        Some(data) => data,
+        None => return None,
+    };
+
+    match parse_xsettings_integer(&data, "Gdk/WindowScalingFactor") {
+        Some(factor) if factor > 0 => Some(factor as f32),
+        _ => None,
+    }
+}
+
+fn get_physical_size_factor(display: &XConnection, screen_id: i32) -> Option<f32> {
+    let (width_px, width_mm) = unsafe {
+        ((display.xlib.XDisplayWidth)(display.display, screen_id),
+         (display.xlib.XDisplayWidthMM)(display.display, screen_id))
+    };
+
+    if width_px <= 0 || width_mm <= 0 {
+        return None;
+    }
+
+    let dpi = width_px as f32 * 25.4 / width_mm as f32;
+
+    // servers that don't know the physical size usually pretend that the screen is 96 DPI, and
+    // small variations are more likely to be rounding errors than actual HiDPI screens
+    let factor = (dpi / 96.0 * 4.0).round() / 4.0;
+    if factor >= 1.0 {
+        Some(factor)
+    } else {
+        Some(1.0)
+    }
+}
+
+/// Looks for an integer setting in the content of a `_XSETTINGS_SETTINGS` property.
+///
+/// See https://specifications.freedesktop.org/xsettings-spec/xsettings-latest.html
+fn parse_xsettings_integer(data: &[u8], name: &str) -> Option<i32> {
+    if data.len() < 12 {
+        return None;
+    }
+
+    let big_endian = data[0] != 0;
+    let read_u16 = |offset: usize| -> Option<u16> {
+        data.get(offset .. offset + 2).map(|b| if big_endian {
+            (b[0] as u16) << 8 | b[1] as u16
+        } else {
+            (b[1] as u16) << 8 | b[0] as u16
+        })
+    };
+    let read_u32 = |offset: usize| -> Option<u32> {
+        data.get(offset .. offset + 4).map(|b| if big_endian {
+            (b[0] as u32) << 24 | (b[1] as u32) << 16 | (b[2] as u32) << 8 | b[3] as u32
+        } else {
+            (b[3] as u32) << 24 | (b[2] as u32) << 16 | (b[1] as u32) << 8 | b[0] as u32
+        })
+    };
+    let pad = |len: usize| (len + 3) & !3;
+
+    let num_settings = match read_u32(8) { Some(n) => n, None => return None };
+    let mut offset = 12;
+
+    for _ in 0 .. num_settings {
+        let setting_type = match data.get(offset) { Some(&t) => t, None => return None };
+        let name_len = match read_u16(offset + 2) { Some(l) => l as usize, None => return None };
+        let setting_name = match data.get(offset + 4 .. offset + 4 + name_len) {
+            Some(n) => n,
+            None => return None,
+        };
+        // skipping the name and the serial of the last change
+        let value_offset = offset + 4 + pad(name_len) + 4;
+
+        offset = match setting_type {
+            // integer
+            0 => {
+                if setting_name == name.as_bytes() {
+                    return read_u32(value_offset).map(|v| v as i32);
+                }
+                value_offset + 4
+            },
+            // string
+            1 => match read_u32(value_offset) {
+                Some(len) => value_offset + 4 + pad(len as usize),
+                None => return None,
+            },
+            // color
+            2 => value_offset + 8,
+            _ => return None,
+        };
+    }
+
+    None
+}
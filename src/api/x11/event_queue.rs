@@ -0,0 +1,335 @@
+//! Distribution of the events of a connection between the windows that use it.
+//!
+//! All the windows share the same `XConnection`, so the events of a window can be read by the
+//! thread of another window. Events are therefore read in one place, then stored in the queue of
+//! the window they are about until that window processes them.
+
+use std::collections::{HashMap, VecDeque};
+use std::mem;
+use std::ptr;
+use std::sync::{Condvar, Mutex, MutexGuard};
+use std::thread;
+use std::time::{Duration, Instant};
+
+use libc;
+
+use super::ffi;
+use super::XConnection;
+
+/// The events that have been read from the display but not processed by their window yet.
+pub struct EventQueues {
+    state: Mutex<State>,
+    /// Notified whenever events are added to the queues, and when the thread that was blocked
+    /// reading the display stops doing so.
+    condvar: Condvar,
+    /// Type of the client messages that wake up a thread blocked reading the display.
+    wakeup_atom: ffi::Atom,
+}
+
+struct State {
+    queues: HashMap<ffi::Window, VecDeque<ffi::XEvent>>,
+    /// The window whose thread is blocked reading the display, if any.
+    reader: Option<ffi::Window>,
+}
+
+impl EventQueues {
+    pub fn new(wakeup_atom: ffi::Atom) -> EventQueues {
+        EventQueues {
+            state: Mutex::new(State {
+                queues: HashMap::new(),
+                reader: None,
+            }),
+            condvar: Condvar::new(),
+            wakeup_atom: wakeup_atom,
+        }
+    }
+
+    /// Starts storing the events of `window`. The events read before are lost.
+    pub fn register(&self, window: ffi::Window) {
+        self.state.lock().unwrap().queues.entry(window).or_insert_with(VecDeque::new);
+    }
+
+    /// Stops storing the events of `window` and discards the ones that haven't been processed.
+    pub fn unregister(&self, xconn: &XConnection, window: ffi::Window) {
+        let queue = self.state.lock().unwrap().queues.remove(&window);
+        for xev in queue.into_iter().flat_map(|queue| queue.into_iter()) {
+            free_event_data(xconn, xev);
+        }
+    }
+
+    /// Reads the events available on the display, then returns the oldest event of `window`.
+    /// Never blocks.
+    ///
+    /// The data of the `GenericEvent`s has already been retrieved with `XGetEventData`, and must
+    /// be freed by the caller with `XFreeEventData`.
+    pub fn poll(&self, xconn: &XConnection, window: ffi::Window) -> Option<ffi::XEvent> {
+        let mut state = self.state.lock().unwrap();
+        self.read_events(xconn, &mut state);
+        state.queues.get_mut(&window).and_then(|queue| queue.pop_front())
+    }
+
+    /// Same as `poll`, but leaves the event in the queue.
+    pub fn peek(&self, xconn: &XConnection, window: ffi::Window) -> Option<ffi::XEvent> {
+        let mut state = self.state.lock().unwrap();
+        self.read_events(xconn, &mut state);
+        state.queues.get(&window).and_then(|queue| queue.front().cloned())
+    }
+
+    /// Same as `poll`, but moves all the events of `window` to the back of `events` with a
+    /// single lock.
+    pub fn drain(&self, xconn: &XConnection, window: ffi::Window,
+                 events: &mut VecDeque<ffi::XEvent>)
+    {
+        let mut state = self.state.lock().unwrap();
+        self.read_events(xconn, &mut state);
+        if let Some(queue) = state.queues.get_mut(&window) {
+            events.extend(queue.drain(..));
+        }
+    }
+
+    /// Returns the number of events in the queue of `window`, after reading the ones available.
+    pub fn len(&self, xconn: &XConnection, window: ffi::Window) -> usize {
+        let mut state = self.state.lock().unwrap();
+        self.read_events(xconn, &mut state);
+        state.queues.get(&window).map(|queue| queue.len()).unwrap_or(0)
+    }
+
+    /// Waits until the queue of `window` has an event matching `predicate` at position `start`
+    /// or after, or until `timeout` has elapsed. The events stay in the queue.
+    ///
+    /// Returns true if such an event was found.
+    pub fn wait_for<F>(&self, xconn: &XConnection, window: ffi::Window, start: usize,
+                       timeout: Duration, predicate: F) -> bool
+        where F: Fn(&ffi::XEvent) -> bool
+    {
+        let deadline = Instant::now() + timeout;
+
+        loop {
+            {
+                let mut state = self.state.lock().unwrap();
+                self.read_events(xconn, &mut state);
+
+                let found = match state.queues.get(&window) {
+                    Some(queue) => queue.iter().skip(start).any(|xev| predicate(xev)),
+                    None => return false,
+                };
+                if found {
+                    return true;
+                }
+            }
+
+            if Instant::now() >= deadline {
+                return false;
+            }
+            thread::sleep(Duration::from_millis(5));
+        }
+    }
+
+    /// Blocks until an event of `window` is available.
+    ///
+    /// Only one thread at a time waits for the display, and the others wait until it has
+    /// distributed the events it read.
+    pub fn wait(&self, xconn: &XConnection, window: ffi::Window) {
+        let mut state = self.state.lock().unwrap();
+
+        loop {
+            self.read_events(xconn, &mut state);
+
+            match state.queues.get(&window) {
+                Some(queue) if queue.is_empty() => (),
+                // an unregistered window never receives events, so there's no point in waiting
+                _ => return,
+            }
+
+            if state.reader.is_some() {
+                state = self.condvar.wait(state).unwrap();
+                continue;
+            }
+
+            state.reader = Some(window);
+            drop(state);
+
+            // blocks until the display has an event, without removing it; the lock isn't held so
+            // that the other windows can still read the events that arrive
+            unsafe {
+                let mut xev = mem::uninitialized();
+                (xconn.xlib.XPeekEvent)(xconn.display, &mut xev);
+            }
+
+            state = self.state.lock().unwrap();
+            state.reader = None;
+            self.condvar.notify_all();
+        }
+    }
+
+    /// Reads all the events available on the display without blocking, and adds them to the
+    /// queues of their windows.
+    ///
+    /// The events are only removed from the display with the lock held, so `XPending` guarantees
+    /// that `XNextEvent` doesn't block.
+    fn read_events(&self, xconn: &XConnection, state: &mut MutexGuard<State>) {
+        let mut received = Vec::new();
+
+        while unsafe { (xconn.xlib.XPending)(xconn.display) } > 0 {
+            let mut xev = unsafe { mem::uninitialized() };
+            unsafe { (xconn.xlib.XNextEvent)(xconn.display, &mut xev) };
+            self.dispatch(xconn, state, xev, &mut received);
+        }
+
+        if received.is_empty() {
+            return;
+        }
+
+        self.condvar.notify_all();
+
+        // the thread blocked in `wait` only wakes up when the display receives an event, so it
+        // must be sent one if we took its events
+        if let Some(reader) = state.reader {
+            if received.contains(&reader) {
+                self.wake_up(xconn, reader);
+            }
+        }
+    }
+
+    /// Adds `xev` to the queue of the window it is about, and pushes that window in `received`.
+    fn dispatch(&self, xconn: &XConnection, state: &mut State, xev: ffi::XEvent,
+                received: &mut Vec<ffi::Window>)
+    {
+        if Some(xev.get_type()) == xconn.xkb_event_base {
+            let any: &ffi::XkbAnyEvent = unsafe { &*(&xev as *const _ as *const _) };
+            match any.xkb_type {
+                ffi::XkbMapNotify => {
+                    let mut map: ffi::XkbMapNotifyEvent = unsafe { *(&xev as *const _ as *const _) };
+                    unsafe { (xconn.xlib.XkbRefreshKeyboardMapping)(&mut map) };
+                    xconn.ignore_error();
+                },
+                ffi::XkbStateNotify => {
+                    let state: &ffi::XkbStateNotifyEvent = unsafe { &*(&xev as *const _ as *const _) };
+                    if state.changed as libc::c_ulong & ffi::XkbGroupStateMask == 0 {
+                        return;
+                    }
+                },
+                _ => return,
+            }
+
+            // the keyboard layout is the same for all the windows
+            for (&window, queue) in state.queues.iter_mut() {
+                queue.push_back(xev.clone());
+                received.push(window);
+            }
+            return;
+        }
+
+        let window = match xev.get_type() {
+            ffi::MappingNotify => {
+                // the keyboard mapping is the same for all the windows
+                let mut mapping: ffi::XMappingEvent = From::from(xev);
+                unsafe { (xconn.xlib.XRefreshKeyboardMapping)(&mut mapping) };
+                xconn.ignore_error();
+                return;
+            },
+
+            ffi::ClientMessage => {
+                let client_msg: ffi::XClientMessageEvent = From::from(xev);
+                if client_msg.message_type == self.wakeup_atom {
+                    return;
+                }
+                client_msg.window
+            },
+
+            ffi::GenericEvent => {
+                let mut cookie: ffi::XGenericEventCookie = From::from(xev);
+
+                // the data must be retrieved before the next event is read, otherwise Xlib frees
+                // it
+                if unsafe { (xconn.xlib.XGetEventData)(xconn.display, &mut cookie) } == ffi::False {
+                    return;
+                }
+
+                if cookie.evtype == ffi::XI_DeviceChanged {
+                    // the devices are the same for all the windows, which only need to know that
+                    // they changed
+                    unsafe { (xconn.xlib.XFreeEventData)(xconn.display, &mut cookie) };
+                    cookie.data = ptr::null_mut();
+
+                    for (&window, queue) in state.queues.iter_mut() {
+                        queue.push_back(From::from(cookie));
+                        received.push(window);
+                    }
+                    return;
+                }
+
+                match cookie.evtype {
+                    // all these events start like `XIDeviceEvent`
+                    ffi::XI_KeyPress ... ffi::XI_FocusOut |
+                    ffi::XI_TouchBegin ... ffi::XI_TouchEnd => {
+                        let event: &ffi::XIDeviceEvent = unsafe { &*(cookie.data as *const _) };
+                        let window = event.event;
+                        if !state.queues.contains_key(&window) {
+                            unsafe { (xconn.xlib.XFreeEventData)(xconn.display, &mut cookie) };
+                            return;
+                        }
+                        state.queues.get_mut(&window).unwrap().push_back(From::from(cookie));
+                        received.push(window);
+                        return;
+                    },
+                    _ => {
+                        unsafe { (xconn.xlib.XFreeEventData)(xconn.display, &mut cookie) };
+                        return;
+                    },
+                }
+            },
+
+            _ => {
+                let any: ffi::XAnyEvent = From::from(xev);
+                any.window
+            },
+        };
+
+        if let Some(queue) = state.queues.get_mut(&window) {
+            queue.push_back(xev);
+            received.push(window);
+            return;
+        }
+
+        // the properties of the root and XSETTINGS windows that are watched for the HiDPI factor
+        // concern all the windows
+        if xev.get_type() == ffi::PropertyNotify {
+            for (&window, queue) in state.queues.iter_mut() {
+                queue.push_back(xev.clone());
+                received.push(window);
+            }
+        }
+    }
+
+    /// Sends an event to `window` that is discarded when it is read, but wakes up the thread
+    /// blocked in `XPeekEvent`.
+    fn wake_up(&self, xconn: &XConnection, window: ffi::Window) {
+        let mut xev = ffi::XClientMessageEvent {
+            type_: ffi::ClientMessage,
+            window: window,
+            format: 32,
+            message_type: self.wakeup_atom,
+            serial: 0,
+            send_event: 0,
+            display: xconn.display,
+            data: unsafe { mem::zeroed() },
+        };
+
+        unsafe {
+            (xconn.xlib.XSendEvent)(xconn.display, window, 0, 0, &mut xev as *mut _ as *mut _);
+            (xconn.xlib.XFlush)(xconn.display);
+        }
+        xconn.ignore_error();
+    }
+}
+
+/// Frees the data of `xev` if it is a `GenericEvent` that has been queued.
+fn free_event_data(xconn: &XConnection, xev: ffi::XEvent) {
+    if xev.get_type() == ffi::GenericEvent {
+        let mut cookie: ffi::XGenericEventCookie = From::from(xev);
+        if !cookie.data.is_null() {
+            unsafe { (xconn.xlib.XFreeEventData)(xconn.display, &mut cookie) };
+        }
+    }
+}
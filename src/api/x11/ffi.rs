@@ -4,9 +4,66 @@ pub use x11_dl::xf86vmode::*;
 pub use x11_dl::xlib::*;
 pub use x11_dl::xinput::*;
 pub use x11_dl::xinput2::*;
+pub use x11_dl::xinerama::{Xlib as Xinerama, XineramaScreenInfo};
+pub use x11_dl::sync::{Xext, XSyncCounter, XSyncValue};
 
 pub use x11_dl::error::OpenError;
 
+// XKB constants that are missing from `x11_dl`
+pub const XKB_MAJOR_VERSION: ::libc::c_int = 1;
+pub const XKB_MINOR_VERSION: ::libc::c_int = 0;
+/// Designates the core keyboard in the functions of XKB that take a device.
+pub const XKB_USE_CORE_KBD: ::libc::c_uint = 0x0100;
+
+/// The functions of the SHAPE extension, which are missing from `x11_dl`.
+#[allow(non_snake_case)]
+pub struct XShape {
+    pub XShapeQueryExtension: unsafe extern "C" fn(*mut Display, *mut ::libc::c_int,
+                                                   *mut ::libc::c_int) -> Bool,
+    pub XShapeCombineRectangles: unsafe extern "C" fn(*mut Display, Window, ::libc::c_int,
+                                                      ::libc::c_int, ::libc::c_int,
+                                                      *mut XRectangle, ::libc::c_int,
+                                                      ::libc::c_int, ::libc::c_int),
+    pub XShapeCombineMask: unsafe extern "C" fn(*mut Display, Window, ::libc::c_int,
+                                                ::libc::c_int, ::libc::c_int, Pixmap,
+                                                ::libc::c_int),
+}
+
+impl XShape {
+    /// Loads the functions from `libXext`, or returns `None` if it isn't available.
+    pub fn open() -> Option<XShape> {
+        use api::dlopen;
+
+        unsafe {
+            let mut lib = dlopen::dlopen(b"libXext.so.6\0".as_ptr() as *const _, dlopen::RTLD_NOW);
+            if lib.is_null() {
+                lib = dlopen::dlopen(b"libXext.so\0".as_ptr() as *const _, dlopen::RTLD_NOW);
+            }
+            if lib.is_null() {
+                return None;
+            }
+
+            let query = dlopen::dlsym(lib, b"XShapeQueryExtension\0".as_ptr() as *const _);
+            let combine = dlopen::dlsym(lib, b"XShapeCombineRectangles\0".as_ptr() as *const _);
+            let combine_mask = dlopen::dlsym(lib, b"XShapeCombineMask\0".as_ptr() as *const _);
+            if query.is_null() || combine.is_null() || combine_mask.is_null() {
+                return None;
+            }
+
+            Some(XShape {
+                XShapeQueryExtension: ::std::mem::transmute(query),
+                XShapeCombineRectangles: ::std::mem::transmute(combine),
+                XShapeCombineMask: ::std::mem::transmute(combine_mask),
+            })
+        }
+    }
+}
+
+// SHAPE constants
+pub const SHAPE_SET: ::libc::c_int = 0;
+/// The shape of a window that receives the pointer events, the other ones going through it.
+pub const SHAPE_INPUT: ::libc::c_int = 2;
+
 pub use self::glx::types::GLXContext;
 
 /// GLX bindings
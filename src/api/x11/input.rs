@@ -5,12 +5,14 @@ use std::{mem, ptr};
 use std::ffi::CString;
 use std::slice::from_raw_parts;
 
+use CreationError;
 use WindowAttributes;
 
 use events::Event;
 
 use super::{events, ffi};
 use super::XConnection;
+use api::pressed_inputs::PressedInputs;
 
 #[derive(Debug)]
 enum AxisType {
@@ -43,6 +45,10 @@ struct InputState {
     axis_values: Vec<AxisValue>
 }
 
+/// Number of pixels scrolled by one scroll increment of a device, which is one notch of a mouse
+/// wheel and a distance chosen by the driver for smooth scrolling devices such as touchpads.
+const PIXELS_PER_SCROLL_INCREMENT: f64 = 20.0;
+
 pub struct XInputEventHandler {
     display: Arc<XConnection>,
     window: ffi::Window,
@@ -50,79 +56,75 @@ pub struct XInputEventHandler {
     axis_list: Vec<Axis>,
     current_state: InputState,
     multitouch: bool,
+    /// The keys and buttons that are held down, used to detect the repeated presses and to
+    /// release them when the window loses the focus
+    pressed_inputs: PressedInputs,
+    /// True if the server supports XInput 2.2 and pointer events are received through it.
+    /// Otherwise, pointer events are core protocol events.
+    xinput2: bool,
+    /// False if the key presses are not given to the input method, see `set_ime_allowed`
+    ime_allowed: bool,
 }
 
 impl XInputEventHandler {
     pub fn new(display: &Arc<XConnection>, window: ffi::Window, ic: ffi::XIC,
-               window_attrs: &WindowAttributes) -> XInputEventHandler {
-        // query XInput support
-        let mut opcode: libc::c_int = 0;
-        let mut event: libc::c_int = 0;
-        let mut error: libc::c_int = 0;
-        let xinput_str = CString::new("XInputExtension").unwrap();
-
-        unsafe {
-            if (display.xlib.XQueryExtension)(display.display, xinput_str.as_ptr(), &mut opcode, &mut event, &mut error) == ffi::False {
-                panic!("XInput not available")
-            }
-        }
-
-        let mut xinput_major_ver = ffi::XI_2_Major;
-        let mut xinput_minor_ver = ffi::XI_2_Minor;
-
-        unsafe {
-            if (display.xinput2.XIQueryVersion)(display.display, &mut xinput_major_ver, &mut xinput_minor_ver) != ffi::Success as libc::c_int {
-                panic!("Unable to determine XInput version");
-            }
-        }
+               window_attrs: &WindowAttributes) -> Result<XInputEventHandler, CreationError> {
+        let xinput2 = try!(select_xinput2_events(display, window, window_attrs.multitouch));
 
-        // specify the XInput events we want to receive.
-        // Button clicks and mouse events are handled via XInput
-        // events. Key presses are still handled via plain core
-        // X11 events.
-        let mut mask: [libc::c_uchar; 3] = [0; 3];
-        let mut input_event_mask = ffi::XIEventMask {
-            deviceid: ffi::XIAllMasterDevices,
-            mask_len: mask.len() as i32,
-            mask: mask.as_mut_ptr()
-        };
-        let events = &[
-            ffi::XI_ButtonPress,
-            ffi::XI_ButtonRelease,
-            ffi::XI_Motion,
-            ffi::XI_Enter,
-            ffi::XI_Leave,
-            ffi::XI_FocusIn,
-            ffi::XI_FocusOut,
-            ffi::XI_TouchBegin,
-            ffi::XI_TouchUpdate,
-            ffi::XI_TouchEnd,
-        ];
-        for event in events {
-            ffi::XISetMask(&mut mask, *event);
-        }
-
-        unsafe {
-            match (display.xinput2.XISelectEvents)(display.display, window, &mut input_event_mask, 1) {
-                status if status as u8 == ffi::Success => (),
-                err => panic!("Failed to select events {:?}", err)
-            }
-        }
-
-        XInputEventHandler {
+        Ok(XInputEventHandler {
             display: display.clone(),
             window: window,
             ic: ic,
-            axis_list: read_input_axis_info(display),
+            axis_list: if xinput2 { read_input_axis_info(display) } else { Vec::new() },
             current_state: InputState {
                 cursor_pos: (0.0, 0.0),
                 axis_values: Vec::new()
             },
             multitouch: window_attrs.multitouch,
+            xinput2: xinput2,
+            pressed_inputs: PressedInputs::new(),
+            ime_allowed: true,
+        })
+    }
+
+    /// Enables or disables the input method. While it is disabled, the key presses aren't given
+    /// to it, so that they are never composed into other characters.
+    pub fn set_ime_allowed(&mut self, allowed: bool) {
+        if allowed == self.ime_allowed {
+            return;
         }
+        self.ime_allowed = allowed;
+
+        unsafe {
+            if allowed {
+                (self.display.xlib.XSetICFocus)(self.ic);
+            } else {
+                (self.display.xlib.XUnsetICFocus)(self.ic);
+            }
+        }
+        self.display.ignore_error();
+    }
+
+    /// Moves the spot where the input method shows the characters being composed, which only
+    /// works if the input context has the over-the-spot style.
+    pub fn set_ime_position(&mut self, x: i32, y: i32) {
+        let mut spot = ffi::XPoint { x: x as libc::c_short, y: y as libc::c_short };
+
+        unsafe {
+            let spot_location = CString::new("spotLocation").unwrap();
+            let preedit_attributes = CString::new("preeditAttributes").unwrap();
+
+            let list = (self.display.xlib.XVaCreateNestedList)(0, spot_location.as_ptr(),
+                                                               &mut spot, ptr::null::<()>());
+            // the input method remembers the spot until the next composition
+            (self.display.xlib.XSetICValues)(self.ic, preedit_attributes.as_ptr(), list,
+                                             ptr::null::<()>());
+            (self.display.xlib.XFree)(list);
+        }
+        self.display.ignore_error();
     }
 
-    pub fn translate_key_event(&self, event: &mut ffi::XKeyEvent) -> Vec<Event> {
+    pub fn translate_key_event(&mut self, event: &mut ffi::XKeyEvent) -> Vec<Event> {
         use events::Event::{KeyboardInput, ReceivedCharacter};
         use events::ElementState::{Pressed, Released};
 
@@ -130,8 +132,10 @@ impl XInputEventHandler {
 
         let state;
         if event.type_ == ffi::KeyPress {
-            let raw_ev: *mut ffi::XKeyEvent = event;
-            unsafe { (self.display.xlib.XFilterEvent)(mem::transmute(raw_ev), self.window) };
+            if self.ime_allowed {
+                let raw_ev: *mut ffi::XKeyEvent = event;
+                unsafe { (self.display.xlib.XFilterEvent)(mem::transmute(raw_ev), self.window) };
+            }
             state = Pressed;
         } else {
             state = Released;
@@ -165,23 +169,93 @@ impl XInputEventHandler {
 
         let vkey = events::keycode_to_element(keysym as libc::c_uint);
 
-        translated_events.push(KeyboardInput(state, event.keycode as u8, vkey));
+        // a press of a key that is already held down comes from the auto-repeat
+        let keycode = event.keycode as u8;
+        let repeat = state == Pressed && self.pressed_inputs.is_key_pressed(keycode);
+
+        translated_events.extend(self.pressed_inputs.process(KeyboardInput(state, keycode, vkey, repeat)));
         translated_events
     }
 
-    pub fn translate_event(&mut self, cookie: &ffi::XGenericEventCookie) -> Option<Event> {
-        use events::Event::{Focused, MouseInput, MouseMoved, MouseWheel};
+    /// Translates a core protocol pointer or focus event.
+    ///
+    /// Pointer events are only received if the server doesn't support XInput 2.2, since the
+    /// XInput 2 selection takes precedence over the core one otherwise.
+    pub fn translate_core_event(&mut self, xev: &ffi::XEvent) -> Vec<Event> {
+        use events::Event::{Focused, MouseInput, MouseMoved, MouseMovedPrecise, MouseWheel};
         use events::ElementState::{Pressed, Released};
-        use events::MouseButton::{Left, Right, Middle};
+        use events::MouseButton::{Left, Right, Middle, Other};
         use events::MouseScrollDelta::LineDelta;
+        use events::TouchPhase;
+
+        let events = match xev.get_type() {
+            ffi::ButtonPress | ffi::ButtonRelease => {
+                let event: &ffi::XButtonEvent = unsafe { mem::transmute(xev) };
+                let state = if xev.get_type() == ffi::ButtonPress { Pressed } else { Released };
+
+                match event.button {
+                    ffi::Button1 => vec![MouseInput(state, Left, None)],
+                    ffi::Button2 => vec![MouseInput(state, Middle, None)],
+                    ffi::Button3 => vec![MouseInput(state, Right, None)],
+                    // the wheel sends a press and a release for each step
+                    4 ... 7 if state == Released => vec![],
+                    4 => vec![MouseWheel(LineDelta(0.0, 1.0), TouchPhase::Moved, None)],
+                    5 => vec![MouseWheel(LineDelta(0.0, -1.0), TouchPhase::Moved, None)],
+                    6 => vec![MouseWheel(LineDelta(-1.0, 0.0), TouchPhase::Moved, None)],
+                    7 => vec![MouseWheel(LineDelta(1.0, 0.0), TouchPhase::Moved, None)],
+                    n => vec![MouseInput(state, Other(n as u8), None)],
+                }
+            },
+            ffi::MotionNotify => {
+                let event: &ffi::XMotionEvent = unsafe { mem::transmute(xev) };
+                let new_cursor_pos = (event.x as f64, event.y as f64);
+                if new_cursor_pos != self.current_state.cursor_pos {
+                    self.current_state.cursor_pos = new_cursor_pos;
+                    vec![MouseMoved(event.x as i32, event.y as i32),
+                         MouseMovedPrecise(new_cursor_pos.0, new_cursor_pos.1)]
+                } else {
+                    vec![]
+                }
+            },
+            ffi::FocusIn | ffi::FocusOut => {
+                if self.xinput2 {
+                    // already reported by `XI_FocusIn` and `XI_FocusOut`
+                    return vec![];
+                }
+                let event: &ffi::XFocusChangeEvent = unsafe { mem::transmute(xev) };
+                // ignore the events generated by grabs and by focus moving between our own
+                // subwindows
+                if event.mode != ffi::NotifyNormal || event.detail == ffi::NotifyInferior {
+                    return vec![];
+                }
+                vec![Focused(xev.get_type() == ffi::FocusIn)]
+            },
+            _ => vec![]
+        };
+
+        self.process_pressed_inputs(events)
+    }
+
+    pub fn translate_event(&mut self, cookie: &ffi::XGenericEventCookie) -> Vec<Event> {
+        use events::Event::{Focused, MouseInput, MouseMoved, MouseMovedPrecise, MouseWheel};
+        use events::ElementState::{Pressed, Released};
+        use events::MouseButton::{Left, Right, Middle};
+        use events::MouseScrollDelta::{LineDelta, PixelDelta};
         use events::{Touch, TouchPhase};
 
-        match cookie.evtype {
+        let events = match cookie.evtype {
+            ffi::XI_DeviceChanged => {
+                // the slave device behind the master pointer changed, or its axes did, so the
+                // previous axis values and increments are no longer relevant
+                self.axis_list = read_input_axis_info(&self.display);
+                self.current_state.axis_values.clear();
+                vec![]
+            },
             ffi::XI_ButtonPress | ffi::XI_ButtonRelease => {
                 let event_data: &ffi::XIDeviceEvent = unsafe{mem::transmute(cookie.data)};
                 if self.multitouch && (event_data.flags & ffi::XIPointerEmulated) != 0 {
                     // Deliver multi-touch events instead of emulated mouse events.
-                    return None
+                    return vec![]
                 }
                 let state = if cookie.evtype == ffi::XI_ButtonPress {
                     Pressed
@@ -189,9 +263,9 @@ impl XInputEventHandler {
                     Released
                 };
                 match event_data.detail as u32 {
-                    ffi::Button1 => Some(MouseInput(state, Left, None)),
-                    ffi::Button2 => Some(MouseInput(state, Middle, None)),
-                    ffi::Button3 => Some(MouseInput(state, Right, None)),
+                    ffi::Button1 => vec![MouseInput(state, Left, None)],
+                    ffi::Button2 => vec![MouseInput(state, Middle, None)],
+                    ffi::Button3 => vec![MouseInput(state, Right, None)],
                     ffi::Button4 | ffi::Button5 => {
                         if event_data.flags & ffi::XIPointerEmulated == 0 {
                             // scroll event from a traditional wheel with
@@ -201,22 +275,22 @@ impl XInputEventHandler {
                             } else {
                                 -1.0
                             };
-                            Some(MouseWheel(LineDelta(0.0, delta), TouchPhase::Moved, None))
+                            vec![MouseWheel(LineDelta(0.0, delta), TouchPhase::Moved, None)]
                         } else {
                             // emulated button event from a touch/smooth-scroll
                             // event. Ignore these events and handle scrolling
                             // via XI_Motion event handler instead
-                            None
+                            vec![]
                         }
                     }
-                    _ => None
+                    _ => vec![]
                 }
             },
             ffi::XI_Motion => {
                 let event_data: &ffi::XIDeviceEvent = unsafe{mem::transmute(cookie.data)};
                 if self.multitouch && (event_data.flags & ffi::XIPointerEmulated) != 0 {
                     // Deliver multi-touch events instead of emulated mouse events.
-                    return None
+                    return vec![]
                 }
                 let axis_state = event_data.valuators;
                 let mask = unsafe{ from_raw_parts(axis_state.mask, axis_state.mask_len as usize) };
@@ -235,16 +309,20 @@ impl XInputEventHandler {
                 }
 
                 if scroll_delta.0.abs() > 0.0 || scroll_delta.1.abs() > 0.0 {
-                    Some(MouseWheel(LineDelta(scroll_delta.0 as f32, scroll_delta.1 as f32),
+                    // the deltas are in scroll increments of the device, which are fractional for
+                    // smooth scrolling devices such as touchpads
+                    vec![MouseWheel(PixelDelta((scroll_delta.0 * PIXELS_PER_SCROLL_INCREMENT) as f32,
+                                               (scroll_delta.1 * PIXELS_PER_SCROLL_INCREMENT) as f32),
                                     TouchPhase::Moved,
-                                    None))
+                                    None)]
                 } else {
                     let new_cursor_pos = (event_data.event_x, event_data.event_y);
                     if new_cursor_pos != self.current_state.cursor_pos {
                         self.current_state.cursor_pos = new_cursor_pos;
-                        Some(MouseMoved(new_cursor_pos.0 as i32, new_cursor_pos.1 as i32))
+                        vec![MouseMoved(new_cursor_pos.0 as i32, new_cursor_pos.1 as i32),
+                             MouseMovedPrecise(new_cursor_pos.0, new_cursor_pos.1)]
                     } else {
-                        None
+                        vec![]
                     }
                 }
             },
@@ -255,14 +333,15 @@ impl XInputEventHandler {
                 // our window however, so clear the previous axis state whenever
                 // the cursor re-enters the window
                 self.current_state.axis_values.clear();
-                None
+                vec![]
+            },
+            ffi::XI_Leave => vec![],
+            ffi::XI_FocusIn | ffi::XI_FocusOut => {
+                vec![Focused(cookie.evtype == ffi::XI_FocusIn)]
             },
-            ffi::XI_Leave => None,
-            ffi::XI_FocusIn => Some(Focused(true)),
-            ffi::XI_FocusOut => Some(Focused(false)),
             ffi::XI_TouchBegin | ffi::XI_TouchUpdate | ffi::XI_TouchEnd => {
                 if !self.multitouch {
-                    return None
+                    return vec![]
                 }
                 let event_data: &ffi::XIDeviceEvent = unsafe{mem::transmute(cookie.data)};
                 let phase = match cookie.evtype {
@@ -271,15 +350,106 @@ impl XInputEventHandler {
                     ffi::XI_TouchEnd => TouchPhase::Ended,
                     _ => unreachable!()
                 };
-                Some(Event::Touch(Touch {
+                // touch ids are only unique for a given device, so the device id is included
+                // to keep them apart when several touch devices are used at the same time
+                vec![Event::Touch(Touch {
                     phase: phase,
                     location: (event_data.event_x, event_data.event_y),
-                    id: event_data.detail as u64,
-                }))
+                    id: ((event_data.sourceid as u64) << 32) | (event_data.detail as u32 as u64),
+                })]
             }
-            _ => None
+            _ => vec![]
+        };
+
+        self.process_pressed_inputs(events)
+    }
+
+    /// Records the presses and releases of the mouse buttons, and releases the keys and buttons
+    /// that are held down when the window loses the focus, since their releases are sent to the
+    /// window that gets it.
+    fn process_pressed_inputs(&mut self, events: Vec<Event>) -> Vec<Event> {
+        let pressed_inputs = &mut self.pressed_inputs;
+        events.into_iter().flat_map(|event| pressed_inputs.process(event)).collect()
+    }
+}
+
+/// Selects the XInput 2 events of the window.
+///
+/// Returns false if the server doesn't support XInput 2.2, in which case the core protocol
+/// events have to be used instead.
+fn select_xinput2_events(display: &Arc<XConnection>, window: ffi::Window, multitouch: bool)
+                         -> Result<bool, CreationError>
+{
+    // query XInput support
+    let mut opcode: libc::c_int = 0;
+    let mut event: libc::c_int = 0;
+    let mut error: libc::c_int = 0;
+    let xinput_str = CString::new("XInputExtension").unwrap();
+
+    unsafe {
+        if (display.xlib.XQueryExtension)(display.display, xinput_str.as_ptr(), &mut opcode, &mut event, &mut error) == ffi::False {
+            return Ok(false);
         }
     }
+
+    // XInput 2.2 is the first version with touch events
+    let mut xinput_major_ver = 2;
+    let mut xinput_minor_ver = 2;
+
+    unsafe {
+        if (display.xinput2.XIQueryVersion)(display.display, &mut xinput_major_ver, &mut xinput_minor_ver) != ffi::Success as libc::c_int {
+            display.ignore_error();
+            return Ok(false);
+        }
+    }
+
+    if (xinput_major_ver, xinput_minor_ver) < (2, 2) {
+        return Ok(false);
+    }
+
+    // specify the XInput events we want to receive.
+    // Button clicks and mouse events are handled via XInput
+    // events. Key presses are still handled via plain core
+    // X11 events.
+    let mut mask: [libc::c_uchar; 3] = [0; 3];
+    let mut input_event_mask = ffi::XIEventMask {
+        deviceid: ffi::XIAllMasterDevices,
+        mask_len: mask.len() as i32,
+        mask: mask.as_mut_ptr()
+    };
+    let events = &[
+        ffi::XI_DeviceChanged,
+        ffi::XI_ButtonPress,
+        ffi::XI_ButtonRelease,
+        ffi::XI_Motion,
+        ffi::XI_Enter,
+        ffi::XI_Leave,
+        ffi::XI_FocusIn,
+        ffi::XI_FocusOut,
+    ];
+    for event in events {
+        ffi::XISetMask(&mut mask, *event);
+    }
+
+    // when touch events are selected, the server stops emulating pointer events from touches,
+    // so we only ask for them if the user wants to handle touches themselves
+    if multitouch {
+        for event in &[ffi::XI_TouchBegin, ffi::XI_TouchUpdate, ffi::XI_TouchEnd] {
+            ffi::XISetMask(&mut mask, *event);
+        }
+    }
+
+    unsafe {
+        match (display.xinput2.XISelectEvents)(display.display, window, &mut input_event_mask, 1) {
+            status if status as u8 == ffi::Success => (),
+            status => {
+                return Err(CreationError::OsError(format!("XISelectEvents failed with status {}",
+                                                          status)));
+            },
+        }
+    }
+
+    Ok(true)
 }
 
 fn read_input_axis_info(display: &Arc<XConnection>) -> Vec<Axis> {
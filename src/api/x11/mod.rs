@@ -1,13 +1,43 @@
 #![cfg(any(target_os = "linux", target_os = "dragonfly", target_os = "freebsd", target_os = "openbsd"))]
 
 pub use self::monitor::{MonitorId, get_available_monitors, get_primary_monitor};
+pub use self::monitor::monitor_from_point;
 pub use self::window::{Window, XWindow, PollEventsIterator, WaitEventsIterator, Context, WindowProxy};
+pub use self::window::SharedContext;
 pub use self::xdisplay::{XConnection, XNotSupported, XError};
 
+use std::sync::Arc;
+
 pub mod ffi;
 
+mod cursor;
+mod dpi;
+mod event_queue;
 mod events;
 mod input;
 mod monitor;
 mod window;
 mod xdisplay;
+
+/// Returns true if windows using this visual can be transparent.
+///
+/// Compositors only blend the windows whose visual has a depth of 32 bits, in which case the
+/// bits that aren't used by the red, green and blue channels contain the alpha channel.
+pub fn is_argb_visual(visual_infos: &ffi::XVisualInfo) -> bool {
+    let rgb_mask = visual_infos.red_mask | visual_infos.green_mask | visual_infos.blue_mask;
+    visual_infos.depth == 32 && (rgb_mask & 0xffffffff) != 0xffffffff
+}
+
+/// Returns true if the default screen has a GLX config that isn't marked as slow. Returns false
+/// if GLX isn't available.
+pub fn has_hardware_acceleration(x: &Arc<XConnection>) -> bool {
+    let glx = match x.glx {
+        Some(ref glx) => glx,
+        None => return false,
+    };
+
+    let screen = unsafe { (x.xlib.XDefaultScreen)(x.display) };
+    let result = unsafe { ::api::glx::has_hardware_acceleration(glx, &x.xlib, x.display, screen) };
+    x.ignore_error();
+    result
+}
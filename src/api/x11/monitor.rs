@@ -1,18 +1,57 @@
 use std::collections::VecDeque;
+use std::ptr;
 use std::sync::Arc;
 
+use super::dpi;
+use super::ffi;
 use super::XConnection;
 use native_monitor::NativeMonitorId;
 
 #[derive(Clone)]
-pub struct MonitorId(pub Arc<XConnection>, pub u32);
+pub struct MonitorId {
+    pub display: Arc<XConnection>,
+    /// The X screen that the monitor displays.
+    pub screen: u32,
+    /// The part of the screen that the monitor displays when Xinerama splits the screen between
+    /// several monitors, in which case the monitors all have the same X screen.
+    pub head: Option<XineramaHead>,
+}
+
+/// A monitor as reported by Xinerama.
+#[derive(Debug, Clone, Copy)]
+pub struct XineramaHead {
+    /// The position of the monitor in the list of Xinerama, which is how window managers
+    /// identify monitors in `_NET_WM_FULLSCREEN_MONITORS`.
+    pub index: u32,
+    pub x: i32,
+    pub y: i32,
+    pub width: u32,
+    pub height: u32,
+}
 
 pub fn get_available_monitors(x: &Arc<XConnection>) -> VecDeque<MonitorId> {
+    let mut monitors = VecDeque::new();
+
+    // Xinerama merges all the monitors into a single screen
+    let heads = get_xinerama_heads(x);
+    if !heads.is_empty() {
+        let screen = unsafe { (x.xlib.XDefaultScreen)(x.display) } as u32;
+        monitors.extend(heads.into_iter().map(|head| MonitorId {
+            display: x.clone(),
+            screen: screen,
+            head: Some(head),
+        }));
+        return monitors;
+    }
+
     let nb_monitors = unsafe { (x.xlib.XScreenCount)(x.display) };
     x.check_errors().expect("Failed to call XScreenCount");
 
-    let mut monitors = VecDeque::new();
-    monitors.extend((0 .. nb_monitors).map(|i| MonitorId(x.clone(), i as u32)));
+    monitors.extend((0 .. nb_monitors).map(|i| MonitorId {
+        display: x.clone(),
+        screen: i as u32,
+        head: None,
+    }));
     monitors
 }
 
@@ -20,25 +59,144 @@ pub fn get_available_monitors(x: &Arc<XConnection>) -> VecDeque<MonitorId> {
 pub fn get_primary_monitor(x: &Arc<XConnection>) -> MonitorId {
     let primary_monitor = unsafe { (x.xlib.XDefaultScreen)(x.display) };
     x.check_errors().expect("Failed to call XDefaultScreen");
-    MonitorId(x.clone(), primary_monitor as u32)
+
+    MonitorId {
+        display: x.clone(),
+        screen: primary_monitor as u32,
+        // RandR puts the primary output first in the list of Xinerama
+        head: get_xinerama_heads(x).into_iter().next(),
+    }
+}
+
+/// Returns the monitor that contains the point, in points in the coordinates of the root window,
+/// like the positions of the windows.
+///
+/// Without Xinerama, the point is considered on the default screen if it is within its
+/// dimensions, since the other X screens have their own coordinates.
+pub fn monitor_from_point(x: &Arc<XConnection>, px: i32, py: i32) -> Option<MonitorId> {
+    let screen = unsafe { (x.xlib.XDefaultScreen)(x.display) };
+    let factor = dpi::get_hidpi_factor(x, screen);
+    let (px, py) = ((px as f32 * factor) as i32, (py as f32 * factor) as i32);
+
+    let contains = |left: i32, top: i32, width: u32, height: u32| {
+        px >= left && py >= top && px < left + width as i32 && py < top + height as i32
+    };
+
+    let heads = get_xinerama_heads(x);
+
+    if !heads.is_empty() {
+        return heads.into_iter()
+                    .find(|head| contains(head.x, head.y, head.width, head.height))
+                    .map(|head| MonitorId {
+                        display: x.clone(),
+                        screen: screen as u32,
+                        head: Some(head),
+                    });
+    }
+
+    let (width, height) = unsafe {
+        ((x.xlib.XDisplayWidth)(x.display, screen), (x.xlib.XDisplayHeight)(x.display, screen))
+    };
+    if !contains(0, 0, width as u32, height as u32) {
+        return None;
+    }
+
+    Some(MonitorId {
+        display: x.clone(),
+        screen: screen as u32,
+        head: None,
+    })
+}
+
+/// Returns the monitors of the screen if Xinerama is active, or an empty list otherwise.
+fn get_xinerama_heads(x: &XConnection) -> Vec<XineramaHead> {
+    let xinerama = match x.xinerama {
+        Some(ref xinerama) => xinerama,
+        None => return Vec::new(),
+    };
+
+    unsafe {
+        if (xinerama.XineramaIsActive)(x.display) == ffi::False {
+            x.ignore_error();
+            return Vec::new();
+        }
+
+        let mut number = 0;
+        let screens = (xinerama.XineramaQueryScreens)(x.display, &mut number);
+        x.ignore_error();
+        if screens.is_null() {
+            return Vec::new();
+        }
+
+        let heads = (0 .. number as isize).map(|i| {
+            let info: ffi::XineramaScreenInfo = ptr::read(screens.offset(i));
+            XineramaHead {
+                index: i as u32,
+                x: info.x_org as i32,
+                y: info.y_org as i32,
+                width: info.width as u32,
+                height: info.height as u32,
+            }
+        }).collect();
+
+        (x.xlib.XFree)(screens as *mut _);
+        heads
+    }
 }
 
 impl MonitorId {
     pub fn get_name(&self) -> Option<String> {
-        let MonitorId(_, screen_num) = *self;
-        Some(format!("Monitor #{}", screen_num))
+        match self.head {
+            Some(ref head) => Some(format!("Monitor #{}", head.index)),
+            None => Some(format!("Monitor #{}", self.screen)),
+        }
     }
 
+    /// Returns the index of the monitor in the list of Xinerama, or the number of its screen
+    /// otherwise.
     #[inline]
     pub fn get_native_identifier(&self) -> NativeMonitorId {
-        NativeMonitorId::Numeric(self.1)
+        match self.head {
+            Some(ref head) => NativeMonitorId::Numeric(head.index),
+            None => NativeMonitorId::Numeric(self.screen),
+        }
     }
 
     pub fn get_dimensions(&self) -> (u32, u32) {
-        let screen = unsafe { (self.0.xlib.XScreenOfDisplay)(self.0.display, self.1 as i32) };
-        let width = unsafe { (self.0.xlib.XWidthOfScreen)(screen) };
-        let height = unsafe { (self.0.xlib.XHeightOfScreen)(screen) };
-        self.0.check_errors().expect("Failed to get monitor dimensions");
+        if let Some(ref head) = self.head {
+            return (head.width, head.height);
+        }
+
+        let screen = unsafe { (self.display.xlib.XScreenOfDisplay)(self.display.display, self.screen as i32) };
+        let width = unsafe { (self.display.xlib.XWidthOfScreen)(screen) };
+        let height = unsafe { (self.display.xlib.XHeightOfScreen)(screen) };
+        self.display.check_errors().expect("Failed to get monitor dimensions");
         (width as u32, height as u32)
     }
+
+    /// Returns the position of the top-left hand corner of the monitor within its screen.
+    #[inline]
+    pub fn get_position(&self) -> (i32, i32) {
+        self.head.map(|head| (head.x, head.y)).unwrap_or((0, 0))
+    }
+
+    /// Reads the `_ICC_PROFILE` property of the root window of the screen, which is set by
+    /// color management daemons such as colord.
+    ///
+    /// The profiles of the other Xinerama monitors than the first one are in `_ICC_PROFILE_1`,
+    /// `_ICC_PROFILE_2`, etc.
+    pub fn get_icc_profile(&self) -> Option<Vec<u8>> {
+        let root = unsafe { (self.display.xlib.XRootWindow)(self.display.display, self.screen as i32) };
+        let atom = match self.head {
+            Some(ref head) if head.index != 0 => {
+                self.display.get_atom(&format!("_ICC_PROFILE_{}", head.index))
+            },
+            _ => self.display.get_atom("_ICC_PROFILE"),
+        };
+        if atom == 0 {
+            return None;
+        }
+
+        self.display.read_property(root, atom, ffi::XA_CARDINAL)
+    }
 }
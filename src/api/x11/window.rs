@@ -5,6 +5,7 @@ use libc;
 use std::borrow::Borrow;
 use std::{mem, ptr, cmp};
 use std::cell::Cell;
+use std::ffi::CStr;
 use std::sync::atomic::AtomicBool;
 use std::collections::VecDeque;
 use std::path::PathBuf;
@@ -15,12 +16,23 @@ use std::time::Duration;
 
 use Api;
 use ContextError;
+use ContextPriority;
+use Robustness;
 use CursorState;
+use CursorGrabMode;
+use ContentProtectionError;
+use CursorStateError;
 use GlAttributes;
 use GlContext;
 use GlRequest;
 use PixelFormat;
 use PixelFormatRequirements;
+use RawEvent;
+use RawEventFilter;
+use Rect;
+use ResizeEdge;
+use ScanCode;
+use VirtualKeyCode;
 use WindowAttributes;
 
 use api::glx::Context as GlxContext;
@@ -28,10 +40,15 @@ use api::egl;
 use api::egl::Context as EglContext;
 
 use platform::MonitorId as PlatformMonitorId;
+use platform::PlatformSpecificWindowBuilderAttributes;
+use os::unix::{RawContext, WindowType};
 
+use super::dpi;
+use super::events;
 use super::input::XInputEventHandler;
 use super::{ffi};
-use super::{MonitorId, XConnection};
+use super::{XConnection, XError};
+use super::monitor::{self, MonitorId, XineramaHead};
 
 // XOpenIM doesn't seem to be thread-safe
 lazy_static! {      // TODO: use a static mutex when that's possible, and put me back in my function
@@ -48,6 +65,8 @@ fn with_c_str<F, T>(s: &str, f: F) -> T where F: FnOnce(*const libc::c_char) ->
 struct WindowProxyData {
     display: Arc<XConnection>,
     window: ffi::Window,
+    wm_protocols: ffi::Atom,
+    wm_delete_window: ffi::Atom,
 }
 
 unsafe impl Send for WindowProxyData {}
@@ -63,6 +82,12 @@ pub struct XWindow {
     im: ffi::XIM,
     colormap: ffi::Colormap,
     window_proxy_data: Arc<Mutex<Option<WindowProxyData>>>,
+    /// The visual that the window and its OpenGL context have been created with.
+    visual_infos: ffi::XVisualInfo,
+    /// The `GLX_FBCONFIG_ID` of the context, if it uses GLX.
+    glx_fbconfig_id: Option<libc::c_int>,
+    /// The counter of `_NET_WM_SYNC_REQUEST`, if the server supports XSync.
+    sync_counter: Option<ffi::XSyncCounter>,
 }
 
 pub enum Context {
@@ -91,10 +116,17 @@ impl Drop for XWindow {
             let _lock = GLOBAL_XOPENIM_LOCK.lock().unwrap();
 
             if self.is_fullscreen {
+                // only set if the video mode was switched
                 if let Some(mut xf86_desk_mode) = self.xf86_desk_mode {
                     (self.display.xf86vmode.XF86VidModeSwitchToMode)(self.display.display, self.screen_id, &mut xf86_desk_mode);
+                    (self.display.xf86vmode.XF86VidModeSetViewPort)(self.display.display, self.screen_id, 0, 0);
                 }
-                (self.display.xf86vmode.XF86VidModeSetViewPort)(self.display.display, self.screen_id, 0, 0);
+            }
+
+            self.display.unregister_window(self.window);
+
+            if let (Some(ref sync), Some(counter)) = (self.display.sync.as_ref(), self.sync_counter) {
+                (sync.XSyncDestroyCounter)(self.display.display, counter);
             }
 
             (self.display.xlib.XDestroyIC)(self.ic);
@@ -133,6 +165,36 @@ impl WindowProxy {
             }
         }
     }
+
+    pub fn request_close(&self) {
+        let window_proxy_data = self.data.lock().unwrap();
+
+        if let Some(ref data) = *window_proxy_data {
+            // the same message as the one that the window manager sends when the user clicks on
+            // the close button, which the events loop turns into `Closed`
+            let mut client_data = ffi::ClientMessageData::new();
+            client_data.set_long(0, data.wm_delete_window as libc::c_long);
+            client_data.set_long(1, ffi::CurrentTime as libc::c_long);
+
+            let mut xev = ffi::XClientMessageEvent {
+                type_: ffi::ClientMessage,
+                window: data.window,
+                format: 32,
+                message_type: data.wm_protocols,
+                serial: 0,
+                send_event: 0,
+                display: data.display.display,
+                data: client_data,
+            };
+
+            unsafe {
+                (data.display.xlib.XSendEvent)(data.display.display, data.window, 0, 0, mem::transmute(&mut xev));
+                // the error is ignored: `XSendEvent` can only fail with `BadWindow` if the
+                // window has been destroyed meanwhile, in which case there is nothing to close
+                (data.display.xlib.XFlush)(data.display.display);
+            }
+        }
+    }
 }
 
 // XEvents of type GenericEvent store their actual data
@@ -145,20 +207,19 @@ struct GenericEventCookie<'a> {
 }
 
 impl<'a> GenericEventCookie<'a> {
-    fn from_event<'b>(display: &'b XConnection, event: ffi::XEvent) -> Option<GenericEventCookie<'b>> {
-        unsafe {
-            let mut cookie: ffi::XGenericEventCookie = From::from(event);
-            if (display.xlib.XGetEventData)(display.display, &mut cookie) == ffi::True {
-                Some(GenericEventCookie{display: display, cookie: cookie})
-            } else {
-                None
-            }
-        }
+    /// Wraps an event returned by `poll_window_event`, whose data has already been retrieved.
+    fn from_event<'b>(display: &'b XConnection, event: ffi::XEvent) -> GenericEventCookie<'b> {
+        GenericEventCookie { display: display, cookie: From::from(event) }
     }
 }
 
 impl<'a> Drop for GenericEventCookie<'a> {
     fn drop(&mut self) {
+        // the data of the events that concern all the windows has already been freed
+        if self.cookie.data.is_null() {
+            return;
+        }
+
         unsafe {
             let xlib = &self.display.xlib;
             (xlib.XFreeEventData)(self.display.display, &mut self.cookie);
@@ -174,91 +235,21 @@ impl<'a> Iterator for PollEventsIterator<'a> {
     type Item = Event;
 
     fn next(&mut self) -> Option<Event> {
-        let xlib = &self.window.x.display.xlib;
-
         loop {
             if let Some(ev) = self.window.pending_events.lock().unwrap().pop_front() {
                 return Some(ev);
             }
 
-            let mut xev = unsafe { mem::uninitialized() };
-            let res = unsafe { (xlib.XCheckMaskEvent)(self.window.x.display.display, -1, &mut xev) };
-
-            if res == 0 {
-                let res = unsafe { (xlib.XCheckTypedEvent)(self.window.x.display.display, ffi::ClientMessage, &mut xev) };
+            // only the events of this window are returned, the ones of the other windows of the
+            // connection are kept for them
+            let xev = match self.window.x.display.poll_window_event(self.window.x.window) {
+                Some(xev) => xev,
+                None => return None,
+            };
 
-                if res == 0 {
-                    let res = unsafe { (xlib.XCheckTypedEvent)(self.window.x.display.display, ffi::GenericEvent, &mut xev) };
-                    if res == 0 {
-                        return None;
-                    }
-                }
+            if let Some(event) = self.window.process_event(xev, &VecDeque::new()) {
+                return Some(event);
             }
-
-            match xev.get_type() {
-                ffi::MappingNotify => {
-                    unsafe { (xlib.XRefreshKeyboardMapping)(mem::transmute(&xev)); }
-                    self.window.x.display.check_errors().expect("Failed to call XRefreshKeyboardMapping");
-                },
-
-                ffi::ClientMessage => {
-                    use events::Event::{Closed, Awakened};
-                    use std::sync::atomic::Ordering::Relaxed;
-
-                    let client_msg: &ffi::XClientMessageEvent = unsafe { mem::transmute(&xev) };
-
-                    if client_msg.data.get_long(0) == self.window.wm_delete_window as libc::c_long {
-                        self.window.is_closed.store(true, Relaxed);
-                        return Some(Closed);
-                    } else {
-                        return Some(Awakened);
-                    }
-                },
-
-                ffi::ConfigureNotify => {
-                    use events::Event::Resized;
-                    let cfg_event: &ffi::XConfigureEvent = unsafe { mem::transmute(&xev) };
-                    let (current_width, current_height) = self.window.current_size.get();
-                    if current_width != cfg_event.width || current_height != cfg_event.height {
-                        self.window.current_size.set((cfg_event.width, cfg_event.height));
-                        return Some(Resized(cfg_event.width as u32, cfg_event.height as u32));
-                    }
-                },
-
-                ffi::Expose => {
-                    use events::Event::Refresh;
-                    return Some(Refresh);
-                },
-
-                ffi::KeyPress | ffi::KeyRelease => {
-                    let mut event: &mut ffi::XKeyEvent = unsafe { mem::transmute(&mut xev) };
-                    let events = self.window.input_handler.lock().unwrap().translate_key_event(&mut event);
-                    for event in events {
-                        self.window.pending_events.lock().unwrap().push_back(event);
-                    }
-                },
-
-                ffi::GenericEvent => {
-                    if let Some(cookie) = GenericEventCookie::from_event(self.window.x.display.borrow(), xev) {
-                        match cookie.cookie.evtype {
-                            ffi::XI_DeviceChanged...ffi::XI_LASTEVENT => {
-                                match self.window.input_handler.lock() {
-                                    Ok(mut handler) => {
-                                        match handler.translate_event(&cookie.cookie) {
-                                            Some(event) => self.window.pending_events.lock().unwrap().push_back(event),
-                                            None => {}
-                                        }
-                                    },
-                                    Err(_) => {}
-                                }
-                            },
-                            _ => {}
-                        }
-                    }
-                }
-
-                _ => {}
-            };
         }
     }
 }
@@ -272,18 +263,15 @@ impl<'a> Iterator for WaitEventsIterator<'a> {
 
     fn next(&mut self) -> Option<Event> {
         use std::sync::atomic::Ordering::Relaxed;
-        use std::mem;
 
         while !self.window.is_closed.load(Relaxed) {
             if let Some(ev) = self.window.pending_events.lock().unwrap().pop_front() {
                 return Some(ev);
             }
 
-            // this will block until an event arrives, but doesn't remove
-            // it from the queue
-            let mut xev = unsafe { mem::uninitialized() };
-            unsafe { (self.window.x.display.xlib.XPeekEvent)(self.window.x.display.display, &mut xev) };
-            self.window.x.display.check_errors().expect("Failed to call XPeekEvent");
+            // this will block until an event of the window arrives, but doesn't remove it from
+            // its queue
+            self.window.x.display.wait_window_event(self.window.x.window);
 
             // calling poll_events()
             if let Some(ev) = self.window.poll_events().next() {
@@ -298,30 +286,80 @@ impl<'a> Iterator for WaitEventsIterator<'a> {
 pub struct Window {
     pub x: Arc<XWindow>,
     is_closed: AtomicBool,
+    /// Whether the window asks the window manager for decorations, see `set_decorations`
+    decorations: AtomicBool,
+    wm_protocols: ffi::Atom,
     wm_delete_window: ffi::Atom,
+    net_wm_ping: ffi::Atom,
+    net_wm_sync_request: ffi::Atom,
+    /// The value that the sync counter must be set to once the window has been redrawn after a
+    /// resize, see `complete_resize`
+    sync_request: Mutex<Option<ffi::XSyncValue>>,
     current_size: Cell<(libc::c_int, libc::c_int)>,
+    /// The last position of the client area relative to the root window, to only report the
+    /// changes
+    current_position: Cell<(libc::c_int, libc::c_int)>,
     /// Events that have been retreived with XLib but not dispatched with iterators yet
     pending_events: Mutex<VecDeque<Event>>,
     cursor_state: Mutex<CursorState>,
-    input_handler: Mutex<XInputEventHandler>
+    /// How the cursor is held by the window, see `set_cursor_grab`
+    cursor_grab: Mutex<CursorGrabMode>,
+    /// The rectangle the cursor is confined to, see `confine_cursor`
+    cursor_confinement: Mutex<Option<Rect>>,
+    /// True if the pointer is currently grabbed by the window, which can differ from the cursor
+    /// state since the grab is released while the window doesn't have the focus
+    pointer_grabbed: AtomicBool,
+    /// The position of the cursor when it was locked, where it is moved back when it is unlocked
+    cursor_position_before_lock: Cell<Option<(i32, i32)>>,
+    input_handler: Mutex<XInputEventHandler>,
+    /// The filter of `set_raw_event_filter`, taken out while it runs, and the number of times
+    /// that it has been set
+    raw_event_filter: Mutex<(Option<RawEventFilter>, u64)>,
+    /// True if the server doesn't send releases for the auto-repeat of keys
+    detectable_auto_repeat: bool,
+    /// Latest value of the HiDPI factor, used to detect changes
+    hidpi_factor: Cell<f32>,
 }
 
 impl Window {
     pub fn new(display: &Arc<XConnection>, window_attrs: &WindowAttributes,
-               pf_reqs: &PixelFormatRequirements, opengl: &GlAttributes<&Window>)
+               pf_reqs: &PixelFormatRequirements, opengl: &GlAttributes<&Window>,
+               pl_attribs: &PlatformSpecificWindowBuilderAttributes)
                -> Result<Window, CreationError>
     {
+        let monitor = match window_attrs.monitor {
+            Some(PlatformMonitorId::X(ref monitor)) => Some(monitor),
+            _ => None,
+        };
+
+        let screen_id = match monitor {
+            Some(monitor) => monitor.screen as i32,
+            None => unsafe { (display.xlib.XDefaultScreen)(display.display) },
+        };
+
+        // the dimensions and the position are in points, like the ones that the window accepts
+        // and returns later, while the server works with pixels
+        let hidpi_factor = dpi::get_hidpi_factor(display, screen_id);
+        let to_pixels = |(w, h): (u32, u32)| {
+            ((w as f32 * hidpi_factor) as u32, (h as f32 * hidpi_factor) as u32)
+        };
+        let min_dimensions = window_attrs.min_dimensions.map(&to_pixels);
+        let max_dimensions = window_attrs.max_dimensions.map(&to_pixels);
+        let initial_position = window_attrs.initial_position.map(|(x, y)| {
+            ((x as f32 * hidpi_factor) as i32, (y as f32 * hidpi_factor) as i32)
+        });
+
         let dimensions = {
 
             // x11 only applies constraints when the window is actively resized
             // by the user, so we have to manually apply the initial constraints
-            let mut dimensions = window_attrs.dimensions.unwrap_or((800, 600));
-            if let Some(max) = window_attrs.max_dimensions {
+            let mut dimensions = to_pixels(window_attrs.dimensions.unwrap_or((800, 600)));
+            if let Some(max) = max_dimensions {
                 dimensions.0 = cmp::min(dimensions.0, max.0);
                 dimensions.1 = cmp::min(dimensions.1, max.1);
             }
 
-            if let Some(min) = window_attrs.min_dimensions {
+            if let Some(min) = min_dimensions {
                 dimensions.0 = cmp::max(dimensions.0, min.0);
                 dimensions.1 = cmp::max(dimensions.1, min.1);
             }
@@ -329,12 +367,8 @@ impl Window {
 
         };
 
-        let screen_id = match window_attrs.monitor {
-            Some(PlatformMonitorId::X(MonitorId(_, monitor))) => monitor as i32,
-            _ => unsafe { (display.xlib.XDefaultScreen)(display.display) },
-        };
-
-        // finding the mode to switch to if necessary
+        // finding the mode to switch to if necessary; the monitors of a Xinerama screen share its
+        // video mode, so it is only switched if the requested mode isn't the current one
         let (mode_to_switch_to, xf86_desk_mode) = unsafe {
             let mut mode_num: libc::c_int = mem::uninitialized();
             let mut modes: *mut *mut ffi::XF86VidModeModeInfo = mem::uninitialized();
@@ -362,7 +396,15 @@ impl Window {
                     None
                 };
                 (display.xlib.XFree)(modes as *mut _);
-                (mode_to_switch_to, Some(xf86_desk_mode))
+
+                // the first mode is the current one
+                match mode_to_switch_to {
+                    Some(mode) if mode.hdisplay != xf86_desk_mode.hdisplay ||
+                                  mode.vdisplay != xf86_desk_mode.vdisplay => {
+                        (Some(mode), Some(xf86_desk_mode))
+                    },
+                    _ => (None, None),
+                }
             }
         };
 
@@ -373,21 +415,78 @@ impl Window {
         }
         let builder_clone_opengl_glx = opengl.clone().map_sharing(|_| unimplemented!());      // FIXME:
         let builder_clone_opengl_egl = opengl.clone().map_sharing(|_| unimplemented!());      // FIXME:
+        // transparent windows need a visual with an alpha channel, and the ones that have none
+        // are skipped so that we get a `NoAvailablePixelFormat` error instead of an opaque window
+        let egl_config_filter = |egl: &egl::ffi::egl::Egl, egl_display, config| {
+            if !window_attrs.transparent {
+                return true;
+            }
+
+            unsafe {
+                let mut visual_id = 0;
+                if egl.GetConfigAttrib(egl_display, config,
+                                       egl::ffi::egl::NATIVE_VISUAL_ID as egl::ffi::egl::types::EGLint,
+                                       &mut visual_id) == 0
+                {
+                    return false;
+                }
+
+                let mut template: ffi::XVisualInfo = mem::zeroed();
+                template.visualid = visual_id as ffi::VisualID;
+                let mut num_visuals = 0;
+                let vi = (display.xlib.XGetVisualInfo)(display.display, ffi::VisualIDMask,
+                                                       &mut template, &mut num_visuals);
+                display.ignore_error();
+                if vi.is_null() {
+                    return false;
+                }
+                let argb = num_visuals >= 1 && super::is_argb_visual(&*vi);
+                (display.xlib.XFree)(vi as *mut _);
+                argb
+            }
+        };
+        // the attributes of the OpenGL ES context that `GlThenGles` falls back to
+        let mut builder_opengles_egl = builder_clone_opengl_egl.clone();
+        if let GlRequest::GlThenGles { opengles_version, .. } = opengl.version {
+            builder_opengles_egl.version = GlRequest::Specific(Api::OpenGlEs, opengles_version);
+        }
+
         let context = match opengl.version {
+            // OpenGL is tried with GLX first, then OpenGL ES with EGL
+            GlRequest::GlThenGles { .. } if display.glx.is_some() => {
+                let glx = display.glx.as_ref().unwrap();
+                match GlxContext::new(glx.clone(), &display.xlib, pf_reqs, &builder_clone_opengl_glx, display.display, screen_id, window_attrs.transparent) {
+                    Ok(context) => Prototype::Glx(context),
+                    Err(glx_err) => {
+                        let egl = match display.egl {
+                            Some(ref egl) => egl,
+                            None => return Err(glx_err),
+                        };
+
+                        match EglContext::new_with_config_filter(egl.clone(), pf_reqs, &builder_opengles_egl, egl::NativeDisplay::X11(Some(display.display as *const _)), &egl_config_filter) {
+                            Ok(context) => Prototype::Egl(context),
+                            Err(egl_err) => {
+                                return Err(CreationError::CreationErrorPair(Box::new(glx_err),
+                                                                            Box::new(egl_err)));
+                            },
+                        }
+                    },
+                }
+            },
             GlRequest::Latest | GlRequest::Specific(Api::OpenGl, _) | GlRequest::GlThenGles { .. } => {
                 // GLX should be preferred over EGL, otherwise crashes may occur
                 // on X11 – issue #314
                 if let Some(ref glx) = display.glx {
-                    Prototype::Glx(try!(GlxContext::new(glx.clone(), &display.xlib, pf_reqs, &builder_clone_opengl_glx, display.display, screen_id)))
+                    Prototype::Glx(try!(GlxContext::new(glx.clone(), &display.xlib, pf_reqs, &builder_clone_opengl_glx, display.display, screen_id, window_attrs.transparent)))
                 } else if let Some(ref egl) = display.egl {
-                    Prototype::Egl(try!(EglContext::new(egl.clone(), pf_reqs, &builder_clone_opengl_egl, egl::NativeDisplay::X11(Some(display.display as *const _)))))
+                    Prototype::Egl(try!(EglContext::new_with_config_filter(egl.clone(), pf_reqs, &builder_clone_opengl_egl, egl::NativeDisplay::X11(Some(display.display as *const _)), &egl_config_filter)))
                 } else {
                     return Err(CreationError::NotSupported);
                 }
             },
             GlRequest::Specific(Api::OpenGlEs, _) => {
                 if let Some(ref egl) = display.egl {
-                    Prototype::Egl(try!(EglContext::new(egl.clone(), pf_reqs, &builder_clone_opengl_egl, egl::NativeDisplay::X11(Some(display.display as *const _)))))
+                    Prototype::Egl(try!(EglContext::new_with_config_filter(egl.clone(), pf_reqs, &builder_clone_opengl_egl, egl::NativeDisplay::X11(Some(display.display as *const _)), &egl_config_filter)))
                 } else {
                     return Err(CreationError::NotSupported);
                 }
@@ -408,7 +507,7 @@ impl Window {
                     let mut num_visuals = 0;
                     let vi = (display.xlib.XGetVisualInfo)(display.display, ffi::VisualIDMask,
                                                            &mut template, &mut num_visuals);
-                    display.check_errors().expect("Failed to call XGetVisualInfo");
+                    try!(display.check_errors());
                     assert!(!vi.is_null());
                     assert!(num_visuals == 1);
 
@@ -424,7 +523,7 @@ impl Window {
             Some(ref w) => w.window as ffi::Window,
             None => {
                 let parent = unsafe { (display.xlib.XDefaultRootWindow)(display.display) };
-                display.check_errors().expect("Failed to get root window");
+                try!(display.check_errors());
                 parent
             }
         };
@@ -434,7 +533,7 @@ impl Window {
             let cmap = (display.xlib.XCreateColormap)(display.display, parent,
                                                       visual_infos.visual as *mut _,
                                                       ffi::AllocNone);
-            display.check_errors().expect("Failed to call XCreateColormap");
+            try!(display.check_errors());
             cmap
         };
 
@@ -445,12 +544,12 @@ impl Window {
             swa.event_mask = ffi::ExposureMask | ffi::StructureNotifyMask |
                 ffi::VisibilityChangeMask | ffi::KeyPressMask | ffi::PointerMotionMask |
                 ffi::KeyReleaseMask | ffi::ButtonPressMask |
-                ffi::ButtonReleaseMask | ffi::KeymapStateMask;
+                ffi::ButtonReleaseMask | ffi::KeymapStateMask | ffi::FocusChangeMask;
             swa.border_pixel = 0;
             if window_attrs.transparent {
                 swa.background_pixel = 0;
             }
-            swa.override_redirect = 0;
+            swa.override_redirect = if pl_attribs.override_redirect { ffi::True } else { ffi::False };
             swa
         };
 
@@ -460,16 +559,168 @@ impl Window {
             window_attributes |= ffi::CWBackPixel;
         }
 
+        if pl_attribs.override_redirect {
+            window_attributes |= ffi::CWOverrideRedirect;
+        }
+
         // finally creating the window
         let window = unsafe {
-            let win = (display.xlib.XCreateWindow)(display.display, parent, 0, 0, dimensions.0 as libc::c_uint,
+            // window managers make a fullscreen window cover the monitor that it is on
+            let (x, y) = initial_position
+                .or_else(|| monitor.map(|monitor| monitor.get_position()))
+                .unwrap_or((0, 0));
+            let win = (display.xlib.XCreateWindow)(display.display, parent, x as libc::c_int,
+                y as libc::c_int, dimensions.0 as libc::c_uint,
                 dimensions.1 as libc::c_uint, 0, visual_infos.depth, ffi::InputOutput as libc::c_uint,
                 visual_infos.visual as *mut _, window_attributes,
                 &mut set_win_attr);
-            display.check_errors().expect("Failed to call XCreateWindow");
+            // errors are reported asynchronously, an invalid parent for example would only be
+            // noticed much later without a round-trip
+            try!(display.sync_and_check_errors());
             win
         };
 
+        // the events of the window are kept apart from the ones of the other windows from now on
+        display.register_window(window);
+
+        // the initial state has to be set before the window is mapped, the window manager
+        // ignores changes of these properties afterwards
+        if window_attrs.maximized && window_attrs.monitor.is_none() {
+            unsafe {
+                let state_atom = display.intern_atom("_NET_WM_STATE");
+                let mut maximized_atoms = [
+                    display.intern_atom("_NET_WM_STATE_MAXIMIZED_HORZ"),
+                    display.intern_atom("_NET_WM_STATE_MAXIMIZED_VERT"),
+                ];
+                (display.xlib.XChangeProperty)(display.display, window, state_atom, ffi::XA_ATOM,
+                    32, ffi::PropModeReplace, maximized_atoms.as_mut_ptr() as *mut libc::c_uchar,
+                    maximized_atoms.len() as libc::c_int);
+                try!(display.check_errors());
+            }
+        }
+
+        if window_attrs.minimized {
+            unsafe {
+                let hints = (display.xlib.XAllocWMHints)();
+                (*hints).flags = ffi::StateHint;
+                // `IconicState`, which x11-dl doesn't define
+                (*hints).initial_state = 3;
+                (display.xlib.XSetWMHints)(display.display, window, hints);
+                (display.xlib.XFree)(hints as *mut _);
+                try!(display.check_errors());
+            }
+        }
+
+        // the window type and the related hints have to be set before mapping the window as well
+        unsafe {
+            let type_atom = display.intern_atom(match pl_attribs.x11_window_type {
+                WindowType::Normal => "_NET_WM_WINDOW_TYPE_NORMAL",
+                WindowType::Dialog => "_NET_WM_WINDOW_TYPE_DIALOG",
+                WindowType::Splash => "_NET_WM_WINDOW_TYPE_SPLASH",
+                WindowType::Utility => "_NET_WM_WINDOW_TYPE_UTILITY",
+                WindowType::Dock => "_NET_WM_WINDOW_TYPE_DOCK",
+                WindowType::Toolbar => "_NET_WM_WINDOW_TYPE_TOOLBAR",
+                WindowType::Menu => "_NET_WM_WINDOW_TYPE_MENU",
+                WindowType::Notification => "_NET_WM_WINDOW_TYPE_NOTIFICATION",
+            });
+            (display.xlib.XChangeProperty)(display.display, window,
+                display.intern_atom("_NET_WM_WINDOW_TYPE"), ffi::XA_ATOM, 32, ffi::PropModeReplace,
+                &type_atom as *const ffi::Atom as *const libc::c_uchar, 1);
+            try!(display.check_errors());
+
+            if let Some(ref transient_for) = pl_attribs.x11_transient_for {
+                (display.xlib.XSetTransientForHint)(display.display, window,
+                                                    transient_for.window as ffi::Window);
+                try!(display.check_errors());
+            }
+
+            if let Some(strut) = pl_attribs.x11_strut {
+                // the strut covers the edges of the screen along the window
+                let (x, y) = initial_position.unwrap_or((0, 0));
+                let (x, y) = (x as c_long, y as c_long);
+                let (width, height) = (dimensions.0 as c_long, dimensions.1 as c_long);
+                let strut_partial: [c_long; 12] = [
+                    strut.left as c_long, strut.right as c_long,
+                    strut.top as c_long, strut.bottom as c_long,
+                    y, y + height - 1,      // left_start_y, left_end_y
+                    y, y + height - 1,      // right_start_y, right_end_y
+                    x, x + width - 1,       // top_start_x, top_end_x
+                    x, x + width - 1,       // bottom_start_x, bottom_end_x
+                ];
+
+                (display.xlib.XChangeProperty)(display.display, window,
+                    display.intern_atom("_NET_WM_STRUT_PARTIAL"), ffi::XA_CARDINAL, 32,
+                    ffi::PropModeReplace, strut_partial.as_ptr() as *const libc::c_uchar, 12);
+                // older window managers only know about `_NET_WM_STRUT`
+                (display.xlib.XChangeProperty)(display.display, window,
+                    display.intern_atom("_NET_WM_STRUT"), ffi::XA_CARDINAL, 32,
+                    ffi::PropModeReplace, strut_partial.as_ptr() as *const libc::c_uchar, 4);
+                try!(display.check_errors());
+            }
+        }
+
+        // like the protocols below, the decorations are only read by some window managers when
+        // the window is mapped
+        if !window_attrs.decorations && !pl_attribs.override_redirect {
+            try!(set_motif_decorations(display, window, false));
+        }
+
+        // the protocols must be set before mapping the window, otherwise the window manager may
+        // not take them into account
+        let wm_protocols = display.intern_atom("WM_PROTOCOLS");
+        let wm_delete_window = display.intern_atom("WM_DELETE_WINDOW");
+        let net_wm_ping = display.intern_atom("_NET_WM_PING");
+        let net_wm_sync_request = display.intern_atom("_NET_WM_SYNC_REQUEST");
+
+        // the window manager reads the counter to know when the window has been redrawn at the
+        // size it was given, so that it doesn't show the new frame with the old content
+        let sync_counter = match display.sync {
+            Some(ref sync) if !pl_attribs.override_redirect => unsafe {
+                let mut value = mem::zeroed();
+                (sync.XSyncIntToValue)(&mut value, 0);
+                let counter = (sync.XSyncCreateCounter)(display.display, value);
+                try!(display.check_errors());
+
+                (display.xlib.XChangeProperty)(display.display, window,
+                    display.intern_atom("_NET_WM_SYNC_REQUEST_COUNTER"), ffi::XA_CARDINAL, 32,
+                    ffi::PropModeReplace, &counter as *const ffi::XSyncCounter as *const libc::c_uchar,
+                    1);
+                try!(display.check_errors());
+                Some(counter)
+            },
+            _ => None,
+        };
+
+        unsafe {
+            // the protocols are only used by window managers, which ignore override-redirect
+            // windows
+            if !pl_attribs.override_redirect {
+                let mut protocols = vec![wm_delete_window, net_wm_ping];
+                if sync_counter.is_some() {
+                    protocols.push(net_wm_sync_request);
+                }
+                (display.xlib.XSetWMProtocols)(display.display, window, protocols.as_mut_ptr(),
+                                               protocols.len() as libc::c_int);
+                try!(display.check_errors());
+            }
+
+            // the window manager uses these to kill the process if it stops answering the pings
+            let pid = libc::getpid() as c_long;
+            (display.xlib.XChangeProperty)(display.display, window,
+                display.intern_atom("_NET_WM_PID"), ffi::XA_CARDINAL, 32, ffi::PropModeReplace,
+                &pid as *const c_long as *const libc::c_uchar, 1);
+            try!(display.check_errors());
+
+            let mut hostname = [0u8; 256];
+            if libc::gethostname(hostname.as_mut_ptr() as *mut libc::c_char, hostname.len()) == 0 {
+                let len = hostname.iter().position(|&c| c == 0).unwrap_or(hostname.len());
+                (display.xlib.XChangeProperty)(display.display, window, ffi::XA_WM_CLIENT_MACHINE,
+                    ffi::XA_STRING, 8, ffi::PropModeReplace, hostname.as_ptr(),
+                    len as libc::c_int);
+                try!(display.check_errors());
+            }
+        }
+
         // set visibility
         if window_attrs.visible {
             unsafe {
@@ -477,23 +728,9 @@ impl Window {
                 (display.xlib.XFlush)(display.display);
             }
 
-            display.check_errors().expect("Failed to set window visibility");
+            try!(display.check_errors());
         }
 
-        // creating window, step 2
-        let wm_delete_window = unsafe {
-            let mut wm_delete_window = with_c_str("WM_DELETE_WINDOW", |delete_window|
-                (display.xlib.XInternAtom)(display.display, delete_window, 0)
-            );
-            display.check_errors().expect("Failed to call XInternAtom");
-            (display.xlib.XSetWMProtocols)(display.display, window, &mut wm_delete_window, 1);
-            display.check_errors().expect("Failed to call XSetWMProtocols");
-            (display.xlib.XFlush)(display.display);
-            display.check_errors().expect("Failed to call XFlush");
-
-            wm_delete_window
-        };
-
         // creating IM
         let im = unsafe {
             let _lock = GLOBAL_XOPENIM_LOCK.lock().unwrap();
@@ -506,60 +743,67 @@ impl Window {
         };
 
         // creating input context
+        //
+        // the over-the-spot style shows the characters being composed at the position of
+        // `set_ime_position`, but not all input methods support it
         let ic = unsafe {
-            let ic = with_c_str("inputStyle", |input_style|
+            let create_ic = |style: libc::c_int| with_c_str("inputStyle", |input_style|
                 with_c_str("clientWindow", |client_window|
                     (display.xlib.XCreateIC)(
-                        im, input_style,
-                        ffi::XIMPreeditNothing | ffi::XIMStatusNothing, client_window,
-                        window, ptr::null::<()>()
+                        im, input_style, style, client_window, window, ptr::null::<()>()
                     )
                 )
             );
+            let mut ic = create_ic(ffi::XIMPreeditPosition | ffi::XIMStatusNothing);
+            if ic.is_null() {
+                ic = create_ic(ffi::XIMPreeditNothing | ffi::XIMStatusNothing);
+            }
             if ic.is_null() {
                 return Err(OsError(format!("XCreateIC failed")));
             }
             (display.xlib.XSetICFocus)(ic);
-            display.check_errors().expect("Failed to call XSetICFocus");
+            try!(display.check_errors());
             ic
         };
 
-        // Attempt to make keyboard input repeat detectable
-        unsafe {
-            let mut supported_ptr = ffi::False;
-            (display.xlib.XkbSetDetectableAutoRepeat)(display.display, ffi::True, &mut supported_ptr);
-            if supported_ptr == ffi::False {
-                return Err(OsError(format!("XkbSetDetectableAutoRepeat failed")));
-            }
-        }
+        // Attempt to make keyboard input repeat detectable, so that the auto-repeat doesn't
+        // generate releases; otherwise they are filtered out in `poll_events`
+        let detectable_auto_repeat = unsafe {
+            let mut supported = ffi::False;
+            (display.xlib.XkbSetDetectableAutoRepeat)(display.display, ffi::True, &mut supported);
+            supported != ffi::False
+        };
 
         // Set ICCCM WM_CLASS property based on initial window title
         unsafe {
-            with_c_str(&*window_attrs.title, |c_name| {
+            try!(with_c_str(&*window_attrs.title, |c_name| {
                 let hint = (display.xlib.XAllocClassHint)();
                 (*hint).res_name = c_name as *mut libc::c_char;
                 (*hint).res_class = c_name as *mut libc::c_char;
                 (display.xlib.XSetClassHint)(display.display, window, hint);
-                display.check_errors().expect("Failed to call XSetClassHint");
                 (display.xlib.XFree)(hint as *mut _);
-            });
+                display.check_errors()
+            }));
         }
 
         let is_fullscreen = window_attrs.monitor.is_some();
 
+        // there is no window manager to send the fullscreen state or the size hints to if the
+        // window is override-redirect, in which case it keeps the position and dimensions it was
+        // created with
         if is_fullscreen {
             let state_atom = unsafe {
                 with_c_str("_NET_WM_STATE", |state|
                     (display.xlib.XInternAtom)(display.display, state, 0)
                 )
             };
-            display.check_errors().expect("Failed to call XInternAtom");
+            try!(display.check_errors());
             let fullscreen_atom = unsafe {
                 with_c_str("_NET_WM_STATE_FULLSCREEN", |state_fullscreen|
                     (display.xlib.XInternAtom)(display.display, state_fullscreen, 0)
                 )
             };
-            display.check_errors().expect("Failed to call XInternAtom");
+            try!(display.check_errors());
 
             let client_message_event = ffi::XClientMessageEvent {
                 type_: ffi::ClientMessage,
@@ -580,15 +824,28 @@ impl Window {
             };
             let mut x_event = ffi::XEvent::from(client_message_event);
 
-            unsafe {
-                (display.xlib.XSendEvent)(
-                    display.display,
-                    parent,
-                    0,
-                    ffi::SubstructureRedirectMask | ffi::SubstructureNotifyMask,
-                    &mut x_event as *mut _
-                );
-                display.check_errors().expect("Failed to call XSendEvent");
+            if !pl_attribs.override_redirect {
+                // the monitors to cover must be known when the window becomes fullscreen
+                let heads: Vec<XineramaHead> = monitor.into_iter()
+                    .chain(pl_attribs.x11_fullscreen_monitors.iter().map(|monitor| match monitor {
+                        &PlatformMonitorId::X(ref monitor) => monitor,
+                    }))
+                    .filter_map(|monitor| monitor.head)
+                    .collect();
+                if !heads.is_empty() {
+                    try!(set_fullscreen_monitors(display, parent, window, &heads));
+                }
+
+                unsafe {
+                    (display.xlib.XSendEvent)(
+                        display.display,
+                        parent,
+                        0,
+                        ffi::SubstructureRedirectMask | ffi::SubstructureNotifyMask,
+                        &mut x_event as *mut _
+                    );
+                    try!(display.check_errors());
+                }
             }
 
             if let Some(mut mode_to_switch_to) = mode_to_switch_to {
@@ -598,18 +855,13 @@ impl Window {
                         screen_id,
                         &mut mode_to_switch_to
                     );
-                    display.check_errors().expect("Failed to call XF86VidModeSwitchToMode");
+                    try!(display.check_errors());
+                    (display.xf86vmode.XF86VidModeSetViewPort)(display.display, screen_id, 0, 0);
+                    try!(display.check_errors());
                 }
             }
-            else {
-                println!("[glutin] Unexpected state: `mode` is None creating fullscreen window");
-            }
-            unsafe {
-                (display.xf86vmode.XF86VidModeSetViewPort)(display.display, screen_id, 0, 0);
-                display.check_errors().expect("Failed to call XF86VidModeSetViewPort");
-            }
 
-        } else {
+        } else if !pl_attribs.override_redirect {
 
             // set size hints
             let mut size_hints: ffi::XSizeHints = unsafe { mem::zeroed() };
@@ -617,13 +869,26 @@ impl Window {
             size_hints.width = dimensions.0 as i32;
             size_hints.height = dimensions.1 as i32;
 
-            if let Some(dimensions) = window_attrs.min_dimensions {
+            // with `NorthWestGravity`, the window manager places the top-left hand corner of its
+            // frame at the position of the window, for `with_position` as well as `set_position`
+            size_hints.flags |= ffi::PWinGravity;
+            size_hints.win_gravity = ffi::NorthWestGravity;
+
+            if let Some((x, y)) = initial_position {
+                // `USPosition` tells the window manager to honor the position instead of placing
+                // the window itself
+                size_hints.flags |= ffi::USPosition;
+                size_hints.x = x as i32;
+                size_hints.y = y as i32;
+            }
+
+            if let Some(dimensions) = min_dimensions {
                 size_hints.flags |= ffi::PMinSize;
                 size_hints.min_width = dimensions.0 as i32;
                 size_hints.min_height = dimensions.1 as i32;
             }
 
-            if let Some(dimensions) = window_attrs.max_dimensions {
+            if let Some(dimensions) = max_dimensions {
                 size_hints.flags |= ffi::PMaxSize;
                 size_hints.max_width = dimensions.0 as i32;
                 size_hints.max_height = dimensions.1 as i32;
@@ -631,28 +896,35 @@ impl Window {
 
             unsafe {
                 (display.xlib.XSetNormalHints)(display.display, window, &mut size_hints);
-                display.check_errors().expect("Failed to call XSetNormalHints");
+                try!(display.check_errors());
             }
 
         }
 
+        let glx_fbconfig_id = match context {
+            Prototype::Glx(ref p) => Some(p.get_fbconfig_id()),
+            Prototype::Egl(_) => None,
+        };
+
         // finish creating the OpenGL context
         let context = match context {
-            Prototype::Glx(ctxt) => {
-                Context::Glx(try!(ctxt.finish(window)))
-            },
-            Prototype::Egl(ctxt) => {
-                Context::Egl(try!(ctxt.finish(window as *const libc::c_void)))
-            },
+            Prototype::Glx(ctxt) => ctxt.finish(window).map(Context::Glx),
+            Prototype::Egl(ctxt) => ctxt.finish(window as *const libc::c_void).map(Context::Egl),
         };
 
-        // creating the OpenGL can produce errors, but since everything is checked we ignore
-        display.ignore_error();
+        // the X error, if any, is more detailed than the error of the context creation
+        let context = match (context, display.sync_and_check_errors()) {
+            (Ok(context), _) => context,
+            (Err(_), Err(x_error)) => return Err(x_error.into()),
+            (Err(err), Ok(())) => return Err(err),
+        };
 
         // creating the window object
         let window_proxy_data = WindowProxyData {
             display: display.clone(),
             window: window,
+            wm_protocols: wm_protocols,
+            wm_delete_window: wm_delete_window,
         };
         let window_proxy_data = Arc::new(Mutex::new(Some(window_proxy_data)));
 
@@ -668,18 +940,47 @@ impl Window {
                 xf86_desk_mode: xf86_desk_mode,
                 colormap: cmap,
                 window_proxy_data: window_proxy_data,
+                visual_infos: visual_infos,
+                glx_fbconfig_id: glx_fbconfig_id,
+                sync_counter: sync_counter,
             }),
             is_closed: AtomicBool::new(false),
+            decorations: AtomicBool::new(window_attrs.decorations),
+            wm_protocols: wm_protocols,
             wm_delete_window: wm_delete_window,
+            net_wm_ping: net_wm_ping,
+            net_wm_sync_request: net_wm_sync_request,
+            sync_request: Mutex::new(None),
             current_size: Cell::new((0, 0)),
+            current_position: Cell::new((0, 0)),
             pending_events: Mutex::new(VecDeque::new()),
             cursor_state: Mutex::new(CursorState::Normal),
-            input_handler: Mutex::new(XInputEventHandler::new(display, window, ic, window_attrs))
+            cursor_grab: Mutex::new(CursorGrabMode::None),
+            cursor_confinement: Mutex::new(None),
+            pointer_grabbed: AtomicBool::new(false),
+            cursor_position_before_lock: Cell::new(None),
+            input_handler: Mutex::new(try!(XInputEventHandler::new(display, window, ic,
+                                                                   window_attrs))),
+            raw_event_filter: Mutex::new((None, 0)),
+            detectable_auto_repeat: detectable_auto_repeat,
+            hidpi_factor: Cell::new(hidpi_factor),
         };
 
+        // watching the properties the HiDPI factor is computed from
+        unsafe {
+            let root = (display.xlib.XRootWindow)(display.display, screen_id);
+            (display.xlib.XSelectInput)(display.display, root, ffi::PropertyChangeMask);
+            if let Some(xsettings) = dpi::get_xsettings_window(display, screen_id) {
+                (display.xlib.XSelectInput)(display.display, xsettings, ffi::PropertyChangeMask);
+            }
+            display.ignore_error();
+        }
+
         window.set_title(&window_attrs.title);
 
-        if window_attrs.visible {
+        // override-redirect windows are not given the focus, which is up to the application, and
+        // minimized windows are not viewable so they can't be given the focus either
+        if window_attrs.visible && !window_attrs.minimized && !pl_attribs.override_redirect {
             unsafe {
                 let ref x_window: &XWindow = window.x.borrow();
 
@@ -688,7 +989,7 @@ impl Window {
                 loop {
                     let mut window_attributes = mem::uninitialized();
                     (display.xlib.XGetWindowAttributes)(display.display, x_window.window, &mut window_attributes);
-                    display.check_errors().expect("Failed to call XGetWindowAttributes");
+                    try!(display.check_errors());
 
                     if window_attributes.map_state == ffi::IsViewable {
                         (display.xlib.XSetInputFocus)(
@@ -697,7 +998,7 @@ impl Window {
                             ffi::RevertToParent,
                             ffi::CurrentTime
                         );
-                        display.check_errors().expect("Failed to call XSetInputFocus");
+                        try!(display.check_errors());
                         break;
                     }
 
@@ -800,6 +1101,10 @@ impl Window {
     }
 
     pub fn hide(&self) {
+        // the server releases the grab by itself once the window is unmapped, but we don't want
+        // the pointer to stay frozen until then
+        self.ungrab_pointer();
+
         unsafe {
             (self.x.display.xlib.XUnmapWindow)(self.x.display.display, self.x.window);
             (self.x.display.xlib.XFlush)(self.x.display.display);
@@ -830,260 +1135,1301 @@ impl Window {
         }
     }
 
-    #[inline]
-    pub fn get_position(&self) -> Option<(i32, i32)> {
-        self.get_geometry().map(|(x, y, _, _, _)| (x, y))
-    }
-
-    pub fn set_position(&self, x: i32, y: i32) {
-        unsafe { (self.x.display.xlib.XMoveWindow)(self.x.display.display, self.x.window, x as libc::c_int, y as libc::c_int); }
-        self.x.display.check_errors().expect("Failed to call XMoveWindow");
-    }
-
-    #[inline]
-    pub fn get_inner_size(&self) -> Option<(u32, u32)> {
-        self.get_geometry().map(|(_, _, w, h, _)| (w, h))
-    }
-
-    #[inline]
-    pub fn get_outer_size(&self) -> Option<(u32, u32)> {
-        self.get_geometry().map(|(_, _, w, h, b)| (w + b, h + b))       // TODO: is this really outside?
-    }
+    /// Returns the position of the top-left hand corner of the client area relative to the root
+    /// window.
+    fn get_inner_position(&self) -> Option<(i32, i32)> {
+        unsafe {
+            let root = (self.x.display.xlib.XDefaultRootWindow)(self.x.display.display);
+            let mut x: libc::c_int = 0;
+            let mut y: libc::c_int = 0;
+            let mut child: ffi::Window = 0;
 
-    #[inline]
-    pub fn set_inner_size(&self, x: u32, y: u32) {
-        unsafe { (self.x.display.xlib.XResizeWindow)(self.x.display.display, self.x.window, x as libc::c_uint, y as libc::c_uint); }
-        self.x.display.check_errors().expect("Failed to call XResizeWindow");
-    }
+            if (self.x.display.xlib.XTranslateCoordinates)(self.x.display.display, self.x.window,
+                root, 0, 0, &mut x, &mut y, &mut child) == 0
+            {
+                return None;
+            }
 
-    #[inline]
-    pub fn create_window_proxy(&self) -> WindowProxy {
-        WindowProxy {
-            data: self.x.window_proxy_data.clone()
+            Some((x as i32, y as i32))
         }
     }
 
-    #[inline]
-    pub fn poll_events(&self) -> PollEventsIterator {
-        PollEventsIterator {
-            window: self
+    /// Returns the size of the decorations added by the window manager, as
+    /// `(left, right, top, bottom)`.
+    ///
+    /// Uses `_NET_FRAME_EXTENTS` if the window manager sets it, otherwise looks for the frame
+    /// window the client was reparented into.
+    fn get_frame_extents(&self) -> (u32, u32, u32, u32) {
+        if let Some(extents) = self.get_net_frame_extents() {
+            return extents;
         }
-    }
 
-    #[inline]
-    pub fn wait_events(&self) -> WaitEventsIterator {
-        WaitEventsIterator {
-            window: self
-        }
-    }
+        let frame = match self.get_frame_window() {
+            Some(frame) if frame != self.x.window => frame,
+            _ => return (0, 0, 0, 0),
+        };
 
-    #[inline]
-    pub fn get_xlib_display(&self) -> *mut libc::c_void {
-        self.x.display.display as *mut libc::c_void
-    }
+        let frame_geometry = unsafe {
+            let mut root: ffi::Window = mem::uninitialized();
+            let mut x: libc::c_int = mem::uninitialized();
+            let mut y: libc::c_int = mem::uninitialized();
+            let mut width: libc::c_uint = mem::uninitialized();
+            let mut height: libc::c_uint = mem::uninitialized();
+            let mut border: libc::c_uint = mem::uninitialized();
+            let mut depth: libc::c_uint = mem::uninitialized();
 
-    #[inline]
-    pub fn platform_display(&self) -> *mut libc::c_void {
-        self.x.display.display as *mut libc::c_void
-    }
+            if (self.x.display.xlib.XGetGeometry)(self.x.display.display, frame,
+                &mut root, &mut x, &mut y, &mut width, &mut height,
+                &mut border, &mut depth) == 0
+            {
+                return (0, 0, 0, 0);
+            }
 
-    #[inline]
-    pub fn get_xlib_window(&self) -> *mut libc::c_void {
-        self.x.window as *mut libc::c_void
-    }
+            (x as i32, y as i32, width as i32, height as i32)
+        };
 
-    #[inline]
-    pub fn platform_window(&self) -> *mut libc::c_void {
-        self.x.window as *mut libc::c_void
-    }
+        let (inner_x, inner_y) = match self.get_inner_position() {
+            Some(p) => p,
+            None => return (0, 0, 0, 0),
+        };
+        let (inner_w, inner_h) = match self.get_inner_size_pixels() {
+            Some(s) => (s.0 as i32, s.1 as i32),
+            None => return (0, 0, 0, 0),
+        };
 
-    #[inline]
-    pub fn set_window_resize_callback(&mut self, _: Option<fn(u32, u32)>) {
+        let left = inner_x - frame_geometry.0;
+        let top = inner_y - frame_geometry.1;
+        let right = frame_geometry.2 - inner_w - left;
+        let bottom = frame_geometry.3 - inner_h - top;
+
+        (left.max(0) as u32, right.max(0) as u32, top.max(0) as u32, bottom.max(0) as u32)
     }
 
-    pub fn set_cursor(&self, cursor: MouseCursor) {
+    /// Reads the `_NET_FRAME_EXTENTS` property of the window.
+    fn get_net_frame_extents(&self) -> Option<(u32, u32, u32, u32)> {
         unsafe {
-            let load = |name: &str| {
-                self.load_cursor(name)
-            };
+            let extents_atom = (self.x.display.xlib.XInternAtom)(self.x.display.display,
+                b"_NET_FRAME_EXTENTS\0".as_ptr() as *const _, 1);
+            self.x.display.check_errors().expect("Failed to call XInternAtom");
+            if extents_atom == 0 {
+                return None;
+            }
 
-            let loadn = |names: &[&str]| {
-                self.load_first_existing_cursor(names)
-            };
+            let mut actual_type: ffi::Atom = 0;
+            let mut actual_format: libc::c_int = 0;
+            let mut nitems: libc::c_ulong = 0;
+            let mut bytes_after: libc::c_ulong = 0;
+            let mut data: *mut libc::c_uchar = ptr::null_mut();
+
+            let result = (self.x.display.xlib.XGetWindowProperty)(self.x.display.display,
+                self.x.window, extents_atom, 0, 4, 0, ffi::XA_CARDINAL, &mut actual_type,
+                &mut actual_format, &mut nitems, &mut bytes_after, &mut data);
+            self.x.display.ignore_error();
 
-            // Try multiple names in some cases where the name
-            // differs on the desktop environments or themes.
-            //
-            // Try the better looking (or more suiting) names first.
-            let mut xcursor = match cursor {
-                MouseCursor::Alias => load("link"),
-                MouseCursor::Arrow => load("arrow"),
-                MouseCursor::Cell => load("plus"),
-                MouseCursor::Copy => load("copy"),
-                MouseCursor::Crosshair => load("crosshair"),
-                MouseCursor::Default => load("left_ptr"),
-                MouseCursor::Hand => load("hand1"),
-                MouseCursor::Help => load("question_arrow"),
-                MouseCursor::Move => load("move"),
-                MouseCursor::Grab => loadn(&["openhand", "grab"]),
-                MouseCursor::Grabbing => loadn(&["closedhand", "grabbing"]),
-                MouseCursor::Progress => load("left_ptr_watch"),
-                MouseCursor::AllScroll => load("all-scroll"),
-                MouseCursor::ContextMenu => load("context-menu"),
-
-                MouseCursor::NoDrop => loadn(&["no-drop", "circle"]),
-                MouseCursor::NotAllowed => load("crossed_circle"),
-
-
-                /// Resize cursors
-                MouseCursor::EResize => load("right_side"),
-                MouseCursor::NResize => load("top_side"),
-                MouseCursor::NeResize => load("top_right_corner"),
-                MouseCursor::NwResize => load("top_left_corner"),
-                MouseCursor::SResize => load("bottom_side"),
-                MouseCursor::SeResize => load("bottom_right_corner"),
-                MouseCursor::SwResize => load("bottom_left_corner"),
-                MouseCursor::WResize => load("left_side"),
-                MouseCursor::EwResize => load("h_double_arrow"),
-                MouseCursor::NsResize => load("v_double_arrow"),
-                MouseCursor::NwseResize => loadn(&["bd_double_arrow", "size_bdiag"]),
-                MouseCursor::NeswResize => loadn(&["fd_double_arrow", "size_fdiag"]),
-                MouseCursor::ColResize => loadn(&["split_h", "h_double_arrow"]),
-                MouseCursor::RowResize => loadn(&["split_v", "v_double_arrow"]),
-
-                MouseCursor::Text => loadn(&["text", "xterm"]),
-                MouseCursor::VerticalText => load("vertical-text"),
-
-                MouseCursor::Wait => load("watch"),
-
-                MouseCursor::ZoomIn => load("zoom-in"),
-                MouseCursor::ZoomOut => load("zoom-out"),
-
-                MouseCursor::NoneCursor => self.create_empty_cursor(),
+            let extents = if result == ffi::Success as libc::c_int && !data.is_null() &&
+                             actual_type == ffi::XA_CARDINAL && actual_format == 32 && nitems == 4
+            {
+                // format 32 properties are returned as an array of `c_long`
+                let data = data as *const libc::c_long;
+                Some((*data.offset(0) as u32, *data.offset(1) as u32,
+                      *data.offset(2) as u32, *data.offset(3) as u32))
+            } else {
+                None
             };
 
-            (self.x.display.xlib.XDefineCursor)(self.x.display.display, self.x.window, xcursor);
-            if xcursor != 0 {
-                (self.x.display.xlib.XFreeCursor)(self.x.display.display, xcursor);
+            if !data.is_null() {
+                (self.x.display.xlib.XFree)(data as *mut _);
             }
-            self.x.display.check_errors().expect("Failed to set or free the cursor");
+
+            extents
         }
     }
 
-    fn load_cursor(&self, name: &str) -> ffi::Cursor {
-        use std::ffi::CString;
+    /// Returns the ancestor of the window that is a direct child of the root window. This is the
+    /// frame window with reparenting window managers, or the window itself otherwise.
+    fn get_frame_window(&self) -> Option<ffi::Window> {
         unsafe {
-            let c_string = CString::new(name.as_bytes()).unwrap();
-            (self.x.display.xcursor.XcursorLibraryLoadCursor)(self.x.display.display, c_string.as_ptr())
-        }
-    }
+            let mut current = self.x.window;
+
+            loop {
+                let mut root: ffi::Window = 0;
+                let mut parent: ffi::Window = 0;
+                let mut children: *mut ffi::Window = ptr::null_mut();
+                let mut nchildren: libc::c_uint = 0;
+
+                if (self.x.display.xlib.XQueryTree)(self.x.display.display, current, &mut root,
+                    &mut parent, &mut children, &mut nchildren) == 0
+                {
+                    return None;
+                }
 
-    fn load_first_existing_cursor(&self, names :&[&str]) -> ffi::Cursor {
-        for name in names.iter() {
-            let xcursor = self.load_cursor(name);
-            if xcursor != 0 {
-                return xcursor;
+                if !children.is_null() {
+                    (self.x.display.xlib.XFree)(children as *mut _);
+                }
+
+                if parent == root || parent == 0 {
+                    return Some(current);
+                }
+
+                current = parent;
             }
         }
-        0
     }
 
-    // TODO: This could maybe be cached. I don't think it's worth
-    // the complexity, since cursor changes are not so common,
-    // and this is just allocating a 1x1 pixmap...
-    fn create_empty_cursor(&self) -> ffi::Cursor {
-        use std::mem;
+    /// Returns the position of the top-left hand corner of the window manager's frame around the
+    /// window, so that `set_position(get_position())` doesn't move the window.
+    /// Returns the position of the frame in pixels.
+    fn get_position_pixels(&self) -> Option<(i32, i32)> {
+        let (left, _, top, _) = self.get_frame_extents();
+        self.get_inner_position().map(|(x, y)| (x - left as i32, y - top as i32))
+    }
 
-        let data = 0;
-        unsafe {
-            let pixmap = (self.x.display.xlib.XCreateBitmapFromData)(self.x.display.display, self.x.window, &data, 1, 1);
-            if pixmap == 0 {
-                // Failed to allocate
-                return 0;
-            }
+    /// Returns the position of the frame in points, like the other platforms.
+    pub fn get_position(&self) -> Option<(i32, i32)> {
+        let factor = self.hidpi_factor();
+        self.get_position_pixels()
+            .map(|(x, y)| ((x as f32 / factor) as i32, (y as f32 / factor) as i32))
+    }
 
-            // We don't care about this color, since it only fills bytes
-            // in the pixmap which are not 0 in the mask.
-            let dummy_color: ffi::XColor = mem::uninitialized();
-            let cursor = (self.x.display.xlib.XCreatePixmapCursor)(self.x.display.display,
-                                                                   pixmap,
-                                                                   pixmap,
-                                                                   &dummy_color as *const _ as *mut _,
-                                                                   &dummy_color as *const _ as *mut _, 0, 0);
-            (self.x.display.xlib.XFreePixmap)(self.x.display.display, pixmap);
-            cursor
-        }
+    #[inline]
+    pub fn get_outer_position(&self) -> Option<(i32, i32)> {
+        self.get_position()
     }
 
-    pub fn set_cursor_state(&self, state: CursorState) -> Result<(), String> {
-        use CursorState::{ Grab, Normal, Hide };
+    pub fn set_position(&self, x: i32, y: i32) {
+        let factor = self.hidpi_factor();
+        self.set_position_pixels((x as f32 * factor) as i32, (y as f32 * factor) as i32);
+    }
 
-        let mut cursor_state = self.cursor_state.lock().unwrap();
-        match (state, *cursor_state) {
-            (Normal, Normal) | (Hide, Hide) | (Grab, Grab) => return Ok(()),
-            _ => {},
+    fn set_position_pixels(&self, x: i32, y: i32) {
+        // the window is created with `NorthWestGravity`, which means that the window manager
+        // interprets the coordinates as the position of its frame and not of the client area
+        unsafe {
+            (self.x.display.xlib.XMoveWindow)(self.x.display.display, self.x.window, x as libc::c_int, y as libc::c_int);
+            (self.x.display.xlib.XFlush)(self.x.display.display);
         }
+        self.x.display.check_errors().expect("Failed to call XMoveWindow");
+    }
+
+    /// Returns the size of the client area in pixels.
+    #[inline]
+    fn get_inner_size_pixels(&self) -> Option<(u32, u32)> {
+        self.get_geometry().map(|(_, _, w, h, _)| (w, h))
+    }
+
+    /// Returns the size of the client area in points, like the other platforms.
+    #[inline]
+    pub fn get_inner_size(&self) -> Option<(u32, u32)> {
+        let factor = self.hidpi_factor();
+        self.get_inner_size_pixels()
+            .map(|(w, h)| ((w as f32 / factor) as u32, (h as f32 / factor) as u32))
+    }
+
+    /// Returns the size of the frame in points, like the other platforms.
+    #[inline]
+    pub fn get_outer_size(&self) -> Option<(u32, u32)> {
+        let (left, right, top, bottom) = self.get_frame_extents();
+        let factor = self.hidpi_factor();
+        self.get_geometry().map(|(_, _, w, h, b)| (w + 2 * b + left + right, h + 2 * b + top + bottom))
+            .map(|(w, h)| ((w as f32 / factor) as u32, (h as f32 / factor) as u32))
+    }
+
+    #[inline]
+    pub fn set_inner_size(&self, x: u32, y: u32) {
+        let factor = self.hidpi_factor();
+        let (x, y) = ((x as f32 * factor) as u32, (y as f32 * factor) as u32);
+        unsafe { (self.x.display.xlib.XResizeWindow)(self.x.display.display, self.x.window, x as libc::c_uint, y as libc::c_uint); }
+        self.x.display.check_errors().expect("Failed to call XResizeWindow");
+    }
+
+    #[inline]
+    pub fn request_inner_size(&self, x: u32, y: u32) -> Option<(u32, u32)> {
+        // the window manager may refuse or adjust the size, which we only know once we receive
+        // the `ConfigureNotify` event
+        self.set_inner_size(x, y);
+        None
+    }
+
+    /// See the docs in the crate root file.
+    pub fn set_min_dimensions(&self, dimensions: Option<(u32, u32)>) {
+        let factor = self.hidpi_factor();
+        self.update_normal_hints(|hints| {
+            match dimensions {
+                Some((width, height)) => {
+                    hints.flags |= ffi::PMinSize;
+                    hints.min_width = (width as f32 * factor) as libc::c_int;
+                    hints.min_height = (height as f32 * factor) as libc::c_int;
+                },
+                None => hints.flags &= !ffi::PMinSize,
+            }
+        });
+    }
+
+    /// See the docs in the crate root file.
+    pub fn set_max_dimensions(&self, dimensions: Option<(u32, u32)>) {
+        let factor = self.hidpi_factor();
+        self.update_normal_hints(|hints| {
+            match dimensions {
+                Some((width, height)) => {
+                    hints.flags |= ffi::PMaxSize;
+                    hints.max_width = (width as f32 * factor) as libc::c_int;
+                    hints.max_height = (height as f32 * factor) as libc::c_int;
+                },
+                None => hints.flags &= !ffi::PMaxSize,
+            }
+        });
+    }
+
+    /// See the docs in the crate root file.
+    pub fn set_aspect_ratio(&self, ratio: Option<(u32, u32)>) {
+        self.update_normal_hints(|hints| {
+            match ratio {
+                Some((x, y)) if x != 0 && y != 0 => {
+                    // the window manager keeps the ratio between these two
+                    hints.flags |= ffi::PAspect;
+                    hints.min_aspect = ffi::AspectRatio { x: x as libc::c_int, y: y as libc::c_int };
+                    hints.max_aspect = hints.min_aspect;
+                },
+                _ => hints.flags &= !ffi::PAspect,
+            }
+        });
+    }
+
+    /// See the docs in the crate root file.
+    ///
+    /// The compositing manager reads the opacity from the `_NET_WM_WINDOW_OPACITY` property,
+    /// which the window manager copies to the frame.
+    pub fn set_opacity(&self, opacity: f32) {
+        let display = &self.x.display;
+        let atom = display.intern_atom("_NET_WM_WINDOW_OPACITY");
+
+        unsafe {
+            if opacity >= 1.0 {
+                // without the property, the window is opaque and isn't blended by the compositor
+                (display.xlib.XDeleteProperty)(display.display, self.x.window, atom);
+            } else {
+                // a format of 32 bits is passed as longs
+                let value = (opacity as f64 * 0xffffffffu32 as f64) as u32 as libc::c_ulong;
+                (display.xlib.XChangeProperty)(display.display, self.x.window, atom,
+                    ffi::XA_CARDINAL, 32, ffi::PropModeReplace,
+                    &value as *const libc::c_ulong as *const libc::c_uchar, 1);
+            }
+            (display.xlib.XFlush)(display.display);
+            display.check_errors().expect("Failed to set the opacity of the window");
+        }
+    }
+
+    #[inline]
+    pub fn drag_window(&self) -> Result<(), ()> {
+        // `_NET_WM_MOVERESIZE_MOVE`
+        self.move_resize(8)
+    }
+
+    pub fn drag_resize_window(&self, edge: ResizeEdge) -> Result<(), ()> {
+        // the `_NET_WM_MOVERESIZE_SIZE_*` directions
+        self.move_resize(match edge {
+            ResizeEdge::TopLeft => 0,
+            ResizeEdge::Top => 1,
+            ResizeEdge::TopRight => 2,
+            ResizeEdge::Right => 3,
+            ResizeEdge::BottomRight => 4,
+            ResizeEdge::Bottom => 5,
+            ResizeEdge::BottomLeft => 6,
+            ResizeEdge::Left => 7,
+        })
+    }
+
+    /// Asks the window manager to move or resize the window with the pointer, in the given
+    /// direction of `_NET_WM_MOVERESIZE`.
+    fn move_resize(&self, direction: c_long) -> Result<(), ()> {
+        use std::sync::atomic::Ordering::Relaxed;
+
+        let display = &self.x.display;
+        if !display.supports_hint("_NET_WM_MOVERESIZE") {
+            return Err(());
+        }
+
+        unsafe {
+            let mut root = 0;
+            let mut child = 0;
+            let (mut root_x, mut root_y, mut window_x, mut window_y) = (0, 0, 0, 0);
+            let mut mask = 0;
+            (display.xlib.XQueryPointer)(display.display, self.x.window, &mut root, &mut child,
+                                         &mut root_x, &mut root_y, &mut window_x, &mut window_y,
+                                         &mut mask);
+            // the move ends when the button is released, so it must be pressed for it to start
+            if mask & ffi::Button1Mask == 0 {
+                return Err(());
+            }
+
+            // the window has the implicit grab of the pointer since the button was pressed, which
+            // must be released for the window manager to grab it; a grab of `set_cursor_grab` is
+            // restored the next time that the window gets the focus
+            (display.xlib.XUngrabPointer)(display.display, ffi::CurrentTime);
+            self.pointer_grabbed.store(false, Relaxed);
+
+            // with the left button, requested by a normal application; the window manager
+            // reports the positions and the sizes with `ConfigureNotify` events
+            let move_resize = display.intern_atom("_NET_WM_MOVERESIZE");
+            send_client_message(display, root, self.x.window, move_resize,
+                                [root_x as c_long, root_y as c_long, direction, 1, 1])
+                .map_err(|_| ())
+        }
+    }
+
+    pub fn focus_window(&self) {
+        let display = &self.x.display;
+
+        unsafe {
+            if display.supports_hint("_NET_ACTIVE_WINDOW") {
+                let root = (display.xlib.XDefaultRootWindow)(display.display);
+                let active_window = display.intern_atom("_NET_ACTIVE_WINDOW");
+                let net_wm_state = display.intern_atom("_NET_WM_STATE");
+                let demands_attention = display.intern_atom("_NET_WM_STATE_DEMANDS_ATTENTION");
+
+                // the request comes from a normal application, and the timestamp of the last
+                // user interaction isn't known
+                let start = display.window_event_count(self.x.window);
+                let _ = send_client_message(display, root, self.x.window, active_window,
+                                            [1, ffi::CurrentTime as c_long, 0, 0, 0]);
+                (display.xlib.XFlush)(display.display);
+
+                // the window manager may refuse to steal the focus, in which case the window
+                // still asks for the attention of the user; the state is removed once the window
+                // is activated
+                let mut focus: ffi::Window = 0;
+                let mut revert_to: libc::c_int = 0;
+                (display.xlib.XGetInputFocus)(display.display, &mut focus, &mut revert_to);
+                let focused = focus == self.x.window ||
+                    display.wait_for_window_event(self.x.window, start, Duration::from_millis(100),
+                                                  |xev| xev.get_type() == ffi::FocusIn);
+                if !focused {
+                    let _ = send_client_message(display, root, self.x.window, net_wm_state,
+                                                [1, demands_attention as c_long, 0, 1, 0]);
+                }
+            } else {
+                // without a window manager, nothing prevents the focus from being taken
+                (display.xlib.XRaiseWindow)(display.display, self.x.window);
+                (display.xlib.XSetInputFocus)(display.display, self.x.window,
+                                              ffi::RevertToParent, ffi::CurrentTime);
+            }
+
+            // `XSetInputFocus` fails if the window isn't viewable yet, in which case there is
+            // nothing to focus
+            let _ = display.check_errors();
+        }
+    }
+
+    /// See the docs in the crate root file.
+    ///
+    /// The X server lets any client read the content of the windows, so this is never honored.
+    #[inline]
+    pub fn set_content_protected(&self, _: bool) -> Result<(), ContentProtectionError> {
+        Err(ContentProtectionError::Unsupported)
+    }
+
+    /// See the docs in the crate root file.
+    ///
+    /// The pointer events go through the parts of a window outside of its input shape, which is
+    /// emptied to let all of them through, and reset to the whole window otherwise.
+    pub fn set_cursor_hittest(&self, hittest: bool) -> Result<(), ()> {
+        let display = &self.x.display;
+        let shape = match display.shape {
+            Some(ref shape) => shape,
+            None => return Err(()),
+        };
+
+        // the errors are reported asynchronously, so the requests must have been processed
+        // before checking for them
+        let serial = display.next_request_serial();
+        unsafe {
+            if hittest {
+                (shape.XShapeCombineMask)(display.display, self.x.window, ffi::SHAPE_INPUT, 0, 0,
+                                          0, ffi::SHAPE_SET);
+            } else {
+                (shape.XShapeCombineRectangles)(display.display, self.x.window, ffi::SHAPE_INPUT,
+                                                0, 0, ptr::null_mut(), 0, ffi::SHAPE_SET,
+                                                ffi::Unsorted);
+            }
+        }
+        display.sync_and_check_errors_since(serial).map_err(|_| ())
+    }
+
+    /// See the docs in the crate root file.
+    ///
+    /// Returns once the window manager has added or removed the frame, so that `get_outer_size`
+    /// and `get_position` take the change into account.
+    pub fn set_decorations(&self, decorations: bool) {
+        use std::sync::atomic::Ordering::Relaxed;
+
+        if self.decorations.swap(decorations, Relaxed) == decorations {
+            return;
+        }
+
+        let display = &self.x.display;
+        let window = self.x.window;
+
+        // only the events caused by the requests sent from now on tell whether the window manager
+        // applied the property; the events carry the serial of the last request the server
+        // processed, which also leaves out the events of the queue that are older
+        let start = display.window_event_count(window);
+        let serial = display.next_request_serial();
+        let since_serial = |xev: &ffi::XEvent, event_type| {
+            let any: ffi::XAnyEvent = From::from(*xev);
+            any.type_ == event_type && any.serial >= serial
+        };
+
+        set_motif_decorations(display, window, decorations)
+            .expect("Failed to set _MOTIF_WM_HINTS");
+        unsafe { (display.xlib.XFlush)(display.display) };
+
+        let map_state = unsafe {
+            let mut attributes = mem::uninitialized();
+            (display.xlib.XGetWindowAttributes)(display.display, window, &mut attributes);
+            attributes.map_state
+        };
+        display.check_errors().expect("Failed to call XGetWindowAttributes");
+
+        // the hints of an unmapped window are read when it is mapped
+        if map_state == ffi::IsUnmapped {
+            return;
+        }
+
+        // mutter, KWin and i3 watch the property and move the window inside its new frame
+        if display.wait_for_window_event(window, start, Duration::from_millis(500),
+                                         |xev| since_serial(xev, ffi::ConfigureNotify))
+        {
+            return;
+        }
+
+        // a window manager that applied the property without moving the window still updates
+        // the size of the frame
+        if let Some((left, right, top, bottom)) = self.get_net_frame_extents() {
+            if (left + right + top + bottom != 0) == decorations {
+                return;
+            }
+        }
+
+        // the other window managers ignore the property until the window is mapped, so the
+        // window is mapped again at the same place; the GL context belongs to the X window,
+        // which stays the same
+        let position = self.get_position_pixels();
+
+        self.ungrab_pointer();
+        let start = display.window_event_count(window);
+        unsafe {
+            (display.xlib.XUnmapWindow)(display.display, window);
+            (display.xlib.XFlush)(display.display);
+        }
+        display.check_errors().expect("Failed to call XUnmapWindow");
+        display.wait_for_window_event(window, start, Duration::from_millis(500),
+                                      |xev| since_serial(xev, ffi::UnmapNotify));
+
+        let start = display.window_event_count(window);
+        unsafe {
+            (display.xlib.XMapRaised)(display.display, window);
+            (display.xlib.XFlush)(display.display);
+        }
+        display.check_errors().expect("Failed to call XMapRaised");
+        display.wait_for_window_event(window, start, Duration::from_millis(500),
+                                      |xev| since_serial(xev, ffi::MapNotify));
+
+        if let Some((x, y)) = position {
+            self.set_position_pixels(x, y);
+        }
+    }
+
+    /// Returns true if the window manager is waiting for the window to be redrawn at its new size
+    /// before showing it, in which case `complete_resize` must be called once it is done.
+    #[inline]
+    pub fn is_resize_pending(&self) -> bool {
+        self.sync_request.lock().unwrap().is_some()
+    }
+
+    /// Tells the window manager that the window has been redrawn since the last resize, by
+    /// setting the counter of `_NET_WM_SYNC_REQUEST` to the value it asked for.
+    ///
+    /// Does nothing if no resize is pending.
+    pub fn complete_resize(&self) {
+        let value = match self.sync_request.lock().unwrap().take() {
+            Some(value) => value,
+            None => return,
+        };
+
+        if let (Some(ref sync), Some(counter)) = (self.x.display.sync.as_ref(), self.x.sync_counter) {
+            unsafe {
+                (sync.XSyncSetCounter)(self.x.display.display, counter, value);
+                (self.x.display.xlib.XFlush)(self.x.display.display);
+            }
+            self.x.display.check_errors().expect("Failed to call XSyncSetCounter");
+        }
+    }
+
+    /// Modifies the `WM_NORMAL_HINTS` of the window with `f`, keeping the hints that it doesn't
+    /// touch, then resizes the window if its current size doesn't respect the new hints.
+    ///
+    /// The window doesn't need to be unmapped, since the window manager watches the property.
+    fn update_normal_hints<F>(&self, f: F) where F: FnOnce(&mut ffi::XSizeHints) {
+        let xlib = &self.x.display.xlib;
+
+        let mut hints: ffi::XSizeHints = unsafe { mem::zeroed() };
+        unsafe {
+            let mut supplied = 0;
+            if (xlib.XGetWMNormalHints)(self.x.display.display, self.x.window, &mut hints,
+                                        &mut supplied) == 0
+            {
+                // the property doesn't exist yet
+                hints = mem::zeroed();
+            }
+        }
+
+        f(&mut hints);
 
-        match *cursor_state {
-            Grab => {
+        unsafe {
+            (xlib.XSetWMNormalHints)(self.x.display.display, self.x.window, &mut hints);
+        }
+        self.x.display.check_errors().expect("Failed to call XSetWMNormalHints");
+
+        // the window manager only applies the hints when the user resizes the window, so the
+        // current size is fixed by hand; the `ConfigureNotify` then produces a `Resized` event
+        if let Some((width, height)) = self.get_inner_size_pixels() {
+            let (new_width, new_height) = constrain_size(&hints, width, height);
+            if (new_width, new_height) != (width, height) {
                 unsafe {
-                    (self.x.display.xlib.XUngrabPointer)(self.x.display.display, ffi::CurrentTime);
-                    self.x.display.check_errors().expect("Failed to call XUngrabPointer");
+                    (xlib.XResizeWindow)(self.x.display.display, self.x.window,
+                                         new_width as libc::c_uint, new_height as libc::c_uint);
                 }
+                self.x.display.check_errors().expect("Failed to call XResizeWindow");
+            }
+        }
+    }
+
+    #[inline]
+    pub fn create_window_proxy(&self) -> WindowProxy {
+        WindowProxy {
+            data: self.x.window_proxy_data.clone()
+        }
+    }
+
+    #[inline]
+    pub fn poll_events(&self) -> PollEventsIterator {
+        PollEventsIterator {
+            window: self
+        }
+    }
+
+    #[inline]
+    pub fn wait_events(&self) -> WaitEventsIterator {
+        WaitEventsIterator {
+            window: self
+        }
+    }
+
+    /// See the docs of the crate root file.
+    pub fn drain_events(&self, events: &mut Vec<Event>) {
+        events.extend(self.pending_events.lock().unwrap().drain(..));
+
+        // the events of the window are all taken from the queue with one lock, then processed in
+        // the same order as `poll_events`
+        let mut queued = VecDeque::new();
+        self.x.display.drain_window_events(self.x.window, &mut queued);
+
+        while let Some(xev) = queued.pop_front() {
+            if let Some(event) = self.process_event(xev, &queued) {
+                events.push(event);
+            }
+            events.extend(self.pending_events.lock().unwrap().drain(..));
+        }
+    }
+
+    /// Takes the latest error reported by the X server, if any.
+    #[inline]
+    pub fn get_xlib_error(&self) -> Option<XError> {
+        self.x.display.check_errors().err()
+    }
+
+    #[inline]
+    pub fn get_xlib_display(&self) -> *mut libc::c_void {
+        self.x.display.display as *mut libc::c_void
+    }
+
+    #[inline]
+    pub fn get_event_fd(&self) -> libc::c_int {
+        unsafe { (self.x.display.xlib.XConnectionNumber)(self.x.display.display) }
+    }
+
+    #[inline]
+    pub fn get_xlib_xconnection(&self) -> Arc<XConnection> {
+        self.x.display.clone()
+    }
+
+    #[inline]
+    pub fn get_xlib_visual_info(&self) -> ffi::XVisualInfo {
+        self.x.visual_infos.clone()
+    }
+
+    #[inline]
+    pub fn get_glx_fbconfig_id(&self) -> Option<libc::c_int> {
+        self.x.glx_fbconfig_id
+    }
+
+    #[inline]
+    pub fn get_raw_context(&self) -> Option<RawContext> {
+        match self.x.context {
+            Context::Glx(ref ctxt) => Some(RawContext::from_glx(ctxt)),
+            Context::Egl(ref ctxt) => Some(RawContext::from_egl(ctxt)),
+            Context::None => None,
+        }
+    }
+
+    /// See the docs of the crate root file.
+    #[inline]
+    pub fn get_context_priority(&self) -> Option<ContextPriority> {
+        match self.x.context {
+            Context::Egl(ref ctxt) => ctxt.get_context_priority(),
+            _ => None,
+        }
+    }
+
+    /// See the docs of the crate root file.
+    #[inline]
+    pub fn get_robustness(&self) -> Option<Robustness> {
+        match self.x.context {
+            Context::Egl(ref ctxt) => Some(ctxt.get_robustness()),
+            _ => None,
+        }
+    }
+
+    /// See the docs of the crate root file.
+    ///
+    /// Only GLX contexts can be shared this way, since an EGL surface can only be current on one
+    /// thread at a time.
+    pub fn create_shared_context(&self, opengl: &GlAttributes<&Window>)
+                                 -> Result<SharedContext, CreationError>
+    {
+        let context = match self.x.context {
+            Context::Glx(ref ctxt) => ctxt,
+            _ => return Err(CreationError::NotSupported),
+        };
+
+        let share = match opengl.sharing.unwrap_or(self).x.context {
+            Context::Glx(ref ctxt) => ctxt,
+            _ => return Err(CreationError::NotSupported),
+        };
+        let mut opengl = opengl.clone().map_sharing(|_| share);
+        opengl.sharing = Some(share);
+
+        let context = try!(context.new_shared(&self.x.display.xlib, &opengl));
+        try!(self.x.display.check_errors());
+
+        Ok(SharedContext {
+            context: context,
+            display: self.x.display.clone(),
+        })
+    }
+
+    #[inline]
+    pub fn platform_display(&self) -> *mut libc::c_void {
+        self.x.display.display as *mut libc::c_void
+    }
+
+    #[inline]
+    pub fn get_xlib_window(&self) -> *mut libc::c_void {
+        self.x.window as *mut libc::c_void
+    }
+
+    #[inline]
+    pub fn platform_window(&self) -> *mut libc::c_void {
+        self.x.window as *mut libc::c_void
+    }
+
+    #[inline]
+    pub fn set_window_resize_callback(&mut self, _: Option<fn(u32, u32)>) {
+    }
+
+    pub fn set_cursor(&self, cursor: MouseCursor) {
+        unsafe {
+            let xcursor = self.x.display.get_cursor(cursor);
+            (self.x.display.xlib.XDefineCursor)(self.x.display.display, self.x.window, xcursor);
+            self.x.display.check_errors().expect("Failed to set the cursor");
+        }
+    }
+
+    /// See `os::unix::WindowExt::set_cursor_by_name`.
+    pub fn set_cursor_by_name(&self, name: &str) -> Result<(), ()> {
+        let xcursor = self.x.display.get_named_cursor(name);
+        if xcursor == 0 {
+            self.set_cursor(MouseCursor::Arrow);
+            return Err(());
+        }
+
+        unsafe {
+            (self.x.display.xlib.XDefineCursor)(self.x.display.display, self.x.window, xcursor);
+            self.x.display.check_errors().expect("Failed to set the cursor");
+        }
+        Ok(())
+    }
+
+    pub fn set_cursor_state(&self, state: CursorState) -> Result<(), CursorStateError> {
+        let mut cursor_state = self.cursor_state.lock().unwrap();
+
+        if cursor_state.is_hidden() && !state.is_hidden() {
+            // NB: Calling XDefineCursor with None (aka 0)
+            // as a value resets the cursor to the default.
+            unsafe {
+                (self.x.display.xlib.XDefineCursor)(self.x.display.display, self.x.window, 0);
+            }
+        } else if !cursor_state.is_hidden() && state.is_hidden() {
+            unsafe {
+                let cursor = self.x.display.get_cursor(MouseCursor::NoneCursor);
+                (self.x.display.xlib.XDefineCursor)(self.x.display.display, self.x.window, cursor);
+                self.x.display.check_errors().expect("Failed to call XDefineCursor");
+            }
+        }
+
+        if let Err(err) = self.set_cursor_grab(state.grab_mode()) {
+            // the visibility has already been changed, but the pointer isn't grabbed
+            *cursor_state = if state.is_hidden() { CursorState::Hide } else { CursorState::Normal };
+            return Err(err);
+        }
+
+        *cursor_state = state;
+        Ok(())
+    }
+
+    /// See the docs of the crate root file.
+    pub fn set_cursor_grab(&self, mode: CursorGrabMode) -> Result<(), CursorStateError> {
+        let mut cursor_grab = self.cursor_grab.lock().unwrap();
+        if *cursor_grab == mode {
+            return Ok(());
+        }
+
+        if mode == CursorGrabMode::None {
+            self.ungrab_pointer();
+        } else if *cursor_grab == CursorGrabMode::None {
+            try!(self.grab_pointer());
+        }
+
+        // a locked cursor is moved to the center of the window, so it is moved back to where it
+        // was once it is unlocked
+        if mode == CursorGrabMode::Locked {
+            self.cursor_position_before_lock.set(self.get_cursor_position());
+            let (x, y) = self.get_locked_cursor_position();
+            let _ = self.set_cursor_position(x, y);
+        } else if *cursor_grab == CursorGrabMode::Locked {
+            if let Some((x, y)) = self.cursor_position_before_lock.take() {
+                let _ = self.set_cursor_position(x as f64, y as f64);
+            }
+        }
+
+        *cursor_grab = mode;
+        Ok(())
+    }
+
+    /// Returns the position of the cursor in the window.
+    fn get_cursor_position(&self) -> Option<(i32, i32)> {
+        let display = &self.x.display;
+        unsafe {
+            let mut root = 0;
+            let mut child = 0;
+            let (mut root_x, mut root_y, mut window_x, mut window_y) = (0, 0, 0, 0);
+            let mut mask = 0;
+            // fails if the pointer is on another screen
+            let same_screen = (display.xlib.XQueryPointer)(display.display, self.x.window,
+                                                           &mut root, &mut child,
+                                                           &mut root_x, &mut root_y,
+                                                           &mut window_x, &mut window_y,
+                                                           &mut mask);
+            if same_screen == ffi::False {
+                return None;
+            }
+            Some((window_x, window_y))
+        }
+    }
+
+    /// Returns the position that a locked cursor is moved back to, the center of the window.
+    fn get_locked_cursor_position(&self) -> (f64, f64) {
+        let (width, height) = self.get_inner_size_pixels().unwrap_or((0, 0));
+        ((width / 2) as f64, (height / 2) as f64)
+    }
+
+    /// Grabs the pointer and confines it to the window.
+    ///
+    /// The grab fails if another client is grabbing the pointer, which is often only the case for
+    /// a short time (for example while the window manager is moving a window). We don't wait for
+    /// it here, since the lock of the grab mode is held: the user can try again, and a grab that
+    /// fails when the focus comes back is tried again at the next focus change.
+    fn grab_pointer(&self) -> Result<(), CursorStateError> {
+        use std::sync::atomic::Ordering::Relaxed;
+
+        let result = unsafe {
+            (self.x.display.xlib.XGrabPointer)(
+                self.x.display.display, self.x.window, ffi::True,
+                (ffi::ButtonPressMask | ffi::ButtonReleaseMask | ffi::EnterWindowMask |
+                ffi::LeaveWindowMask | ffi::PointerMotionMask | ffi::PointerMotionHintMask |
+                ffi::Button1MotionMask | ffi::Button2MotionMask | ffi::Button3MotionMask |
+                ffi::Button4MotionMask | ffi::Button5MotionMask | ffi::ButtonMotionMask |
+                ffi::KeymapStateMask) as libc::c_uint,
+                ffi::GrabModeAsync, ffi::GrabModeAsync,
+                self.x.window, 0, ffi::CurrentTime
+            )
+        };
+
+        match result {
+            ffi::GrabSuccess => {
+                self.pointer_grabbed.store(true, Relaxed);
+                Ok(())
             },
-            Normal => {},
-            Hide => {
-                // NB: Calling XDefineCursor with None (aka 0)
-                // as a value resets the cursor to the default.
-                unsafe {
-                    (self.x.display.xlib.XDefineCursor)(self.x.display.display, self.x.window, 0);
+            ffi::AlreadyGrabbed => Err(CursorStateError::AlreadyGrabbed),
+            ffi::GrabFrozen => Err(CursorStateError::Frozen),
+            ffi::GrabNotViewable => Err(CursorStateError::NotViewable),
+            ffi::GrabInvalidTime => {
+                Err(CursorStateError::OsError {
+                    code: Some(result),
+                    message: "XGrabPointer failed: invalid time".to_string(),
+                })
+            },
+            _ => {
+                self.x.display.ignore_error();
+                Err(CursorStateError::OsError {
+                    code: Some(result),
+                    message: "XGrabPointer failed".to_string(),
+                })
+            },
+        }
+    }
+
+    /// Releases the pointer if the window is grabbing it.
+    fn ungrab_pointer(&self) {
+        use std::sync::atomic::Ordering::Relaxed;
+
+        // the pointer must not be released if another window of the connection grabbed it
+        if !self.pointer_grabbed.swap(false, Relaxed) {
+            return;
+        }
+
+        // this is also called when the window is dropped, so the errors can't be reported, and
+        // `XUngrabPointer` doesn't fail anyway if the pointer isn't grabbed anymore
+        unsafe {
+            (self.x.display.xlib.XUngrabPointer)(self.x.display.display, ffi::CurrentTime);
+            (self.x.display.xlib.XFlush)(self.x.display.display);
+        }
+    }
+
+    /// Releases the pointer when the window loses the focus, and grabs it again when the window
+    /// gets the focus back, so that a grabbed pointer doesn't prevent the user from switching to
+    /// another window.
+    fn update_grab_on_focus(&self, focused: bool) {
+        let cursor_grab = self.cursor_grab.lock().unwrap();
+        if *cursor_grab == CursorGrabMode::None {
+            return;
+        }
+
+        if focused {
+            // there is no way to report the error from here, and the grab mode stays the same
+            // so that the next focus change tries again
+            let _ = self.grab_pointer();
+        } else {
+            self.ungrab_pointer();
+        }
+    }
+
+    /// See the docs of the crate root file.
+    #[inline]
+    pub fn set_ime_position(&self, x: i32, y: i32) {
+        self.input_handler.lock().unwrap().set_ime_position(x, y);
+    }
+
+    /// Returns the current group of the keyboard, which is the layout that the user selected.
+    fn keyboard_group(&self) -> libc::c_int {
+        let display = &self.x.display;
+        unsafe {
+            let mut state: ffi::XkbStateRec = mem::zeroed();
+            (display.xlib.XkbGetState)(display.display, ffi::XKB_USE_CORE_KBD, &mut state);
+            display.ignore_error();
+            state.group as libc::c_int
+        }
+    }
+
+    /// Returns the keysym of a key without modifiers in a group of the keyboard.
+    fn keysym(&self, scancode: ScanCode, group: libc::c_int) -> ffi::KeySym {
+        let display = &self.x.display;
+        unsafe { (display.xlib.XkbKeycodeToKeysym)(display.display, scancode, group, 0) }
+    }
+
+    /// See the docs of the crate root file.
+    pub fn scancode_to_keycode(&self, scancode: ScanCode) -> Option<VirtualKeyCode> {
+        match self.keysym(scancode, self.keyboard_group()) {
+            0 => None,
+            keysym => events::keycode_to_element(keysym as libc::c_uint),
+        }
+    }
+
+    /// See the docs of the crate root file.
+    pub fn keycode_to_scancode(&self, keycode: VirtualKeyCode) -> Option<ScanCode> {
+        // X keycodes start at 8
+        let group = self.keyboard_group();
+        (8u16 .. 256).map(|scancode| scancode as ScanCode).find(|&scancode| {
+            match self.keysym(scancode, group) {
+                0 => false,
+                keysym => events::keycode_to_element(keysym as libc::c_uint) == Some(keycode),
+            }
+        })
+    }
+
+    /// See the docs of the crate root file.
+    pub fn key_name(&self, scancode: ScanCode) -> Option<String> {
+        match self.keysym(scancode, self.keyboard_group()) {
+            0 => None,
+            keysym => unsafe {
+                let name = (self.x.display.xlib.XKeysymToString)(keysym);
+                if name.is_null() {
+                    None
+                } else {
+                    Some(CStr::from_ptr(name).to_string_lossy().into_owned())
                 }
             },
         }
+    }
 
-        *cursor_state = state;
-        match state {
-            Normal => Ok(()),
-            Hide => {
-                unsafe {
-                    let cursor = self.create_empty_cursor();
-                    (self.x.display.xlib.XDefineCursor)(self.x.display.display, self.x.window, cursor);
-                    if cursor != 0 {
-                        (self.x.display.xlib.XFreeCursor)(self.x.display.display, cursor);
+    /// See the docs of the crate root file.
+    #[inline]
+    pub fn set_raw_event_filter(&self, filter: Option<RawEventFilter>) {
+        let mut current = self.raw_event_filter.lock().unwrap();
+        current.0 = filter;
+        current.1 += 1;
+    }
+
+    /// Gives an event to the filter of `set_raw_event_filter`, and returns true if it consumed
+    /// the event.
+    fn filter_raw_event(&self, xev: &ffi::XEvent) -> bool {
+        // the filter is taken out while it runs, so that it can call the methods of the window
+        let (mut filter, generation) = {
+            let mut current = self.raw_event_filter.lock().unwrap();
+            match current.0.take() {
+                Some(filter) => (filter, current.1),
+                None => return false,
+            }
+        };
+
+        let consumed = filter(&RawEvent::Xlib(xev as *const ffi::XEvent as *const libc::c_void));
+
+        // unless the filter has been replaced or removed meanwhile
+        let mut current = self.raw_event_filter.lock().unwrap();
+        if current.1 == generation {
+            current.0 = Some(filter);
+        }
+        consumed
+    }
+
+    /// See the docs of the crate root file.
+    #[inline]
+    pub fn set_ime_allowed(&self, allowed: bool) {
+        self.input_handler.lock().unwrap().set_ime_allowed(allowed);
+    }
+
+    /// See the docs of the crate root file.
+    #[inline]
+    pub fn get_available_monitors(&self) -> VecDeque<MonitorId> {
+        monitor::get_available_monitors(&self.x.display)
+    }
+
+    /// See the docs of the crate root file.
+    ///
+    /// With Xinerama, the monitor is the one that has the largest intersection with the client
+    /// area of the window. Otherwise it's the monitor of the X screen of the window.
+    pub fn get_current_monitor(&self) -> Option<MonitorId> {
+        let monitors = monitor::get_available_monitors(&self.x.display);
+        let screen = self.x.screen_id as u32;
+
+        let (x, y) = self.get_inner_position().unwrap_or((0, 0));
+        let (width, height) = self.get_inner_size_pixels().unwrap_or((0, 0));
+        let intersection = |m: &MonitorId| -> i64 {
+            let head = match m.head {
+                Some(head) => head,
+                None => return 0,
+            };
+            let w = cmp::min(x + width as i32, head.x + head.width as i32) - cmp::max(x, head.x);
+            let h = cmp::min(y + height as i32, head.y + head.height as i32) - cmp::max(y, head.y);
+            cmp::max(w, 0) as i64 * cmp::max(h, 0) as i64
+        };
+
+        let best = monitors.iter()
+                           .filter(|m| m.screen == screen)
+                           .max_by_key(|m| intersection(m))
+                           .cloned();
+        match best {
+            // a window outside of all the monitors is considered on the first one
+            Some(ref m) if m.head.is_some() && intersection(m) == 0 => {
+                monitors.into_iter().find(|m| m.screen == screen)
+            },
+            best => best,
+        }
+    }
+
+    /// See the documentation of the `dpi` module for how the factor is computed.
+    #[inline]
+    pub fn hidpi_factor(&self) -> f32 {
+        self.hidpi_factor.get()
+    }
+
+    /// See the docs of the crate root file.
+    ///
+    /// X11 can only confine the cursor to a window, so the cursor is moved back into the
+    /// rectangle when it leaves it instead.
+    pub fn confine_cursor(&self, rect: Option<Rect>) -> Result<(), CursorStateError> {
+        *self.cursor_confinement.lock().unwrap() = rect;
+        Ok(())
+    }
+
+    /// Translates an event of the window read from the display, queueing the additional events
+    /// it produces in `pending_events`. `queued` contains the events of the window that were
+    /// fetched after it and haven't been processed yet.
+    fn process_event(&self, mut xev: ffi::XEvent, queued: &VecDeque<ffi::XEvent>) -> Option<Event> {
+        let xlib = &self.x.display.xlib;
+
+        if self.filter_raw_event(&xev) {
+            return None;
+        }
+
+        match xev.get_type() {
+            ffi::ClientMessage => {
+                use events::Event::{Closed, Awakened};
+                use std::sync::atomic::Ordering::Relaxed;
+
+                let client_msg: &ffi::XClientMessageEvent = unsafe { mem::transmute(&xev) };
+
+                // the messages that aren't about the protocols come from `WindowProxy`
+                if client_msg.message_type != self.wm_protocols {
+                    return Some(Awakened);
+                }
+
+                let protocol = client_msg.data.get_long(0) as ffi::Atom;
+
+                if protocol == self.wm_delete_window {
+                    self.is_closed.store(true, Relaxed);
+                    return Some(Closed);
+
+                } else if protocol == self.net_wm_sync_request {
+                    // the window manager waits for the counter to reach this value before
+                    // showing the frame at the size of the next `ConfigureNotify`
+                    let value = ffi::XSyncValue {
+                        lo: client_msg.data.get_long(2) as libc::c_uint,
+                        hi: client_msg.data.get_long(3) as libc::c_int,
+                    };
+                    *self.sync_request.lock().unwrap() = Some(value);
+
+                } else if protocol == self.net_wm_ping {
+                    // the window manager checks that we are still responding by sending us
+                    // pings, which must be sent back to the root window
+                    unsafe {
+                        let root = (xlib.XDefaultRootWindow)(self.x.display.display);
+                        let mut reply = *client_msg;
+                        reply.window = root;
+                        (xlib.XSendEvent)(self.x.display.display, root, ffi::False,
+                                          ffi::SubstructureNotifyMask |
+                                          ffi::SubstructureRedirectMask,
+                                          &mut reply as *mut _ as *mut ffi::XEvent);
+                        (xlib.XFlush)(self.x.display.display);
                     }
-                    self.x.display.check_errors().expect("Failed to call XDefineCursor or free the empty cursor");
+                    self.x.display.check_errors().expect("Failed to reply to _NET_WM_PING");
                 }
-                Ok(())
             },
-            Grab => {
-                unsafe {
-                    match (self.x.display.xlib.XGrabPointer)(
-                        self.x.display.display, self.x.window, ffi::True,
-                        (ffi::ButtonPressMask | ffi::ButtonReleaseMask | ffi::EnterWindowMask |
-                        ffi::LeaveWindowMask | ffi::PointerMotionMask | ffi::PointerMotionHintMask |
-                        ffi::Button1MotionMask | ffi::Button2MotionMask | ffi::Button3MotionMask |
-                        ffi::Button4MotionMask | ffi::Button5MotionMask | ffi::ButtonMotionMask |
-                        ffi::KeymapStateMask) as libc::c_uint,
-                        ffi::GrabModeAsync, ffi::GrabModeAsync,
-                        self.x.window, 0, ffi::CurrentTime
-                    ) {
-                        ffi::GrabSuccess => Ok(()),
-                        ffi::AlreadyGrabbed | ffi::GrabInvalidTime |
-                        ffi::GrabNotViewable | ffi::GrabFrozen
-                            => Err("cursor could not be grabbed".to_string()),
-                        _ => unreachable!(),
+
+            ffi::ConfigureNotify => {
+                use events::Event::{Moved, Resized};
+                let cfg_event: &ffi::XConfigureEvent = unsafe { mem::transmute(&xev) };
+
+                // the coordinates of the events sent by the server are relative to the frame
+                // that the window manager reparented the window to, and the window manager
+                // sends events with the coordinates relative to the root window when it
+                // moves the window, during moves started with `drag_window` for example
+                if cfg_event.send_event != 0 &&
+                   self.current_position.get() != (cfg_event.x, cfg_event.y)
+                {
+                    self.current_position.set((cfg_event.x, cfg_event.y));
+                    // same position as `get_position`
+                    let (left, _, top, _) = self.get_frame_extents();
+                    let factor = self.hidpi_factor();
+                    let x = (cfg_event.x - left as i32) as f32 / factor;
+                    let y = (cfg_event.y - top as i32) as f32 / factor;
+                    self.pending_events.lock().unwrap().push_back(Moved(x as i32, y as i32));
+                }
+
+                let (current_width, current_height) = self.current_size.get();
+                if current_width != cfg_event.width || current_height != cfg_event.height {
+                    self.current_size.set((cfg_event.width, cfg_event.height));
+                    return Some(Resized(cfg_event.width as u32, cfg_event.height as u32));
+                }
+            },
+
+            ffi::Expose => {
+                use events::Event::Refresh;
+                return Some(Refresh);
+            },
+
+            ffi::UnmapNotify => {
+                use std::sync::atomic::Ordering::Relaxed;
+
+                // the server releases the grab of a window that becomes invisible, for
+                // example when it is minimized, and the window gets it back with the focus
+                self.pointer_grabbed.store(false, Relaxed);
+            },
+
+            ffi::PropertyNotify => {
+                use events::Event::HiDpiFactorChanged;
+                let event: &ffi::XPropertyEvent = unsafe { mem::transmute(&xev) };
+                let display = &self.x.display;
+                let screen_id = self.x.screen_id;
+
+                if dpi::is_hidpi_property(display, screen_id, event.window, event.atom) {
+                    let factor = dpi::get_hidpi_factor(display, screen_id);
+                    if factor != self.hidpi_factor.get() {
+                        self.hidpi_factor.set(factor);
+                        return Some(HiDpiFactorChanged(factor));
                     }
                 }
             },
+
+            ffi::KeyPress | ffi::KeyRelease => {
+                if !self.detectable_auto_repeat && self.is_auto_repeat_release(&xev, queued) {
+                    // the press that follows is reported as a repeat instead
+                    return None;
+                }
+
+                let mut event: &mut ffi::XKeyEvent = unsafe { mem::transmute(&mut xev) };
+                let events = self.input_handler.lock().unwrap().translate_key_event(&mut event);
+                for event in events {
+                    self.pending_events.lock().unwrap().push_back(event);
+                }
+            },
+
+            ffi::ButtonPress | ffi::ButtonRelease | ffi::MotionNotify |
+            ffi::FocusIn | ffi::FocusOut => {
+                // pointer events are only received if the server doesn't support XInput 2.2
+                let events = self.input_handler.lock().unwrap().translate_core_event(&xev);
+                for event in events {
+                    self.push_input_event(event);
+                }
+            },
+
+            ffi::GenericEvent => {
+                let cookie = GenericEventCookie::from_event(self.x.display.borrow(), xev);
+                match cookie.cookie.evtype {
+                    ffi::XI_DeviceChanged...ffi::XI_LASTEVENT => {
+                        match self.input_handler.lock() {
+                            Ok(mut handler) => {
+                                for event in handler.translate_event(&cookie.cookie) {
+                                    self.push_input_event(event);
+                                }
+                            },
+                            Err(_) => {}
+                        }
+                    },
+                    _ => {}
+                }
+            }
+
+            ty if Some(ty) == self.x.display.xkb_event_base => {
+                // only the changes of the layout are read from the display
+                use events::Event::KeyboardLayoutChanged;
+                return Some(KeyboardLayoutChanged);
+            },
+
+            _ => {}
         }
+
+        None
     }
 
-    #[inline]
-    pub fn hidpi_factor(&self) -> f32 {
-        1.0
+    /// Returns true if `xev` is a key release generated by the auto-repeat, which is immediately
+    /// followed by a press of the same key with the same timestamp.
+    fn is_auto_repeat_release(&self, xev: &ffi::XEvent, queued: &VecDeque<ffi::XEvent>) -> bool {
+        if xev.get_type() != ffi::KeyRelease {
+            return false;
+        }
+
+        let release: &ffi::XKeyEvent = unsafe { mem::transmute(xev) };
+
+        let next = queued.front().cloned()
+                         .or_else(|| self.x.display.peek_window_event(self.x.window));
+        let next = match next {
+            Some(next) => next,
+            None => return false,
+        };
+        if next.get_type() != ffi::KeyPress {
+            return false;
+        }
+
+        let press: &ffi::XKeyEvent = unsafe { mem::transmute(&next) };
+        press.keycode == release.keycode && press.time == release.time
     }
 
-    pub fn set_cursor_position(&self, x: i32, y: i32) -> Result<(), ()> {
+    /// Queues an event produced by the input handler, after applying the cursor grab and the
+    /// cursor confinement to it.
+    fn push_input_event(&self, event: Event) {
+        use std::sync::atomic::Ordering::Relaxed;
+
+        let locked = *self.cursor_grab.lock().unwrap() == CursorGrabMode::Locked;
+
+        let event = match event {
+            Event::Focused(focused) => {
+                self.update_grab_on_focus(focused);
+                event
+            },
+
+            // the position of a locked cursor doesn't change
+            Event::MouseMoved(..) if locked => return,
+
+            Event::MouseMovedPrecise(x, y) if locked => {
+                // the pointer isn't locked while it is released
+                if !self.pointer_grabbed.load(Relaxed) {
+                    return;
+                }
+
+                let (center_x, center_y) = self.get_locked_cursor_position();
+                let (delta_x, delta_y) = (x - center_x, y - center_y);
+                // moving the cursor back to the center produces a motion without any distance
+                if delta_x == 0.0 && delta_y == 0.0 {
+                    return;
+                }
+
+                let _ = self.set_cursor_position(center_x, center_y);
+                Event::MouseMotion(delta_x, delta_y)
+            },
+
+            Event::MouseMoved(x, y) => {
+                match *self.cursor_confinement.lock().unwrap() {
+                    Some(rect) => {
+                        let (confined_x, confined_y) = rect.clamp(x, y);
+                        if (confined_x, confined_y) != (x, y) {
+                            let _ = self.set_cursor_position(confined_x as f64,
+                                                             confined_y as f64);
+                        }
+                        Event::MouseMoved(confined_x, confined_y)
+                    },
+                    None => event,
+                }
+            },
+
+            Event::MouseMovedPrecise(x, y) => {
+                match *self.cursor_confinement.lock().unwrap() {
+                    Some(rect) => {
+                        let (x, y) = rect.clamp_precise(x, y);
+                        Event::MouseMovedPrecise(x, y)
+                    },
+                    None => event,
+                }
+            },
+
+            event => event,
+        };
+
+        self.pending_events.lock().unwrap().push_back(event);
+    }
+
+    pub fn set_cursor_position(&self, x: f64, y: f64) -> Result<(), ()> {
+        let (x, y) = (x.round() as libc::c_int, y.round() as libc::c_int);
         unsafe {
             (self.x.display.xlib.XWarpPointer)(self.x.display.display, 0, self.x.window, 0, 0, 0, 0, x, y);
             self.x.display.check_errors().map_err(|_| ())
@@ -1091,14 +2437,30 @@ impl Window {
     }
 }
 
+impl Drop for Window {
+    #[inline]
+    fn drop(&mut self) {
+        // a grab that outlives the window would freeze the pointer of the whole session
+        self.ungrab_pointer();
+    }
+}
+
 impl GlContext for Window {
     #[inline]
     unsafe fn make_current(&self) -> Result<(), ContextError> {
-        match self.x.context {
+        let serial = self.x.display.next_request_serial();
+        let result = match self.x.context {
             Context::Glx(ref ctxt) => ctxt.make_current(),
             Context::Egl(ref ctxt) => ctxt.make_current(),
             Context::None => Ok(())
+        };
+
+        // the X error, `BadDrawable` if the window has been destroyed for example, describes
+        // the failure better but may not have been received yet
+        if result.is_err() {
+            try!(self.x.display.sync_and_check_errors_since(serial));
         }
+        result
     }
 
     #[inline]
@@ -1120,11 +2482,56 @@ impl GlContext for Window {
     }
 
     #[inline]
-    fn swap_buffers(&self) -> Result<(), ContextError> {
+    fn is_extension_supported(&self, name: &str) -> bool {
         match self.x.context {
+            Context::Glx(ref ctxt) => ctxt.is_extension_supported(name),
+            Context::Egl(ref ctxt) => ctxt.is_extension_supported(name),
+            Context::None => false
+        }
+    }
+
+    #[inline]
+    fn swap_buffers(&self) -> Result<(), ContextError> {
+        let serial = self.x.display.next_request_serial();
+        let result = match self.x.context {
             Context::Glx(ref ctxt) => ctxt.swap_buffers(),
             Context::Egl(ref ctxt) => ctxt.swap_buffers(),
             Context::None => Ok(())
+        };
+
+        // `glXSwapBuffers` doesn't wait for a reply, so only the errors that were already
+        // received are reported, rather than syncing every frame
+        try!(self.x.display.check_errors_since(serial));
+
+        // the frame that was just presented has the size of the latest `ConfigureNotify`
+        self.complete_resize();
+        result
+    }
+
+    fn swap_buffers_with_damage(&self, rects: &[Rect]) -> Result<(), ContextError> {
+        let serial = self.x.display.next_request_serial();
+        let result = match self.x.context {
+            Context::Glx(ref ctxt) => ctxt.swap_buffers_with_damage(rects),
+            Context::Egl(ref ctxt) => ctxt.swap_buffers_with_damage(rects),
+            Context::None => Ok(())
+        };
+
+        // nothing was presented
+        if let Err(ContextError::Unsupported) = result {
+            return result;
+        }
+
+        try!(self.x.display.check_errors_since(serial));
+        self.complete_resize();
+        result
+    }
+
+    #[inline]
+    fn buffer_age(&self) -> Option<u32> {
+        match self.x.context {
+            Context::Glx(ref ctxt) => ctxt.buffer_age(),
+            Context::Egl(ref ctxt) => ctxt.buffer_age(),
+            Context::None => None
         }
     }
 
@@ -1146,3 +2553,163 @@ impl GlContext for Window {
         }
     }
 }
+
+/// A GLX context that renders to the window of another one. See `Window::create_shared_context`.
+pub struct SharedContext {
+    // the fields are dropped in this order, the context must be destroyed before the connection
+    // is closed
+    context: GlxContext,
+    display: Arc<XConnection>,
+}
+
+impl SharedContext {
+    #[inline]
+    pub fn get_raw_context(&self) -> RawContext {
+        RawContext::from_glx(&self.context)
+    }
+}
+
+impl GlContext for SharedContext {
+    unsafe fn make_current(&self) -> Result<(), ContextError> {
+        let serial = self.display.next_request_serial();
+        let result = self.context.make_current();
+
+        // `BadDrawable` if the window has been destroyed
+        if result.is_err() {
+            try!(self.display.sync_and_check_errors_since(serial));
+        }
+        result
+    }
+
+    #[inline]
+    fn is_current(&self) -> bool {
+        self.context.is_current()
+    }
+
+    #[inline]
+    fn get_proc_address(&self, addr: &str) -> *const () {
+        self.context.get_proc_address(addr)
+    }
+
+    #[inline]
+    fn is_extension_supported(&self, name: &str) -> bool {
+        self.context.is_extension_supported(name)
+    }
+
+    #[inline]
+    fn swap_buffers(&self) -> Result<(), ContextError> {
+        let serial = self.display.next_request_serial();
+        let result = self.context.swap_buffers();
+        try!(self.display.check_errors_since(serial));
+        result
+    }
+
+    #[inline]
+    fn buffer_age(&self) -> Option<u32> {
+        self.context.buffer_age()
+    }
+
+    #[inline]
+    fn get_api(&self) -> Api {
+        self.context.get_api()
+    }
+
+    #[inline]
+    fn get_pixel_format(&self) -> PixelFormat {
+        self.context.get_pixel_format()
+    }
+}
+
+/// Asks the window manager to make the fullscreen window cover the bounding box of `heads`, by
+/// sending the `_NET_WM_FULLSCREEN_MONITORS` message. `heads` must not be empty.
+fn set_fullscreen_monitors(display: &XConnection, root: ffi::Window, window: ffi::Window,
+                           heads: &[XineramaHead]) -> Result<(), XError>
+{
+    // the monitors that contain the top, bottom, left and right edges of the bounding box
+    let top = heads.iter().min_by_key(|head| head.y).unwrap();
+    let bottom = heads.iter().max_by_key(|head| head.y + head.height as i32).unwrap();
+    let left = heads.iter().min_by_key(|head| head.x).unwrap();
+    let right = heads.iter().max_by_key(|head| head.x + head.width as i32).unwrap();
+
+    // the last long means that the request comes from a normal application
+    send_client_message(display, root, window, display.intern_atom("_NET_WM_FULLSCREEN_MONITORS"),
+                        [top.index as c_long, bottom.index as c_long, left.index as c_long,
+                         right.index as c_long, 1])
+}
+
+/// Sends a client message about `window` to the window manager.
+fn send_client_message(display: &XConnection, root: ffi::Window, window: ffi::Window,
+                       message_type: ffi::Atom, longs: [c_long; 5]) -> Result<(), XError>
+{
+    let mut data = ffi::ClientMessageData::new();
+    for (i, &long) in longs.iter().enumerate() {
+        data.set_long(i, long);
+    }
+
+    let client_message_event = ffi::XClientMessageEvent {
+        type_: ffi::ClientMessage,
+        serial: 0,
+        send_event: 1,
+        display: display.display,
+        window: window,
+        message_type: message_type,
+        format: 32,
+        data: data,
+    };
+    let mut x_event = ffi::XEvent::from(client_message_event);
+
+    unsafe {
+        (display.xlib.XSendEvent)(display.display, root, 0,
+                                  ffi::SubstructureRedirectMask | ffi::SubstructureNotifyMask,
+                                  &mut x_event);
+    }
+    display.check_errors()
+}
+
+/// Sets the `_MOTIF_WM_HINTS` property, which most window managers use to know whether the
+/// window wants a frame.
+fn set_motif_decorations(display: &XConnection, window: ffi::Window, decorations: bool)
+                         -> Result<(), XError>
+{
+    // flags, functions, decorations, input mode and status; the flags say that only the
+    // decorations field is set
+    const MWM_HINTS_DECORATIONS: c_long = 1 << 1;
+    let hints: [c_long; 5] = [MWM_HINTS_DECORATIONS, 0, decorations as c_long, 0, 0];
+
+    let motif_hints = display.intern_atom("_MOTIF_WM_HINTS");
+    unsafe {
+        (display.xlib.XChangeProperty)(display.display, window, motif_hints, motif_hints, 32,
+                                       ffi::PropModeReplace,
+                                       hints.as_ptr() as *const libc::c_uchar,
+                                       hints.len() as libc::c_int);
+    }
+    display.check_errors()
+}
+
+/// Returns the size that is the closest to the given one while respecting the aspect ratio and
+/// the minimum and maximum sizes of `hints`.
+///
+/// The aspect ratio is applied by shrinking the dimension that is too large.
+fn constrain_size(hints: &ffi::XSizeHints, mut width: u32, mut height: u32) -> (u32, u32) {
+    if hints.flags & ffi::PAspect != 0 && hints.min_aspect.x > 0 && hints.min_aspect.y > 0 {
+        // `set_aspect_ratio` sets the same minimum and maximum ratio
+        let (x, y) = (hints.min_aspect.x as u64, hints.min_aspect.y as u64);
+        if width as u64 * y > height as u64 * x {
+            width = cmp::max((height as u64 * x / y) as u32, 1);
+        } else {
+            height = cmp::max((width as u64 * y / x) as u32, 1);
+        }
+    }
+
+    if hints.flags & ffi::PMaxSize != 0 {
+        width = cmp::min(width, cmp::max(hints.max_width, 1) as u32);
+        height = cmp::min(height, cmp::max(hints.max_height, 1) as u32);
+    }
+
+    if hints.flags & ffi::PMinSize != 0 {
+        width = cmp::max(width, cmp::max(hints.min_width, 0) as u32);
+        height = cmp::max(height, cmp::max(hints.min_height, 0) as u32);
+    }
+
+    (width, height)
+}
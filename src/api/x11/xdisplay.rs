@@ -1,11 +1,21 @@
+use std::collections::{HashMap, VecDeque};
 use std::ptr;
 use std::fmt;
+use std::slice;
+use std::io;
 use std::error::Error;
 use std::ffi::CString;
 use std::sync::Mutex;
+use std::time::Duration;
 
 use libc;
 
+use ContextError;
+use CreationError;
+use MouseCursor;
+
+use super::cursor;
+use super::event_queue::EventQueues;
 use super::ffi;
 use api::egl::ffi::egl::Egl;
 use api::dlopen;
@@ -16,10 +26,22 @@ pub struct XConnection {
     pub xf86vmode: ffi::Xf86vmode,
     pub xcursor: ffi::Xcursor,
     pub xinput2: ffi::XInput2,
+    /// Only used to tell the monitors of a screen apart, so it is optional.
+    pub xinerama: Option<ffi::Xinerama>,
+    /// The functions of the XSync extension, if the server supports it.
+    pub sync: Option<ffi::Xext>,
+    /// The functions of the SHAPE extension, if the server supports it.
+    pub shape: Option<ffi::XShape>,
     pub glx: Option<ffi::glx::Glx>,
     pub egl: Option<Egl>,
     pub display: *mut ffi::Display,
+    /// The type of the events of the XKB extension, which report the changes of the keyboard
+    /// layout, if the server supports it.
+    pub xkb_event_base: Option<libc::c_int>,
     pub latest_error: Mutex<Option<XError>>,
+    cursors: Mutex<HashMap<MouseCursor, ffi::Cursor>>,
+    named_cursors: Mutex<HashMap<String, ffi::Cursor>>,
+    event_queues: EventQueues,
 }
 
 unsafe impl Send for XConnection {}
@@ -34,6 +56,8 @@ impl XConnection {
         let xcursor = try!(ffi::Xcursor::open());
         let xf86vmode = try!(ffi::Xf86vmode::open());
         let xinput2 = try!(ffi::XInput2::open());
+        let xinerama = ffi::Xinerama::open().ok();
+        let xext = ffi::Xext::open().ok();
 
         unsafe { (xlib.XInitThreads)() };
         unsafe { (xlib.XSetErrorHandler)(error_handler) };
@@ -81,15 +105,67 @@ impl XConnection {
             display
         };
 
+        // the extension must be initialized once per display before its functions can be used
+        let sync = xext.and_then(|xext| unsafe {
+            let (mut event_base, mut error_base) = (0, 0);
+            let (mut major, mut minor) = (0, 0);
+            if (xext.XSyncQueryExtension)(display, &mut event_base, &mut error_base) != 0 &&
+               (xext.XSyncInitialize)(display, &mut major, &mut minor) != 0
+            {
+                Some(xext)
+            } else {
+                None
+            }
+        });
+
+        let shape = ffi::XShape::open().and_then(|shape| unsafe {
+            let (mut event_base, mut error_base) = (0, 0);
+            if (shape.XShapeQueryExtension)(display, &mut event_base, &mut error_base) != 0 {
+                Some(shape)
+            } else {
+                None
+            }
+        });
+
+        // the keyboard layouts are switched by changing the group of XKB, or by loading another
+        // keymap
+        let xkb_event_base = unsafe {
+            let (mut opcode, mut event_base, mut error_base) = (0, 0, 0);
+            let (mut major, mut minor) = (ffi::XKB_MAJOR_VERSION, ffi::XKB_MINOR_VERSION);
+            if (xlib.XkbQueryExtension)(display, &mut opcode, &mut event_base, &mut error_base,
+                                        &mut major, &mut minor) != 0
+            {
+                (xlib.XkbSelectEvents)(display, ffi::XKB_USE_CORE_KBD, ffi::XkbMapNotifyMask,
+                                       ffi::XkbMapNotifyMask);
+                (xlib.XkbSelectEventDetails)(display, ffi::XKB_USE_CORE_KBD,
+                                             ffi::XkbStateNotify as libc::c_uint,
+                                             ffi::XkbGroupStateMask, ffi::XkbGroupStateMask);
+                Some(event_base)
+            } else {
+                None
+            }
+        };
+
+        let wakeup_atom = unsafe {
+            (xlib.XInternAtom)(display, b"_GLUTIN_WAKEUP\0".as_ptr() as *const _, ffi::False)
+        };
+
         Ok(XConnection {
             xlib: xlib,
             xf86vmode: xf86vmode,
             xcursor: xcursor,
             xinput2: xinput2,
+            xinerama: xinerama,
+            sync: sync,
+            shape: shape,
             glx: glx,
             egl: egl,
             display: display,
+            xkb_event_base: xkb_event_base,
             latest_error: Mutex::new(None),
+            cursors: Mutex::new(HashMap::new()),
+            named_cursors: Mutex::new(HashMap::new()),
+            event_queues: EventQueues::new(wakeup_atom),
         })
     }
 
@@ -110,6 +186,221 @@ impl XConnection {
     pub fn ignore_error(&self) {
         *self.latest_error.lock().unwrap() = None;
     }
+
+    /// Waits for the X server to process all the requests sent so far, then checks whether an
+    /// error was triggered.
+    ///
+    /// Errors are reported asynchronously by the server, so `check_errors` alone doesn't see the
+    /// errors of requests that haven't been processed yet.
+    #[inline]
+    pub fn sync_and_check_errors(&self) -> Result<(), XError> {
+        unsafe { (self.xlib.XSync)(self.display, ffi::False) };
+        self.check_errors()
+    }
+
+    /// Returns the serial number of the next request, to be given to `check_errors_since`.
+    #[inline]
+    pub fn next_request_serial(&self) -> libc::c_ulong {
+        unsafe { (self.xlib.XNextRequest)(self.display) }
+    }
+
+    /// Checks whether an error has been triggered by the requests sent since `serial` was
+    /// returned by `next_request_serial`.
+    ///
+    /// The errors of the previous requests, which may have been sent by another window of the
+    /// connection, are left for `check_errors`.
+    #[inline]
+    pub fn check_errors_since(&self, serial: libc::c_ulong) -> Result<(), XError> {
+        match take_error_since(&mut self.latest_error.lock().unwrap(), serial) {
+            Some(error) => Err(error),
+            None => Ok(()),
+        }
+    }
+
+    /// Same as `check_errors_since`, but waits for the X server to process the requests first.
+    #[inline]
+    pub fn sync_and_check_errors_since(&self, serial: libc::c_ulong) -> Result<(), XError> {
+        unsafe { (self.xlib.XSync)(self.display, ffi::False) };
+        self.check_errors_since(serial)
+    }
+
+    /// Returns the X11 cursor corresponding to `cursor`, or 0 if it couldn't be created.
+    ///
+    /// Cursors are loaded once and then shared by all the windows of the connection, so the
+    /// returned cursor must not be freed.
+    pub fn get_cursor(&self, cursor: MouseCursor) -> ffi::Cursor {
+        let mut cursors = self.cursors.lock().unwrap();
+        if let Some(&xcursor) = cursors.get(&cursor) {
+            return xcursor;
+        }
+
+        let xcursor = cursor::load_cursor(self, cursor);
+        if xcursor != 0 {
+            cursors.insert(cursor, xcursor);
+        }
+        xcursor
+    }
+
+    /// Returns the cursor with the given name in the cursor theme, or 0 if there is none.
+    ///
+    /// Same as `get_cursor`, the returned cursor must not be freed.
+    pub fn get_named_cursor(&self, name: &str) -> ffi::Cursor {
+        let mut cursors = self.named_cursors.lock().unwrap();
+        if let Some(&xcursor) = cursors.get(name) {
+            return xcursor;
+        }
+
+        let xcursor = cursor::load_named_cursor(self, name);
+        if xcursor != 0 {
+            cursors.insert(name.to_owned(), xcursor);
+        }
+        xcursor
+    }
+
+    /// Starts keeping the events of `window` for `poll_window_event`. Must be called right after
+    /// the window has been created, since the events read before are lost.
+    #[inline]
+    pub fn register_window(&self, window: ffi::Window) {
+        self.event_queues.register(window)
+    }
+
+    /// Stops keeping the events of `window` and discards the ones that haven't been processed.
+    #[inline]
+    pub fn unregister_window(&self, window: ffi::Window) {
+        self.event_queues.unregister(self, window)
+    }
+
+    /// Returns the oldest event of `window` that hasn't been processed yet, or `None` if there is
+    /// none. Never blocks.
+    ///
+    /// The events are read from the display once and shared between the windows of the
+    /// connection, so the events of a window are never seen by another one. The data of the
+    /// `GenericEvent`s has already been retrieved and must be freed with `XFreeEventData`.
+    #[inline]
+    pub fn poll_window_event(&self, window: ffi::Window) -> Option<ffi::XEvent> {
+        self.event_queues.poll(self, window)
+    }
+
+    /// Same as `poll_window_event`, but leaves the event in the queue of the window.
+    #[inline]
+    pub fn peek_window_event(&self, window: ffi::Window) -> Option<ffi::XEvent> {
+        self.event_queues.peek(self, window)
+    }
+
+    /// Moves all the events of `window` that haven't been processed yet to the back of `events`,
+    /// oldest first. Never blocks.
+    #[inline]
+    pub fn drain_window_events(&self, window: ffi::Window, events: &mut VecDeque<ffi::XEvent>) {
+        self.event_queues.drain(self, window, events)
+    }
+
+    /// Blocks until `poll_window_event` has an event for `window`.
+    #[inline]
+    pub fn wait_window_event(&self, window: ffi::Window) {
+        self.event_queues.wait(self, window)
+    }
+
+    /// Returns the number of events of `window` that haven't been processed yet, which can be
+    /// passed to `wait_for_window_event` to only consider the events received afterwards.
+    #[inline]
+    pub fn window_event_count(&self, window: ffi::Window) -> usize {
+        self.event_queues.len(self, window)
+    }
+
+    /// Waits until `window` has received an event matching `predicate`, ignoring the first
+    /// `start` unprocessed events, or until `timeout` has elapsed. The events are still
+    /// returned by `poll_window_event` afterwards.
+    ///
+    /// Returns true if such an event was received.
+    #[inline]
+    pub fn wait_for_window_event<F>(&self, window: ffi::Window, start: usize, timeout: Duration,
+                                    predicate: F) -> bool
+        where F: Fn(&ffi::XEvent) -> bool
+    {
+        self.event_queues.wait_for(self, window, start, timeout, predicate)
+    }
+
+    /// Returns the atom with the given name, or 0 if it doesn't exist.
+    pub fn get_atom(&self, name: &str) -> ffi::Atom {
+        let name = CString::new(name).unwrap();
+        let atom = unsafe { (self.xlib.XInternAtom)(self.display, name.as_ptr(), ffi::True) };
+        self.ignore_error();
+        atom
+    }
+
+    /// Returns the atom with the given name, creating it if it doesn't exist yet.
+    ///
+    /// Properties that we set must use this instead of `get_atom`, since their name may not have
+    /// been interned by anyone else yet.
+    pub fn intern_atom(&self, name: &str) -> ffi::Atom {
+        let name = CString::new(name).unwrap();
+        unsafe { (self.xlib.XInternAtom)(self.display, name.as_ptr(), ffi::False) }
+    }
+
+    /// Reads the whole content of a property whose format is 8 bits.
+    ///
+    /// Returns `None` if the property doesn't exist or doesn't have the given type.
+    pub fn read_property(&self, window: ffi::Window, property: ffi::Atom,
+                         property_type: ffi::Atom) -> Option<Vec<u8>>
+    {
+        unsafe {
+            let mut actual_type: ffi::Atom = 0;
+            let mut actual_format: libc::c_int = 0;
+            let mut nitems: libc::c_ulong = 0;
+            let mut bytes_after: libc::c_ulong = 0;
+            let mut data: *mut libc::c_uchar = ptr::null_mut();
+
+            let result = (self.xlib.XGetWindowProperty)(self.display, window, property, 0,
+                libc::c_long::max_value(), ffi::False, property_type, &mut actual_type,
+                &mut actual_format, &mut nitems, &mut bytes_after, &mut data);
+            self.ignore_error();
+
+            if result != ffi::Success as libc::c_int || data.is_null() {
+                return None;
+            }
+
+            let content = if actual_type == property_type && actual_format == 8 {
+                Some(slice::from_raw_parts(data, nitems as usize).to_vec())
+            } else {
+                None
+            };
+
+            (self.xlib.XFree)(data as *mut _);
+            content
+        }
+    }
+
+    /// Returns true if the window manager lists the given hint in the `_NET_SUPPORTED` property
+    /// of the root window.
+    pub fn supports_hint(&self, hint: &str) -> bool {
+        let supported = self.intern_atom("_NET_SUPPORTED");
+        let hint = self.intern_atom(hint);
+
+        unsafe {
+            let root = (self.xlib.XDefaultRootWindow)(self.display);
+            let mut actual_type: ffi::Atom = 0;
+            let mut actual_format: libc::c_int = 0;
+            let mut nitems: libc::c_ulong = 0;
+            let mut bytes_after: libc::c_ulong = 0;
+            let mut data: *mut libc::c_uchar = ptr::null_mut();
+
+            let result = (self.xlib.XGetWindowProperty)(self.display, root, supported, 0,
+                libc::c_long::max_value(), ffi::False, ffi::XA_ATOM, &mut actual_type,
+                &mut actual_format, &mut nitems, &mut bytes_after, &mut data);
+            self.ignore_error();
+
+            if result != ffi::Success as libc::c_int || data.is_null() {
+                return false;
+            }
+
+            // a format of 32 bits is returned as longs
+            let found = actual_type == ffi::XA_ATOM && actual_format == 32 &&
+                slice::from_raw_parts(data as *const ffi::Atom, nitems as usize).contains(&hint);
+
+            (self.xlib.XFree)(data as *mut _);
+            found
+        }
+    }
 }
 
 impl Drop for XConnection {
@@ -119,6 +410,15 @@ impl Drop for XConnection {
     }
 }
 
+/// Takes `error` if it was triggered by a request sent since `serial`.
+fn take_error_since(error: &mut Option<XError>, serial: libc::c_ulong) -> Option<XError> {
+    match *error {
+        Some(ref err) if err.serial >= serial => (),
+        _ => return None,
+    }
+    error.take()
+}
+
 /// Error triggered by xlib.
 #[derive(Debug, Clone)]
 pub struct XError {
@@ -126,6 +426,8 @@ pub struct XError {
     pub error_code: u8,
     pub request_code: u8,
     pub minor_code: u8,
+    /// The serial number of the request that triggered the error.
+    pub serial: libc::c_ulong,
 }
 
 impl Error for XError {
@@ -142,6 +444,20 @@ impl fmt::Display for XError {
     }
 }
 
+impl From<XError> for CreationError {
+    #[inline]
+    fn from(err: XError) -> CreationError {
+        CreationError::OsError(format!("{}", err))
+    }
+}
+
+impl From<XError> for ContextError {
+    #[inline]
+    fn from(err: XError) -> ContextError {
+        ContextError::IoError(io::Error::new(io::ErrorKind::Other, format!("{}", err)))
+    }
+}
+
 /// Error returned if this system doesn't have XLib or can't create an X connection.
 #[derive(Clone, Debug)]
 pub enum XNotSupported {
@@ -181,3 +497,42 @@ impl fmt::Display for XNotSupported {
         formatter.write_str(self.description())
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::{XError, take_error_since};
+    use CreationError;
+
+    fn error(serial: u64) -> XError {
+        XError {
+            description: "BadWindow (invalid Window parameter)".to_owned(),
+            error_code: 3,
+            request_code: 1,
+            minor_code: 0,
+            serial: serial as _,
+        }
+    }
+
+    #[test]
+    fn errors_of_previous_requests_are_left() {
+        let mut latest_error = Some(error(10));
+        assert!(take_error_since(&mut latest_error, 11).is_none());
+        assert!(latest_error.is_some());
+    }
+
+    #[test]
+    fn errors_since_the_serial_are_taken() {
+        let mut latest_error = Some(error(11));
+        assert_eq!(take_error_since(&mut latest_error, 11).unwrap().serial, 11);
+        assert!(latest_error.is_none());
+        assert!(take_error_since(&mut latest_error, 11).is_none());
+    }
+
+    #[test]
+    fn creation_error_contains_the_description() {
+        match CreationError::from(error(1)) {
+            CreationError::OsError(description) => assert!(description.contains("BadWindow")),
+            err => panic!("unexpected error: {:?}", err),
+        }
+    }
+}
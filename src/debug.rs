@@ -0,0 +1,184 @@
+use std::ffi::CStr;
+use std::io;
+use std::mem;
+use std::os::raw::{c_char, c_int, c_uint, c_void};
+use std::ptr;
+use std::slice;
+
+use ContextError;
+use GlContext;
+
+const GL_DEBUG_OUTPUT_SYNCHRONOUS: c_uint = 0x8242;
+const GL_DEBUG_CALLBACK_FUNCTION: c_uint = 0x8244;
+const GL_DEBUG_CALLBACK_USER_PARAM: c_uint = 0x8245;
+const GL_DEBUG_OUTPUT: c_uint = 0x92E0;
+
+type DebugProc = extern "system" fn(c_uint, c_uint, c_uint, c_uint, c_int, *const c_char,
+                                    *mut c_void);
+type DebugMessageCallbackFn = extern "system" fn(Option<DebugProc>, *const c_void);
+type GetPointervFn = extern "system" fn(c_uint, *mut *mut c_void);
+type EnableFn = extern "system" fn(c_uint);
+
+type Callback = Box<FnMut(DebugMessage)>;
+
+/// A message sent by the OpenGL implementation through the `GL_KHR_debug` extension.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DebugMessage {
+    /// The component that generated the message.
+    pub source: DebugSource,
+    /// What the message is about.
+    pub ty: DebugType,
+    /// An identifier of the message, whose meaning depends on the implementation.
+    pub id: u32,
+    /// How important the message is.
+    pub severity: DebugSeverity,
+    /// The human-readable text of the message.
+    pub message: String,
+}
+
+/// The component that generated a `DebugMessage`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DebugSource {
+    Api,
+    WindowSystem,
+    ShaderCompiler,
+    ThirdParty,
+    Application,
+    Other,
+}
+
+/// What a `DebugMessage` is about.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DebugType {
+    Error,
+    DeprecatedBehavior,
+    UndefinedBehavior,
+    Portability,
+    Performance,
+    Marker,
+    PushGroup,
+    PopGroup,
+    Other,
+}
+
+/// How important a `DebugMessage` is.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DebugSeverity {
+    High,
+    Medium,
+    Low,
+    Notification,
+}
+
+impl DebugSource {
+    fn from_gl(value: c_uint) -> DebugSource {
+        match value {
+            0x8246 => DebugSource::Api,
+            0x8247 => DebugSource::WindowSystem,
+            0x8248 => DebugSource::ShaderCompiler,
+            0x8249 => DebugSource::ThirdParty,
+            0x824A => DebugSource::Application,
+            _ => DebugSource::Other,
+        }
+    }
+}
+
+impl DebugType {
+    fn from_gl(value: c_uint) -> DebugType {
+        match value {
+            0x824C => DebugType::Error,
+            0x824D => DebugType::DeprecatedBehavior,
+            0x824E => DebugType::UndefinedBehavior,
+            0x824F => DebugType::Portability,
+            0x8250 => DebugType::Performance,
+            0x8268 => DebugType::Marker,
+            0x8269 => DebugType::PushGroup,
+            0x826A => DebugType::PopGroup,
+            _ => DebugType::Other,
+        }
+    }
+}
+
+impl DebugSeverity {
+    fn from_gl(value: c_uint) -> DebugSeverity {
+        match value {
+            0x9146 => DebugSeverity::High,
+            0x9147 => DebugSeverity::Medium,
+            0x9148 => DebugSeverity::Low,
+            _ => DebugSeverity::Notification,
+        }
+    }
+}
+
+/// See the docs of `GlContext::set_debug_callback`.
+pub fn set_debug_callback<C: ?Sized>(context: &C, callback: Callback) -> Result<(), ContextError>
+    where C: GlContext
+{
+    // the ARB and KHR variants of the function have the same signature as the core one
+    let debug_message_callback = ["glDebugMessageCallback", "glDebugMessageCallbackKHR",
+                                  "glDebugMessageCallbackARB"].iter()
+                                 .map(|name| context.get_proc_address(name))
+                                 .find(|addr| !addr.is_null());
+    let debug_message_callback: DebugMessageCallbackFn = match debug_message_callback {
+        Some(addr) => unsafe { mem::transmute(addr) },
+        None => return Err(ContextError::IoError(io::Error::new(io::ErrorKind::Other,
+                                                 "The context doesn't support debug output"))),
+    };
+
+    let get_pointerv = context.get_proc_address("glGetPointerv");
+    let enable = context.get_proc_address("glEnable");
+
+    unsafe {
+        // the previous callback is freed if we are the ones who registered it
+        if !get_pointerv.is_null() {
+            let get_pointerv: GetPointervFn = mem::transmute(get_pointerv);
+            let mut function = ptr::null_mut();
+            get_pointerv(GL_DEBUG_CALLBACK_FUNCTION, &mut function);
+            let mut user_param = ptr::null_mut();
+            get_pointerv(GL_DEBUG_CALLBACK_USER_PARAM, &mut user_param);
+
+            if function == callback_trampoline as *mut c_void && !user_param.is_null() {
+                debug_message_callback(None, ptr::null());
+                drop(Box::from_raw(user_param as *mut Callback));
+            }
+        }
+
+        // synchronous output makes the implementation call the callback from within the OpenGL
+        // function that triggered the message, which is required since the callback isn't `Send`
+        if !enable.is_null() {
+            let enable: EnableFn = mem::transmute(enable);
+            enable(GL_DEBUG_OUTPUT);
+            enable(GL_DEBUG_OUTPUT_SYNCHRONOUS);
+        }
+
+        let user_param = Box::into_raw(Box::new(callback));
+        debug_message_callback(Some(callback_trampoline), user_param as *const c_void);
+    }
+
+    Ok(())
+}
+
+extern "system" fn callback_trampoline(source: c_uint, ty: c_uint, id: c_uint, severity: c_uint,
+                                       length: c_int, message: *const c_char,
+                                       user_param: *mut c_void)
+{
+    let message = unsafe {
+        if length >= 0 {
+            let bytes = slice::from_raw_parts(message as *const u8, length as usize);
+            String::from_utf8_lossy(bytes).into_owned()
+        } else {
+            CStr::from_ptr(message).to_string_lossy().into_owned()
+        }
+    };
+
+    let message = DebugMessage {
+        source: DebugSource::from_gl(source),
+        ty: DebugType::from_gl(ty),
+        id: id,
+        severity: DebugSeverity::from_gl(severity),
+        message: message,
+    };
+
+    let callback = unsafe { &mut *(user_param as *mut Callback) };
+    callback(message);
+}
@@ -3,9 +3,16 @@ use std::path::PathBuf;
 #[derive(Clone, Debug)]
 pub enum Event {
     /// The size of the window has changed.
+    ///
+    /// The size is the one of the client area in pixels, like `Window::get_inner_size_pixels`,
+    /// and not in points like `Window::get_inner_size`, so that it can be given to `glViewport`
+    /// directly. The size is never smaller than 1×1, even if the window manager shrinks the
+    /// window further.
     Resized(u32, u32),
 
     /// The position of the window has changed.
+    ///
+    /// The position is the one returned by `Window::get_position`, in the same unit.
     Moved(i32, i32),
 
     /// The window has been closed.
@@ -20,16 +27,42 @@ pub enum Event {
     /// The window gained or lost focus.
     ///
     /// The parameter is true if the window has gained focus, and false if it has lost focus.
+    ///
+    /// The releases of the keys and mouse buttons that are held down when the window loses the
+    /// focus are sent to the window that gets it, so they are reported with `KeyboardInput` and
+    /// `MouseInput` events right before `Focused(false)` instead, on Windows, macOS and X11.
     Focused(bool),
 
     /// An event from the keyboard has been received.
-    KeyboardInput(ElementState, ScanCode, Option<VirtualKeyCode>),
+    ///
+    /// The last parameter is true if the event was generated by the auto-repeat of a key that is
+    /// held down. Repeated events are always `Pressed`, and a single `Released` event is sent
+    /// when the key is finally released, on all platforms.
+    KeyboardInput(ElementState, ScanCode, Option<VirtualKeyCode>, bool),
+
+    /// The user switched to another keyboard layout, which changes the virtual keys and the
+    /// names that `Window::scancode_to_keycode` and `Window::key_name` return.
+    ///
+    /// Not sent on Android, iOS and Emscripten.
+    KeyboardLayoutChanged,
 
     /// The cursor has moved on the window.
     ///
     /// The parameter are the (x,y) coords in pixels relative to the top-left corner of the window.
     MouseMoved(i32, i32),
 
+    /// The cursor has moved on the window, with subpixel precision.
+    ///
+    /// This is sent right after the corresponding `MouseMoved` event, with the same coordinates
+    /// before rounding.
+    MouseMovedPrecise(f64, f64),
+
+    /// The mouse has moved while the cursor is locked with `CursorGrabMode::Locked`.
+    ///
+    /// The parameters are the horizontal and vertical distances in pixels, positive towards the
+    /// right and the bottom.
+    MouseMotion(f64, f64),
+
     /// A mouse wheel movement or touchpad scroll occurred.
     MouseWheel(MouseScrollDelta, TouchPhase, Option<(i32, i32)>),
 
@@ -49,9 +82,55 @@ pub enum Event {
     /// The window needs to be redrawn.
     Refresh,
 
+    /// The HiDPI factor of the window has changed, for example because the user changed the
+    /// scaling settings of their desktop or moved the window to a screen with another density.
+    ///
+    /// The parameter is the new value returned by `hidpi_factor`. On MacOS, a `Resized` event
+    /// with the new size in pixels follows. On Windows, a `HiDpiSuggestedSize` event follows,
+    /// then the window is resized to that size and a `Resized` event is sent as well, unless this
+    /// has been disabled with `WindowBuilderExt::with_dpi_resize`.
+    HiDpiFactorChanged(f32),
+
+    /// The inner size in pixels that keeps the same physical size for the window after a
+    /// `HiDpiFactorChanged` event, as suggested by the system.
+    ///
+    /// Sent right after `HiDpiFactorChanged`, whether the window is resized automatically or
+    /// not. Applications that disabled the automatic resize with
+    /// `WindowBuilderExt::with_dpi_resize` can pass it to `set_inner_size`.
+    ///
+    /// Only sent on Windows.
+    HiDpiSuggestedSize(u32, u32),
+
+    /// The OpenGL context is now rendered by another GPU, for example because a MacBook Pro
+    /// switched between its integrated and discrete GPUs, or because the window moved to a screen
+    /// that is connected to another GPU.
+    ///
+    /// The objects of the context are preserved, but the limits and extensions of the new
+    /// renderer may differ, so they should be queried again. Resources that depend on them
+    /// should be recreated.
+    ///
+    /// Only sent on MacOS.
+    RendererChanged,
+
+    /// The screen of the window refreshed, which is the time to render the next frame.
+    ///
+    /// Only sent on MacOS, when enabled with `os::macos::WindowBuilderExt::with_display_link`.
+    DisplayLinkTick,
+
     /// App has been suspended or resumed.
     ///
     /// The parameter is true if app was suspended, and false if it has been resumed.
+    ///
+    /// On Android, the surface of the window is destroyed when the app is suspended. Until it is
+    /// resumed, `Window::make_current` and the `swap_buffers` methods of a `CurrentContext` that
+    /// was obtained before return `ContextError::ContextLost`, and `is_current` returns false.
+    /// The surface is recreated and the context made current again before `Suspended(false)` is
+    /// received, and the objects of the context are preserved.
+    ///
+    /// On iOS, the context stays valid, but the system terminates apps that render while they
+    /// are in the background.
+    ///
+    /// Only sent on Android and iOS.
     Suspended(bool),
 
 
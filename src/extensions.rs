@@ -0,0 +1,79 @@
+use std::ffi::CStr;
+use std::mem;
+use std::os::raw::{c_char, c_int, c_uint};
+
+use GlContext;
+
+const GL_EXTENSIONS: c_uint = 0x1F03;
+const GL_VERSION: c_uint = 0x1F02;
+const GL_NUM_EXTENSIONS: c_uint = 0x821D;
+
+type GetStringFn = unsafe extern "system" fn(c_uint) -> *const c_char;
+type GetStringiFn = unsafe extern "system" fn(c_uint, c_uint) -> *const c_char;
+type GetIntegervFn = unsafe extern "system" fn(c_uint, *mut c_int);
+
+/// Returns true if `name` is in the list of extensions of the OpenGL implementation of the
+/// context. Returns false if the context isn't current, since the functions would be called on
+/// another context, if any.
+///
+/// See the docs of `GlContext::is_extension_supported`.
+pub fn is_gl_extension_supported<C: ?Sized>(context: &C, name: &str) -> bool
+    where C: GlContext
+{
+    if !context.is_current() {
+        return false;
+    }
+
+    let get_string = context.get_proc_address("glGetString");
+    if get_string.is_null() {
+        return false;
+    }
+    let get_string: GetStringFn = unsafe { mem::transmute(get_string) };
+
+    let version = unsafe {
+        let version = get_string(GL_VERSION);
+        if version.is_null() {
+            // the context has been lost, for example
+            return false;
+        }
+        CStr::from_ptr(version).to_string_lossy().into_owned()
+    };
+
+    // core profiles don't support `GL_EXTENSIONS` in `glGetString` anymore, so starting with
+    // version 3 the extensions must be queried one by one
+    if parse_major_version(&version) >= 3 {
+        let get_stringi = context.get_proc_address("glGetStringi");
+        let get_integerv = context.get_proc_address("glGetIntegerv");
+
+        if !get_stringi.is_null() && !get_integerv.is_null() {
+            let get_stringi: GetStringiFn = unsafe { mem::transmute(get_stringi) };
+            let get_integerv: GetIntegervFn = unsafe { mem::transmute(get_integerv) };
+
+            let mut num_extensions = 0;
+            unsafe { get_integerv(GL_NUM_EXTENSIONS, &mut num_extensions) };
+
+            return (0 .. num_extensions as c_uint).any(|index| {
+                let extension = unsafe { get_stringi(GL_EXTENSIONS, index) };
+                !extension.is_null() &&
+                    unsafe { CStr::from_ptr(extension) }.to_bytes() == name.as_bytes()
+            });
+        }
+    }
+
+    let extensions = unsafe { get_string(GL_EXTENSIONS) };
+    if extensions.is_null() {
+        return false;
+    }
+
+    let extensions = unsafe { CStr::from_ptr(extensions) }.to_bytes();
+    extensions.split(|&c| c == b' ').any(|extension| extension == name.as_bytes())
+}
+
+/// Extracts the major version from the content of `GL_VERSION`, which is for example
+/// `4.5.0 NVIDIA 375.26` or `OpenGL ES 3.0 Mesa 13.0.2`.
+fn parse_major_version(version: &str) -> u32 {
+    version.split(|c: char| !c.is_digit(10))
+           .find(|s| !s.is_empty())
+           .and_then(|s| s.parse().ok())
+           .unwrap_or(0)
+}
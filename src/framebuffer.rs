@@ -0,0 +1,246 @@
+use std::cell::Cell;
+use std::mem;
+use std::ptr;
+
+use ContextError;
+use CreationError;
+use Event;
+use GlContext;
+
+#[allow(dead_code, non_upper_case_globals, unused_imports)]
+mod gl {
+    pub use self::Gles2 as Gl;
+    include!(concat!(env!("OUT_DIR"), "/framebuffer_gl_bindings.rs"));
+}
+
+/// An offscreen framebuffer object whose attachments follow the size of a window.
+///
+/// The framebuffer has an RGBA8 color texture and a 24 bits depth / 8 bits stencil renderbuffer.
+/// You can render to it by binding `get_framebuffer_id()`, and present it with `blit_to_window`
+/// before calling `swap_buffers`.
+///
+/// Pass the events of the window to `handle_event` so that the attachments are reallocated when
+/// the window is resized.
+///
+/// All the methods of this object, including its destructor, must be called while the context
+/// that created it is current.
+pub struct SizedFramebuffer {
+    gl: gl::Gl,
+    framebuffer: gl::types::GLuint,
+    texture: gl::types::GLuint,
+    depth_stencil: gl::types::GLuint,
+    dimensions: Cell<(u32, u32)>,
+}
+
+impl SizedFramebuffer {
+    /// Builds a new framebuffer with the given dimensions, using the functions of `context`.
+    ///
+    /// The context must be current.
+    pub fn new<C: ?Sized>(context: &C, dimensions: (u32, u32))
+                          -> Result<SizedFramebuffer, CreationError>
+        where C: GlContext
+    {
+        let gl = gl::Gl::load_with(|symbol| context.get_proc_address(symbol) as *const _);
+
+        if !gl.GenFramebuffers.is_loaded() || !gl.BlitFramebuffer.is_loaded() ||
+           !gl.GenRenderbuffers.is_loaded()
+        {
+            return Err(CreationError::NotSupported);
+        }
+
+        let (framebuffer, texture, depth_stencil) = unsafe {
+            let mut framebuffer = mem::uninitialized();
+            gl.GenFramebuffers(1, &mut framebuffer);
+            let mut texture = mem::uninitialized();
+            gl.GenTextures(1, &mut texture);
+            let mut depth_stencil = mem::uninitialized();
+            gl.GenRenderbuffers(1, &mut depth_stencil);
+            (framebuffer, texture, depth_stencil)
+        };
+
+        let framebuffer = SizedFramebuffer {
+            gl: gl,
+            framebuffer: framebuffer,
+            texture: texture,
+            depth_stencil: depth_stencil,
+            dimensions: Cell::new(dimensions),
+        };
+
+        unsafe {
+            framebuffer.allocate_storage(dimensions);
+
+            let gl = &framebuffer.gl;
+            let mut previous = mem::uninitialized();
+            gl.GetIntegerv(gl::FRAMEBUFFER_BINDING, &mut previous);
+
+            gl.BindFramebuffer(gl::FRAMEBUFFER, framebuffer.framebuffer);
+            gl.FramebufferTexture2D(gl::FRAMEBUFFER, gl::COLOR_ATTACHMENT0, gl::TEXTURE_2D,
+                                    framebuffer.texture, 0);
+            gl.FramebufferRenderbuffer(gl::FRAMEBUFFER, gl::DEPTH_STENCIL_ATTACHMENT,
+                                       gl::RENDERBUFFER, framebuffer.depth_stencil);
+            let status = gl.CheckFramebufferStatus(gl::FRAMEBUFFER);
+            gl.BindFramebuffer(gl::FRAMEBUFFER, previous as gl::types::GLuint);
+
+            if status != gl::FRAMEBUFFER_COMPLETE {
+                return Err(CreationError::OsError(format!("The framebuffer is incomplete \
+                                                           (status 0x{:x})", status)));
+            }
+        }
+
+        Ok(framebuffer)
+    }
+
+    /// Returns the name of the OpenGL framebuffer object.
+    #[inline]
+    pub fn get_framebuffer_id(&self) -> u32 {
+        self.framebuffer
+    }
+
+    /// Returns the name of the OpenGL texture that is the color attachment of the framebuffer.
+    ///
+    /// The texture is reallocated when the framebuffer is resized, but keeps the same name.
+    #[inline]
+    pub fn get_texture_id(&self) -> u32 {
+        self.texture
+    }
+
+    /// Returns the name of the OpenGL renderbuffer that is the depth-stencil attachment of the
+    /// framebuffer.
+    #[inline]
+    pub fn get_depth_stencil_renderbuffer_id(&self) -> u32 {
+        self.depth_stencil
+    }
+
+    /// Returns the current dimensions of the attachments, in pixels.
+    #[inline]
+    pub fn get_dimensions(&self) -> (u32, u32) {
+        self.dimensions.get()
+    }
+
+    /// Updates the framebuffer according to an event of the window.
+    ///
+    /// The attachments are reallocated if the event is `Resized`, other events are ignored.
+    #[inline]
+    pub fn handle_event(&self, event: &Event) {
+        if let Event::Resized(width, height) = *event {
+            self.resize(width, height);
+        }
+    }
+
+    /// Reallocates the attachments with new dimensions. Their content is lost.
+    ///
+    /// This is a no-op if the dimensions didn't change.
+    pub fn resize(&self, width: u32, height: u32) {
+        if self.dimensions.get() == (width, height) {
+            return;
+        }
+
+        self.dimensions.set((width, height));
+        unsafe { self.allocate_storage((width, height)); }
+    }
+
+    /// Copies the color attachment to the default framebuffer of the window, which can then be
+    /// presented with `swap_buffers`.
+    pub fn blit_to_window(&self) {
+        let (width, height) = self.dimensions.get();
+        let (width, height) = (width as gl::types::GLint, height as gl::types::GLint);
+
+        unsafe {
+            let gl = &self.gl;
+            let mut previous_read = mem::uninitialized();
+            gl.GetIntegerv(gl::READ_FRAMEBUFFER_BINDING, &mut previous_read);
+            let mut previous_draw = mem::uninitialized();
+            gl.GetIntegerv(gl::DRAW_FRAMEBUFFER_BINDING, &mut previous_draw);
+
+            gl.BindFramebuffer(gl::READ_FRAMEBUFFER, self.framebuffer);
+            gl.BindFramebuffer(gl::DRAW_FRAMEBUFFER, 0);
+            gl.BlitFramebuffer(0, 0, width, height, 0, 0, width, height,
+                               gl::COLOR_BUFFER_BIT, gl::NEAREST);
+
+            gl.BindFramebuffer(gl::READ_FRAMEBUFFER, previous_read as gl::types::GLuint);
+            gl.BindFramebuffer(gl::DRAW_FRAMEBUFFER, previous_draw as gl::types::GLuint);
+        }
+    }
+
+    unsafe fn allocate_storage(&self, (width, height): (u32, u32)) {
+        // zero-sized storage makes the framebuffer incomplete, which happens for example when
+        // the window is minimized on Windows
+        let (width, height) = (width.max(1) as gl::types::GLsizei,
+                               height.max(1) as gl::types::GLsizei);
+        let gl = &self.gl;
+
+        let mut previous_texture = mem::uninitialized();
+        gl.GetIntegerv(gl::TEXTURE_BINDING_2D, &mut previous_texture);
+        gl.BindTexture(gl::TEXTURE_2D, self.texture);
+        gl.TexParameteri(gl::TEXTURE_2D, gl::TEXTURE_MIN_FILTER, gl::LINEAR as gl::types::GLint);
+        gl.TexParameteri(gl::TEXTURE_2D, gl::TEXTURE_MAG_FILTER, gl::LINEAR as gl::types::GLint);
+        gl.TexImage2D(gl::TEXTURE_2D, 0, gl::RGBA8 as gl::types::GLint, width, height, 0,
+                      gl::RGBA, gl::UNSIGNED_BYTE, ptr::null());
+        gl.BindTexture(gl::TEXTURE_2D, previous_texture as gl::types::GLuint);
+
+        let mut previous_renderbuffer = mem::uninitialized();
+        gl.GetIntegerv(gl::RENDERBUFFER_BINDING, &mut previous_renderbuffer);
+        gl.BindRenderbuffer(gl::RENDERBUFFER, self.depth_stencil);
+        gl.RenderbufferStorage(gl::RENDERBUFFER, gl::DEPTH24_STENCIL8, width, height);
+        gl.BindRenderbuffer(gl::RENDERBUFFER, previous_renderbuffer as gl::types::GLuint);
+    }
+}
+
+/// Reads the pixels of a rectangle of the default framebuffer, see
+/// `GlContext::read_framebuffer`.
+pub fn read_framebuffer<C: ?Sized>(context: &C, x: i32, y: i32, width: u32, height: u32)
+                                   -> Result<Vec<u8>, ContextError>
+    where C: GlContext
+{
+    // the pixels would be read from whichever context is current
+    if !context.is_current() {
+        return Err(ContextError::NotCurrent);
+    }
+
+    let row_len = width as usize * 4;
+    let mut pixels = vec![0u8; row_len * height as usize];
+    if pixels.is_empty() {
+        return Ok(pixels);
+    }
+
+    let gl = gl::Gl::load_with(|symbol| context.get_proc_address(symbol) as *const _);
+
+    unsafe {
+        // without framebuffer objects, the default framebuffer is always the one that is bound
+        let mut previous_framebuffer = 0;
+        if gl.BindFramebuffer.is_loaded() {
+            gl.GetIntegerv(gl::FRAMEBUFFER_BINDING, &mut previous_framebuffer);
+            gl.BindFramebuffer(gl::FRAMEBUFFER, 0);
+        }
+
+        // the rows must not be padded
+        let mut previous_alignment = 4;
+        gl.GetIntegerv(gl::PACK_ALIGNMENT, &mut previous_alignment);
+        gl.PixelStorei(gl::PACK_ALIGNMENT, 1);
+
+        gl.ReadPixels(x, y, width as gl::types::GLsizei, height as gl::types::GLsizei,
+                      gl::RGBA, gl::UNSIGNED_BYTE, pixels.as_mut_ptr() as *mut _);
+
+        gl.PixelStorei(gl::PACK_ALIGNMENT, previous_alignment);
+        if gl.BindFramebuffer.is_loaded() {
+            gl.BindFramebuffer(gl::FRAMEBUFFER, previous_framebuffer as gl::types::GLuint);
+        }
+    }
+
+    // OpenGL returns the bottom row first
+    let mut flipped = Vec::with_capacity(pixels.len());
+    for row in pixels.chunks(row_len).rev() {
+        flipped.extend_from_slice(row);
+    }
+    Ok(flipped)
+}
+
+impl Drop for SizedFramebuffer {
+    fn drop(&mut self) {
+        unsafe {
+            self.gl.DeleteFramebuffers(1, &self.framebuffer);
+            self.gl.DeleteTextures(1, &self.texture);
+            self.gl.DeleteRenderbuffers(1, &self.depth_stencil);
+        }
+    }
+}
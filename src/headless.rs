@@ -1,5 +1,6 @@
 use Api;
 use ContextError;
+use ContextPriority;
 use CreationError;
 use GlAttributes;
 use GlProfile;
@@ -7,6 +8,7 @@ use GlRequest;
 use GlContext;
 use PixelFormat;
 use PixelFormatRequirements;
+use Rect;
 use Robustness;
 
 use platform;
@@ -24,7 +26,7 @@ pub struct HeadlessRendererBuilder<'a> {
     pf_reqs: PixelFormatRequirements,
 
     /// Platform-specific configuration.
-    platform_specific: platform::PlatformSpecificHeadlessBuilderAttributes,
+    pub(crate) platform_specific: platform::PlatformSpecificHeadlessBuilderAttributes,
 }
 
 impl<'a> HeadlessRendererBuilder<'a> {
@@ -70,12 +72,47 @@ impl<'a> HeadlessRendererBuilder<'a> {
         self
     }
 
+    /// Sets the priority of the OpenGL context in the scheduling of the GPU. This is only a
+    /// hint, see `GlAttributes::context_priority`.
+    #[inline]
+    pub fn with_gl_context_priority(mut self, priority: ContextPriority)
+                                    -> HeadlessRendererBuilder<'a>
+    {
+        self.opengl.context_priority = Some(priority);
+        self
+    }
+
     /// Builds the headless context.
     ///
     /// Error should be very rare and only occur in case of permission denied, incompatible system,
     ///  out of memory, etc.
+    ///
+    /// Headless contexts are never presented to a screen, so `NotSupported` is returned if
+    /// `opengl.vsync` is set.
+    ///
+    /// ## Platform-specific
+    ///
+    /// On Linux, no display server is needed. The context is created by the first of these
+    /// backends that works: `egl-surfaceless` (an EGL pbuffer on the surfaceless platform of
+    /// Mesa), `egl` (an EGL pbuffer on the default display) and `osmesa`. The
+    /// `GLUTIN_HEADLESS_BACKENDS` environment variable can be set to a comma-separated list of
+    /// backends to choose others, such as `egl-surfaceless-context` (an EGL context without any
+    /// surface, which can only render to framebuffer objects).
+    /// `HeadlessRendererBuilderExt::with_headless_backends` takes precedence over both. If none
+    /// of the backends works, `NoBackendAvailable` is returned with the reason why each one
+    /// failed.
+    ///
+    /// On Windows, OpenGL ES is requested from EGL first when it is available, through ANGLE or
+    /// the drivers of AMD, in which case the context renders to a pbuffer of the requested
+    /// dimensions. Otherwise the context is created with WGL on a hidden 1×1 window that belongs
+    /// to the calling thread, so the dimensions are ignored and you must render to a framebuffer
+    /// object of your own. A WGL context can't share its objects with an EGL one.
     #[inline]
     pub fn build(self) -> Result<HeadlessContext, CreationError> {
+        if self.opengl.vsync {
+            return Err(CreationError::NotSupported);
+        }
+
         platform::HeadlessContext::new(self.dimensions, &self.pf_reqs, &self.opengl,
                                        &self.platform_specific)
                 .map(|w| HeadlessContext { context: w })
@@ -92,8 +129,13 @@ impl<'a> HeadlessRendererBuilder<'a> {
 }
 
 /// Represents a headless OpenGL context.
+///
+/// ## Platform-specific
+///
+/// - **Windows:** WGL contexts are created on a hidden window, which can only be destroyed by
+///   the thread that created it, so the context isn't `Send` nor `Sync`.
 pub struct HeadlessContext {
-    context: platform::HeadlessContext,
+    pub(crate) context: platform::HeadlessContext,
 }
 
 impl HeadlessContext {
@@ -147,11 +189,26 @@ impl GlContext for HeadlessContext {
         self.context.get_proc_address(addr)
     }
 
+    #[inline]
+    fn is_extension_supported(&self, name: &str) -> bool {
+        self.context.is_extension_supported(name)
+    }
+
     #[inline]
     fn swap_buffers(&self) -> Result<(), ContextError> {
         self.context.swap_buffers()
     }
 
+    #[inline]
+    fn swap_buffers_with_damage(&self, rects: &[Rect]) -> Result<(), ContextError> {
+        self.context.swap_buffers_with_damage(rects)
+    }
+
+    #[inline]
+    fn buffer_age(&self) -> Option<u32> {
+        self.context.buffer_age()
+    }
+
     #[inline]
     fn get_api(&self) -> Api {
         self.context.get_api()
@@ -62,12 +62,16 @@ extern crate x11_dl;
           any(target_os = "linux", target_os = "dragonfly", target_os = "freebsd", target_os = "openbsd")))]
 extern crate image;
 
+pub use debug::{DebugMessage, DebugSeverity, DebugSource, DebugType};
 pub use events::*;
+pub use framebuffer::SizedFramebuffer;
 pub use headless::{HeadlessRendererBuilder, HeadlessContext};
-pub use window::{WindowProxy, PollEventsIterator, WaitEventsIterator};
+pub use window::{WindowProxy, PollEventsIterator, WaitEventsIterator, CurrentContext, Context};
 pub use window::{AvailableMonitorsIter, MonitorId, get_available_monitors, get_primary_monitor};
+pub use window::{monitor_from_point, has_hardware_acceleration};
 pub use native_monitor::NativeMonitorId;
 
+use std::cmp;
 use std::io;
 #[cfg(all(not(target_os = "macos"),not(target_os = "ios")))]
 use std::cmp::Ordering;
@@ -75,7 +79,10 @@ use std::path::PathBuf;
 
 mod api;
 mod platform;
+mod debug;
 mod events;
+mod extensions;
+mod framebuffer;
 mod headless;
 mod window;
 
@@ -88,7 +95,7 @@ pub mod os;
 /// ```ignore
 /// let window = Window::new().unwrap();
 ///
-/// unsafe { window.make_current() };
+/// let context = window.make_current().unwrap();
 ///
 /// loop {
 ///     for event in window.poll_events() {
@@ -100,12 +107,16 @@ pub mod os;
 ///
 ///     // draw everything here
 ///
-///     window.swap_buffers();
+///     context.swap_buffers();
 ///     std::old_io::timer::sleep(17);
 /// }
 /// ```
 pub struct Window {
     window: platform::Window,
+    /// The requirements that the pixel format has been chosen with.
+    pf_reqs: PixelFormatRequirements,
+    /// The thread the context is bound to by `CurrentContext` guards, and the number of guards.
+    bound_thread: std::sync::Mutex<Option<(std::thread::ThreadId, usize)>>,
 }
 
 /// Object that allows you to build windows.
@@ -145,6 +156,106 @@ pub trait GlContext {
     /// you can't know in advance whether `swap_buffers` will block or not.
     fn swap_buffers(&self) -> Result<(), ContextError>;
 
+    /// Same as `swap_buffers`, but tells the compositor that only the given rectangles of the
+    /// back buffer have changed since the previous frame, which saves bandwidth when only a
+    /// small part of the window is redrawn.
+    ///
+    /// The origin of the rectangles is the **bottom-left** corner of the surface, like in
+    /// OpenGL and in the extensions that implement this, and not the top-left corner like in
+    /// the rest of glutin. The content of the back buffer outside of the rectangles must still
+    /// be valid, see `buffer_age`.
+    ///
+    /// An empty list of rectangles is the same as calling `swap_buffers`.
+    ///
+    /// Returns `ContextError::Unsupported` if the list isn't empty and the implementation
+    /// doesn't support `EGL_KHR_swap_buffers_with_damage` or
+    /// `EGL_EXT_swap_buffers_with_damage`, which is always the case with WGL, GLX and on macOS.
+    fn swap_buffers_with_damage(&self, rects: &[Rect]) -> Result<(), ContextError> {
+        if rects.is_empty() {
+            self.swap_buffers()
+        } else {
+            Err(ContextError::Unsupported)
+        }
+    }
+
+    /// Returns the number of frames since the content of the back buffer was presented, so that
+    /// applications can only redraw the parts of the window that changed since then.
+    ///
+    /// `Some(0)` means that the content of the back buffer is undefined and that the whole
+    /// window must be redrawn. The context must be current.
+    ///
+    /// Returns `None` with WGL, CGL and OSMesa, which have no such query, and if the
+    /// implementation supports neither `EGL_EXT_buffer_age` nor `GLX_EXT_buffer_age`.
+    fn buffer_age(&self) -> Option<u32> {
+        None
+    }
+
+    /// Flushes the OpenGL commands that have been issued so far by calling `glFlush`.
+    ///
+    /// Contrary to `swap_buffers`, this doesn't present anything on the screen. This is useful
+    /// for example to make sure that rendering to a surface has been submitted before handing it
+    /// to another process or to a compositor.
+    ///
+    /// The context must be current, otherwise this has no effect on it.
+    fn flush(&self) {
+        let flush = self.get_proc_address("glFlush");
+        if flush.is_null() {
+            return;
+        }
+
+        unsafe {
+            let flush: extern "system" fn() = std::mem::transmute(flush);
+            flush();
+        }
+    }
+
+    /// Registers a callback that receives the messages of the `GL_KHR_debug` extension, such as
+    /// errors and performance warnings, and enables the debug output.
+    ///
+    /// The context must be current. The callback replaces the one registered previously, and is
+    /// called synchronously from within the OpenGL function that triggered the message. Some
+    /// implementations only send messages if the context was created with the `debug` flag.
+    ///
+    /// The last callback is leaked when the context is destroyed, since it is owned by the
+    /// OpenGL implementation.
+    ///
+    /// Returns an error if the context supports neither `GL_KHR_debug` nor
+    /// `GL_ARB_debug_output`.
+    fn set_debug_callback(&self, callback: Box<FnMut(DebugMessage)>) -> Result<(), ContextError> {
+        debug::set_debug_callback(self, callback)
+    }
+
+    /// Reads the pixels of a rectangle of the default framebuffer, for example to save a
+    /// screenshot or to check the result of rendering in tests.
+    ///
+    /// `x` and `y` are the position of the bottom-left corner of the rectangle, with the origin
+    /// at the bottom-left corner of the framebuffer like in `glReadPixels`. The pixels are
+    /// returned as RGBA with 8 bits per component, row by row from the top of the rectangle to
+    /// its bottom like in most image formats.
+    ///
+    /// Returns `ContextError::NotCurrent` if the context isn't current on the calling thread.
+    /// With double buffering, this reads the back buffer, so it must be called before
+    /// `swap_buffers`. The framebuffer that is bound and the pack alignment are restored
+    /// afterwards.
+    fn read_framebuffer(&self, x: i32, y: i32, width: u32, height: u32)
+                        -> Result<Vec<u8>, ContextError>
+    {
+        framebuffer::read_framebuffer(self, x, y, width, height)
+    }
+
+    /// Returns true if the implementation supports the OpenGL extension or the extension of the
+    /// platform (WGL, GLX or EGL) with the given name, for example `GL_ARB_debug_output` or
+    /// `GLX_EXT_swap_control`.
+    ///
+    /// Contrary to checking whether `get_proc_address` returns a null pointer, which some
+    /// drivers don't do for functions that they don't support, this looks the name up in the
+    /// lists of extensions reported by the implementation.
+    ///
+    /// The context must be current, otherwise only the extensions of the platform are found.
+    fn is_extension_supported(&self, name: &str) -> bool {
+        extensions::is_gl_extension_supported(self, name)
+    }
+
     /// Returns the OpenGL API being used.
     fn get_api(&self) -> Api;
 
@@ -162,6 +273,9 @@ pub enum CreationError {
     RobustnessNotSupported,
     OpenGlVersionNotSupported,
     NoAvailablePixelFormat,
+    /// Two ways of creating the context were tried and both failed, for example OpenGL and then
+    /// OpenGL ES with `GlRequest::GlThenGles`. Contains the error of each attempt, in order.
+    CreationErrorPair(Box<CreationError>, Box<CreationError>),
 }
 
 impl CreationError {
@@ -176,13 +290,19 @@ impl CreationError {
                                                          supported.",
             CreationError::NoAvailablePixelFormat => "Couldn't find any pixel format that matches \
                                                       the criterias.",
+            CreationError::CreationErrorPair(_, _) => "Received two errors.",
         }
     }
 }
 
 impl std::fmt::Display for CreationError {
     fn fmt(&self, formatter: &mut std::fmt::Formatter) -> Result<(), std::fmt::Error> {
-        formatter.write_str(self.to_string())
+        match *self {
+            CreationError::CreationErrorPair(ref first, ref second) => {
+                write!(formatter, "{} Then: {}", first, second)
+            },
+            _ => formatter.write_str(self.to_string()),
+        }
     }
 }
 
@@ -194,16 +314,34 @@ impl std::error::Error for CreationError {
     fn cause(&self) -> Option<&std::error::Error> {
         match *self {
             CreationError::NoBackendAvailable(ref err) => Some(&**err),
+            CreationError::CreationErrorPair(ref first, _) => Some(&**first),
             _ => None
         }
     }
 }
 
+/// A requirement that `WindowBuilder::build_best_effort` relaxed to create the window.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum RelaxedRequirement {
+    /// Multisampling is no longer requested.
+    Multisampling,
+    /// The stencil buffer is no longer requested.
+    StencilBuffer,
+    /// The version of OpenGL or OpenGL ES was lowered to the one of this request.
+    GlVersion(GlRequest),
+}
+
 /// Error that can happen when manipulating an OpenGL context.
 #[derive(Debug)]
 pub enum ContextError {
     IoError(io::Error),
     ContextLost,
+    /// The context is bound to another thread by a `CurrentContext` guard.
+    CurrentOnOtherThread,
+    /// The operation needs the context to be current on the calling thread.
+    NotCurrent,
+    /// The operation isn't supported by the implementation or by the platform.
+    Unsupported,
 }
 
 impl ContextError {
@@ -211,7 +349,10 @@ impl ContextError {
         use std::error::Error;
         match *self {
             ContextError::IoError(ref err) => err.description(),
-            ContextError::ContextLost => "Context lost"
+            ContextError::ContextLost => "Context lost",
+            ContextError::CurrentOnOtherThread => "The context is current on another thread",
+            ContextError::NotCurrent => "The context is not current",
+            ContextError::Unsupported => "The operation is not supported",
         }
     }
 }
@@ -228,6 +369,112 @@ impl std::error::Error for ContextError {
     }
 }
 
+/// Error that can happen when changing the state of the cursor.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum CursorStateError {
+    /// Another application is already grabbing the cursor.
+    AlreadyGrabbed,
+    /// The cursor is frozen by a grab of another application.
+    Frozen,
+    /// The window isn't visible, so the cursor can't be confined to it.
+    NotViewable,
+    /// The platform can't change the state of the cursor this way.
+    Unsupported,
+    /// The window doesn't have the focus, so the cursor can't be confined to it yet. On Windows,
+    /// a grab that fails this way is applied once the window gets the focus.
+    WindowNotFocused,
+    /// A function of the system failed for another reason than the ones above, for example
+    /// because the window has been destroyed.
+    OsError {
+        /// The error code of the system, such as the one of `GetLastError` on Windows or the
+        /// status returned by `XGrabPointer` on X11, if there is one.
+        code: Option<i32>,
+        /// A description of the error.
+        message: String,
+    },
+}
+
+impl CursorStateError {
+    fn to_string(&self) -> &str {
+        match *self {
+            CursorStateError::AlreadyGrabbed => "The cursor is already grabbed by another \
+                                                 application",
+            CursorStateError::Frozen => "The cursor is frozen by another application",
+            CursorStateError::NotViewable => "The window isn't visible",
+            CursorStateError::Unsupported => "The operation is not supported",
+            CursorStateError::WindowNotFocused => "The window doesn't have the focus",
+            CursorStateError::OsError { ref message, .. } => &message,
+        }
+    }
+}
+
+impl std::fmt::Display for CursorStateError {
+    fn fmt(&self, formatter: &mut std::fmt::Formatter) -> Result<(), std::fmt::Error> {
+        match *self {
+            CursorStateError::OsError { code: Some(code), ref message } => {
+                write!(formatter, "{} (error {})", message, code)
+            },
+            _ => formatter.write_str(self.to_string()),
+        }
+    }
+}
+
+// the cursor functions used to return strings
+impl From<CursorStateError> for String {
+    #[inline]
+    fn from(err: CursorStateError) -> String {
+        format!("{}", err)
+    }
+}
+
+impl std::error::Error for CursorStateError {
+    fn description(&self) -> &str {
+        self.to_string()
+    }
+}
+
+/// Error that can happen when excluding a window from screenshots and screen sharing.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ContentProtectionError {
+    /// The platform can't exclude windows from the captures.
+    Unsupported,
+    /// The system refused to exclude the window, for example because desktop composition is
+    /// disabled on Windows.
+    OsError {
+        /// The error code of the system, such as the one of `GetLastError` on Windows, if there
+        /// is one.
+        code: Option<i32>,
+        /// A description of the error.
+        message: String,
+    },
+}
+
+impl ContentProtectionError {
+    fn to_string(&self) -> &str {
+        match *self {
+            ContentProtectionError::Unsupported => "The operation is not supported",
+            ContentProtectionError::OsError { ref message, .. } => &message,
+        }
+    }
+}
+
+impl std::fmt::Display for ContentProtectionError {
+    fn fmt(&self, formatter: &mut std::fmt::Formatter) -> Result<(), std::fmt::Error> {
+        match *self {
+            ContentProtectionError::OsError { code: Some(code), ref message } => {
+                write!(formatter, "{} (error {})", message, code)
+            },
+            _ => formatter.write_str(self.to_string()),
+        }
+    }
+}
+
+impl std::error::Error for ContentProtectionError {
+    fn description(&self) -> &str {
+        self.to_string()
+    }
+}
+
 /// All APIs related to OpenGL that you can possibly get while using glutin.
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum Api {
@@ -249,7 +496,7 @@ pub enum GlProfile {
 }
 
 /// Describes the OpenGL API and version that are being requested when a context is created.
-#[derive(Debug, Copy, Clone)]
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
 pub enum GlRequest {
     /// Request the latest version of the "best" API of this platform.
     ///
@@ -264,6 +511,10 @@ pub enum GlRequest {
     /// If OpenGL is available, create an OpenGL context with the specified `opengl_version`.
     /// Else if OpenGL ES or WebGL is available, create a context with the
     /// specified `opengles_version`.
+    ///
+    /// On Windows and X11, an OpenGL ES context is also created with EGL (ANGLE on Windows) if
+    /// creating the OpenGL context fails, and `CreationErrorPair` is returned if both fail.
+    /// `GlContext::get_api` tells which API the context uses.
     GlThenGles {
         /// The version to use for OpenGL.
         opengl_version: (u8, u8),
@@ -322,6 +573,18 @@ pub enum Robustness {
     TryRobustLoseContextOnReset,
 }
 
+/// The priority of an OpenGL context in the scheduling of the GPU.
+///
+/// Compositors and VR applications can ask for a higher priority than the other processes to
+/// meet their deadlines. The implementation may grant a lower priority than requested, for
+/// example if the process doesn't have the permission to use a high priority.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum ContextPriority {
+    Low,
+    Medium,
+    High,
+}
+
 /// The behavior of the driver when you change the current context.
 #[derive(Debug, Copy, Clone, PartialEq, Eq)]
 pub enum ReleaseBehavior {
@@ -332,7 +595,7 @@ pub enum ReleaseBehavior {
     Flush,
 }
 
-#[derive(Debug, Copy, Clone, PartialEq)]
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
 pub enum MouseCursor {
     /// The platform-dependent default cursor.
     Default,
@@ -399,10 +662,130 @@ pub enum CursorState {
 
     /// Grabs the mouse cursor. The cursor's motion will be confined to this
     /// window and the window has exclusive access to further events regarding
-    /// the cursor.
+    /// the cursor. This is the same as `CursorGrabMode::Confined`.
     ///
-    /// This is useful for first-person cameras for example.
+    /// The cursor stays visible.
     Grab,
+
+    /// Grabs the mouse cursor like `Grab`, and hides it like `Hide`.
+    ///
+    /// This is useful for first-person cameras for example.
+    GrabAndHide,
+}
+
+// only used by some of the backends
+#[allow(dead_code)]
+impl CursorState {
+    /// Returns true if the cursor is confined to the window in this state.
+    fn is_grabbed(&self) -> bool {
+        match *self {
+            CursorState::Grab | CursorState::GrabAndHide => true,
+            CursorState::Normal | CursorState::Hide => false,
+        }
+    }
+
+    /// Returns true if the cursor is invisible over the window in this state.
+    fn is_hidden(&self) -> bool {
+        match *self {
+            CursorState::Hide | CursorState::GrabAndHide => true,
+            CursorState::Normal | CursorState::Grab => false,
+        }
+    }
+
+    /// Returns the grab mode that corresponds to this state.
+    fn grab_mode(&self) -> CursorGrabMode {
+        if self.is_grabbed() { CursorGrabMode::Confined } else { CursorGrabMode::None }
+    }
+}
+
+/// How the cursor is held by a window. See `Window::set_cursor_grab`.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum CursorGrabMode {
+    /// The cursor moves freely.
+    None,
+
+    /// The cursor can move, but can't leave the window.
+    Confined,
+
+    /// The cursor stays at the same position, and the window receives the motion of the mouse
+    /// as `MouseMotion` events.
+    ///
+    /// This is useful for first-person cameras for example.
+    Locked,
+}
+
+/// An event of the platform, before glutin translates it. See `Window::set_raw_event_filter`.
+///
+/// The pointers are only valid during the call of the filter.
+#[derive(Debug, Copy, Clone)]
+pub enum RawEvent {
+    /// A message that Windows sends or posts to the window procedure.
+    Win32 {
+        /// The `HWND` of the window.
+        hwnd: *mut libc::c_void,
+        message: u32,
+        wparam: usize,
+        lparam: isize,
+    },
+
+    /// An `XEvent` of the window on X11.
+    Xlib(*const libc::c_void),
+
+    /// An `NSEvent` that the application received on macOS, before AppKit dispatches it.
+    Cocoa(*mut libc::c_void),
+}
+
+/// The filter of `Window::set_raw_event_filter`.
+pub(crate) type RawEventFilter = Box<FnMut(&RawEvent) -> bool + Send>;
+
+/// An edge or a corner of a window. See `Window::drag_resize_window`.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub enum ResizeEdge {
+    Top,
+    Bottom,
+    Left,
+    Right,
+    TopLeft,
+    TopRight,
+    BottomLeft,
+    BottomRight,
+}
+
+/// A rectangle in window coordinates, in pixels.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub struct Rect {
+    /// The position of the left edge.
+    pub x: i32,
+    /// The position of the top edge.
+    pub y: i32,
+    pub width: u32,
+    pub height: u32,
+}
+
+// only used by some of the backends
+#[allow(dead_code)]
+impl Rect {
+    /// Returns true if the point is inside of the rectangle.
+    fn contains(&self, x: i32, y: i32) -> bool {
+        x >= self.x && y >= self.y &&
+            x < self.x + self.width as i32 && y < self.y + self.height as i32
+    }
+
+    /// Returns the point of the rectangle that is the closest to the given point.
+    ///
+    /// Empty rectangles are considered to be one pixel wide and tall.
+    fn clamp(&self, x: i32, y: i32) -> (i32, i32) {
+        let right = self.x + cmp::max(self.width, 1) as i32 - 1;
+        let bottom = self.y + cmp::max(self.height, 1) as i32 - 1;
+        (cmp::min(cmp::max(x, self.x), right), cmp::min(cmp::max(y, self.y), bottom))
+    }
+
+    /// Same as `clamp`, but with subpixel precision.
+    fn clamp_precise(&self, x: f64, y: f64) -> (f64, f64) {
+        let right = (self.x + cmp::max(self.width, 1) as i32 - 1) as f64;
+        let bottom = (self.y + cmp::max(self.height, 1) as i32 - 1) as f64;
+        (x.max(self.x as f64).min(right), y.max(self.y as f64).min(bottom))
+    }
 }
 
 /// Describes a possible format. Unused.
@@ -418,6 +801,28 @@ pub struct PixelFormat {
     pub double_buffer: bool,
     pub multisampling: Option<u16>,
     pub srgb: bool,
+    /// The number of auxiliary color buffers. Always 0 with EGL and OSMesa, and on iOS.
+    pub aux_buffers: u8,
+    /// The number of bits of the accumulation buffer, for the red, green, blue and alpha
+    /// components together, or 0 if there is none. Always 0 with EGL and OSMesa, and on iOS.
+    pub accum_bits: u8,
+    /// The identifier of the format in the native API, to find out which one was picked when
+    /// debugging or to create a matching context with another library.
+    ///
+    /// This is the index of the pixel format with WGL, the `GLX_FBCONFIG_ID` with GLX, the
+    /// `EGL_CONFIG_ID` with EGL and a hash of the attributes of the `NSOpenGLPixelFormat` on
+    /// macOS. `None` if the backend has no such identifier.
+    pub native_id: Option<u64>,
+}
+
+/// The pixel format that was requested when building a window, and the one that the backend
+/// granted. See `Window::current_pixel_format`.
+#[derive(Debug, Clone)]
+pub struct CurrentPixelFormat {
+    /// The requirements that were passed to the `WindowBuilder`.
+    pub requested: PixelFormatRequirements,
+    /// The format of the window.
+    pub granted: PixelFormat,
 }
 
 /// Describes how the backend should choose a pixel format.
@@ -441,12 +846,12 @@ pub struct PixelFormatRequirements {
     /// The default is `Some(8)`.
     pub alpha_bits: Option<u8>,
 
-    /// Minimum number of bits for the depth buffer. `None` means "don't care".
-    /// The default value is `Some(24)`.
+    /// Minimum number of bits for the depth buffer. `None` means "don't care", while `Some(0)`
+    /// means that there must be no depth buffer. The default value is `Some(24)`.
     pub depth_bits: Option<u8>,
 
-    /// Minimum number of bits for the depth buffer. `None` means "don't care".
-    /// The default value is `Some(8)`.
+    /// Minimum number of bits for the stencil buffer. `None` means "don't care", while `Some(0)`
+    /// means that there must be no stencil buffer. The default value is `Some(8)`.
     pub stencil_bits: Option<u8>,
 
     /// If true, only double-buffered formats will be considered. If false, only single-buffer
@@ -525,6 +930,15 @@ pub struct WindowAttributes {
     /// The default is `None`.
     pub max_dimensions: Option<(u32, u32)>,
 
+    /// The position of the top-left hand corner of the window when it is created, in screen
+    /// coordinates. If this is `None`, the position is chosen by the system.
+    ///
+    /// The position is applied before the window is shown, so the window doesn't visibly jump
+    /// from its default location. It is ignored for fullscreen windows.
+    ///
+    /// The default is `None`.
+    pub initial_position: Option<(i32, i32)>,
+
     /// If `Some`, the window will be in fullscreen mode with the given monitor.
     ///
     /// The default is `None`.
@@ -540,6 +954,17 @@ pub struct WindowAttributes {
     /// The default is `true`.
     pub visible: bool,
 
+    /// Whether the window should be maximized upon creation. Ignored for fullscreen windows.
+    ///
+    /// The default is `false`.
+    pub maximized: bool,
+
+    /// Whether the window should be minimized upon creation. If the window is also maximized,
+    /// it is minimized and gets maximized when restored, on the platforms that support it.
+    ///
+    /// The default is `false`.
+    pub minimized: bool,
+
     /// Whether the the window should be transparent. If this is true, writing colors
     /// with alpha values different than `1.0` will produce a transparent window.
     ///
@@ -551,8 +976,20 @@ pub struct WindowAttributes {
     /// The default is `true`.
     pub decorations: bool,
 
-    /// [iOS only] Enable multitouch, see [UIView#multipleTouchEnabled]
+    /// Whether the content of the window should be excluded from screenshots and screen
+    /// sharing. See `Window::set_content_protected`.
+    ///
+    /// The window is protected before it is shown for the first time, so it is never captured.
+    /// This is ignored on the platforms that don't support it.
+    ///
+    /// The default is `false`.
+    pub content_protected: bool,
+
+    /// [iOS and X11 only] Enable multitouch, see [UIView#multipleTouchEnabled]
     /// (https://developer.apple.com/library/ios/documentation/UIKit/Reference/UIView_Class/#//apple_ref/occ/instp/UIView/multipleTouchEnabled)
+    ///
+    /// On X11, touch screens produce `Event::Touch` events if this is enabled, and emulate the
+    /// mouse otherwise.
     pub multitouch: bool,
 
     /// A path to an icon for the window. This may not be supported on every windowing system.
@@ -574,11 +1011,15 @@ impl Default for WindowAttributes {
             dimensions: None,
             min_dimensions: None,
             max_dimensions: None,
+            initial_position: None,
             monitor: None,
             title: "glutin window".to_owned(),
             visible: true,
+            maximized: false,
+            minimized: false,
             transparent: false,
             decorations: true,
+            content_protected: false,
             multitouch: false,
             icon: None,
             parent: None,
@@ -606,7 +1047,8 @@ pub struct GlAttributes<S> {
 
     /// Whether to enable the `debug` flag of the context.
     ///
-    /// Debug contexts are usually slower but give better error reporting.
+    /// Debug contexts are usually slower but give better error reporting, see
+    /// `GlContext::set_debug_callback`. This is ignored on macOS, which has no debug contexts.
     ///
     /// The default is `true` in debug mode and `false` in release mode.
     pub debug: bool,
@@ -617,6 +1059,14 @@ pub struct GlAttributes<S> {
     /// OpenGL context. However for safety you should consider `TryRobustLoseContextOnReset`.
     pub robustness: Robustness,
 
+    /// The priority of the context in the scheduling of the GPU.
+    ///
+    /// This is only a hint, which is honored by EGL when the `EGL_IMG_context_priority`
+    /// extension is available and ignored otherwise.
+    ///
+    /// The default is `None`, which lets the implementation choose.
+    pub context_priority: Option<ContextPriority>,
+
     /// Whether to use vsync. If vsync is enabled, calling `swap_buffers` will block until the
     /// screen refreshes. This is typically used to prevent screen tearing.
     ///
@@ -634,6 +1084,7 @@ impl<S> GlAttributes<S> {
             profile: self.profile,
             debug: self.debug,
             robustness: self.robustness,
+            context_priority: self.context_priority,
             vsync: self.vsync,
         }
     }
@@ -648,6 +1099,7 @@ impl<S> Default for GlAttributes<S> {
             profile: None,
             debug: cfg!(debug_assertions),
             robustness: Robustness::NotRobust,
+            context_priority: None,
             vsync: false,
         }
     }
@@ -0,0 +1,60 @@
+#![cfg(target_os = "android")]
+
+use libc;
+use Window;
+use WindowBuilder;
+
+/// Additional methods on `Window` that are specific to Android.
+pub trait WindowExt {
+    /// Returns a pointer to the `ANativeWindow` that the window renders to, for the other APIs
+    /// of the NDK that need it.
+    ///
+    /// Android destroys the native window when the app is suspended, so the pointer must not be
+    /// used after `Event::Suspended(true)` has been received. Call this function again after
+    /// `Event::Suspended(false)` to get the new one. Returns a null pointer while the app is
+    /// suspended.
+    fn get_native_window(&self) -> *mut libc::c_void;
+
+    /// Shows or hides the soft keyboard, for example while a text field has the focus.
+    ///
+    /// The input method belongs to the main thread of the activity, which is not the thread that
+    /// runs `main`. The NDK posts the request to that thread, so this can be called from any
+    /// thread but the keyboard only appears or disappears once the main thread has handled it.
+    /// The user can also hide the keyboard at any time.
+    ///
+    /// Returns `Err` if the window was built without `WindowBuilderExt::with_native_activity`.
+    ///
+    /// The keys typed on the soft keyboard are reported as `KeyboardInput` events without a
+    /// scan code nor a virtual key, and not as `ReceivedCharacter` events, because the native
+    /// activity glue doesn't forward the codes of the keys.
+    fn set_soft_keyboard_visible(&self, visible: bool) -> Result<(), ()>;
+}
+
+impl WindowExt for Window {
+    #[inline]
+    fn get_native_window(&self) -> *mut libc::c_void {
+        self.window.get_native_window()
+    }
+
+    #[inline]
+    fn set_soft_keyboard_visible(&self, visible: bool) -> Result<(), ()> {
+        self.window.set_soft_keyboard_visible(visible)
+    }
+}
+
+/// Additional methods on `WindowBuilder` that are specific to Android.
+pub trait WindowBuilderExt {
+    /// Gives the `ANativeActivity` of the app to the window, which `set_soft_keyboard_visible`
+    /// needs. The glue that starts the app receives it in `ANativeActivity_onCreate`.
+    ///
+    /// The activity must outlive the window.
+    unsafe fn with_native_activity(self, activity: *mut libc::c_void) -> Self;
+}
+
+impl<'a> WindowBuilderExt for WindowBuilder<'a> {
+    #[inline]
+    unsafe fn with_native_activity(mut self, activity: *mut libc::c_void) -> WindowBuilder<'a> {
+        self.platform_specific.activity = activity;
+        self
+    }
+}
@@ -3,7 +3,9 @@
 use std::convert::From;
 use std::os::raw::c_void;
 use cocoa::appkit::NSApplicationActivationPolicy;
-use {Window, WindowBuilder};
+use cocoa::foundation::{NSPoint, NSRect, NSSize};
+use {Context, CreationError, HeadlessContext, Window, WindowBuilder};
+use platform;
 
 /// Additional methods on `Window` that are specific to MacOS.
 pub trait WindowExt {
@@ -11,6 +13,40 @@ pub trait WindowExt {
     ///
     /// The pointer will become invalid when the glutin `Window` is destroyed.
     fn get_nswindow(&self) -> *mut c_void;
+
+    /// Shows or hides the close, miniaturize and zoom buttons of the title bar, for example to
+    /// draw custom ones.
+    ///
+    /// Only windows with decorations have these buttons, so this does nothing for borderless,
+    /// transparent and fullscreen windows. Hiding a button doesn't disable its action: the
+    /// window can still be closed with Cmd+W, for example.
+    fn set_button_visibility(&self, close: bool, miniaturize: bool, zoom: bool);
+
+    /// Sets the cursor to the system cursor with the given name, for the cursors that
+    /// `MouseCursor` doesn't have. The names are the ones of the class methods of `NSCursor`
+    /// that return a cursor, such as `"disappearingItemCursor"`.
+    ///
+    /// Returns `Err` and sets the arrow cursor if `NSCursor` has no cursor with this name.
+    fn set_cursor_by_name(&self, name: &str) -> Result<(), ()>;
+
+    /// Sets the subtitle that is shown under the title in the title bar, such as the path of
+    /// the document. An empty string removes it.
+    ///
+    /// Subtitles only exist since macOS 11, so this does nothing on older versions.
+    fn set_subtitle(&self, subtitle: &str);
+
+    /// Creates a headless context that shares its objects with the context of the window and
+    /// renders to an `IOSurface` of the given dimensions, which the window can then sample. This
+    /// lets another thread render the frames, for example to process video, while the window
+    /// composites them. See `HeadlessContextExt::get_io_surface`.
+    ///
+    /// The context uses the pixel format of the window. It must not be used after the window is
+    /// destroyed.
+    ///
+    /// On the other platforms, a context created by `Window::create_shared_context` can render
+    /// to a texture of a framebuffer object, which the context of the window samples.
+    fn create_io_surface_context(&self, width: u32, height: u32)
+                                 -> Result<HeadlessContext, CreationError>;
 }
 
 impl WindowExt for Window {
@@ -18,6 +54,84 @@ impl WindowExt for Window {
     fn get_nswindow(&self) -> *mut c_void {
         self.window.platform_window() as *mut c_void
     }
+
+    #[inline]
+    fn set_button_visibility(&self, close: bool, miniaturize: bool, zoom: bool) {
+        self.window.set_button_visibility(close, miniaturize, zoom)
+    }
+
+    #[inline]
+    fn set_cursor_by_name(&self, name: &str) -> Result<(), ()> {
+        self.window.set_cursor_by_name(name)
+    }
+
+    #[inline]
+    fn set_subtitle(&self, subtitle: &str) {
+        self.window.set_subtitle(subtitle)
+    }
+
+    #[inline]
+    fn create_io_surface_context(&self, width: u32, height: u32)
+                                 -> Result<HeadlessContext, CreationError>
+    {
+        self.window.create_io_surface_context((width, height))
+                   .map(|context| HeadlessContext { context: context })
+    }
+}
+
+/// Additional methods on `HeadlessContext` that are specific to MacOS.
+pub trait HeadlessContextExt {
+    /// Returns the `IOSurfaceRef` that the context renders to, or `None` if it hasn't been
+    /// created by `WindowExt::create_io_surface_context`.
+    ///
+    /// The surface has a BGRA layout with 4 bytes per pixel. Each context that uses it binds it
+    /// to a texture of its own by calling `CGLTexImageIOSurface2D` with the
+    /// `GL_TEXTURE_RECTANGLE` target, the `GL_RGBA` internal format, the `GL_BGRA` format and
+    /// the `GL_UNSIGNED_INT_8_8_8_8_REV` type. The headless context renders to it through a
+    /// framebuffer object, and must call `glFlush` before the window samples it.
+    ///
+    /// The surface is owned by glutin: the pointer becomes invalid when the context is
+    /// destroyed, unless it is retained with `CFRetain`.
+    fn get_io_surface(&self) -> Option<*mut c_void>;
+}
+
+impl HeadlessContextExt for HeadlessContext {
+    #[inline]
+    fn get_io_surface(&self) -> Option<*mut c_void> {
+        self.context.get_io_surface()
+    }
+}
+
+/// Additional methods on the objects that own an OpenGL context, which are specific to MacOS.
+pub trait ContextExt {
+    /// Returns the `CGLContextObj` of the OpenGL context, for interoperability with APIs that
+    /// take it, such as OpenCL or IOSurface.
+    ///
+    /// The context is owned by glutin: it must not be released, and the pointer becomes invalid
+    /// when the glutin object is destroyed. The context must not be made current through it
+    /// while it is current in another thread.
+    fn get_cgl_context(&self) -> *mut c_void;
+}
+
+impl ContextExt for Window {
+    #[inline]
+    fn get_cgl_context(&self) -> *mut c_void {
+        self.window.get_cgl_context()
+    }
+}
+
+impl ContextExt for Context {
+    #[inline]
+    fn get_cgl_context(&self) -> *mut c_void {
+        self.context.get_cgl_context()
+    }
+}
+
+impl ContextExt for HeadlessContext {
+    #[inline]
+    fn get_cgl_context(&self) -> *mut c_void {
+        self.context.get_cgl_context()
+    }
 }
 
 /// Corresponds to `NSApplicationActivationPolicy`.
@@ -54,6 +168,24 @@ impl From<ActivationPolicy> for NSApplicationActivationPolicy {
 pub trait WindowBuilderExt<'a> {
     fn with_activation_policy(self, activation_policy: ActivationPolicy) -> WindowBuilder<'a>;
     fn with_app_name(self, app_name: String) -> WindowBuilder<'a>;
+
+    /// Disables the popup that macOS shows to type accented characters when a key is held, so
+    /// that the key is repeated instead, which is what games expect.
+    ///
+    /// This sets `ApplePressAndHoldEnabled` to `NO` in the user defaults of the app while the
+    /// window exists, so it affects all the windows of the app, and the value is restored when
+    /// the window is destroyed. Text fields of the app can't offer the accented characters
+    /// meanwhile, and the previous value isn't restored if the process is killed.
+    fn with_press_and_hold_disabled(self, disabled: bool) -> WindowBuilder<'a>;
+
+    /// Sends a `DisplayLinkTick` event at each refresh of the screen of the window, from a
+    /// `CVDisplayLink` that follows the window when it moves to another screen. Rendering a frame
+    /// for each tick paces the animations with the screen, instead of relying on the blocking of
+    /// `swap_buffers`.
+    ///
+    /// The event loop is woken up at each tick, and a single tick is queued at a time, so the
+    /// ticks that arrive while the application is still rendering are skipped.
+    fn with_display_link(self, enabled: bool) -> WindowBuilder<'a>;
 }
 
 impl<'a> WindowBuilderExt<'a> for WindowBuilder<'a> {
@@ -70,4 +202,38 @@ impl<'a> WindowBuilderExt<'a> for WindowBuilder<'a> {
         self.platform_specific.app_name = Some(app_name);
         self
     }
+
+    #[inline]
+    fn with_press_and_hold_disabled(mut self, disabled: bool) -> WindowBuilder<'a> {
+        self.platform_specific.disable_press_and_hold = disabled;
+        self
+    }
+
+    #[inline]
+    fn with_display_link(mut self, enabled: bool) -> WindowBuilder<'a> {
+        self.platform_specific.display_link = enabled;
+        self
+    }
+}
+
+/// Converts the origin of a rectangle of height `height` in the screen coordinates of Cocoa to
+/// the position of its top-left hand corner in the coordinates of `Window::get_position`.
+///
+/// The origin of Cocoa is the bottom-left hand corner of the primary screen, the one with the
+/// menu bar, and its y axis goes up. The origin of glutin is the top-left hand corner of the same
+/// screen and its y axis goes down, so the screens on the left or above it have negative
+/// coordinates. This is the convention of Windows as well.
+pub fn cocoa_to_top_left(x: f64, y: f64, height: f64) -> (i32, i32) {
+    let rect = NSRect::new(NSPoint::new(x, y), NSSize::new(0., height));
+    unsafe { platform::cocoa_to_top_left(rect) }
+}
+
+/// Converts the position of the top-left hand corner of a rectangle of height `height`, in the
+/// coordinates of `Window::get_position`, to the origin of the rectangle in the screen
+/// coordinates of Cocoa.
+///
+/// This is the inverse of `cocoa_to_top_left`.
+pub fn top_left_to_cocoa(x: i32, y: i32, height: f64) -> (f64, f64) {
+    let point = unsafe { platform::top_left_to_cocoa(x, y, height) };
+    (point.x, point.y)
 }
@@ -1,9 +1,58 @@
 #![cfg(any(target_os = "linux", target_os = "dragonfly", target_os = "freebsd", target_os = "openbsd"))]
 
+use std::os::unix::io::RawFd;
+use std::sync::Arc;
+
 use libc;
+use Context;
 use Window;
+use api::x11::XConnection as X11Connection;
+pub use api::x11::XError;
+pub use api::x11::ffi::XVisualInfo;
+use api::egl::Context as EglContext;
+use api::glx::Context as GlxContext;
+use platform::HeadlessContext as LinuxHeadlessContext;
+use platform::SharedContext as LinuxSharedContext;
 use platform::Window as LinuxWindow;
+use HeadlessContext;
+use HeadlessRendererBuilder;
+use MonitorId;
 use WindowBuilder;
+use WindowID;
+
+/// A handle to the connection to the X server that is used by glutin.
+///
+/// See `WindowExt::get_xlib_xconnection`.
+#[derive(Clone)]
+pub struct XConnection {
+    inner: Arc<X11Connection>,
+}
+
+impl XConnection {
+    /// Returns a pointer to the `Display` object of xlib.
+    ///
+    /// The pointer stays valid as long as a clone of this handle is alive.
+    #[inline]
+    pub fn get_xlib_display(&self) -> *mut libc::c_void {
+        self.inner.display as *mut libc::c_void
+    }
+
+    /// Takes the latest error that the X server reported for the connection.
+    #[inline]
+    pub fn check_errors(&self) -> Result<(), XError> {
+        self.inner.check_errors()
+    }
+
+    /// Waits for the X server to process all the requests sent so far, then takes the latest
+    /// error that it reported.
+    ///
+    /// Errors are reported asynchronously by the server, so `check_errors` alone doesn't see the
+    /// errors of requests that haven't been processed yet.
+    #[inline]
+    pub fn sync_and_check_errors(&self) -> Result<(), XError> {
+        self.inner.sync_and_check_errors()
+    }
+}
 
 /// Additional methods on `Window` that are specific to Unix.
 pub trait WindowExt {
@@ -20,6 +69,83 @@ pub trait WindowExt {
     ///
     /// The pointer will become invalid when the glutin `Window` is destroyed.
     fn get_xlib_display(&self) -> Option<*mut libc::c_void>;
+
+    /// Returns the file descriptor of the connection to the X server, which becomes readable
+    /// when events arrive, so that the window can be handled by an event loop that waits for
+    /// several sources at once, such as the one of an async runtime.
+    ///
+    /// Xlib reads all the available events at once, and glutin queues some events itself, so
+    /// `poll_events` must be called until it returns `None` every time the descriptor is
+    /// readable, and before waiting for it the first time. Otherwise events can stay in the
+    /// queues until the next one arrives. `WindowProxy::wakeup_event_loop` sends an event
+    /// through the connection, so it also makes the descriptor readable.
+    ///
+    /// The descriptor is shared by all the windows of glutin. It must neither be read from nor
+    /// closed.
+    ///
+    /// Returns `None` if the window doesn't use xlib.
+    fn get_event_fd(&self) -> Option<RawFd>;
+
+    /// Takes the latest error that the X server reported for the connection of this window.
+    ///
+    /// X errors are asynchronous, so they may be reported a while after the request that
+    /// triggered them. Applications can poll this function to be notified of them.
+    ///
+    /// Returns `None` if there is no error or if the window doesn't use xlib.
+    fn get_xlib_error(&self) -> Option<XError>;
+
+    /// Returns the connection to the X server that is used by this window.
+    ///
+    /// Holding the returned handle keeps the connection, and thus the pointer returned by
+    /// `get_xlib_display`, alive even after the window is destroyed. The connection is shared by
+    /// all the windows of glutin, so it must never be closed.
+    ///
+    /// `XInitThreads` has been called before opening the display, so it can be used from any
+    /// thread. However the X errors triggered by your requests are reported to glutin's error
+    /// handler, so you must retrieve them with `get_xlib_error` or `XConnection::check_errors`,
+    /// and they may be mixed up with the errors of glutin's own requests if several threads use
+    /// the connection at the same time.
+    ///
+    /// Returns `None` if the window doesn't use xlib.
+    fn get_xlib_xconnection(&self) -> Option<XConnection>;
+
+    /// Returns the visual that the window and its OpenGL context have been created with.
+    ///
+    /// Windows that are meant to share the context, such as child windows created by a toolkit,
+    /// must use this visual and depth, otherwise the X server reports a `BadMatch` error. The
+    /// `visual` pointer is valid as long as the connection is alive.
+    ///
+    /// Returns `None` if the window doesn't use xlib.
+    fn get_xlib_visual_info(&self) -> Option<XVisualInfo>;
+
+    /// Returns the `GLX_FBCONFIG_ID` of the framebuffer configuration of the OpenGL context.
+    ///
+    /// Returns `None` if the context doesn't use GLX.
+    fn get_glx_fbconfig_id(&self) -> Option<i32>;
+
+    /// Returns true if the window manager has resized the window and waits for it to be
+    /// redrawn before showing it at its new size, which avoids showing a frame with the old
+    /// content while the user resizes the window.
+    ///
+    /// Render loops can use this to draw the next frame right away. The resize is completed by
+    /// the next call to `swap_buffers`, or explicitly with `complete_resize`.
+    ///
+    /// Always returns false if the window doesn't use xlib, or if the X server doesn't support
+    /// the XSync extension.
+    fn is_resize_pending(&self) -> bool;
+
+    /// Tells the window manager that the window has been redrawn since it was resized. Only
+    /// needed for windows that don't present their frames with `swap_buffers`.
+    ///
+    /// Does nothing if no resize is pending.
+    fn complete_resize(&self);
+
+    /// Sets the cursor to the one with the given name in the cursor theme, for the cursors that
+    /// `MouseCursor` doesn't have. The names are the ones of the freedesktop cursor
+    /// specification, such as `"dnd-ask"`, or of the legacy X cursors, such as `"pirate"`.
+    ///
+    /// Returns `Err` and sets the arrow cursor if the theme has no cursor with this name.
+    fn set_cursor_by_name(&self, name: &str) -> Result<(), ()>;
 }
 
 impl WindowExt for Window {
@@ -36,12 +162,333 @@ impl WindowExt for Window {
             LinuxWindow::X(ref w) => Some(w.get_xlib_display()),
         }
     }
+
+    #[inline]
+    fn get_event_fd(&self) -> Option<RawFd> {
+        match self.window {
+            LinuxWindow::X(ref w) => Some(w.get_event_fd()),
+        }
+    }
+
+    #[inline]
+    fn get_xlib_error(&self) -> Option<XError> {
+        match self.window {
+            LinuxWindow::X(ref w) => w.get_xlib_error(),
+        }
+    }
+
+    #[inline]
+    fn get_xlib_xconnection(&self) -> Option<XConnection> {
+        match self.window {
+            LinuxWindow::X(ref w) => Some(XConnection { inner: w.get_xlib_xconnection() }),
+        }
+    }
+
+    #[inline]
+    fn get_xlib_visual_info(&self) -> Option<XVisualInfo> {
+        match self.window {
+            LinuxWindow::X(ref w) => Some(w.get_xlib_visual_info()),
+        }
+    }
+
+    #[inline]
+    fn get_glx_fbconfig_id(&self) -> Option<i32> {
+        match self.window {
+            LinuxWindow::X(ref w) => w.get_glx_fbconfig_id(),
+        }
+    }
+
+    #[inline]
+    fn is_resize_pending(&self) -> bool {
+        match self.window {
+            LinuxWindow::X(ref w) => w.is_resize_pending(),
+        }
+    }
+
+    #[inline]
+    fn complete_resize(&self) {
+        match self.window {
+            LinuxWindow::X(ref w) => w.complete_resize(),
+        }
+    }
+
+    #[inline]
+    fn set_cursor_by_name(&self, name: &str) -> Result<(), ()> {
+        match self.window {
+            LinuxWindow::X(ref w) => w.set_cursor_by_name(name),
+        }
+    }
+}
+
+/// The type of an X11 window, which the window manager uses to decide how to decorate and place
+/// it. Corresponds to the `_NET_WM_WINDOW_TYPE` property.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum WindowType {
+    /// A normal, top-level window.
+    Normal,
+    /// A dialog window. Dialogs should usually be transient for another window, see
+    /// `with_x11_transient_for`.
+    Dialog,
+    /// A splash screen displayed while an application is starting up.
+    Splash,
+    /// A small persistent window, such as a palette or toolbox.
+    Utility,
+    /// A dock or panel. Docks can reserve space at the edges of the screen, see
+    /// `with_x11_strut`.
+    Dock,
+    /// A toolbar torn off from the main window.
+    Toolbar,
+    /// A menu torn off from the main window.
+    Menu,
+    /// A notification, such as a bubble that appears in a corner of the screen.
+    Notification,
+}
+
+impl Default for WindowType {
+    #[inline]
+    fn default() -> WindowType {
+        WindowType::Normal
+    }
+}
+
+/// The space that a window reserves at the edges of the screen, in pixels. Corresponds to the
+/// `_NET_WM_STRUT_PARTIAL` property.
+///
+/// Maximized windows don't cover the reserved space. The space is reserved along the whole side
+/// of the window that touches the edge.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct Strut {
+    pub left: u32,
+    pub right: u32,
+    pub top: u32,
+    pub bottom: u32,
 }
 
 /// Additional methods on `WindowBuilder` that are specific to Unix.
 pub trait WindowBuilderExt {
+    /// Requests that the window manager ignores the window, by setting the `override_redirect`
+    /// attribute of the X11 window.
+    ///
+    /// The window will have no decorations, and the window manager will neither move it,
+    /// resize it nor give it the focus. Stacking and focus become the responsibility of the
+    /// application, which makes this useful for popups, tooltips and overlays.
+    ///
+    /// The window still receives the input events, and `set_position` takes effect immediately.
+    fn with_override_redirect(self, override_redirect: bool) -> Self;
+
+    /// Sets the type of the window, which the window manager uses to decide how to treat it.
+    ///
+    /// The default is `WindowType::Normal`.
+    fn with_x11_window_type(self, window_type: WindowType) -> Self;
 
+    /// Reserves space at the edges of the screen, which is mostly useful for windows of type
+    /// `WindowType::Dock`.
+    fn with_x11_strut(self, strut: Strut) -> Self;
+
+    /// Tells the window manager that the window is transient for the given X11 window, usually
+    /// because it is a dialog that belongs to it. It is then kept above it and isn't shown in
+    /// the taskbar.
+    ///
+    /// Unlike `with_parent`, the window isn't embedded in the other one.
+    fn with_x11_transient_for(self, window: WindowID) -> Self;
+
+    /// Makes a fullscreen window span these monitors in addition to the one passed to
+    /// `with_fullscreen`, for example to cover a video wall. The window covers the smallest
+    /// rectangle that contains all of them. Corresponds to `_NET_WM_FULLSCREEN_MONITORS`.
+    ///
+    /// Window managers can only tell the monitors apart when Xinerama is active, which is the
+    /// case with RandR; otherwise each monitor is a separate X screen that can't be spanned.
+    /// Has no effect if the window isn't fullscreen.
+    fn with_x11_fullscreen_monitors(self, monitors: Vec<MonitorId>) -> Self;
 }
 
 impl<'a> WindowBuilderExt for WindowBuilder<'a> {
+    #[inline]
+    fn with_override_redirect(mut self, override_redirect: bool) -> WindowBuilder<'a> {
+        self.platform_specific.override_redirect = override_redirect;
+        self
+    }
+
+    #[inline]
+    fn with_x11_window_type(mut self, window_type: WindowType) -> WindowBuilder<'a> {
+        self.platform_specific.x11_window_type = window_type;
+        self
+    }
+
+    #[inline]
+    fn with_x11_strut(mut self, strut: Strut) -> WindowBuilder<'a> {
+        self.platform_specific.x11_strut = Some(strut);
+        self
+    }
+
+    #[inline]
+    fn with_x11_transient_for(mut self, window: WindowID) -> WindowBuilder<'a> {
+        self.platform_specific.x11_transient_for = Some(window);
+        self
+    }
+
+    #[inline]
+    fn with_x11_fullscreen_monitors(mut self, monitors: Vec<MonitorId>) -> WindowBuilder<'a> {
+        self.platform_specific.x11_fullscreen_monitors = monitors.into_iter()
+                                                                 .map(|monitor| monitor.0)
+                                                                 .collect();
+        self
+    }
+}
+
+/// A backend that headless contexts can be created with.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum HeadlessBackend {
+    /// An EGL context that isn't bound to any surface, which requires
+    /// `EGL_KHR_surfaceless_context`. It is created on the surfaceless platform of Mesa if
+    /// possible, so that it only needs a GPU. Corresponds to `egl-surfaceless-context` in
+    /// `GLUTIN_HEADLESS_BACKENDS`.
+    ///
+    /// The context has no default framebuffer and ignores the dimensions, so you must render to a
+    /// framebuffer object. This backend isn't tried by default for this reason.
+    EglSurfacelessContext,
+    /// An EGL pbuffer on the surfaceless platform of Mesa, which doesn't need a display server.
+    /// Corresponds to `egl-surfaceless`.
+    EglSurfaceless,
+    /// An EGL pbuffer on the default display of EGL. Corresponds to `egl`.
+    Egl,
+    /// A buffer in memory rendered to by OSMesa, the software renderer of Mesa, which needs
+    /// neither a GPU nor a display server. Corresponds to `osmesa`.
+    OsMesa,
+}
+
+/// Additional methods on `HeadlessRendererBuilder` that are specific to Unix.
+pub trait HeadlessRendererBuilderExt<'a> {
+    /// Sets the backends to try, in this order, instead of the default ones or those of the
+    /// `GLUTIN_HEADLESS_BACKENDS` environment variable.
+    ///
+    /// For example `&[HeadlessBackend::OsMesa]` forces software rendering, so that tests give
+    /// the same results on every machine, and `&[HeadlessBackend::EglSurfacelessContext,
+    /// HeadlessBackend::EglSurfaceless, HeadlessBackend::Egl]` avoids allocating a pbuffer when
+    /// the implementation allows it.
+    fn with_headless_backends(self, backends: &[HeadlessBackend]) -> HeadlessRendererBuilder<'a>;
+}
+
+impl<'a> HeadlessRendererBuilderExt<'a> for HeadlessRendererBuilder<'a> {
+    #[inline]
+    fn with_headless_backends(mut self, backends: &[HeadlessBackend])
+                              -> HeadlessRendererBuilder<'a>
+    {
+        self.platform_specific.backends = Some(backends.to_vec());
+        self
+    }
+}
+
+/// Additional methods on `HeadlessContext` that are specific to Unix.
+pub trait HeadlessContextExt {
+    /// Returns the buffer that OSMesa renders to, as RGBA bytes with the bottom row first.
+    ///
+    /// The content is only up to date once the rendering commands have been executed, for
+    /// example after `glFinish`.
+    ///
+    /// Returns `None` if the context doesn't use OSMesa.
+    fn get_osmesa_buffer(&self) -> Option<&[u8]>;
+}
+
+impl HeadlessContextExt for HeadlessContext {
+    #[inline]
+    fn get_osmesa_buffer(&self) -> Option<&[u8]> {
+        match self.context {
+            LinuxHeadlessContext::OsMesa(ref c) => Some(c.get_framebuffer_bytes()),
+            _ => None,
+        }
+    }
+}
+
+/// The native handles of an OpenGL context, for interoperability with APIs that take them, such
+/// as OpenCL or a video decoder.
+#[derive(Debug, Copy, Clone)]
+pub enum RawContext {
+    /// A GLX context.
+    Glx {
+        /// The `Display` of xlib that the context has been created on.
+        display: *mut libc::c_void,
+        /// The `GLXContext`.
+        context: *mut libc::c_void,
+        /// The X window that the context renders to.
+        drawable: libc::c_ulong,
+    },
+
+    /// An EGL context.
+    Egl {
+        /// The `EGLDisplay`.
+        display: *mut libc::c_void,
+        /// The `EGLContext`.
+        context: *mut libc::c_void,
+        /// The `EGLSurface`, which is `EGL_NO_SURFACE` for a surfaceless context.
+        surface: *mut libc::c_void,
+    },
+
+    /// An OSMesa context.
+    OsMesa {
+        /// The `OSMesaContext`.
+        context: *mut libc::c_void,
+    },
+}
+
+impl RawContext {
+    #[inline]
+    pub(crate) fn from_glx(context: &GlxContext) -> RawContext {
+        RawContext::Glx {
+            display: context.get_display() as *mut libc::c_void,
+            context: context.get_glx_context() as *mut libc::c_void,
+            drawable: context.get_drawable(),
+        }
+    }
+
+    #[inline]
+    pub(crate) fn from_egl(context: &EglContext) -> RawContext {
+        RawContext::Egl {
+            display: context.get_egl_display() as *mut libc::c_void,
+            context: context.get_egl_context() as *mut libc::c_void,
+            surface: context.get_egl_surface() as *mut libc::c_void,
+        }
+    }
+}
+
+/// Additional methods on the objects that own an OpenGL context, which are specific to Unix.
+pub trait ContextExt {
+    /// Returns the native handles of the OpenGL context.
+    ///
+    /// The handles are owned by glutin: they must not be destroyed, and they become invalid when
+    /// the glutin object is destroyed. The context must not be made current through them while
+    /// it is current in another thread.
+    ///
+    /// Returns `None` if the window has no OpenGL context.
+    fn get_raw_context(&self) -> Option<RawContext>;
+}
+
+impl ContextExt for Window {
+    #[inline]
+    fn get_raw_context(&self) -> Option<RawContext> {
+        match self.window {
+            LinuxWindow::X(ref w) => w.get_raw_context(),
+        }
+    }
+}
+
+impl ContextExt for Context {
+    #[inline]
+    fn get_raw_context(&self) -> Option<RawContext> {
+        match self.context {
+            LinuxSharedContext::X(ref c) => Some(c.get_raw_context()),
+        }
+    }
+}
+
+impl ContextExt for HeadlessContext {
+    #[inline]
+    fn get_raw_context(&self) -> Option<RawContext> {
+        match self.context {
+            LinuxHeadlessContext::Egl(ref c) => Some(RawContext::from_egl(c)),
+            LinuxHeadlessContext::OsMesa(ref c) => Some(RawContext::OsMesa {
+                context: c.get_osmesa_context() as *mut libc::c_void,
+            }),
+        }
+    }
 }
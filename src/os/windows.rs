@@ -1,9 +1,16 @@
 #![cfg(target_os = "windows")]
 
 use libc;
+use Context;
+use HeadlessContext;
+use Rect;
 use Window;
 use WindowBuilder;
 
+use api::egl::Context as EglContext;
+use api::wgl::Context as WglContext;
+use platform::HeadlessContext as WindowsHeadlessContext;
+
 /// Additional methods on `Window` that are specific to Windows.
 pub trait WindowExt {
     /// Returns a pointer to the `Window` object of xlib that is used by this window.
@@ -12,6 +19,34 @@ pub trait WindowExt {
     ///
     /// The pointer will become invalid when the glutin `Window` is destroyed.
     fn get_hwnd(&self) -> *mut libc::c_void;
+
+    /// Dispatches the messages that are waiting in the queue of the calling thread, which turns
+    /// the messages of the window into events that `poll_events` returns.
+    ///
+    /// This is only needed for windows built with `with_message_thread(false)`, and must be
+    /// called on the thread that built the window. Messages of other windows of the thread are
+    /// dispatched as well, so this can replace the events loop of an application that embeds
+    /// the window. If the loop of the application already dispatches the messages, this doesn't
+    /// need to be called at all.
+    ///
+    /// Returns `Err` without dispatching anything if called on another thread than the one that
+    /// built the window.
+    fn pump_messages(&self) -> Result<(), ()>;
+
+    /// Sets the width in pixels of the borders that can be dragged to resize the window when it
+    /// doesn't have decorations. See `WindowBuilderExt::with_resize_border`.
+    fn set_resize_border(&self, width: u32);
+
+    /// Sets the region of the window that can be dragged to move it when it doesn't have
+    /// decorations. See `WindowBuilderExt::with_caption_region`.
+    fn set_caption_region(&self, region: Option<Rect>);
+
+    /// Sets the cursor to the system cursor with the given name, for the cursors that
+    /// `MouseCursor` doesn't have. The names are the ones of the `IDC_*` constants of
+    /// `LoadCursor`, such as `"IDC_PERSON"` or `"IDC_PIN"`.
+    ///
+    /// Returns `Err` and sets the arrow cursor if there is no system cursor with this name.
+    fn set_cursor_by_name(&self, name: &str) -> Result<(), ()>;
 }
 
 impl WindowExt for Window {
@@ -19,12 +54,226 @@ impl WindowExt for Window {
     fn get_hwnd(&self) -> *mut libc::c_void {
         self.window.platform_window()
     }
+
+    #[inline]
+    fn pump_messages(&self) -> Result<(), ()> {
+        self.window.pump_messages()
+    }
+
+    #[inline]
+    fn set_resize_border(&self, width: u32) {
+        self.window.set_resize_border(width)
+    }
+
+    #[inline]
+    fn set_caption_region(&self, region: Option<Rect>) {
+        self.window.set_caption_region(region)
+    }
+
+    #[inline]
+    fn set_cursor_by_name(&self, name: &str) -> Result<(), ()> {
+        self.window.set_cursor_by_name(name)
+    }
 }
 
 /// Additional methods on `WindowBuilder` that are specific to Windows.
 pub trait WindowBuilderExt {
+    /// If false, the window is created on the calling thread instead of the thread that runs
+    /// the events loop of the other windows, and its events are only delivered when the
+    /// messages of the thread are dispatched, either by the events loop of the application or
+    /// by `WindowExt::pump_messages`.
+    ///
+    /// `wait_events` pumps the messages by itself in that case, and returns `None` once the
+    /// thread received `WM_QUIT`, which is posted again when the window is dropped. The window
+    /// can still be sent to other threads, but its messages can only be pumped by the thread
+    /// that built it: `pump_messages` returns `Err` elsewhere, `wait_events` waits for that
+    /// thread to dispatch them, and dropping the window only destroys it when that thread
+    /// dispatches its messages.
+    ///
+    /// The default is true.
+    fn with_message_thread(self, message_thread: bool) -> Self;
+
+    /// Sets the width in pixels of the borders of the client area that can be dragged to resize
+    /// the window, like the frame of decorated windows.
+    ///
+    /// Windows without decorations don't have a frame, so they can't be resized by the user
+    /// otherwise. This has no effect on decorated and fullscreen windows. The size limits of the
+    /// window are still enforced.
+    ///
+    /// The default is 0, which means that the window can't be resized by the user.
+    fn with_resize_border(self, width: u32) -> Self;
 
+    /// Sets the region of the client area that can be dragged to move the window, like the title
+    /// bar of decorated windows. Double-clicking it maximizes the window as well.
+    ///
+    /// The resize border takes precedence over this region. This has no effect on decorated and
+    /// fullscreen windows.
+    ///
+    /// The default is `None`.
+    fn with_caption_region(self, region: Option<Rect>) -> Self;
+
+    /// If true, windows without decorations have the drop shadow of decorated windows, so that
+    /// they don't look flat. This requires desktop composition, which is always enabled since
+    /// Windows 8.
+    ///
+    /// The shadow is drawn around a 1px frame that DWM composes behind the edges of the client
+    /// area, so the pixels of these edges must be opaque for the frame not to show through.
+    /// This has no effect on decorated and fullscreen windows.
+    ///
+    /// The default is false.
+    fn with_drop_shadow(self, drop_shadow: bool) -> Self;
+
+    /// Sets what DWM draws behind the transparent pixels of a window built with
+    /// `with_transparency(true)`. This has no effect on opaque windows.
+    ///
+    /// The default is `Backdrop::BlurBehind`.
+    fn with_backdrop(self, backdrop: Backdrop) -> Self;
+
+    /// If true, the window is resized to the rectangle suggested by Windows when it moves to a
+    /// monitor with another DPI, so that it keeps the same physical size. A `Resized` event
+    /// follows the `HiDpiFactorChanged` and `HiDpiSuggestedSize` events in that case.
+    ///
+    /// Set it to false to choose the new size with `set_inner_size` after receiving
+    /// `HiDpiFactorChanged` instead. The `HiDpiSuggestedSize` event that follows it still
+    /// contains the size that Windows suggests.
+    ///
+    /// The default is true.
+    fn with_dpi_resize(self, dpi_resize: bool) -> Self;
+}
+
+/// What DWM draws behind a transparent window.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum Backdrop {
+    /// The desktop and the windows below are visible as they are.
+    None,
+
+    /// The desktop and the windows below are blurred. Since Windows 8, the blur is only applied
+    /// if the window doesn't have decorations.
+    BlurBehind,
+
+    /// The acrylic material of the transient windows of Windows 11, such as menus. Falls back to
+    /// `BlurBehind` on older versions.
+    ///
+    /// The shadow of the frame is always drawn, so `with_drop_shadow` has no effect.
+    Acrylic,
+
+    /// The mica material of the main windows of Windows 11, which shows the tinted desktop
+    /// wallpaper. Falls back to `BlurBehind` on older versions.
+    ///
+    /// The shadow of the frame is always drawn, so `with_drop_shadow` has no effect.
+    Mica,
 }
 
 impl<'a> WindowBuilderExt for WindowBuilder<'a> {
+    #[inline]
+    fn with_message_thread(mut self, message_thread: bool) -> WindowBuilder<'a> {
+        self.platform_specific.message_thread = message_thread;
+        self
+    }
+
+    #[inline]
+    fn with_resize_border(mut self, width: u32) -> WindowBuilder<'a> {
+        self.platform_specific.resize_border = width;
+        self
+    }
+
+    #[inline]
+    fn with_caption_region(mut self, region: Option<Rect>) -> WindowBuilder<'a> {
+        self.platform_specific.caption_region = region;
+        self
+    }
+
+    #[inline]
+    fn with_drop_shadow(mut self, drop_shadow: bool) -> WindowBuilder<'a> {
+        self.platform_specific.drop_shadow = drop_shadow;
+        self
+    }
+
+    #[inline]
+    fn with_backdrop(mut self, backdrop: Backdrop) -> WindowBuilder<'a> {
+        self.platform_specific.backdrop = backdrop;
+        self
+    }
+
+    #[inline]
+    fn with_dpi_resize(mut self, dpi_resize: bool) -> WindowBuilder<'a> {
+        self.platform_specific.dpi_resize = dpi_resize;
+        self
+    }
+}
+
+/// The native handles of an OpenGL context, for interoperability with APIs that take them, such
+/// as OpenCL or Direct3D through `WGL_NV_DX_interop`.
+#[derive(Debug, Copy, Clone)]
+pub enum RawContext {
+    /// A WGL context.
+    Wgl {
+        /// The device context that the context renders to.
+        hdc: *mut libc::c_void,
+        /// The `HGLRC`.
+        hglrc: *mut libc::c_void,
+    },
+
+    /// An EGL context, created by ANGLE.
+    Egl {
+        /// The `EGLDisplay`.
+        display: *mut libc::c_void,
+        /// The `EGLContext`.
+        context: *mut libc::c_void,
+        /// The `EGLSurface`.
+        surface: *mut libc::c_void,
+    },
+}
+
+impl RawContext {
+    #[inline]
+    pub(crate) fn from_wgl(context: &WglContext) -> RawContext {
+        RawContext::Wgl {
+            hdc: context.get_hdc() as *mut libc::c_void,
+            hglrc: context.get_hglrc() as *mut libc::c_void,
+        }
+    }
+
+    #[inline]
+    pub(crate) fn from_egl(context: &EglContext) -> RawContext {
+        RawContext::Egl {
+            display: context.get_egl_display() as *mut libc::c_void,
+            context: context.get_egl_context() as *mut libc::c_void,
+            surface: context.get_egl_surface() as *mut libc::c_void,
+        }
+    }
+}
+
+/// Additional methods on the objects that own an OpenGL context, which are specific to Windows.
+pub trait ContextExt {
+    /// Returns the native handles of the OpenGL context.
+    ///
+    /// The handles are owned by glutin: they must not be destroyed, and they become invalid when
+    /// the glutin object is destroyed. The context must not be made current through them while
+    /// it is current in another thread.
+    fn get_raw_context(&self) -> RawContext;
+}
+
+impl ContextExt for Window {
+    #[inline]
+    fn get_raw_context(&self) -> RawContext {
+        self.window.get_raw_context()
+    }
+}
+
+impl ContextExt for Context {
+    #[inline]
+    fn get_raw_context(&self) -> RawContext {
+        self.context.get_raw_context()
+    }
+}
+
+impl ContextExt for HeadlessContext {
+    #[inline]
+    fn get_raw_context(&self) -> RawContext {
+        match self.context {
+            WindowsHeadlessContext::HiddenWindow(ref c) => c.get_raw_context(),
+            WindowsHeadlessContext::EglPbuffer(ref c) => RawContext::from_egl(c),
+        }
+    }
 }
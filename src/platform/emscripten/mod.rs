@@ -10,6 +10,8 @@ use PixelFormatRequirements;
 
 pub use api::emscripten::{Window, WindowProxy, MonitorId, get_available_monitors};
 pub use api::emscripten::{get_primary_monitor, WaitEventsIterator, PollEventsIterator};
+pub use api::emscripten::{monitor_from_point, has_hardware_acceleration};
+pub use api::emscripten::SharedContext;
 
 pub struct HeadlessContext(Window);
 
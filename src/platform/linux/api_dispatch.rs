@@ -5,16 +5,28 @@ use std::collections::VecDeque;
 use std::sync::Arc;
 
 use ContextError;
+use ContextPriority;
+use Robustness;
 use CreationError;
 use CursorState;
+use CursorGrabMode;
+use ContentProtectionError;
+use CursorStateError;
 use Event;
 use GlAttributes;
 use GlContext;
 use MouseCursor;
 use PixelFormat;
 use PixelFormatRequirements;
+use RawEventFilter;
+use Rect;
+use ResizeEdge;
+use ScanCode;
+use VirtualKeyCode;
 use WindowAttributes;
+use WindowID;
 use libc;
+use os::unix::{Strut, WindowType};
 
 use api::x11;
 use api::x11::XConnection;
@@ -22,7 +34,13 @@ use api::x11::XError;
 use api::x11::XNotSupported;
 
 #[derive(Clone, Default)]
-pub struct PlatformSpecificWindowBuilderAttributes;
+pub struct PlatformSpecificWindowBuilderAttributes {
+    pub override_redirect: bool,
+    pub x11_window_type: WindowType,
+    pub x11_strut: Option<Strut>,
+    pub x11_transient_for: Option<WindowID>,
+    pub x11_fullscreen_monitors: Vec<MonitorId>,
+}
 
 enum Backend {
     X(Arc<XConnection>),
@@ -43,6 +61,11 @@ pub enum Window {
     X(x11::Window),
 }
 
+pub enum SharedContext {
+    #[doc(hidden)]
+    X(x11::SharedContext),
+}
+
 #[derive(Clone)]
 pub enum WindowProxy {
     #[doc(hidden)]
@@ -56,14 +79,19 @@ impl WindowProxy {
             &WindowProxy::X(ref wp) => wp.wakeup_event_loop(),
         }
     }
+
+    #[inline]
+    pub fn request_close(&self) {
+        match self {
+            &WindowProxy::X(ref wp) => wp.request_close(),
+        }
+    }
 }
 
 #[derive(Clone)]
 pub enum MonitorId {
     #[doc(hidden)]
     X(x11::MonitorId),
-    #[doc(hidden)]
-    None,
 }
 
 #[inline]
@@ -73,15 +101,31 @@ pub fn get_available_monitors() -> VecDeque<MonitorId> {
                                     .into_iter()
                                     .map(MonitorId::X)
                                     .collect(),
-        Backend::Error(_) => { let mut d = VecDeque::new(); d.push_back(MonitorId::None); d},
+        Backend::Error(_) => VecDeque::new(),
+    }
+}
+
+#[inline]
+pub fn get_primary_monitor() -> Option<MonitorId> {
+    match *BACKEND {
+        Backend::X(ref connec) => Some(MonitorId::X(x11::get_primary_monitor(connec))),
+        Backend::Error(_) => None,
     }
 }
 
 #[inline]
-pub fn get_primary_monitor() -> MonitorId {
+pub fn monitor_from_point(x: i32, y: i32) -> Option<MonitorId> {
     match *BACKEND {
-        Backend::X(ref connec) => MonitorId::X(x11::get_primary_monitor(connec)),
-        Backend::Error(_) => MonitorId::None,
+        Backend::X(ref connec) => x11::monitor_from_point(connec, x, y).map(MonitorId::X),
+        Backend::Error(_) => None,
+    }
+}
+
+#[inline]
+pub fn has_hardware_acceleration() -> bool {
+    match *BACKEND {
+        Backend::X(ref connec) => x11::has_hardware_acceleration(connec),
+        Backend::Error(_) => false,
     }
 }
 
@@ -90,7 +134,6 @@ impl MonitorId {
     pub fn get_name(&self) -> Option<String> {
         match self {
             &MonitorId::X(ref m) => m.get_name(),
-            &MonitorId::None => None,
         }
     }
 
@@ -98,7 +141,6 @@ impl MonitorId {
     pub fn get_native_identifier(&self) -> ::native_monitor::NativeMonitorId {
         match self {
             &MonitorId::X(ref m) => m.get_native_identifier(),
-            &MonitorId::None => unimplemented!()        // FIXME:
         }
     }
 
@@ -106,7 +148,13 @@ impl MonitorId {
     pub fn get_dimensions(&self) -> (u32, u32) {
         match self {
             &MonitorId::X(ref m) => m.get_dimensions(),
-            &MonitorId::None => (800, 600),     // FIXME:
+        }
+    }
+
+    #[inline]
+    pub fn get_icc_profile(&self) -> Option<Vec<u8>> {
+        match self {
+            &MonitorId::X(ref m) => m.get_icc_profile(),
         }
     }
 }
@@ -117,7 +165,7 @@ pub use api::x11::{PollEventsIterator, WaitEventsIterator};
 impl Window {
     #[inline]
     pub fn new(window: &WindowAttributes, pf_reqs: &PixelFormatRequirements,
-               opengl: &GlAttributes<&Window>, _: &PlatformSpecificWindowBuilderAttributes)
+               opengl: &GlAttributes<&Window>, pl_attribs: &PlatformSpecificWindowBuilderAttributes)
                -> Result<Window, CreationError>
     {
         match *BACKEND {
@@ -126,7 +174,7 @@ impl Window {
                     &Window::X(ref w) => w,
                 });
 
-                x11::Window::new(connec, window, pf_reqs, &opengl).map(Window::X)
+                x11::Window::new(connec, window, pf_reqs, &opengl, pl_attribs).map(Window::X)
             },
 
             Backend::Error(ref error) => Err(CreationError::NoBackendAvailable(Box::new(error.clone())))
@@ -154,6 +202,27 @@ impl Window {
         }
     }
 
+    #[inline]
+    pub fn drag_window(&self) -> Result<(), ()> {
+        match self {
+            &Window::X(ref w) => w.drag_window(),
+        }
+    }
+
+    #[inline]
+    pub fn drag_resize_window(&self, edge: ResizeEdge) -> Result<(), ()> {
+        match self {
+            &Window::X(ref w) => w.drag_resize_window(edge),
+        }
+    }
+
+    #[inline]
+    pub fn focus_window(&self) {
+        match self {
+            &Window::X(ref w) => w.focus_window(),
+        }
+    }
+
     #[inline]
     pub fn get_position(&self) -> Option<(i32, i32)> {
         match self {
@@ -161,6 +230,13 @@ impl Window {
         }
     }
 
+    #[inline]
+    pub fn get_outer_position(&self) -> Option<(i32, i32)> {
+        match self {
+            &Window::X(ref w) => w.get_outer_position(),
+        }
+    }
+
     #[inline]
     pub fn set_position(&self, x: i32, y: i32) {
         match self {
@@ -189,6 +265,62 @@ impl Window {
         }
     }
 
+    #[inline]
+    pub fn request_inner_size(&self, x: u32, y: u32) -> Option<(u32, u32)> {
+        match self {
+            &Window::X(ref w) => w.request_inner_size(x, y),
+        }
+    }
+
+    #[inline]
+    pub fn set_min_dimensions(&self, dimensions: Option<(u32, u32)>) {
+        match self {
+            &Window::X(ref w) => w.set_min_dimensions(dimensions),
+        }
+    }
+
+    #[inline]
+    pub fn set_max_dimensions(&self, dimensions: Option<(u32, u32)>) {
+        match self {
+            &Window::X(ref w) => w.set_max_dimensions(dimensions),
+        }
+    }
+
+    #[inline]
+    pub fn set_aspect_ratio(&self, ratio: Option<(u32, u32)>) {
+        match self {
+            &Window::X(ref w) => w.set_aspect_ratio(ratio),
+        }
+    }
+
+    #[inline]
+    pub fn set_decorations(&self, decorations: bool) {
+        match self {
+            &Window::X(ref w) => w.set_decorations(decorations),
+        }
+    }
+
+    #[inline]
+    pub fn set_opacity(&self, opacity: f32) {
+        match self {
+            &Window::X(ref w) => w.set_opacity(opacity),
+        }
+    }
+
+    #[inline]
+    pub fn set_content_protected(&self, protected: bool) -> Result<(), ContentProtectionError> {
+        match self {
+            &Window::X(ref w) => w.set_content_protected(protected),
+        }
+    }
+
+    #[inline]
+    pub fn set_cursor_hittest(&self, hittest: bool) -> Result<(), ()> {
+        match self {
+            &Window::X(ref w) => w.set_cursor_hittest(hittest),
+        }
+    }
+
     #[inline]
     pub fn create_window_proxy(&self) -> WindowProxy {
         match self {
@@ -210,6 +342,13 @@ impl Window {
         }
     }
 
+    #[inline]
+    pub fn drain_events(&self, events: &mut Vec<Event>) {
+        match self {
+            &Window::X(ref w) => w.drain_events(events),
+        }
+    }
+
     #[inline]
     pub fn set_window_resize_callback(&mut self, callback: Option<fn(u32, u32)>) {
         match self {
@@ -225,12 +364,96 @@ impl Window {
     }
 
     #[inline]
-    pub fn set_cursor_state(&self, state: CursorState) -> Result<(), String> {
+    pub fn set_cursor_state(&self, state: CursorState) -> Result<(), CursorStateError> {
         match self {
             &Window::X(ref w) => w.set_cursor_state(state),
         }
     }
 
+    #[inline]
+    pub fn set_cursor_grab(&self, mode: CursorGrabMode) -> Result<(), CursorStateError> {
+        match self {
+            &Window::X(ref w) => w.set_cursor_grab(mode),
+        }
+    }
+
+    #[inline]
+    pub fn confine_cursor(&self, rect: Option<Rect>) -> Result<(), CursorStateError> {
+        match self {
+            &Window::X(ref w) => w.confine_cursor(rect),
+        }
+    }
+
+    #[inline]
+    pub fn set_ime_position(&self, x: i32, y: i32) {
+        match self {
+            &Window::X(ref w) => w.set_ime_position(x, y),
+        }
+    }
+
+    #[inline]
+    pub fn scancode_to_keycode(&self, scancode: ScanCode) -> Option<VirtualKeyCode> {
+        match self {
+            &Window::X(ref w) => w.scancode_to_keycode(scancode),
+        }
+    }
+
+    #[inline]
+    pub fn keycode_to_scancode(&self, keycode: VirtualKeyCode) -> Option<ScanCode> {
+        match self {
+            &Window::X(ref w) => w.keycode_to_scancode(keycode),
+        }
+    }
+
+    #[inline]
+    pub fn key_name(&self, scancode: ScanCode) -> Option<String> {
+        match self {
+            &Window::X(ref w) => w.key_name(scancode),
+        }
+    }
+
+    #[inline]
+    pub fn set_raw_event_filter(&self, filter: Option<RawEventFilter>) {
+        match self {
+            &Window::X(ref w) => w.set_raw_event_filter(filter),
+        }
+    }
+
+    #[inline]
+    pub fn set_ime_allowed(&self, allowed: bool) {
+        match self {
+            &Window::X(ref w) => w.set_ime_allowed(allowed),
+        }
+    }
+
+    #[inline]
+    pub fn get_context_priority(&self) -> Option<ContextPriority> {
+        match self {
+            &Window::X(ref w) => w.get_context_priority(),
+        }
+    }
+
+    #[inline]
+    pub fn get_robustness(&self) -> Option<Robustness> {
+        match self {
+            &Window::X(ref w) => w.get_robustness(),
+        }
+    }
+
+    #[inline]
+    pub fn get_available_monitors(&self) -> VecDeque<MonitorId> {
+        match self {
+            &Window::X(ref w) => w.get_available_monitors().into_iter().map(MonitorId::X).collect(),
+        }
+    }
+
+    #[inline]
+    pub fn get_current_monitor(&self) -> Option<MonitorId> {
+        match self {
+            &Window::X(ref w) => w.get_current_monitor().map(MonitorId::X),
+        }
+    }
+
     #[inline]
     pub fn hidpi_factor(&self) -> f32 {
        match self {
@@ -239,12 +462,27 @@ impl Window {
     }
 
     #[inline]
-    pub fn set_cursor_position(&self, x: i32, y: i32) -> Result<(), ()> {
+    pub fn set_cursor_position(&self, x: f64, y: f64) -> Result<(), ()> {
         match self {
             &Window::X(ref w) => w.set_cursor_position(x, y),
         }
     }
 
+    #[inline]
+    pub fn create_shared_context(&self, opengl: &GlAttributes<&Window>)
+                                 -> Result<SharedContext, CreationError>
+    {
+        match self {
+            &Window::X(ref w) => {
+                let opengl = opengl.clone().map_sharing(|w| match w {
+                    &Window::X(ref w) => w,
+                });
+
+                w.create_shared_context(&opengl).map(SharedContext::X)
+            },
+        }
+    }
+
     #[inline]
     pub fn platform_display(&self) -> *mut libc::c_void {
         match self {
@@ -282,6 +520,13 @@ impl GlContext for Window {
         }
     }
 
+    #[inline]
+    fn is_extension_supported(&self, name: &str) -> bool {
+        match self {
+            &Window::X(ref w) => w.is_extension_supported(name),
+        }
+    }
+
     #[inline]
     fn swap_buffers(&self) -> Result<(), ContextError> {
         match self {
@@ -289,6 +534,20 @@ impl GlContext for Window {
         }
     }
 
+    #[inline]
+    fn swap_buffers_with_damage(&self, rects: &[Rect]) -> Result<(), ContextError> {
+        match self {
+            &Window::X(ref w) => w.swap_buffers_with_damage(rects),
+        }
+    }
+
+    #[inline]
+    fn buffer_age(&self) -> Option<u32> {
+        match self {
+            &Window::X(ref w) => w.buffer_age(),
+        }
+    }
+
     #[inline]
     fn get_api(&self) -> ::Api {
         match self {
@@ -304,6 +563,64 @@ impl GlContext for Window {
     }
 }
 
+impl GlContext for SharedContext {
+    #[inline]
+    unsafe fn make_current(&self) -> Result<(), ContextError> {
+        match self {
+            &SharedContext::X(ref c) => c.make_current(),
+        }
+    }
+
+    #[inline]
+    fn is_current(&self) -> bool {
+        match self {
+            &SharedContext::X(ref c) => c.is_current(),
+        }
+    }
+
+    #[inline]
+    fn get_proc_address(&self, addr: &str) -> *const () {
+        match self {
+            &SharedContext::X(ref c) => c.get_proc_address(addr),
+        }
+    }
+
+    #[inline]
+    fn is_extension_supported(&self, name: &str) -> bool {
+        match self {
+            &SharedContext::X(ref c) => c.is_extension_supported(name),
+        }
+    }
+
+    #[inline]
+    fn swap_buffers(&self) -> Result<(), ContextError> {
+        match self {
+            &SharedContext::X(ref c) => c.swap_buffers(),
+        }
+    }
+
+    #[inline]
+    fn buffer_age(&self) -> Option<u32> {
+        match self {
+            &SharedContext::X(ref c) => c.buffer_age(),
+        }
+    }
+
+    #[inline]
+    fn get_api(&self) -> ::Api {
+        match self {
+            &SharedContext::X(ref c) => c.get_api(),
+        }
+    }
+
+    #[inline]
+    fn get_pixel_format(&self) -> PixelFormat {
+        match self {
+            &SharedContext::X(ref c) => c.get_pixel_format(),
+        }
+    }
+}
+
 unsafe extern "C" fn x_error_callback(dpy: *mut x11::ffi::Display, event: *mut x11::ffi::XErrorEvent)
                                       -> libc::c_int
 {
@@ -319,6 +636,7 @@ unsafe extern "C" fn x_error_callback(dpy: *mut x11::ffi::Display, event: *mut x
             error_code: (*event).error_code,
             request_code: (*event).request_code,
             minor_code: (*event).minor_code,
+            serial: (*event).serial,
         };
 
         *x.latest_error.lock().unwrap() = Some(error);
@@ -7,65 +7,283 @@ use GlAttributes;
 use GlContext;
 use PixelFormat;
 use PixelFormatRequirements;
+use Rect;
 
+use std::env;
+use std::error::Error;
+use std::ffi::CString;
+use std::fmt;
+
+use api::dlopen;
+use api::egl;
+use api::egl::Context as EglContext;
+use api::egl::ffi::egl::Egl;
 use api::osmesa::{self, OsMesaContext};
+use os::unix::HeadlessBackend;
 
 pub use self::api_dispatch::{Window, WindowProxy, MonitorId, get_available_monitors, get_primary_monitor};
+pub use self::api_dispatch::SharedContext;
 pub use self::api_dispatch::{WaitEventsIterator, PollEventsIterator};
 pub use self::api_dispatch::PlatformSpecificWindowBuilderAttributes;
+pub use self::api_dispatch::{monitor_from_point, has_hardware_acceleration};
 mod api_dispatch;
 
+/// Stupid wrapper because `*const libc::c_void` doesn't implement `Sync`.
+struct EglWrapper(Egl);
+unsafe impl Sync for EglWrapper {}
+
+lazy_static! {
+    // The EGL implementation of the system, which the headless contexts use without any display
+    // server.
+    static ref EGL: Option<EglWrapper> = {
+        // TODO: use something safer than raw "dlopen"
+        let mut libegl = unsafe { dlopen::dlopen(b"libEGL.so.1\0".as_ptr() as *const _, dlopen::RTLD_NOW) };
+        if libegl.is_null() {
+            libegl = unsafe { dlopen::dlopen(b"libEGL.so\0".as_ptr() as *const _, dlopen::RTLD_NOW) };
+        }
+
+        if libegl.is_null() {
+            None
+        } else {
+            Some(EglWrapper(Egl::load_with(|sym| {
+                let sym = CString::new(sym).unwrap();
+                unsafe { dlopen::dlsym(libegl, sym.as_ptr()) }
+            })))
+        }
+    };
+}
+
+/// The backends that headless contexts try in order. `GLUTIN_HEADLESS_BACKENDS` can be set to a
+/// comma-separated list of these names to change the order or to skip some of them.
+const DEFAULT_HEADLESS_BACKENDS: &'static [&'static str] = &["egl-surfaceless", "egl", "osmesa"];
+
 #[derive(Clone, Default)]
-pub struct PlatformSpecificHeadlessBuilderAttributes;
+pub struct PlatformSpecificHeadlessBuilderAttributes {
+    /// Takes precedence over `GLUTIN_HEADLESS_BACKENDS` if set.
+    pub backends: Option<Vec<HeadlessBackend>>,
+}
 
-pub struct HeadlessContext(OsMesaContext);
+pub enum HeadlessContext {
+    /// An EGL pbuffer.
+    Egl(EglContext),
+    /// A buffer in memory rendered to by OSMesa.
+    OsMesa(OsMesaContext),
+}
 
 impl HeadlessContext {
     pub fn new(dimensions: (u32, u32), pf_reqs: &PixelFormatRequirements,
                opengl: &GlAttributes<&HeadlessContext>,
-               _: &PlatformSpecificHeadlessBuilderAttributes)
+               pl_attribs: &PlatformSpecificHeadlessBuilderAttributes)
                -> Result<HeadlessContext, CreationError>
     {
-        let opengl = opengl.clone().map_sharing(|c| &c.0);
+        let backends = match (&pl_attribs.backends, env::var("GLUTIN_HEADLESS_BACKENDS")) {
+            (&Some(ref backends), _) => backends.iter().map(|&backend| match backend {
+                HeadlessBackend::EglSurfacelessContext => "egl-surfaceless-context".to_string(),
+                HeadlessBackend::EglSurfaceless => "egl-surfaceless".to_string(),
+                HeadlessBackend::Egl => "egl".to_string(),
+                HeadlessBackend::OsMesa => "osmesa".to_string(),
+            }).collect(),
+            (&None, Ok(backends)) => backends.split(',').map(|s| s.trim().to_lowercase()).collect(),
+            (&None, Err(_)) => {
+                DEFAULT_HEADLESS_BACKENDS.iter().map(|s| s.to_string()).collect::<Vec<_>>()
+            },
+        };
 
-        match OsMesaContext::new(dimensions, pf_reqs, &opengl) {
-            Ok(c) => return Ok(HeadlessContext(c)),
-            Err(osmesa::OsMesaCreationError::NotSupported) => (),
-            Err(osmesa::OsMesaCreationError::CreationError(e)) => return Err(e),
+        let mut attempts = Vec::with_capacity(backends.len());
+
+        for backend in backends.iter() {
+            let result = match &backend[..] {
+                "egl-surfaceless-context" => HeadlessContext::new_egl_surfaceless(pf_reqs, opengl),
+                "egl-surfaceless" => HeadlessContext::new_egl(dimensions, pf_reqs, opengl,
+                                                              egl::NativeDisplay::Surfaceless),
+                "egl" => HeadlessContext::new_egl(dimensions, pf_reqs, opengl,
+                                                  egl::NativeDisplay::Other(None)),
+                "osmesa" => HeadlessContext::new_osmesa(dimensions, pf_reqs, opengl),
+                _ => Err(format!("unknown backend")),
+            };
+
+            match result {
+                Ok(context) => return Ok(context),
+                Err(error) => attempts.push((backend.clone(), error)),
+            }
+        }
+
+        Err(CreationError::NoBackendAvailable(Box::new(NoHeadlessBackend { attempts: attempts })))
+    }
+
+    fn new_egl(dimensions: (u32, u32), pf_reqs: &PixelFormatRequirements,
+               opengl: &GlAttributes<&HeadlessContext>, native_display: egl::NativeDisplay)
+               -> Result<HeadlessContext, String>
+    {
+        let egl = match *EGL {
+            Some(ref egl) => egl,
+            None => return Err(format!("libEGL couldn't be loaded")),
+        };
+
+        let opengl = try!(egl_sharing(opengl));
+        EglContext::new_pbuffer(egl.0.clone(), pf_reqs, &opengl, native_display)
+            .and_then(|prototype| prototype.finish_pbuffer(dimensions))
+            .map(HeadlessContext::Egl)
+            .map_err(|err| format!("{}", err))
+    }
+
+    /// Creates an EGL context without any surface, on the surfaceless platform of Mesa if
+    /// possible or on the default display otherwise.
+    fn new_egl_surfaceless(pf_reqs: &PixelFormatRequirements,
+                           opengl: &GlAttributes<&HeadlessContext>)
+                           -> Result<HeadlessContext, String>
+    {
+        let egl = match *EGL {
+            Some(ref egl) => egl,
+            None => return Err(format!("libEGL couldn't be loaded")),
         };
 
-        Err(CreationError::NotSupported)
+        let opengl = try!(egl_sharing(opengl));
+        EglContext::new_surfaceless(egl.0.clone(), pf_reqs, &opengl,
+                                    egl::NativeDisplay::Surfaceless)
+            .and_then(|prototype| prototype.finish_surfaceless())
+            .or_else(|_| {
+                EglContext::new_surfaceless(egl.0.clone(), pf_reqs, &opengl,
+                                            egl::NativeDisplay::Other(None))
+                    .and_then(|prototype| prototype.finish_surfaceless())
+            })
+            .map(HeadlessContext::Egl)
+            .map_err(|err| format!("{}", err))
+    }
+
+    fn new_osmesa(dimensions: (u32, u32), pf_reqs: &PixelFormatRequirements,
+                  opengl: &GlAttributes<&HeadlessContext>)
+                  -> Result<HeadlessContext, String>
+    {
+        match opengl.sharing {
+            Some(&HeadlessContext::OsMesa(_)) | None => (),
+            Some(_) => return Err(format!("OSMesa can't share objects with an EGL context")),
+        }
+        let opengl = opengl.clone().map_sharing(|c| match c {
+            &HeadlessContext::OsMesa(ref c) => c,
+            _ => unreachable!(),
+        });
+
+        match OsMesaContext::new(dimensions, pf_reqs, &opengl) {
+            Ok(c) => Ok(HeadlessContext::OsMesa(c)),
+            Err(osmesa::OsMesaCreationError::NotSupported) => {
+                Err(format!("libOSMesa couldn't be loaded"))
+            },
+            Err(osmesa::OsMesaCreationError::CreationError(e)) => Err(format!("{}", e)),
+        }
+    }
+}
+
+/// Returns the attributes of an EGL headless context, or an error if the objects must be shared
+/// with a context of another backend.
+fn egl_sharing<'a>(opengl: &GlAttributes<&'a HeadlessContext>)
+                   -> Result<GlAttributes<&'a EglContext>, String>
+{
+    match opengl.sharing {
+        Some(&HeadlessContext::Egl(_)) | None => (),
+        Some(_) => return Err(format!("EGL can't share objects with an OSMesa context")),
+    }
+
+    Ok(opengl.clone().map_sharing(|c| match c {
+        &HeadlessContext::Egl(ref c) => c,
+        _ => unreachable!(),
+    }))
+}
+
+/// Error returned when none of the headless backends could create a context.
+#[derive(Debug, Clone)]
+pub struct NoHeadlessBackend {
+    /// The backends that were tried, with the reason why each one failed.
+    pub attempts: Vec<(String, String)>,
+}
+
+impl Error for NoHeadlessBackend {
+    #[inline]
+    fn description(&self) -> &str {
+        "None of the headless backends could create a context"
+    }
+}
+
+impl fmt::Display for NoHeadlessBackend {
+    fn fmt(&self, formatter: &mut fmt::Formatter) -> Result<(), fmt::Error> {
+        try!(formatter.write_str(self.description()));
+        for (i, &(ref backend, ref error)) in self.attempts.iter().enumerate() {
+            try!(write!(formatter, "{} {}: {}", if i == 0 { ":" } else { ";" }, backend, error));
+        }
+        Ok(())
     }
 }
 
 impl GlContext for HeadlessContext {
     #[inline]
     unsafe fn make_current(&self) -> Result<(), ContextError> {
-        self.0.make_current()
+        match self {
+            &HeadlessContext::Egl(ref ctxt) => ctxt.make_current(),
+            &HeadlessContext::OsMesa(ref ctxt) => ctxt.make_current(),
+        }
     }
 
     #[inline]
     fn is_current(&self) -> bool {
-        self.0.is_current()
+        match self {
+            &HeadlessContext::Egl(ref ctxt) => ctxt.is_current(),
+            &HeadlessContext::OsMesa(ref ctxt) => ctxt.is_current(),
+        }
     }
 
     #[inline]
     fn get_proc_address(&self, addr: &str) -> *const () {
-        self.0.get_proc_address(addr)
+        match self {
+            &HeadlessContext::Egl(ref ctxt) => ctxt.get_proc_address(addr),
+            &HeadlessContext::OsMesa(ref ctxt) => ctxt.get_proc_address(addr),
+        }
+    }
+
+    #[inline]
+    fn is_extension_supported(&self, name: &str) -> bool {
+        match self {
+            &HeadlessContext::Egl(ref ctxt) => ctxt.is_extension_supported(name),
+            &HeadlessContext::OsMesa(ref ctxt) => ctxt.is_extension_supported(name),
+        }
     }
 
     #[inline]
     fn swap_buffers(&self) -> Result<(), ContextError> {
-        self.0.swap_buffers()
+        match self {
+            &HeadlessContext::Egl(ref ctxt) => ctxt.swap_buffers(),
+            &HeadlessContext::OsMesa(ref ctxt) => ctxt.swap_buffers(),
+        }
+    }
+
+    #[inline]
+    fn swap_buffers_with_damage(&self, rects: &[Rect]) -> Result<(), ContextError> {
+        match self {
+            &HeadlessContext::Egl(ref ctxt) => ctxt.swap_buffers_with_damage(rects),
+            &HeadlessContext::OsMesa(ref ctxt) => ctxt.swap_buffers_with_damage(rects),
+        }
+    }
+
+    #[inline]
+    fn buffer_age(&self) -> Option<u32> {
+        match self {
+            &HeadlessContext::Egl(ref ctxt) => ctxt.buffer_age(),
+            &HeadlessContext::OsMesa(ref ctxt) => ctxt.buffer_age(),
+        }
     }
 
     #[inline]
     fn get_api(&self) -> Api {
-        self.0.get_api()
+        match self {
+            &HeadlessContext::Egl(ref ctxt) => ctxt.get_api(),
+            &HeadlessContext::OsMesa(ref ctxt) => ctxt.get_api(),
+        }
     }
 
     #[inline]
     fn get_pixel_format(&self) -> PixelFormat {
-        self.0.get_pixel_format()
+        match self {
+            &HeadlessContext::Egl(ref ctxt) => ctxt.get_pixel_format(),
+            &HeadlessContext::OsMesa(ref ctxt) => ctxt.get_pixel_format(),
+        }
     }
 }
@@ -2,15 +2,20 @@
 
 pub use api::win32;
 pub use api::win32::{MonitorId, get_available_monitors, get_primary_monitor};
+pub use api::win32::{monitor_from_point, has_hardware_acceleration};
 pub use api::win32::{WindowProxy, PollEventsIterator, WaitEventsIterator};
+pub use api::win32::PlatformSpecificWindowBuilderAttributes;
+pub use api::win32::SharedContext;
 
 use Api;
 use ContextError;
 use CreationError;
 use PixelFormat;
 use PixelFormatRequirements;
+use Rect;
 use GlAttributes;
 use GlContext;
+use GlRequest;
 use WindowAttributes;
 
 use api::egl::ffi::egl::Egl;
@@ -53,8 +58,6 @@ lazy_static! {
     };
 }
 
-#[derive(Clone, Default)]
-pub struct PlatformSpecificWindowBuilderAttributes;
 #[derive(Clone, Default)]
 pub struct PlatformSpecificHeadlessBuilderAttributes;
 
@@ -65,11 +68,19 @@ impl Window {
     /// See the docs in the crate root file.
     #[inline]
     pub fn new(window: &WindowAttributes, pf_reqs: &PixelFormatRequirements,
-               opengl: &GlAttributes<&Window>, _: &PlatformSpecificWindowBuilderAttributes)
+               opengl: &GlAttributes<&Window>, pl_attribs: &PlatformSpecificWindowBuilderAttributes)
                -> Result<Window, CreationError>
     {
         win32::Window::new(window, pf_reqs, &opengl.clone().map_sharing(|w| &w.0),
-                           EGL.as_ref().map(|w| &w.0)).map(|w| Window(w))
+                           EGL.as_ref().map(|w| &w.0), pl_attribs).map(|w| Window(w))
+    }
+
+    /// See the docs in the crate root file.
+    #[inline]
+    pub fn create_shared_context(&self, opengl: &GlAttributes<&Window>)
+                                 -> Result<SharedContext, CreationError>
+    {
+        self.0.create_shared_context(&opengl.clone().map_sharing(|w| &w.0))
     }
 }
 
@@ -91,8 +102,8 @@ impl DerefMut for Window {
 
 ///
 pub enum HeadlessContext {
-    /// A regular window, but invisible.
-    HiddenWindow(win32::Window),
+    /// A WGL context on a hidden 1×1 window.
+    HiddenWindow(win32::HeadlessContext),
     /// An EGL pbuffer.
     EglPbuffer(EglContext),
 }
@@ -103,23 +114,34 @@ impl HeadlessContext {
                _: &PlatformSpecificHeadlessBuilderAttributes)
                -> Result<HeadlessContext, CreationError>
     {
-        // if EGL is available, we try using EGL first
-        // if EGL returns an error, we try the hidden window method
-        if let &Some(ref egl) = &*EGL {
-            let context = EglContext::new(egl.0.clone(), pf_reqs, &opengl.clone().map_sharing(|_| unimplemented!()),       // TODO: 
-                                          egl::NativeDisplay::Other(None))
-                                .and_then(|prototype| prototype.finish_pbuffer(dimensions))
-                                .map(|ctxt| HeadlessContext::EglPbuffer(ctxt));
-
-            if let Ok(context) = context {
-                return Ok(context);
+        // OpenGL ES is requested from EGL first, which is provided by ANGLE or the drivers of
+        // AMD, and WGL is used otherwise or if EGL returns an error
+        // FIXME: sharing with EGL pbuffers
+        if let (GlRequest::Specific(Api::OpenGlEs, _), None) = (opengl.version, opengl.sharing) {
+            if let &Some(ref egl) = &*EGL {
+                let context = EglContext::new_pbuffer(egl.0.clone(), pf_reqs,
+                                                      &opengl.clone().map_sharing(|_| unreachable!()),
+                                                      egl::NativeDisplay::Angle(None))
+                                    .and_then(|prototype| prototype.finish_pbuffer(dimensions))
+                                    .map(|ctxt| HeadlessContext::EglPbuffer(ctxt));
+
+                if let Ok(context) = context {
+                    return Ok(context);
+                }
             }
         }
 
-        let window = try!(win32::Window::new(&WindowAttributes { visible: false, .. Default::default() },
-                                             pf_reqs, &opengl.clone().map_sharing(|_| unimplemented!()),            //TODO:
-                                             EGL.as_ref().map(|w| &w.0)));
-        Ok(HeadlessContext::HiddenWindow(window))
+        // contexts can only share their objects with contexts of the same API
+        if let Some(&HeadlessContext::EglPbuffer(_)) = opengl.sharing {
+            return Err(CreationError::NotSupported);
+        }
+
+        let opengl = opengl.clone().map_sharing(|ctxt| match ctxt {
+            &HeadlessContext::HiddenWindow(ref ctxt) => ctxt.get_hglrc(),
+            &HeadlessContext::EglPbuffer(_) => unreachable!(),
+        });
+        let context = try!(win32::HeadlessContext::new(pf_reqs, &opengl));
+        Ok(HeadlessContext::HiddenWindow(context))
     }
 }
 
@@ -148,6 +170,14 @@ impl GlContext for HeadlessContext {
         }
     }
 
+    #[inline]
+    fn is_extension_supported(&self, name: &str) -> bool {
+        match self {
+            &HeadlessContext::HiddenWindow(ref ctxt) => ctxt.is_extension_supported(name),
+            &HeadlessContext::EglPbuffer(ref ctxt) => ctxt.is_extension_supported(name),
+        }
+    }
+
     #[inline]
     fn swap_buffers(&self) -> Result<(), ContextError> {
         match self {
@@ -156,6 +186,22 @@ impl GlContext for HeadlessContext {
         }
     }
 
+    #[inline]
+    fn swap_buffers_with_damage(&self, rects: &[Rect]) -> Result<(), ContextError> {
+        match self {
+            &HeadlessContext::HiddenWindow(ref ctxt) => ctxt.swap_buffers_with_damage(rects),
+            &HeadlessContext::EglPbuffer(ref ctxt) => ctxt.swap_buffers_with_damage(rects),
+        }
+    }
+
+    #[inline]
+    fn buffer_age(&self) -> Option<u32> {
+        match self {
+            &HeadlessContext::HiddenWindow(ref ctxt) => ctxt.buffer_age(),
+            &HeadlessContext::EglPbuffer(ref ctxt) => ctxt.buffer_age(),
+        }
+    }
+
     #[inline]
     fn get_api(&self) -> Api {
         match self {
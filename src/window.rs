@@ -1,18 +1,36 @@
+use std::cmp;
 use std::collections::vec_deque::IntoIter as VecDequeIter;
 use std::default::Default;
+use std::marker::PhantomData;
+use std::ops::Deref;
 use std::path::PathBuf;
+use std::sync::Mutex;
+use std::thread;
 
 use Api;
 use ContextError;
+use ContextPriority;
 use CreationError;
+use CurrentPixelFormat;
 use CursorState;
+use CursorGrabMode;
+use ContentProtectionError;
+use CursorStateError;
 use Event;
+use GlAttributes;
 use GlContext;
 use GlProfile;
 use GlRequest;
 use MouseCursor;
 use PixelFormat;
+use RawEvent;
+use Rect;
+use RelaxedRequirement;
+use ResizeEdge;
+use ScanCode;
+use VirtualKeyCode;
 use Robustness;
+use SizedFramebuffer;
 use Window;
 use WindowID;
 use WindowBuilder;
@@ -35,7 +53,7 @@ impl<'a> WindowBuilder<'a> {
 
     /// Requests the window to be of specific dimensions.
     ///
-    /// Width and height are in pixels.
+    /// Width and height are in points, like `Window::get_inner_size`.
     #[inline]
     pub fn with_dimensions(mut self, width: u32, height: u32) -> WindowBuilder<'a> {
         self.window.dimensions = Some((width, height));
@@ -44,7 +62,7 @@ impl<'a> WindowBuilder<'a> {
     
     /// Sets a minimum dimension size for the window
     ///
-    /// Width and height are in pixels.
+    /// Width and height are in points, like `Window::get_inner_size`.
     #[inline]
     pub fn with_min_dimensions(mut self, width: u32, height: u32) -> WindowBuilder<'a> {
         self.window.min_dimensions = Some((width, height));
@@ -53,13 +71,23 @@ impl<'a> WindowBuilder<'a> {
 
     /// Sets a maximum dimension size for the window
     ///
-    /// Width and height are in pixels.
+    /// Width and height are in points, like `Window::get_inner_size`.
     #[inline]
     pub fn with_max_dimensions(mut self, width: u32, height: u32) -> WindowBuilder<'a> {
         self.window.max_dimensions = Some((width, height));
         self
     }
 
+    /// Requests the window to be created at a specific position.
+    ///
+    /// The coordinates are the same as the ones used by `Window::set_position`. Unlike calling
+    /// `set_position` after `build`, the window is placed before it is shown.
+    #[inline]
+    pub fn with_position(mut self, x: i32, y: i32) -> WindowBuilder<'a> {
+        self.window.initial_position = Some((x, y));
+        self
+    }
+
     /// Requests a specific title for the window.
     #[inline]
     pub fn with_title<T: Into<String>>(mut self, title: T) -> WindowBuilder<'a> {
@@ -80,6 +108,11 @@ impl<'a> WindowBuilder<'a> {
     /// The created window will share all its OpenGL objects with the window in the parameter.
     ///
     /// There are some exceptions, like FBOs or VAOs. See the OpenGL documentation.
+    ///
+    /// ## Platform-specific
+    ///
+    /// On Windows, the objects of EGL contexts can't be shared, so `NotSupported` is returned if
+    /// `other` uses EGL, and the new window uses WGL even if OpenGL ES is requested.
     #[inline]
     pub fn with_shared_lists(mut self, other: &'a Window) -> WindowBuilder<'a> {
         self.opengl.sharing = Some(&other.window);
@@ -117,6 +150,14 @@ impl<'a> WindowBuilder<'a> {
         self
     }
 
+    /// Sets the priority of the OpenGL context in the scheduling of the GPU. This is only a
+    /// hint, see `GlAttributes::context_priority`.
+    #[inline]
+    pub fn with_gl_context_priority(mut self, priority: ContextPriority) -> WindowBuilder<'a> {
+        self.opengl.context_priority = Some(priority);
+        self
+    }
+
     /// Requests that the window has vsync enabled.
     #[inline]
     pub fn with_vsync(mut self) -> WindowBuilder<'a> {
@@ -131,6 +172,20 @@ impl<'a> WindowBuilder<'a> {
         self
     }
 
+    /// Sets whether the window will be initially maximized.
+    #[inline]
+    pub fn with_maximized(mut self, maximized: bool) -> WindowBuilder<'a> {
+        self.window.maximized = maximized;
+        self
+    }
+
+    /// Sets whether the window will be initially minimized.
+    #[inline]
+    pub fn with_minimized(mut self, minimized: bool) -> WindowBuilder<'a> {
+        self.window.minimized = minimized;
+        self
+    }
+
     /// Sets the multisampling level to request.
     ///
     /// # Panic
@@ -143,14 +198,22 @@ impl<'a> WindowBuilder<'a> {
         self
     }
 
-    /// Sets the number of bits in the depth buffer.
+    /// Sets the minimum number of bits in the depth buffer.
+    ///
+    /// A value of 0 means that the window must have no depth buffer, and creating it fails with
+    /// `NoAvailablePixelFormat` if every pixel format has one. Without calling this function, a
+    /// depth buffer of at least 24 bits is requested.
     #[inline]
     pub fn with_depth_buffer(mut self, bits: u8) -> WindowBuilder<'a> {
         self.pf_reqs.depth_bits = Some(bits);
         self
     }
 
-    /// Sets the number of bits in the stencil buffer.
+    /// Sets the minimum number of bits in the stencil buffer.
+    ///
+    /// A value of 0 means that the window must have no stencil buffer, and creating it fails
+    /// with `NoAvailablePixelFormat` if every pixel format has one. Without calling this
+    /// function, a stencil buffer of at least 8 bits is requested.
     #[inline]
     pub fn with_stencil_buffer(mut self, bits: u8) -> WindowBuilder<'a> {
         self.pf_reqs.stencil_bits = Some(bits);
@@ -172,6 +235,20 @@ impl<'a> WindowBuilder<'a> {
         self
     }
 
+    /// Sets whether the context should be double-buffered. `None` means "I don't care", in which
+    /// case a double-buffered context is preferred.
+    ///
+    /// A single-buffered context draws directly to the window, and `swap_buffers` only flushes
+    /// the commands. This is useful for tools that measure the rendering or draw incrementally,
+    /// but the user sees the frames while they are drawn, which tears and flickers. Most
+    /// applications should keep double-buffering. With EGL, `Some(true)` fails with
+    /// `CreationError::NoAvailablePixelFormat`, and the surfaces are always double-buffered.
+    #[inline]
+    pub fn with_double_buffer(mut self, double_buffer: Option<bool>) -> WindowBuilder<'a> {
+        self.pf_reqs.double_buffer = double_buffer;
+        self
+    }
+
     /// Sets whether sRGB should be enabled on the window. `None` means "I don't care".
     #[inline]
     pub fn with_srgb(mut self, srgb_enabled: Option<bool>) -> WindowBuilder<'a> {
@@ -193,6 +270,14 @@ impl<'a> WindowBuilder<'a> {
         self
     }
 
+    /// Sets whether the content of the window should be excluded from screenshots and screen
+    /// sharing, see `Window::set_content_protected`.
+    #[inline]
+    pub fn with_content_protection(mut self, protected: bool) -> WindowBuilder<'a> {
+        self.window.content_protected = protected;
+        self
+    }
+
     /// Enables multitouch
     #[inline]
     pub fn with_multitouch(mut self) -> WindowBuilder<'a> {
@@ -229,8 +314,9 @@ impl<'a> WindowBuilder<'a> {
         }
 
         // building
+        let pf_reqs = self.pf_reqs.clone();
         platform::Window::new(&self.window, &self.pf_reqs, &self.opengl, &self.platform_specific)
-                            .map(|w| Window { window: w })
+            .map(|w| Window { window: w, pf_reqs: pf_reqs, bound_thread: Mutex::new(None) })
     }
 
     /// Builds the window.
@@ -241,8 +327,106 @@ impl<'a> WindowBuilder<'a> {
     pub fn build_strict(self) -> Result<Window, CreationError> {
         self.build()
     }
+
+    /// Builds the window, relaxing the requirements that can't be met until it succeeds.
+    ///
+    /// The full requirements are tried first, then multisampling is dropped, then the stencil
+    /// buffer, then the version of OpenGL or OpenGL ES is lowered one version at a time. Returns
+    /// the window with the requirements that were relaxed, in that order.
+    ///
+    /// Only `NoAvailablePixelFormat` and `OpenGlVersionNotSupported` lead to a relaxation. The
+    /// other errors are returned immediately, and the error of the last attempt is returned when
+    /// nothing is left to relax.
+    pub fn build_best_effort(self) -> Result<(Window, Vec<RelaxedRequirement>), CreationError> {
+        let mut builder = self;
+        let mut relaxed: Vec<RelaxedRequirement> = Vec::new();
+
+        loop {
+            let err = match builder.clone().build() {
+                Ok(window) => return Ok((window, relaxed)),
+                Err(err) => err,
+            };
+            if !can_be_relaxed(&err) {
+                return Err(err);
+            }
+
+            match builder.relax() {
+                // only the last version is reported
+                Some(requirement @ RelaxedRequirement::GlVersion(_)) => {
+                    relaxed.retain(|r| match *r {
+                        RelaxedRequirement::GlVersion(_) => false,
+                        _ => true,
+                    });
+                    relaxed.push(requirement);
+                },
+                Some(requirement) => relaxed.push(requirement),
+                None => return Err(err),
+            }
+        }
+    }
+
+    /// Relaxes the next requirement of `build_best_effort`, or returns `None` if there is none
+    /// left.
+    fn relax(&mut self) -> Option<RelaxedRequirement> {
+        if self.pf_reqs.multisampling.map_or(false, |samples| samples > 0) {
+            self.pf_reqs.multisampling = None;
+            return Some(RelaxedRequirement::Multisampling);
+        }
+
+        if self.pf_reqs.stencil_bits.map_or(false, |bits| bits > 0) {
+            self.pf_reqs.stencil_bits = None;
+            return Some(RelaxedRequirement::StencilBuffer);
+        }
+
+        let version = match self.opengl.version {
+            GlRequest::Specific(api, version) => {
+                lower_gl_version(api, version).map(|version| GlRequest::Specific(api, version))
+            },
+            GlRequest::GlThenGles { opengl_version, opengles_version } => {
+                lower_gl_version(Api::OpenGl, opengl_version).map(|version| {
+                    GlRequest::GlThenGles {
+                        opengl_version: version,
+                        opengles_version: opengles_version,
+                    }
+                })
+            },
+            GlRequest::Latest => None,
+        };
+
+        version.map(|version| {
+            self.opengl.version = version;
+            RelaxedRequirement::GlVersion(version)
+        })
+    }
 }
 
+/// Returns true if `build_best_effort` can try again with relaxed requirements after `err`.
+fn can_be_relaxed(err: &CreationError) -> bool {
+    match *err {
+        CreationError::NoAvailablePixelFormat | CreationError::OpenGlVersionNotSupported => true,
+        CreationError::CreationErrorPair(ref first, ref second) => {
+            can_be_relaxed(first) && can_be_relaxed(second)
+        },
+        _ => false,
+    }
+}
+
+/// Returns the highest version of `api` that is lower than `version`, if any.
+fn lower_gl_version(api: Api, version: (u8, u8)) -> Option<(u8, u8)> {
+    const OPENGL_VERSIONS: &'static [(u8, u8)] = &[
+        (4, 6), (4, 5), (4, 4), (4, 3), (4, 2), (4, 1), (4, 0),
+        (3, 3), (3, 2), (3, 1), (3, 0), (2, 1), (2, 0),
+    ];
+    const OPENGL_ES_VERSIONS: &'static [(u8, u8)] = &[(3, 2), (3, 1), (3, 0), (2, 0)];
+    const WEBGL_VERSIONS: &'static [(u8, u8)] = &[(2, 0), (1, 0)];
+
+    let versions = match api {
+        Api::OpenGl => OPENGL_VERSIONS,
+        Api::OpenGlEs => OPENGL_ES_VERSIONS,
+        Api::WebGl => WEBGL_VERSIONS,
+    };
+    versions.iter().cloned().find(|&lower| lower < version)
+}
 
 impl Default for Window {
     #[inline]
@@ -294,6 +478,69 @@ impl Window {
         self.window.hide()
     }
 
+    /// Starts moving the window with the mouse, like when the user drags its title bar.
+    ///
+    /// This is meant for windows without decorations that draw a title bar of their own, and
+    /// must be called when receiving `MouseInput(Pressed, Left, ..)`. The window follows the
+    /// pointer until the button is released, then a `Moved` event reports its final position.
+    ///
+    /// Returns `Err` if the left button isn't pressed or if the system can't move the window.
+    ///
+    /// ## Platform-specific
+    ///
+    /// - On macOS, this requires macOS 10.11.
+    /// - On X11, this requires a window manager that supports `_NET_WM_MOVERESIZE`.
+    /// - Always returns `Err` on Android, iOS and Emscripten.
+    #[inline]
+    pub fn drag_window(&self) -> Result<(), ()> {
+        self.window.drag_window()
+    }
+
+    /// Starts resizing the window with the mouse from the given edge or corner, like when the user
+    /// drags the frame of decorated windows.
+    ///
+    /// This is meant for windows without decorations, and must be called when receiving
+    /// `MouseInput(Pressed, Left, ..)`, like `drag_window`. The edge follows the pointer until the
+    /// button is released, within the limits of `set_min_dimensions` and `set_max_dimensions`,
+    /// and `Resized` events report the sizes of the window during the resize.
+    ///
+    /// On Windows, the thread that created the window is blocked during the resize, like when
+    /// the user drags the frame, so the events are only received in time by windows built with
+    /// a message thread.
+    ///
+    /// Returns `Err` if the left button isn't pressed or if the system can't resize the window.
+    ///
+    /// ## Platform-specific
+    ///
+    /// - On macOS, there is no native way to start a resize, so the frame of the window is
+    ///   resized by glutin when it receives the motion of the mouse.
+    /// - On X11, this requires a window manager that supports `_NET_WM_MOVERESIZE`.
+    /// - Always returns `Err` on Android, iOS and Emscripten.
+    #[inline]
+    pub fn drag_resize_window(&self, edge: ResizeEdge) -> Result<(), ()> {
+        self.window.drag_resize_window(edge)
+    }
+
+    /// Brings the window to the front and gives it the keyboard focus.
+    ///
+    /// The system may refuse to take the focus from the window the user is interacting with, in
+    /// which case the user is notified that the window wants their attention instead. A
+    /// `Focused(true)` event is received once the window has the focus.
+    ///
+    /// ## Platform-specific
+    ///
+    /// - On Windows, the taskbar button of the window flashes if the focus is refused.
+    /// - On macOS, the dock icon bounces once if the focus is refused.
+    /// - On X11, the window manager is asked to activate the window through
+    ///   `_NET_ACTIVE_WINDOW`, and the window is marked as demanding attention if it doesn't
+    ///   receive the focus shortly afterwards. Without a window manager, the focus is taken
+    ///   directly.
+    /// - Has no effect on Android, iOS and Emscripten.
+    #[inline]
+    pub fn focus_window(&self) {
+        self.window.focus_window()
+    }
+
     /// Returns the position of the top-left hand corner of the window relative to the
     ///  top-left hand corner of the desktop.
     ///
@@ -304,12 +551,34 @@ impl Window {
     /// The coordinates can be negative if the top-left hand corner of the window is outside
     ///  of the visible screen region.
     ///
+    /// The position is the one of the outer frame of the window, including the title bar and
+    ///  borders drawn by the window manager, so that `set_position` with the returned value
+    ///  doesn't move the window. Like the sizes, it is in points.
+    ///
     /// Returns `None` if the window no longer exists.
+    ///
+    /// ## Platform-specific
+    ///
+    /// - On Windows and MacOS, the origin is the top-left hand corner of the primary monitor
+    ///   instead, so the monitors on the left or above it have negative coordinates. On MacOS,
+    ///   `os::macos::cocoa_to_top_left` and `os::macos::top_left_to_cocoa` convert from and to
+    ///   the bottom-left hand corner origin of Cocoa.
     #[inline]
     pub fn get_position(&self) -> Option<(i32, i32)> {
         self.window.get_position()
     }
 
+    /// Returns the position of the top-left hand corner of the outer frame of the window,
+    ///  including the title bar and borders, in the same coordinates as `get_position`.
+    ///
+    /// This is the same position as `get_position`, and the counterpart of `get_outer_size`.
+    ///
+    /// Returns `None` if the window no longer exists.
+    #[inline]
+    pub fn get_outer_position(&self) -> Option<(i32, i32)> {
+        self.window.get_outer_position()
+    }
+
     /// Modifies the position of the window.
     ///
     /// See `get_position` for more informations about the coordinates.
@@ -325,12 +594,17 @@ impl Window {
     /// The client area is the content of the window, excluding the title bar and borders.
     /// To get the dimensions of the frame buffer when calling `glViewport`, multiply with hidpi factor.
     ///
-    /// Returns `None` if the window no longer exists.
+    /// Returns `None` if the window no longer exists. The size is never smaller than 1×1, even
+    /// if the window manager shrinks the window further.
+    ///
+    /// Points are pixels divided by `hidpi_factor`. The sizes and positions of the window,
+    /// including the ones given to the builder, are in points, except the size reported by
+    /// `Event::Resized` and the one returned by `get_inner_size_pixels`, which are in pixels.
     ///
     /// DEPRECATED
     #[inline]
     pub fn get_inner_size(&self) -> Option<(u32, u32)> {
-        self.window.get_inner_size()
+        self.window.get_inner_size().map(clamp_size)
     }
     
     /// Returns the size in points of the client area of the window.
@@ -338,10 +612,11 @@ impl Window {
     /// The client area is the content of the window, excluding the title bar and borders.
     /// To get the dimensions of the frame buffer when calling `glViewport`, multiply with hidpi factor.
     ///
-    /// Returns `None` if the window no longer exists.
+    /// Returns `None` if the window no longer exists. The size is never smaller than 1×1, even
+    /// if the window manager shrinks the window further.
     #[inline]
     pub fn get_inner_size_points(&self) -> Option<(u32, u32)> {
-        self.window.get_inner_size()
+        self.window.get_inner_size().map(clamp_size)
     }
 
 
@@ -351,16 +626,17 @@ impl Window {
     /// These are the dimensions of the frame buffer, and the dimensions that you should use
     ///  when you call `glViewport`.
     ///
-    /// Returns `None` if the window no longer exists.
+    /// Returns `None` if the window no longer exists. The size is never smaller than 1×1, so
+    /// that it is always valid for a framebuffer.
     #[inline]
     pub fn get_inner_size_pixels(&self) -> Option<(u32, u32)> {
         self.window.get_inner_size().map(|(x, y)| {
             let hidpi = self.hidpi_factor();
-            ((x as f32 * hidpi) as u32, (y as f32 * hidpi) as u32)
+            clamp_size(((x as f32 * hidpi) as u32, (y as f32 * hidpi) as u32))
         })
     }
 
-    /// Returns the size in pixels of the window.
+    /// Returns the size in points of the window, like `get_inner_size`.
     ///
     /// These dimensions include title bar and borders. If you don't want these, you should use
     ///  use `get_inner_size` instead.
@@ -381,6 +657,128 @@ impl Window {
         self.window.set_inner_size(x, y)
     }
 
+    /// Same as `set_inner_size`, but tells whether the new size was applied immediately.
+    ///
+    /// Returns `Some` with the new inner size if the window was resized synchronously, which is
+    /// the case on Windows, MacOS and Emscripten. The size may differ from the requested one, for
+    /// example because of the minimum and maximum dimensions.
+    ///
+    /// Returns `None` if the window system applies the size asynchronously, which is the case on
+    /// X11 where the window manager has the final say. A `Resized` event is sent once the window
+    /// has actually been resized. Android and iOS can't resize windows and always return `None`.
+    #[inline]
+    pub fn request_inner_size(&self, x: u32, y: u32) -> Option<(u32, u32)> {
+        self.window.request_inner_size(x, y)
+    }
+
+    /// Changes the minimum dimensions of the client area, see `WindowBuilder::with_min_dimensions`.
+    ///
+    /// `None` removes the limit. If the current size of the window is smaller than the new
+    /// limit, the window is resized and a `Resized` event is sent.
+    ///
+    /// Has no effect on Android, iOS and Emscripten.
+    #[inline]
+    pub fn set_min_dimensions(&self, dimensions: Option<(u32, u32)>) {
+        self.window.set_min_dimensions(dimensions)
+    }
+
+    /// Changes the maximum dimensions of the client area, see `WindowBuilder::with_max_dimensions`.
+    ///
+    /// `None` removes the limit. If the current size of the window is larger than the new
+    /// limit, the window is resized and a `Resized` event is sent.
+    ///
+    /// Has no effect on Android, iOS and Emscripten.
+    #[inline]
+    pub fn set_max_dimensions(&self, dimensions: Option<(u32, u32)>) {
+        self.window.set_max_dimensions(dimensions)
+    }
+
+    /// Constrains the ratio between the width and the height of the client area when the user
+    /// resizes the window. For example `Some((16, 9))` keeps the window in 16:9.
+    ///
+    /// `None`, or a ratio with a zero component, removes the constraint. If the current size of
+    /// the window doesn't have the ratio, the window is shrunk to it and a `Resized` event is
+    /// sent.
+    ///
+    /// On X11 and MacOS the window manager enforces the ratio by itself. On Windows, the height
+    /// follows the width when the user drags a side or a corner of the window, and the width
+    /// follows the height when they drag the top or the bottom.
+    #[inline]
+    pub fn set_aspect_ratio(&self, ratio: Option<(u32, u32)>) {
+        self.window.set_aspect_ratio(ratio)
+    }
+
+    /// Adds or removes the title bar and borders of the window, see
+    /// `WindowBuilder::with_decorations`.
+    ///
+    /// The size of the client area doesn't change. On X11, this returns once the window manager
+    /// has updated the frame, so that `get_outer_size` and `get_position` are up to date; window
+    /// managers that ignore the change while the window is visible get it by having the window
+    /// hidden and shown again.
+    ///
+    /// Has no effect on MacOS, Android, iOS and Emscripten.
+    #[inline]
+    pub fn set_decorations(&self, decorations: bool) {
+        self.window.set_decorations(decorations)
+    }
+
+    /// Sets the opacity of the whole window, including its decorations, for example to fade it
+    /// in or out. The value is clamped between 0.0, which makes the window invisible, and 1.0,
+    /// which makes it opaque.
+    ///
+    /// This is independent of the transparency of the pixels of a transparent window, and both
+    /// are combined. An opacity of 1.0 removes the extra composition, so that opaque windows
+    /// don't pay its cost.
+    ///
+    /// ## Platform-specific
+    ///
+    /// - On X11, this requires a compositing manager, and the window stays opaque otherwise.
+    /// - Has no effect on Android, iOS and Emscripten.
+    #[inline]
+    pub fn set_opacity(&self, opacity: f32) {
+        self.window.set_opacity(opacity.max(0.0).min(1.0))
+    }
+
+    /// Excludes the content of the window from screenshots and screen sharing, or includes it
+    /// again, see `WindowBuilder::with_content_protection`.
+    ///
+    /// Returns `ContentProtectionError::Unsupported` if the platform can't enforce it, and
+    /// `ContentProtectionError::OsError` if the system refused, in which case the window can
+    /// still be captured. Applications that must never be captured should build the window
+    /// hidden, call this and only show the window if it succeeded.
+    ///
+    /// ## Platform-specific
+    ///
+    /// - On Windows, the window is left out of the captures since Windows 10 version 2004, and
+    ///   is captured as a black rectangle on older versions. This fails if desktop composition
+    ///   is disabled.
+    /// - On MacOS, the window is left out of the captures, although recent versions of the
+    ///   system may ignore it for some capture APIs.
+    /// - Not supported on X11, where any client can read the content of the windows, and on
+    ///   Android, iOS and Emscripten.
+    #[inline]
+    pub fn set_content_protected(&self, protected: bool) -> Result<(), ContentProtectionError> {
+        self.window.set_content_protected(protected)
+    }
+
+    /// Makes the window receive the mouse events, which is the default, or lets them go through
+    /// to the windows below it, for overlays for example. The window is still drawn when it
+    /// ignores the cursor.
+    ///
+    /// Returns `Err` if the platform doesn't support it, in which case the window keeps
+    /// receiving the mouse events.
+    ///
+    /// ## Platform-specific
+    ///
+    /// - On Windows, a window that ignores the cursor is layered, which makes DWM compose it even
+    ///   if it is opaque.
+    /// - On X11, this requires the SHAPE extension.
+    /// - Not supported on Android, iOS and Emscripten.
+    #[inline]
+    pub fn set_cursor_hittest(&self, hittest: bool) -> Result<(), ()> {
+        self.window.set_cursor_hittest(hittest)
+    }
+
     /// Returns an iterator that poll for the next event in the window's events queue.
     /// Returns `None` if there is no event in the queue.
     ///
@@ -390,19 +788,69 @@ impl Window {
         PollEventsIterator(self.window.poll_events())
     }
 
+    /// Moves all the events that are available to the end of `events`, in the same order as
+    /// `poll_events` would return them. Never blocks.
+    ///
+    /// The events are fetched in batches instead of one by one, which avoids locking the event
+    /// queues for every event. Reusing the same vector between frames also avoids allocating.
+    ///
+    /// ## Platform-specific
+    ///
+    /// - On Android, iOS and Emscripten, this is the same as collecting `poll_events`.
+    #[inline]
+    pub fn drain_events(&self, events: &mut Vec<Event>) {
+        let start = events.len();
+        self.window.drain_events(events);
+        // like `clamp_resized`, without moving the events out of the vector
+        for event in &mut events[start ..] {
+            if let Event::Resized(ref mut width, ref mut height) = *event {
+                let (clamped_width, clamped_height) = clamp_size((*width, *height));
+                *width = clamped_width;
+                *height = clamped_height;
+            }
+        }
+    }
+
     /// Returns an iterator that returns events one by one, blocking if necessary until one is
     /// available.
     ///
     /// The iterator never returns `None`.
+    ///
+    /// Event loops that can't block, such as the ones of async runtimes, can wait for the file
+    /// descriptor of `os::unix::WindowExt::get_event_fd` on Linux. On the other platforms, they
+    /// can call `wait_events` on a thread dedicated to it (the main thread on MacOS) and forward
+    /// the events through a channel, using a `WindowProxy` to wake the thread up when needed.
     #[inline]
     pub fn wait_events(&self) -> WaitEventsIterator {
         WaitEventsIterator(self.window.wait_events())
     }
 
-    /// Sets the context as the current context.
-    #[inline]
-    pub unsafe fn make_current(&self) -> Result<(), ContextError> {
-        self.window.make_current()
+    /// Sets the context as the current context, and returns the guard through which OpenGL is
+    /// used.
+    ///
+    /// The returned guard binds the context to the calling thread. It can't be sent to another
+    /// thread, so the functions that issue OpenGL calls, which are only available on the guard,
+    /// can't be called from another thread. While it is alive, trying to make the context
+    /// current on another thread returns `ContextError::CurrentOnOtherThread`.
+    ///
+    /// Dropping the guard releases the binding, but the context stays current on the thread.
+    pub fn make_current(&self) -> Result<CurrentContext, ContextError> {
+        let thread = thread::current().id();
+
+        let mut bound_thread = self.bound_thread.lock().unwrap();
+        let count = match *bound_thread {
+            Some((t, _)) if t != thread => return Err(ContextError::CurrentOnOtherThread),
+            Some((_, count)) => count,
+            None => 0,
+        };
+
+        try!(unsafe { self.window.make_current() });
+        *bound_thread = Some((thread, count + 1));
+
+        Ok(CurrentContext {
+            window: self,
+            marker: PhantomData,
+        })
     }
 
     /// Returns true if this context is the current one in this thread.
@@ -411,25 +859,18 @@ impl Window {
         self.window.is_current()
     }
 
-    /// Returns the address of an OpenGL function.
+    /// Creates an offscreen framebuffer whose attachments have the same size as the window.
     ///
-    /// Contrary to `wglGetProcAddress`, all available OpenGL functions return an address.
-    #[inline]
-    pub fn get_proc_address(&self, addr: &str) -> *const () {
-        self.window.get_proc_address(addr)
-    }
-
-    /// Swaps the buffers in case of double or triple buffering.
-    ///
-    /// You should call this function every time you have finished rendering, or the image
-    ///  may not be displayed on the screen.
+    /// The context of the window is made current on the calling thread first, since the
+    /// framebuffer must only be used while it is current. This is the same as calling
+    /// `CurrentContext::create_sized_framebuffer` on the guard returned by `make_current`.
     ///
-    /// **Warning**: if you enabled vsync, this function will block until the next time the screen
-    /// is refreshed. However drivers can choose to override your vsync settings, which means that
-    /// you can't know in advance whether `swap_buffers` will block or not.
-    #[inline]
-    pub fn swap_buffers(&self) -> Result<(), ContextError> {
-        self.window.swap_buffers()
+    /// See the documentation of `SizedFramebuffer`.
+    pub fn create_sized_framebuffer(&self) -> Result<SizedFramebuffer, CreationError> {
+        match self.make_current() {
+            Ok(context) => context.create_sized_framebuffer(),
+            Err(err) => Err(CreationError::OsError(format!("{}", err))),
+        }
     }
 
     /// DEPRECATED. Gets the native platform specific display for this window.
@@ -464,6 +905,39 @@ impl Window {
         self.window.get_pixel_format()
     }
 
+    /// Returns the pixel format that was requested when building the window along with the one
+    /// that the backend granted.
+    ///
+    /// The backends pick the format that matches the requirements best, which may differ from
+    /// them when the requirements are soft, for example when multisampling isn't in effect
+    /// because the driver has no multisampled format. Comparing both tells what was left out.
+    #[inline]
+    pub fn current_pixel_format(&self) -> CurrentPixelFormat {
+        CurrentPixelFormat {
+            requested: self.pf_reqs.clone(),
+            granted: self.window.get_pixel_format(),
+        }
+    }
+
+    /// Returns the priority that the implementation granted to the OpenGL context, which may be
+    /// lower than the one requested with `with_gl_context_priority`.
+    ///
+    /// Returns `None` if the implementation doesn't support priorities, which is always the case
+    /// outside of EGL.
+    #[inline]
+    pub fn get_context_priority(&self) -> Option<ContextPriority> {
+        self.window.get_context_priority()
+    }
+
+    /// Returns the robustness that the OpenGL context was created with, which is `NotRobust` if
+    /// a `Try*` robustness was requested with `with_gl_robustness` but isn't supported.
+    ///
+    /// Returns `None` if the backend can't tell, which is always the case outside of EGL.
+    #[inline]
+    pub fn get_robustness(&self) -> Option<Robustness> {
+        self.window.get_robustness()
+    }
+
     /// Create a window proxy for this window, that can be freely
     /// passed to different threads.
     #[inline]
@@ -476,6 +950,11 @@ impl Window {
     /// Sets a resize callback that is called by Mac (and potentially other
     /// operating systems) during resize operations. This can be used to repaint
     /// during window resizing.
+    ///
+    /// On Mac, the events loop is blocked while the user is resizing the window, and the
+    /// `Resized` event is only received once they are done. Until then, the last frame is
+    /// stretched to the size of the window, unless the callback draws and swaps the buffers. The
+    /// context has already been updated to the new size when the callback is called.
     #[inline]
     pub fn set_window_resize_callback(&mut self, callback: Option<fn(u32, u32)>) {
         self.window.set_window_resize_callback(callback);
@@ -487,38 +966,486 @@ impl Window {
         self.window.set_cursor(cursor);
     }
 
+    /// Sets whether the keystrokes go through the input method of the system, which composes
+    /// characters, such as the ones of Asian languages, from several keystrokes.
+    ///
+    /// Text-entry applications want the composed characters in the `ReceivedCharacter` events,
+    /// while games usually want the raw `KeyboardInput` events without the interference of the
+    /// input method. The input method is allowed by default.
+    ///
+    /// ## Platform-specific
+    ///
+    /// - On Windows, the input context of the window is removed while the input method isn't
+    ///   allowed.
+    /// - On macOS, the keystrokes never go through the input method, so this has no effect.
+    /// - Has no effect on Android, iOS and Emscripten.
+    #[inline]
+    pub fn set_ime_allowed(&self, allowed: bool) {
+        self.window.set_ime_allowed(allowed)
+    }
+
+    /// Sets the position in the window, in pixels like the positions of `MouseMoved`, where the
+    /// input method shows the characters being composed and the candidates to choose from.
+    /// Text editors set it to the position of the caret, so that these windows appear next to
+    /// the text.
+    ///
+    /// The position can be set before a composition starts, and is kept for the next ones.
+    ///
+    /// ## Platform-specific
+    ///
+    /// - On X11, this only works with the input methods that support the over-the-spot style,
+    ///   the others show the characters in a window of their own.
+    /// - Has no effect on macOS, where the keystrokes don't go through the input method, and on
+    ///   Android, iOS and Emscripten.
+    #[inline]
+    pub fn set_ime_position(&self, x: i32, y: i32) {
+        self.window.set_ime_position(x, y)
+    }
+
+    /// Returns the virtual key that the key with a scan code of `KeyboardInput` produces with the
+    /// current keyboard layout, for example to bind an action to the position of a key and show
+    /// the binding to the user.
+    ///
+    /// The result changes when the user switches layouts, which is reported by the
+    /// `KeyboardLayoutChanged` event.
+    ///
+    /// ## Platform-specific
+    ///
+    /// - On macOS, the virtual keys are the positions of the keys on an ANSI keyboard, like in
+    ///   the `KeyboardInput` events, so only `key_name` follows the layout.
+    /// - On X11, the virtual key is the one of the current group of the keyboard.
+    /// - Always returns `None` on Android, iOS and Emscripten.
+    #[inline]
+    pub fn scancode_to_keycode(&self, scancode: ScanCode) -> Option<VirtualKeyCode> {
+        self.window.scancode_to_keycode(scancode)
+    }
+
+    /// Returns the scan code of the key that produces a virtual key with the current keyboard
+    /// layout, or `None` if no key of the layout produces it. This is the opposite of
+    /// `scancode_to_keycode`.
+    ///
+    /// ## Platform-specific
+    ///
+    /// - Always returns `None` on Android, iOS and Emscripten.
+    #[inline]
+    pub fn keycode_to_scancode(&self, keycode: VirtualKeyCode) -> Option<ScanCode> {
+        self.window.keycode_to_scancode(keycode)
+    }
+
+    /// Returns the label of the key with a scan code in the current keyboard layout, to show it
+    /// to the user.
+    ///
+    /// ## Platform-specific
+    ///
+    /// - On Windows, this is the name that `GetKeyNameTextW` returns, like "A" or "Ctrl".
+    /// - On macOS, this is the character that the key types, like "a", and `None` for the keys
+    ///   that don't type one.
+    /// - On X11, this is the name of the keysym, like "a" or "Control_L".
+    /// - Always returns `None` on Android, iOS and Emscripten.
+    #[inline]
+    pub fn key_name(&self, scancode: ScanCode) -> Option<String> {
+        self.window.key_name(scancode)
+    }
+
+    /// Sets a filter that receives the events of the platform before glutin translates them,
+    /// for example to handle the messages that glutin doesn't support. If the filter returns
+    /// `true`, the event is consumed and glutin ignores it. `None` removes the filter.
+    ///
+    /// The filter is called in the order the platform delivers the events, before any `Event`
+    /// is produced from them. The pointers in the `RawEvent` are only valid during the call and
+    /// must not be kept. Consuming an event that glutin relies on, like the ones about the
+    /// focus or the size of the window, leaves the state of the window out of date.
+    ///
+    /// The filter may call the methods of the window, but the events that these methods
+    /// produce while it runs aren't given to it. Setting another filter from inside the
+    /// filter replaces it once it returns.
+    ///
+    /// ## Platform-specific
+    ///
+    /// - On Windows, the filter receives the messages of the window procedure, and runs on the
+    ///   thread that owns the window, not the one that polls the events. A consumed message
+    ///   returns 0 without going to `DefWindowProc`.
+    /// - On X11, the filter receives the `XEvent`s of the window while `poll_events` or
+    ///   `wait_events` runs.
+    /// - On macOS, the filter receives the `NSEvent`s while `poll_events` or `wait_events` runs.
+    ///   A consumed event isn't dispatched to AppKit either.
+    /// - Has no effect on Android, iOS and Emscripten.
+    #[inline]
+    pub fn set_raw_event_filter(&self, filter: Option<Box<FnMut(&RawEvent) -> bool + Send>>) {
+        self.window.set_raw_event_filter(filter)
+    }
+
+    /// Returns the list of all the monitors available on the system that the window belongs to.
+    ///
+    /// This is the same list as the one of `get_available_monitors`.
+    #[inline]
+    pub fn available_monitors(&self) -> AvailableMonitorsIter {
+        let data = self.window.get_available_monitors();
+        AvailableMonitorsIter{ data: data.into_iter() }
+    }
+
+    /// Returns the monitor that the window is on, which is the one that contains the largest
+    /// part of the window if it spans several monitors.
+    ///
+    /// Returns `None` if the system doesn't report any monitor.
+    ///
+    /// ## Platform-specific
+    ///
+    /// - On X11 without Xinerama, returns the monitor of the X screen of the window.
+    /// - Returns the only monitor on Android, iOS and Emscripten.
+    #[inline]
+    pub fn current_monitor(&self) -> Option<MonitorId> {
+        self.window.get_current_monitor().map(MonitorId)
+    }
+
     /// Returns the ratio between the backing framebuffer resolution and the
     /// window size in screen pixels. This is typically one for a normal display
     /// and two for a retina display.
+    ///
+    /// On X11 the factor is guessed from the `Xft.dpi` resource, then the XSETTINGS
+    /// `Gdk/WindowScalingFactor`, then the physical size of the screen. The
+    /// `GLUTIN_X11_HIDPI_SOURCES` environment variable can be set to a comma-separated list of
+    /// `xft`, `xsettings` and `randr` to change this order, and `GLUTIN_X11_HIDPI_FACTOR` to
+    /// force a value. An `HiDpiFactorChanged` event is sent when the factor changes.
+    ///
+    /// On Windows the factor is the one of the monitor of the window, and the process is made
+    /// DPI-aware when the first window is created. An `HiDpiFactorChanged` event is sent when the
+    /// window moves to a monitor with another factor.
     #[inline]
     pub fn hidpi_factor(&self) -> f32 {
         self.window.hidpi_factor()
     }
 
-    /// Changes the position of the cursor in window coordinates.
+    /// Changes the position of the cursor in window coordinates, in points.
+    ///
+    /// The coordinates are multiplied by `hidpi_factor()` to get the position in pixels. Use
+    /// `set_cursor_position_physical` to move the cursor to a position reported by the mouse
+    /// events, which are in pixels.
     #[inline]
     pub fn set_cursor_position(&self, x: i32, y: i32) -> Result<(), ()> {
-        self.window.set_cursor_position(x, y)
+        self.set_cursor_position_precise(x as f64, y as f64)
+    }
+
+    /// Changes the position of the cursor in window coordinates, in points, with subpixel
+    /// precision.
+    ///
+    /// The position is rounded on platforms that don't support subpixel cursor positions.
+    #[inline]
+    pub fn set_cursor_position_precise(&self, x: f64, y: f64) -> Result<(), ()> {
+        let factor = self.window.hidpi_factor() as f64;
+        self.window.set_cursor_position(x * factor, y * factor)
+    }
+
+    /// Changes the position of the cursor in window coordinates, in pixels.
+    ///
+    /// The coordinates are the same as the ones of `MouseMoved`, so moving the cursor to the
+    /// position of the latest `MouseMoved` event doesn't move it.
+    #[inline]
+    pub fn set_cursor_position_physical(&self, x: i32, y: i32) -> Result<(), ()> {
+        self.window.set_cursor_position(x as f64, y as f64)
     }
 
     /// Sets how glutin handles the cursor. See the documentation of `CursorState` for details.
     ///
-    /// Has no effect on Android.
+    /// `Grab` and `GrabAndHide` confine the cursor like
+    /// `set_cursor_grab(CursorGrabMode::Confined)`, and the other states release it, whatever grab
+    /// mode was set before.
+    ///
+    /// On X11, a grabbed cursor is released while the window doesn't have the focus or is
+    /// hidden, and grabbed again when the window gets the focus back. Any state can be changed to
+    /// any other one. On Windows, grabbing the cursor while the window doesn't have the focus
+    /// returns `CursorStateError::WindowNotFocused` like `set_cursor_grab`, but the other
+    /// changes of the state, such as hiding the cursor, are applied.
+    ///
+    /// Returns `CursorStateError::Unsupported` for the grabbed states on Android, iOS and
+    /// Emscripten, where the cursor is never hidden either.
     #[inline]
-    pub fn set_cursor_state(&self, state: CursorState) -> Result<(), String> {
+    pub fn set_cursor_state(&self, state: CursorState) -> Result<(), CursorStateError> {
         self.window.set_cursor_state(state)
     }
+
+    /// Confines the cursor to the window or locks it in place, without changing its visibility.
+    /// See the documentation of `CursorGrabMode` for details.
+    ///
+    /// While the cursor is locked, the window receives `MouseMotion` events instead of
+    /// `MouseMoved` and `MouseMovedPrecise`, and the rectangle of `confine_cursor` is ignored.
+    /// When the cursor is unlocked, it is moved back to the position it had in the window when
+    /// it was locked, so that toggling a first-person camera doesn't make the cursor jump.
+    ///
+    /// On X11, the cursor is grabbed like with `set_cursor_state` and is locked by moving it
+    /// back to the center of the window after every motion. On Windows, a locked cursor is
+    /// confined to the window and moved back to its center the same way. On Mac, a confined
+    /// cursor is moved back into the window when it leaves it.
+    ///
+    /// On Windows, the system releases the cursor when the focus changes, and the cursor is
+    /// grabbed again when the window gets the focus back. If the window isn't the foreground
+    /// window, this returns `CursorStateError::WindowNotFocused` and the cursor is grabbed once
+    /// the window gets the focus, unless another mode is set meanwhile.
+    ///
+    /// Returns `CursorStateError::Unsupported` for the modes other than `None` on Android, iOS
+    /// and Emscripten.
+    #[inline]
+    pub fn set_cursor_grab(&self, mode: CursorGrabMode) -> Result<(), CursorStateError> {
+        self.window.set_cursor_grab(mode)
+    }
+
+    /// Confines the cursor to a rectangle of the window, or stops confining it if `None`.
+    ///
+    /// A rectangle that is one pixel tall or wide locks the cursor on a horizontal or vertical
+    /// line, which is useful for sliders for example.
+    ///
+    /// On Windows, the system prevents the cursor from leaving the rectangle, and changing the
+    /// cursor state replaces the confinement. On X11 and Mac, the cursor is moved back into the
+    /// rectangle every time it leaves it, and the mouse events report the confined position.
+    /// On Windows, this fails with `CursorStateError::WindowNotFocused` like `set_cursor_grab`.
+    ///
+    /// Returns `CursorStateError::Unsupported` for a rectangle on Android, iOS and Emscripten.
+    #[inline]
+    pub fn confine_cursor(&self, rect: Option<Rect>) -> Result<(), CursorStateError> {
+        self.window.confine_cursor(rect)
+    }
+
+    /// Creates another OpenGL context that renders to this window, for example so that one
+    /// thread renders while another one uploads resources.
+    ///
+    /// The new context shares its objects with the context of `opengl.sharing`, or with the
+    /// context of this window if it is `None`. It uses the pixel format of the window, so the
+    /// pixel format requirements of the builder don't apply. The context can be current on a
+    /// thread while the context of the window is current on another one, and `swap_buffers`
+    /// presents the back buffer of the window like `Window::swap_buffers`.
+    ///
+    /// The context must not be used after the window is destroyed, `make_current` returns an
+    /// error if it is.
+    ///
+    /// ## Platform-specific
+    ///
+    /// - On X11, only GLX contexts can be shared this way. `NotSupported` is returned if the
+    ///   window uses EGL, since an EGL surface can only be current on one thread at a time.
+    /// - On Windows, `NoAvailablePixelFormat` is returned if the pixel format of the window
+    ///   isn't the one that glutin has set, since WGL requires the same pixel format.
+    /// - On macOS, the version and profile are part of the pixel format, so those of `opengl`
+    ///   are ignored. `NoAvailablePixelFormat` is returned if the pixel format of the window
+    ///   isn't compatible with the one of the context of `opengl.sharing`.
+    /// - `NotSupported` is returned on Android, iOS and Emscripten.
+    #[inline]
+    pub fn create_shared_context(&self, opengl: &GlAttributes<&Window>)
+                                 -> Result<Context, CreationError>
+    {
+        let opengl = opengl.clone().map_sharing(|w| &w.window);
+        self.window.create_shared_context(&opengl).map(|context| Context { context: context })
+    }
+}
+
+/// An OpenGL context that renders to the window it has been created from. See
+/// `Window::create_shared_context`.
+pub struct Context {
+    pub(crate) context: platform::SharedContext,
 }
 
+impl Context {
+    /// Sets the context as the current context.
+    #[inline]
+    pub unsafe fn make_current(&self) -> Result<(), ContextError> {
+        self.context.make_current()
+    }
+
+    /// Returns true if this context is the current one in this thread.
+    #[inline]
+    pub fn is_current(&self) -> bool {
+        self.context.is_current()
+    }
+
+    /// Returns the address of an OpenGL function.
+    #[inline]
+    pub fn get_proc_address(&self, addr: &str) -> *const () {
+        self.context.get_proc_address(addr)
+    }
+
+    /// Swaps the buffers of the window, like `Window::swap_buffers`.
+    #[inline]
+    pub fn swap_buffers(&self) -> Result<(), ContextError> {
+        self.context.swap_buffers()
+    }
+
+    /// Returns the API that is currently provided by this context.
+    #[inline]
+    pub fn get_api(&self) -> Api {
+        self.context.get_api()
+    }
+
+    /// Returns the pixel format of the window.
+    #[inline]
+    pub fn get_pixel_format(&self) -> PixelFormat {
+        self.context.get_pixel_format()
+    }
+}
+
+impl GlContext for Context {
+    #[inline]
+    unsafe fn make_current(&self) -> Result<(), ContextError> {
+        self.context.make_current()
+    }
+
+    #[inline]
+    fn is_current(&self) -> bool {
+        self.context.is_current()
+    }
+
+    #[inline]
+    fn get_proc_address(&self, addr: &str) -> *const () {
+        self.context.get_proc_address(addr)
+    }
+
+    #[inline]
+    fn is_extension_supported(&self, name: &str) -> bool {
+        self.context.is_extension_supported(name)
+    }
+
+    #[inline]
+    fn swap_buffers(&self) -> Result<(), ContextError> {
+        self.context.swap_buffers()
+    }
+
+    #[inline]
+    fn buffer_age(&self) -> Option<u32> {
+        self.context.buffer_age()
+    }
+
+    #[inline]
+    fn get_api(&self) -> Api {
+        self.context.get_api()
+    }
+
+    #[inline]
+    fn get_pixel_format(&self) -> PixelFormat {
+        self.context.get_pixel_format()
+    }
+}
+
+/// DEPRECATED. Use `Window::make_current` and the `CurrentContext` that it returns instead.
+///
+/// This implementation is kept so that code written for the unsafe `make_current` keeps
+/// working. `GlContext::make_current` still fails with `ContextError::CurrentOnOtherThread`
+/// while a `CurrentContext` of the window is alive on another thread, but the other functions
+/// don't check on which thread they are called.
 impl GlContext for Window {
     #[inline]
     unsafe fn make_current(&self) -> Result<(), ContextError> {
-        self.make_current()
+        Window::make_current(self).map(|_| ())
     }
 
     #[inline]
     fn is_current(&self) -> bool {
-        self.is_current()
+        self.window.is_current()
+    }
+
+    #[inline]
+    fn get_proc_address(&self, addr: &str) -> *const () {
+        self.window.get_proc_address(addr)
+    }
+
+    #[inline]
+    fn is_extension_supported(&self, name: &str) -> bool {
+        self.window.is_extension_supported(name)
+    }
+
+    #[inline]
+    fn swap_buffers(&self) -> Result<(), ContextError> {
+        self.window.swap_buffers()
+    }
+
+    #[inline]
+    fn swap_buffers_with_damage(&self, rects: &[Rect]) -> Result<(), ContextError> {
+        self.window.swap_buffers_with_damage(rects)
+    }
+
+    #[inline]
+    fn buffer_age(&self) -> Option<u32> {
+        self.window.buffer_age()
+    }
+
+    #[inline]
+    fn get_api(&self) -> Api {
+        self.window.get_api()
+    }
+
+    #[inline]
+    fn get_pixel_format(&self) -> PixelFormat {
+        self.window.get_pixel_format()
+    }
+}
+
+/// Guard returned by `Window::make_current`, which binds the context of the window to the
+/// current thread while it is alive.
+///
+/// The functions that issue OpenGL calls are only available on this object, which isn't `Send`,
+/// so that they can't be called from another thread than the one the context is current on. It
+/// dereferences to the `Window` for the other functions.
+///
+/// # Migrating from the unsafe `make_current`
+///
+/// Code that called `unsafe { window.make_current() }` and then the OpenGL functions on the
+/// window keeps the returned guard instead, and calls `get_proc_address`, `swap_buffers` and the
+/// functions of `GlContext` on it:
+///
+/// ```ignore
+/// let context = window.make_current().unwrap();
+/// gl::load_with(|symbol| context.get_proc_address(symbol) as *const _);
+/// // drawing
+/// context.swap_buffers().unwrap();
+/// ```
+///
+/// The implementation of `GlContext` for `Window` still exists but is deprecated. The
+/// `HeadlessContext` and the `Context` returned by `Window::create_shared_context` don't return
+/// a guard yet, and keep the unsafe `GlContext::make_current` for now.
+pub struct CurrentContext<'a> {
+    window: &'a Window,
+    marker: PhantomData<*mut ()>,
+}
+
+impl<'a> CurrentContext<'a> {
+    /// Returns the address of an OpenGL function.
+    ///
+    /// Contrary to `wglGetProcAddress`, all available OpenGL functions return an address.
+    #[inline]
+    pub fn get_proc_address(&self, addr: &str) -> *const () {
+        self.window.window.get_proc_address(addr)
+    }
+
+    /// Swaps the buffers in case of double or triple buffering.
+    ///
+    /// You should call this function every time you have finished rendering, or the image
+    ///  may not be displayed on the screen.
+    ///
+    /// **Warning**: if you enabled vsync, this function will block until the next time the screen
+    /// is refreshed. However drivers can choose to override your vsync settings, which means that
+    /// you can't know in advance whether `swap_buffers` will block or not.
+    #[inline]
+    pub fn swap_buffers(&self) -> Result<(), ContextError> {
+        self.window.window.swap_buffers()
+    }
+
+    /// Creates an offscreen framebuffer whose attachments have the same size as the window.
+    ///
+    /// See the documentation of `SizedFramebuffer`.
+    #[inline]
+    pub fn create_sized_framebuffer(&self) -> Result<SizedFramebuffer, CreationError> {
+        let dimensions = self.window.get_inner_size_pixels().unwrap_or((1, 1));
+        SizedFramebuffer::new(self, dimensions)
+    }
+}
+
+impl<'a> GlContext for CurrentContext<'a> {
+    /// Makes the context current again on the thread of the guard, for example after another
+    /// context was made current on it.
+    #[inline]
+    unsafe fn make_current(&self) -> Result<(), ContextError> {
+        self.window.window.make_current()
+    }
+
+    #[inline]
+    fn is_current(&self) -> bool {
+        self.window.is_current()
     }
 
     #[inline]
@@ -526,19 +1453,53 @@ impl GlContext for Window {
         self.get_proc_address(addr)
     }
 
+    #[inline]
+    fn is_extension_supported(&self, name: &str) -> bool {
+        self.window.window.is_extension_supported(name)
+    }
+
     #[inline]
     fn swap_buffers(&self) -> Result<(), ContextError> {
         self.swap_buffers()
     }
 
+    #[inline]
+    fn swap_buffers_with_damage(&self, rects: &[Rect]) -> Result<(), ContextError> {
+        self.window.window.swap_buffers_with_damage(rects)
+    }
+
+    #[inline]
+    fn buffer_age(&self) -> Option<u32> {
+        self.window.window.buffer_age()
+    }
+
     #[inline]
     fn get_api(&self) -> Api {
-        self.get_api()
+        self.window.get_api()
     }
 
     #[inline]
     fn get_pixel_format(&self) -> PixelFormat {
-        self.get_pixel_format()
+        self.window.get_pixel_format()
+    }
+}
+
+impl<'a> Deref for CurrentContext<'a> {
+    type Target = Window;
+
+    #[inline]
+    fn deref(&self) -> &Window {
+        self.window
+    }
+}
+
+impl<'a> Drop for CurrentContext<'a> {
+    fn drop(&mut self) {
+        let mut bound_thread = self.window.bound_thread.lock().unwrap();
+        *bound_thread = match *bound_thread {
+            Some((thread, count)) if count > 1 => Some((thread, count - 1)),
+            _ => None,
+        };
     }
 }
 
@@ -558,6 +1519,22 @@ impl WindowProxy {
     pub fn wakeup_event_loop(&self) {
         self.proxy.wakeup_event_loop();
     }
+
+    /// Asks the window to close, as if the user had clicked on its close button, for example to
+    /// shut down the application from a worker thread.
+    ///
+    /// A `Closed` event is delivered to the events loop of the window, which wakes up a thread
+    /// that is blocked in `wait_events`. The window itself is not destroyed: the application
+    /// reacts to the event as usual, and can keep the window open.
+    ///
+    /// ## Platform-specific
+    ///
+    /// - Does nothing on iOS, where the system manages the lifetime of the application, and on
+    ///   Emscripten and caca. On Android, the `Closed` event doesn't stop the activity.
+    #[inline]
+    pub fn request_close(&self) {
+        self.proxy.request_close();
+    }
 }
 /// An iterator for the `poll_events` function.
 pub struct PollEventsIterator<'a>(platform::PollEventsIterator<'a>);
@@ -567,7 +1544,7 @@ impl<'a> Iterator for PollEventsIterator<'a> {
 
     #[inline]
     fn next(&mut self) -> Option<Event> {
-        self.0.next()
+        self.0.next().map(clamp_resized)
     }
 
     #[inline]
@@ -584,7 +1561,7 @@ impl<'a> Iterator for WaitEventsIterator<'a> {
 
     #[inline]
     fn next(&mut self) -> Option<Event> {
-        self.0.next()
+        self.0.next().map(clamp_resized)
     }
 
     #[inline]
@@ -593,6 +1570,27 @@ impl<'a> Iterator for WaitEventsIterator<'a> {
     }
 }
 
+/// Enlarges a size of the client area to at least 1×1.
+///
+/// Some window managers shrink windows to a height of 0, for example while they are rolled up,
+/// and framebuffers can't be created with such a size.
+#[inline]
+fn clamp_size((width, height): (u32, u32)) -> (u32, u32) {
+    (cmp::max(width, 1), cmp::max(height, 1))
+}
+
+/// Applies `clamp_size` to the `Resized` events.
+#[inline]
+fn clamp_resized(event: Event) -> Event {
+    match event {
+        Event::Resized(width, height) => {
+            let (width, height) = clamp_size((width, height));
+            Event::Resized(width, height)
+        },
+        event => event,
+    }
+}
+
 /// An iterator for the list of available monitors.
 // Implementation note: we retreive the list once, then serve each element by one by one.
 // This may change in the future.
@@ -615,6 +1613,9 @@ impl Iterator for AvailableMonitorsIter {
 }
 
 /// Returns the list of all available monitors.
+///
+/// The list is empty if no windowing system is available, for example on a Linux machine
+/// without an X server.
 #[inline]
 pub fn get_available_monitors() -> AvailableMonitorsIter {
     let data = platform::get_available_monitors();
@@ -622,13 +1623,55 @@ pub fn get_available_monitors() -> AvailableMonitorsIter {
 }
 
 /// Returns the primary monitor of the system.
+///
+/// Returns `None` if no windowing system is available, for example on a Linux machine without
+/// an X server, or if the system doesn't report a primary monitor.
 #[inline]
-pub fn get_primary_monitor() -> MonitorId {
-    MonitorId(platform::get_primary_monitor())
+pub fn get_primary_monitor() -> Option<MonitorId> {
+    platform::get_primary_monitor().map(MonitorId)
+}
+
+/// Returns the monitor that contains a point of the desktop, in the coordinates of
+/// `Window::get_position`.
+///
+/// Unlike `Window::get_current_monitor`, this doesn't depend on a window, so it can tell for
+/// example which monitor the cursor is over before a window is opened there.
+///
+/// Returns `None` if no monitor contains the point.
+///
+/// ## Platform-specific
+///
+/// - On X11, the monitors are the heads reported by Xinerama rather than the CRTCs of XRandR.
+///   With RandR 1.2 and later these are the same rectangles, but mirrored outputs are reported as
+///   a single head. Without Xinerama, only the default screen is considered.
+/// - Always returns the only monitor on Android, iOS and Emscripten.
+#[inline]
+pub fn monitor_from_point(x: i32, y: i32) -> Option<MonitorId> {
+    platform::monitor_from_point(x, y).map(MonitorId)
+}
+
+/// Returns true if the system provides hardware-accelerated OpenGL, without creating a window
+/// or a context.
+///
+/// This is what `PixelFormat::hardware_accelerated` is likely to report for a window created
+/// with the default requirements, which lets applications switch to a software fallback before
+/// showing a window.
+///
+/// ## Platform-specific
+///
+/// - On X11, checks whether the default screen has a GLX config that isn't marked as slow.
+///   Returns `false` if there is no X server or if GLX isn't available.
+/// - On Windows, checks whether a pixel format of the screen is provided by the driver rather
+///   than by the generic implementation of Windows.
+/// - On macOS, checks whether an accelerated `NSOpenGLPixelFormat` can be created.
+/// - Always returns `true` on Android, iOS and Emscripten.
+#[inline]
+pub fn has_hardware_acceleration() -> bool {
+    platform::has_hardware_acceleration()
 }
 
 /// Identifier for a monitor.
-pub struct MonitorId(platform::MonitorId);
+pub struct MonitorId(pub(crate) platform::MonitorId);
 
 impl MonitorId {
     /// Returns a human-readable name of the monitor.
@@ -651,4 +1694,64 @@ impl MonitorId {
         let &MonitorId(ref id) = self;
         id.get_dimensions()
     }
+
+    /// Returns the raw bytes of the ICC color profile of the monitor.
+    ///
+    /// Returns `None` if no profile is assigned to the monitor or if the platform doesn't
+    /// support color profiles.
+    #[inline]
+    pub fn get_icc_profile(&self) -> Option<Vec<u8>> {
+        let &MonitorId(ref id) = self;
+        id.get_icc_profile()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{can_be_relaxed, lower_gl_version};
+    use Api;
+    use CreationError;
+
+    #[test]
+    fn lower_gl_version_skips_to_the_next_release() {
+        assert_eq!(lower_gl_version(Api::OpenGl, (4, 6)), Some((4, 5)));
+        assert_eq!(lower_gl_version(Api::OpenGl, (3, 0)), Some((2, 1)));
+        assert_eq!(lower_gl_version(Api::OpenGlEs, (3, 0)), Some((2, 0)));
+        assert_eq!(lower_gl_version(Api::WebGl, (2, 0)), Some((1, 0)));
+    }
+
+    #[test]
+    fn lower_gl_version_handles_unknown_versions() {
+        // versions that were never released are lowered to the closest existing one
+        assert_eq!(lower_gl_version(Api::OpenGl, (5, 0)), Some((4, 6)));
+        assert_eq!(lower_gl_version(Api::OpenGl, (3, 9)), Some((3, 3)));
+        assert_eq!(lower_gl_version(Api::OpenGlEs, (2, 5)), Some((2, 0)));
+    }
+
+    #[test]
+    fn lower_gl_version_stops_at_the_oldest_version() {
+        assert_eq!(lower_gl_version(Api::OpenGl, (2, 0)), None);
+        assert_eq!(lower_gl_version(Api::OpenGlEs, (2, 0)), None);
+        assert_eq!(lower_gl_version(Api::WebGl, (1, 0)), None);
+    }
+
+    #[test]
+    fn only_unsupported_requirements_can_be_relaxed() {
+        assert!(can_be_relaxed(&CreationError::NoAvailablePixelFormat));
+        assert!(can_be_relaxed(&CreationError::OpenGlVersionNotSupported));
+        assert!(!can_be_relaxed(&CreationError::NotSupported));
+        assert!(!can_be_relaxed(&CreationError::OsError("BadAlloc".to_owned())));
+    }
+
+    #[test]
+    fn error_pairs_can_be_relaxed_if_both_errors_can() {
+        let pair = |first, second| {
+            CreationError::CreationErrorPair(Box::new(first), Box::new(second))
+        };
+
+        assert!(can_be_relaxed(&pair(CreationError::OpenGlVersionNotSupported,
+                                     CreationError::NoAvailablePixelFormat)));
+        assert!(!can_be_relaxed(&pair(CreationError::OpenGlVersionNotSupported,
+                                      CreationError::NotSupported)));
+    }
 }
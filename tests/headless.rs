@@ -9,6 +9,20 @@ mod gl {
 }
 use gl::types::*;
 
+/// Builds the context, or prints why the test is skipped and returns `None` if no headless
+/// backend of this machine can create it.
+#[cfg(any(target_os = "linux", target_os = "dragonfly", target_os = "freebsd", target_os = "openbsd"))]
+fn build_or_skip(builder: HeadlessRendererBuilder) -> Option<HeadlessContext> {
+    match builder.build() {
+        Ok(context) => Some(context),
+        Err(CreationError::NoBackendAvailable(err)) => {
+            println!("Skipping the test, no headless backend is available: {}", err);
+            None
+        },
+        Err(err) => panic!("{:?}", err),
+    }
+}
+
 
 #[cfg(target_os = "macos")]
 #[test]
@@ -59,3 +73,196 @@ fn test_headless() {
         assert_eq!(values[7], 255);
     }
 }
+
+// no display server is needed, so this also runs on CI machines
+#[cfg(any(target_os = "linux", target_os = "dragonfly", target_os = "freebsd", target_os = "openbsd"))]
+#[test]
+fn test_headless_read_pixels() {
+    // neither EGL nor OSMesa is installed
+    let context = match build_or_skip(glutin::HeadlessRendererBuilder::new(16, 16)) {
+        Some(context) => context,
+        None => return,
+    };
+
+    unsafe { context.make_current().unwrap() };
+
+    let gl = gl::Gl::load_with(|symbol| context.get_proc_address(symbol) as *const _);
+
+    unsafe {
+        gl.ClearColor(0.0, 1.0, 0.0, 1.0);
+        gl.Clear(gl::COLOR_BUFFER_BIT);
+
+        let mut pixel: [u8; 4] = [0; 4];
+        gl.ReadPixels(8, 8, 1, 1, gl::RGBA, gl::UNSIGNED_BYTE, pixel.as_mut_ptr() as *mut GLvoid);
+
+        assert_eq!(pixel, [0, 255, 0, 255]);
+    }
+}
+
+#[cfg(any(target_os = "linux", target_os = "dragonfly", target_os = "freebsd", target_os = "openbsd"))]
+#[test]
+fn test_headless_read_framebuffer() {
+    let context = match build_or_skip(glutin::HeadlessRendererBuilder::new(16, 16)) {
+        Some(context) => context,
+        None => return,
+    };
+
+    // nothing is current on the thread of the test yet
+    assert!(context.read_framebuffer(0, 0, 1, 2).is_err());
+
+    unsafe { context.make_current().unwrap() };
+
+    let gl = gl::Gl::load_with(|symbol| context.get_proc_address(symbol) as *const _);
+
+    unsafe {
+        gl.ClearColor(0.0, 1.0, 0.0, 1.0);
+        gl.Clear(gl::COLOR_BUFFER_BIT);
+        gl.Enable(gl::SCISSOR_TEST);
+        gl.Scissor(0, 0, 16, 1);
+        gl.ClearColor(1.0, 0.0, 0.0, 1.0);
+        gl.Clear(gl::COLOR_BUFFER_BIT);
+        gl.Disable(gl::SCISSOR_TEST);
+    }
+
+    // the bottom row of the framebuffer is the last one
+    let pixels = context.read_framebuffer(0, 0, 1, 2).unwrap();
+    assert_eq!(pixels, [0, 255, 0, 255, 255, 0, 0, 255]);
+}
+
+#[cfg(any(target_os = "linux", target_os = "dragonfly", target_os = "freebsd", target_os = "openbsd"))]
+#[test]
+fn test_headless_osmesa() {
+    use glutin::os::unix::{HeadlessBackend, HeadlessContextExt, HeadlessRendererBuilderExt};
+
+    // libOSMesa isn't installed
+    let builder = glutin::HeadlessRendererBuilder::new(16, 16)
+                        .with_headless_backends(&[HeadlessBackend::OsMesa]);
+    let context = match build_or_skip(builder) {
+        Some(context) => context,
+        None => return,
+    };
+
+    unsafe { context.make_current().unwrap() };
+
+    let gl = gl::Gl::load_with(|symbol| context.get_proc_address(symbol) as *const _);
+
+    unsafe {
+        gl.ClearColor(0.0, 1.0, 0.0, 1.0);
+        gl.Clear(gl::COLOR_BUFFER_BIT);
+        gl.Finish();
+    }
+
+    let buffer = context.get_osmesa_buffer().unwrap();
+    assert_eq!(buffer.len(), 16 * 16 * 4);
+    assert_eq!(&buffer[.. 4], &[0, 255, 0, 255]);
+    assert!(!context.get_pixel_format().hardware_accelerated);
+}
+
+#[cfg(any(target_os = "linux", target_os = "dragonfly", target_os = "freebsd", target_os = "openbsd"))]
+#[test]
+fn test_headless_surfaceless_context() {
+    use glutin::os::unix::{HeadlessBackend, HeadlessRendererBuilderExt};
+
+    // EGL_KHR_surfaceless_context isn't supported
+    let builder = glutin::HeadlessRendererBuilder::new(16, 16)
+                        .with_headless_backends(&[HeadlessBackend::EglSurfacelessContext]);
+    let context = match build_or_skip(builder) {
+        Some(context) => context,
+        None => return,
+    };
+
+    unsafe { context.make_current().unwrap() };
+
+    let gl = gl::Gl::load_with(|symbol| context.get_proc_address(symbol) as *const _);
+
+    unsafe {
+        // there is no default framebuffer
+        let mut framebuffer = 0;
+        let mut renderbuffer = 0;
+        gl.GenFramebuffers(1, &mut framebuffer);
+        gl.BindFramebuffer(gl::FRAMEBUFFER, framebuffer);
+        gl.GenRenderbuffers(1, &mut renderbuffer);
+        gl.BindRenderbuffer(gl::RENDERBUFFER, renderbuffer);
+        gl.RenderbufferStorage(gl::RENDERBUFFER, gl::RGBA4, 16, 16);
+        gl.FramebufferRenderbuffer(gl::FRAMEBUFFER, gl::COLOR_ATTACHMENT0, gl::RENDERBUFFER,
+                                   renderbuffer);
+        assert_eq!(gl.CheckFramebufferStatus(gl::FRAMEBUFFER), gl::FRAMEBUFFER_COMPLETE);
+
+        gl.ClearColor(0.0, 1.0, 0.0, 1.0);
+        gl.Clear(gl::COLOR_BUFFER_BIT);
+
+        let mut pixel: [u8; 4] = [0; 4];
+        gl.ReadPixels(8, 8, 1, 1, gl::RGBA, gl::UNSIGNED_BYTE, pixel.as_mut_ptr() as *mut GLvoid);
+
+        assert_eq!(pixel, [0, 255, 0, 255]);
+    }
+
+    context.swap_buffers().unwrap();
+}
+
+#[cfg(any(target_os = "linux", target_os = "dragonfly", target_os = "freebsd", target_os = "openbsd"))]
+#[test]
+fn test_headless_core_debug() {
+    // not in the bindings of OpenGL ES 2
+    const CONTEXT_FLAGS: GLenum = 0x821E;
+    const CONTEXT_FLAG_DEBUG_BIT: GLint = 0x2;
+    const CONTEXT_PROFILE_MASK: GLenum = 0x9126;
+    const CONTEXT_CORE_PROFILE_BIT: GLint = 0x1;
+
+    let builder = glutin::HeadlessRendererBuilder::new(16, 16)
+                        .with_gl(GlRequest::Specific(Api::OpenGl, (3, 2)))
+                        .with_gl_profile(GlProfile::Core)
+                        .with_gl_debug_flag(true);
+    let context = match build_or_skip(builder) {
+        Some(context) => context,
+        None => return,
+    };
+
+    unsafe { context.make_current().unwrap() };
+    assert_eq!(context.get_api(), Api::OpenGl);
+
+    let gl = gl::Gl::load_with(|symbol| context.get_proc_address(symbol) as *const _);
+
+    unsafe {
+        let mut flags = 0;
+        gl.GetIntegerv(CONTEXT_FLAGS, &mut flags);
+        assert!(flags & CONTEXT_FLAG_DEBUG_BIT != 0);
+
+        let mut profile = 0;
+        gl.GetIntegerv(CONTEXT_PROFILE_MASK, &mut profile);
+        assert!(profile & CONTEXT_CORE_PROFILE_BIT != 0);
+    }
+}
+
+#[test]
+fn test_headless_vsync_is_rejected() {
+    let mut builder = glutin::HeadlessRendererBuilder::new(16, 16);
+    builder.opengl.vsync = true;
+
+    match builder.build() {
+        Err(CreationError::NotSupported) => (),
+        Err(err) => panic!("{:?}", err),
+        Ok(_) => panic!("vsync was accepted"),
+    }
+}
+
+// `wglGetProcAddress` alone doesn't return the functions of OpenGL 1.1
+#[cfg(target_os = "windows")]
+#[test]
+fn test_headless_get_string() {
+    use std::ffi::CStr;
+    use std::mem;
+
+    let context = glutin::HeadlessRendererBuilder::new(16, 16).build().unwrap();
+    unsafe { context.make_current().unwrap() };
+
+    let get_string = context.get_proc_address("glGetString");
+    assert!(!get_string.is_null());
+
+    unsafe {
+        let get_string: extern "system" fn(GLenum) -> *const u8 = mem::transmute(get_string);
+        let version = get_string(gl::VERSION);
+        assert!(!version.is_null());
+        assert!(!CStr::from_ptr(version as *const _).to_bytes().is_empty());
+    }
+}
@@ -0,0 +1,31 @@
+#![cfg(any(target_os = "linux", target_os = "dragonfly", target_os = "freebsd", target_os = "openbsd"))]
+
+extern crate glutin;
+extern crate x11_dl;
+
+use std::ptr;
+use std::thread;
+
+use glutin::WindowBuilder;
+use glutin::os::unix::WindowExt;
+use x11_dl::xlib;
+
+// needs an X server, run with `cargo test -- --ignored`
+#[test]
+#[ignore]
+fn make_current_fails_once_the_window_is_destroyed() {
+    let window = WindowBuilder::new().with_visibility(false).build().unwrap();
+    let xid = window.get_xlib_window().unwrap() as xlib::Window;
+
+    // another client destroys the window behind glutin's back
+    thread::spawn(move || unsafe {
+        let xlib = xlib::Xlib::open().unwrap();
+        let display = (xlib.XOpenDisplay)(ptr::null());
+        assert!(!display.is_null());
+        (xlib.XDestroyWindow)(display, xid);
+        (xlib.XCloseDisplay)(display);
+    }).join().unwrap();
+
+    assert!(window.make_current().is_err());
+    assert!(!window.is_current());
+}
@@ -0,0 +1,14 @@
+extern crate glutin;
+
+#[test]
+fn primary_monitor_is_available() {
+    // without a windowing system (headless CI machines for example), there is no monitor at all
+    // instead of made-up ones
+    match glutin::get_primary_monitor() {
+        Some(primary) => {
+            let primary = primary.get_native_identifier();
+            assert!(glutin::get_available_monitors().any(|m| m.get_native_identifier() == primary));
+        },
+        None => assert_eq!(glutin::get_available_monitors().count(), 0),
+    }
+}
@@ -0,0 +1,32 @@
+#![cfg(any(target_os = "linux", target_os = "dragonfly", target_os = "freebsd", target_os = "openbsd"))]
+
+extern crate glutin;
+
+use std::thread;
+
+use glutin::{CreationError, GlAttributes, WindowBuilder};
+
+// needs an X server, run with `cargo test -- --ignored`
+#[test]
+#[ignore]
+fn shared_context_is_current_on_another_thread() {
+    let window = WindowBuilder::new().with_visibility(false).build().unwrap();
+
+    let context = match window.create_shared_context(&GlAttributes::default()) {
+        // the window uses EGL
+        Err(CreationError::NotSupported) => return,
+        result => result.unwrap(),
+    };
+
+    let _current = window.make_current().unwrap();
+
+    let context = thread::spawn(move || {
+        unsafe { context.make_current() }.unwrap();
+        assert!(context.is_current());
+        context
+    }).join().unwrap();
+
+    // each thread has its own current context
+    assert!(window.is_current());
+    assert!(!context.is_current());
+}
@@ -0,0 +1,47 @@
+#![cfg(target_os = "macos")]
+
+extern crate glutin;
+
+use glutin::WindowBuilder;
+
+// AppKit requires windows to be created on the main thread, which the test harness doesn't
+// guarantee, run with `cargo test --test window_position -- --ignored --test-threads=1`
+#[test]
+#[ignore]
+fn set_position_round_trips_on_every_monitor() {
+    let window = WindowBuilder::new()
+        .with_dimensions(200, 100)
+        .with_visibility(false)
+        .build()
+        .unwrap();
+
+    let primary = glutin::get_primary_monitor().unwrap().get_native_identifier();
+    let (width, height) = glutin::get_primary_monitor().unwrap().get_dimensions();
+    let (width, height) = (width as i32, height as i32);
+
+    // the arrangement of the monitors is unknown, but the origin is the top-left hand corner of
+    // the primary monitor, so a monitor on the right or below it is past its dimensions
+    let positions = [(100, 100), (width + 100, 100), (100, height + 100)];
+
+    let mut secondary_tested = false;
+    for &(x, y) in positions.iter() {
+        window.set_position(x, y);
+
+        let monitor = window.current_monitor().unwrap().get_native_identifier();
+        if monitor == primary {
+            assert_eq!(window.get_position(), Some((x, y)));
+            assert_eq!(window.get_outer_position(), Some((x, y)));
+        } else {
+            // the window might have been moved to fit on the screen, but setting the position
+            // that is reported back must not move it
+            let position = window.get_position().unwrap();
+            window.set_position(position.0, position.1);
+            assert_eq!(window.get_position(), Some(position));
+            secondary_tested = true;
+        }
+    }
+
+    if glutin::get_available_monitors().count() > 1 {
+        assert!(secondary_tested, "no position was on a secondary monitor");
+    }
+}
@@ -0,0 +1,26 @@
+#![cfg(any(target_os = "linux", target_os = "dragonfly", target_os = "freebsd", target_os = "openbsd"))]
+
+extern crate glutin;
+
+use glutin::{CreationError, WindowBuilder, WindowID};
+
+// needs an X server, run with `cargo test -- --ignored`
+#[test]
+#[ignore]
+fn invalid_parent_reports_x_error() {
+    // an XID that no client allocated, so that `XCreateWindow` fails with `BadWindow`
+    let parent = WindowID::new(0x7fff_fffe as *mut _);
+
+    let result = WindowBuilder::new()
+        .with_visibility(false)
+        .with_parent(Some(parent))
+        .build();
+
+    match result {
+        Err(CreationError::OsError(description)) => {
+            assert!(description.contains("BadWindow"), "unexpected error: {}", description);
+        },
+        Err(err) => panic!("unexpected error: {:?}", err),
+        Ok(_) => panic!("creating a window with an invalid parent succeeded"),
+    }
+}